@@ -4,7 +4,6 @@ use crate::version::Kind;
 
 use anyhow::Context;
 use aws_sdk_s3::model::ObjectCannedAcl;
-use aws_sdk_s3::types::ByteStream;
 use s3::BucketContext;
 
 
@@ -86,14 +85,21 @@ impl Manifest {
     }
 }
 
+/// Upload the edition file and add it to the editions manifest.
+///
+/// Requires AWS credentials in the environment, unless the local storage is used.
 pub async fn update_manifest(repo_context: &impl IsRepo, edition_file: &Path) -> Result {
+    let client = async { Ok(s3::client_from_env().await) };
     let bucket_context = BucketContext {
-        client:     aws_sdk_s3::Client::new(&aws_config::load_from_env().await),
-        bucket:     EDITIONS_BUCKET_NAME.to_string(),
-        upload_acl: ObjectCannedAcl::PublicRead,
+        storage:    s3::storage::select(EDITIONS_BUCKET_NAME, ObjectCannedAcl::PublicRead, client)
+            .await?,
         key_prefix: Some(repo_context.name().to_string()),
     };
+    add_edition(&bucket_context, edition_file).await
+}
 
+/// Upload the edition file to the bucket and add it to the manifest stored there.
+pub async fn add_edition(bucket_context: &BucketContext, edition_file: &Path) -> Result {
     let new_edition_name = Edition(
         edition_file
             .file_stem()
@@ -113,13 +119,8 @@ pub async fn update_manifest(repo_context: &impl IsRepo, edition_file: &Path) ->
         debug!("Should remove {}", nightly_to_remove);
     }
 
-    let new_edition_filename =
-        edition_file.file_name().context("Edition file path is missing filename!")?;
-    bucket_context
-        .put(new_edition_filename.as_str(), ByteStream::from_path(&edition_file).await?)
-        .await?;
-
-    bucket_context.put_yaml("manifest.yaml", &new_manifest).await?;
+    bucket_context.put_file(edition_file).await?;
+    bucket_context.put_yaml(MANIFEST_FILENAME, &new_manifest).await?;
     Ok(())
 }
 
@@ -147,4 +148,30 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn adding_edition_to_local_storage() -> Result {
+        let temp = tempfile::tempdir()?;
+        let storage = s3::storage::LocalStorage::new(temp.path().join("bucket"));
+        let bucket_context = BucketContext {
+            storage:    Arc::new(storage.clone()),
+            key_prefix: Some("enso".into()),
+        };
+        let manifest = Manifest { editions: vec!["2024.1.1".into()] };
+        bucket_context.put_yaml(MANIFEST_FILENAME, &manifest).await?;
+
+        let edition_file = temp.path().join("2024.2.1.yaml");
+        ide_ci::fs::write(&edition_file, "engine-version: 2024.2.1\n")?;
+        add_edition(&bucket_context, &edition_file).await?;
+
+        assert_eq!(storage.keys()?, ["enso/2024.2.1.yaml", "enso/manifest.yaml"]);
+        let manifest = bucket_context.get_yaml::<Manifest>(MANIFEST_FILENAME).await?;
+        let editions = manifest.editions.iter().map(|edition| edition.as_ref()).collect_vec();
+        assert_eq!(editions, ["2024.1.1", "2024.2.1"]);
+        assert_eq!(
+            bucket_context.get("2024.2.1.yaml").await?,
+            Bytes::from("engine-version: 2024.2.1\n")
+        );
+        Ok(())
+    }
 }
@@ -2,9 +2,8 @@
 
 use crate::prelude::*;
 
-use aws_sdk_s3::model::ObjectCannedAcl;
-use aws_sdk_s3::output::PutObjectOutput;
-use aws_sdk_s3::types::ByteStream;
+use crate::aws::s3::storage::Storage;
+
 use bytes::Buf;
 use enso_build_base::extensions::path::SplitFilename;
 use mime::Mime;
@@ -16,6 +15,7 @@ use walkdir::WalkDir;
 // ==============
 
 pub mod gui;
+pub mod storage;
 
 
 
@@ -25,13 +25,10 @@ pub async fn client_from_env() -> aws_sdk_s3::Client {
 }
 
 /// Everything we need to get/put files to S3.
-#[derive(Clone)]
-#[derive_where(Debug)]
+#[derive(Clone, Debug)]
 pub struct BucketContext {
-    #[derive_where(skip)]
-    pub client:     aws_sdk_s3::Client,
-    pub bucket:     String,
-    pub upload_acl: ObjectCannedAcl,
+    /// Where the objects are actually stored. See [`storage::select`].
+    pub storage:    Arc<dyn Storage>,
     /// Prefix that will be prepended to the object key.
     pub key_prefix: Option<String>,
 }
@@ -47,63 +44,40 @@ impl BucketContext {
         }
     }
 
-    pub async fn get(&self, path: &str) -> Result<ByteStream> {
+    pub async fn get(&self, path: &str) -> Result<Bytes> {
         trace!("Downloading {path} at {self:?}.");
-        Ok(self
-            .client
-            .get_object()
-            .bucket(&self.bucket)
-            .key(self.key(path))
-            .send()
-            .await
-            .with_context(|| {
-                format!("Failed to download {} from S3 bucket {}.", self.key(path), self.bucket)
-            })?
-            .body)
-    }
-
-    pub async fn put(&self, path: &str, data: ByteStream) -> Result<PutObjectOutput> {
+        self.storage.get(self.key(path)).await
+    }
+
+    pub async fn put(&self, path: &str, data: Bytes) -> Result {
         trace!("Uploading {path} at {self:?}.");
-        let mut request = self
-            .client
-            .put_object()
-            .bucket(&self.bucket)
-            .acl(self.upload_acl.clone())
-            .key(self.key(path))
-            .body(data);
-
-        // Cloud requested us to set content encoding and type.
-        let content_headers = ContentHeaders::from_path(path);
-        request = content_headers.apply(request);
-
-        request.send().await.with_context(|| {
-            format!("Failed to upload {} to S3 bucket {}.", self.key(path), self.bucket)
-        })
+        self.storage.put(self.key(path), data, ContentHeaders::from_path(path)).await
     }
 
     #[instrument(fields(path = %path.as_ref().display()))]
-    pub async fn put_file(&self, path: impl AsRef<Path>) -> Result<PutObjectOutput> {
+    pub async fn put_file(&self, path: impl AsRef<Path>) -> Result {
         let path = path.as_ref();
-        let stream = ByteStream::from_path(path).await?;
-        let path = path.try_file_name()?;
-        self.put(path.as_str(), stream).await
+        let name = path.try_file_name()?;
+        self.put_file_as(name.as_str(), path).await
     }
 
     /// Put the file at `path` to the S3 bucket. The key will be suffixed with the relative path
     /// between `root` and `path`.
     #[instrument(fields(path = %path.as_ref().display(), root = %root.as_ref().display()))]
-    pub async fn put_subtree_file(
-        &self,
-        root: impl AsRef<Path>,
-        path: impl AsRef<Path>,
-    ) -> Result<PutObjectOutput> {
+    pub async fn put_subtree_file(&self, root: impl AsRef<Path>, path: impl AsRef<Path>) -> Result {
         let root = root.as_ref().absolutize()?;
         let path = path.as_ref().absolutize()?;
         let key_suffix = path.strip_prefix(&root).with_context(|| {
             format!("{} is not a subpath of {}.", root.display(), path.display())
         })?;
-        let stream = ByteStream::from_path(&path).await?;
-        self.put(key_suffix.as_str(), stream).await
+        self.put_file_as(key_suffix.as_str(), &path).await
+    }
+
+    /// Put the file at `path` to the S3 bucket under the given key suffix.
+    async fn put_file_as(&self, key_suffix: &str, path: &Path) -> Result {
+        trace!("Uploading {} as {key_suffix} at {self:?}.", path.display());
+        let headers = ContentHeaders::from_path(key_suffix);
+        self.storage.put_file(self.key(key_suffix), path.to_owned(), headers).await
     }
 
     /// Put an item to the S3 bucket.
@@ -126,13 +100,13 @@ impl BucketContext {
     }
 
     pub async fn get_yaml<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
-        let text = self.get(path).await?.collect().await?;
+        let text = self.get(path).await?;
         Ok(serde_yaml::from_reader(text.reader())?)
     }
 
-    pub async fn put_yaml(&self, path: &str, data: &impl Serialize) -> Result<PutObjectOutput> {
+    pub async fn put_yaml(&self, path: &str, data: &impl Serialize) -> Result {
         let buf = serde_yaml::to_string(data)?;
-        self.put(path, ByteStream::from(buf.into_bytes())).await
+        self.put(path, buf.into_bytes().into()).await
     }
 }
 
@@ -234,4 +208,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn put_item_to_local_storage() -> Result {
+        let temp = tempfile::tempdir()?;
+        let storage = storage::LocalStorage::new(temp.path().join("bucket"));
+        let bucket = BucketContext {
+            storage:    Arc::new(storage.clone()),
+            key_prefix: Some("ide/1.0".into()),
+        };
+        let dist = temp.path().join("dist");
+        ide_ci::fs::write(dist.join("index.js.gz"), "js")?;
+        ide_ci::fs::write(dist.join("assets/style.css"), "css")?;
+        bucket.put_item(&dist).await?;
+        bucket.put_item(dist.join("index.js.gz")).await?;
+
+        assert_eq!(storage.keys()?, [
+            "ide/1.0/dist/assets/style.css",
+            "ide/1.0/dist/index.js.gz",
+            "ide/1.0/index.js.gz"
+        ]);
+        let metadata = storage.metadata("ide/1.0/index.js.gz")?;
+        assert_eq!(metadata.content_type, "text/javascript");
+        assert_eq!(metadata.content_encoding.as_deref(), Some("gzip"));
+        let metadata = storage.metadata("ide/1.0/dist/assets/style.css")?;
+        assert_eq!(metadata.content_type, "text/css");
+        assert_eq!(metadata.content_encoding, None);
+        assert_eq!(bucket.get("dist/assets/style.css").await?, Bytes::from("css"));
+        Ok(())
+    }
 }
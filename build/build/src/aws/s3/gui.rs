@@ -1,5 +1,6 @@
 use crate::prelude::*;
 
+use crate::aws::s3::storage;
 use crate::aws::s3::BucketContext;
 
 use aws_config::meta::region::RegionProviderChain;
@@ -25,12 +26,11 @@ pub async fn client_from_env() -> Result<aws_sdk_s3::Client> {
 
 /// Construct a context for handling a given GUI version release.
 ///
-/// Requires AWS credentials in the environment.
+/// Requires AWS credentials in the environment, unless the local storage is used.
 pub async fn context(version: &Version) -> Result<BucketContext> {
+    let upload_acl = aws_sdk_s3::model::ObjectCannedAcl::PublicRead;
     Ok(BucketContext {
-        client:     client_from_env().await?,
-        bucket:     BUCKET.to_string(),
-        upload_acl: aws_sdk_s3::model::ObjectCannedAcl::PublicRead,
+        storage:    storage::select(BUCKET, upload_acl, client_from_env()).await?,
         key_prefix: Some(format!("ide/{version}")),
     })
 }
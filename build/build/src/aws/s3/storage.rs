@@ -0,0 +1,253 @@
+//! Backends for storing objects in a bucket.
+//!
+//! The [`BucketContext`](crate::aws::s3::BucketContext) talks to a [`Storage`] rather than to S3
+//! directly, so the code uploading release artifacts can be run against a local directory, without
+//! AWS credentials.
+
+use crate::prelude::*;
+
+use crate::aws::s3::ContentHeaders;
+
+use aws_sdk_s3::model::ObjectCannedAcl;
+use aws_sdk_s3::types::ByteStream;
+use std::sync::OnceLock;
+
+
+
+/// Root directory of the local storage, if it was requested. See [`use_local_storage`].
+static LOCAL_STORAGE_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Make all buckets subsequently created by [`select`] use the local storage.
+///
+/// Each bucket is emulated by a subdirectory of `root`, named after the bucket.
+pub fn use_local_storage(root: impl Into<PathBuf>) {
+    let root = root.into();
+    info!("Using local storage at {} instead of S3.", root.display());
+    if let Err(root) = LOCAL_STORAGE_ROOT.set(root) {
+        warn!("Local storage was already set up, ignoring {}.", root.display());
+    }
+}
+
+/// Select the storage backend for the given bucket.
+///
+/// If [`use_local_storage`] was called, the bucket is emulated locally and the `client` is never
+/// awaited. Otherwise, the S3 bucket is used through the `client`.
+pub async fn select(
+    bucket: &str,
+    upload_acl: ObjectCannedAcl,
+    client: impl Future<Output = Result<aws_sdk_s3::Client>>,
+) -> Result<Arc<dyn Storage>> {
+    if let Some(root) = LOCAL_STORAGE_ROOT.get() {
+        Ok(Arc::new(LocalStorage::new(root.join(bucket))))
+    } else {
+        Ok(Arc::new(S3Storage { client: client.await?, bucket: bucket.into(), upload_acl }))
+    }
+}
+
+/// Place where the bucket objects are stored.
+///
+/// The keys are full object keys, i.e. they already include any prefix.
+pub trait Storage: Debug + Send + Sync {
+    /// Download the object stored under the given key.
+    fn get(&self, key: String) -> BoxFuture<'static, Result<Bytes>>;
+
+    /// Store the data under the given key, replacing any existing object.
+    fn put(&self, key: String, data: Bytes, headers: ContentHeaders) -> BoxFuture<'static, Result>;
+
+    /// Store the file's contents under the given key, replacing any existing object.
+    fn put_file(
+        &self,
+        key: String,
+        path: PathBuf,
+        headers: ContentHeaders,
+    ) -> BoxFuture<'static, Result>;
+}
+
+
+
+// ==========
+// === S3 ===
+// ==========
+
+/// An actual S3 bucket.
+#[derive(Clone)]
+#[derive_where(Debug)]
+pub struct S3Storage {
+    #[derive_where(skip)]
+    pub client:     aws_sdk_s3::Client,
+    pub bucket:     String,
+    pub upload_acl: ObjectCannedAcl,
+}
+
+impl S3Storage {
+    fn put_stream(
+        &self,
+        key: String,
+        data: ByteStream,
+        headers: ContentHeaders,
+    ) -> BoxFuture<'static, Result> {
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .acl(self.upload_acl.clone())
+            .key(&key)
+            .body(data);
+        // Cloud requested us to set content encoding and type.
+        let request = headers.apply(request);
+        let bucket = self.bucket.clone();
+        async move {
+            request
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload {key} to S3 bucket {bucket}."))?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+impl Storage for S3Storage {
+    fn get(&self, key: String) -> BoxFuture<'static, Result<Bytes>> {
+        let request = self.client.get_object().bucket(&self.bucket).key(&key);
+        let bucket = self.bucket.clone();
+        async move {
+            let context = || format!("Failed to download {key} from S3 bucket {bucket}.");
+            let output = request.send().await.with_context(context)?;
+            Ok(output.body.collect().await.with_context(context)?.into_bytes())
+        }
+        .boxed()
+    }
+
+    fn put(&self, key: String, data: Bytes, headers: ContentHeaders) -> BoxFuture<'static, Result> {
+        self.put_stream(key, ByteStream::from(data), headers)
+    }
+
+    fn put_file(
+        &self,
+        key: String,
+        path: PathBuf,
+        headers: ContentHeaders,
+    ) -> BoxFuture<'static, Result> {
+        let this = self.clone();
+        async move {
+            let stream = ByteStream::from_path(&path).await?;
+            this.put_stream(key, stream, headers).await
+        }
+        .boxed()
+    }
+}
+
+
+
+// =============
+// === Local ===
+// =============
+
+/// Metadata stored by the [`LocalStorage`] alongside each object.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectMetadata {
+    /// The `Content-Type` the object would be served with, e.g. `text/javascript`.
+    pub content_type:     String,
+    /// The `Content-Encoding` the object would be served with, e.g. `gzip`.
+    pub content_encoding: Option<String>,
+}
+
+impl From<&ContentHeaders> for ObjectMetadata {
+    fn from(headers: &ContentHeaders) -> Self {
+        Self {
+            content_type:     headers.content_type.to_string(),
+            content_encoding: headers.content_encoding().map(Into::into),
+        }
+    }
+}
+
+/// A directory emulating a bucket.
+///
+/// Objects are stored under `objects/<key>`, and their [`ObjectMetadata`] under
+/// `metadata/<key>.json`. Keeping the two in separate trees ensures that no key can collide with
+/// another object's metadata.
+#[derive(Clone, Debug)]
+pub struct LocalStorage {
+    pub root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Path where the object with the given key is stored.
+    pub fn object_path(&self, key: &str) -> PathBuf {
+        self.root.join("objects").join(key)
+    }
+
+    /// Path where the metadata of the object with the given key is stored.
+    pub fn metadata_path(&self, key: &str) -> PathBuf {
+        self.root.join("metadata").join(format!("{key}.json"))
+    }
+
+    /// Read the metadata stored with the object under the given key.
+    pub fn metadata(&self, key: &str) -> Result<ObjectMetadata> {
+        ide_ci::fs::read_json(self.metadata_path(key))
+    }
+
+    /// Keys of all the stored objects, sorted.
+    pub fn keys(&self) -> Result<Vec<String>> {
+        let objects = self.root.join("objects");
+        if !objects.exists() {
+            return Ok(default());
+        }
+        let mut keys = vec![];
+        for entry in walkdir::WalkDir::new(&objects) {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let relative = entry.path().strip_prefix(&objects)?;
+                keys.push(path_slash::PathExt::to_slash_lossy(relative).into_owned());
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn write_metadata(&self, key: &str, headers: &ContentHeaders) -> Result {
+        ide_ci::fs::write_json(self.metadata_path(key), &ObjectMetadata::from(headers))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn get(&self, key: String) -> BoxFuture<'static, Result<Bytes>> {
+        let path = self.object_path(&key);
+        async move {
+            let data = ide_ci::fs::tokio::read(&path)
+                .await
+                .with_context(|| format!("Failed to get {key} from the local storage."))?;
+            Ok(data.into())
+        }
+        .boxed()
+    }
+
+    fn put(&self, key: String, data: Bytes, headers: ContentHeaders) -> BoxFuture<'static, Result> {
+        let this = self.clone();
+        async move {
+            ide_ci::fs::tokio::write(this.object_path(&key), &data).await?;
+            this.write_metadata(&key, &headers)
+        }
+        .boxed()
+    }
+
+    fn put_file(
+        &self,
+        key: String,
+        path: PathBuf,
+        headers: ContentHeaders,
+    ) -> BoxFuture<'static, Result> {
+        let this = self.clone();
+        async move {
+            ide_ci::fs::tokio::copy(&path, this.object_path(&key)).await?;
+            this.write_metadata(&key, &headers)
+        }
+        .boxed()
+    }
+}
@@ -10,6 +10,8 @@ use pulldown_cmark::Tag::Heading;
 // ==============
 
 pub mod check;
+pub mod lint;
+pub mod model;
 
 
 
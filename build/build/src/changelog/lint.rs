@@ -0,0 +1,228 @@
+//! Checks of the changelog structure that are not enforced by the Markdown formatter.
+
+use crate::prelude::*;
+
+use crate::changelog::model::Document;
+use crate::changelog::model::Item;
+use crate::changelog::model::SectionKind;
+use crate::changelog::model::NEXT_RELEASE;
+
+use std::collections::BTreeSet;
+use strum::IntoEnumIterator;
+
+
+
+/// An issue found in the changelog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    /// The release header text.
+    pub release: String,
+    /// The section header text, if the problem is within a section.
+    pub section: Option<String>,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.section {
+            Some(section) => write!(f, "{} / {}: {}", self.release, section, self.message),
+            None => write!(f, "{}: {}", self.release, self.message),
+        }
+    }
+}
+
+/// Find all the problems in the changelog.
+///
+/// Links are checked in every release: each reference must be defined in the same section, and
+/// each definition must be used there. The structure is checked only for the [`NEXT_RELEASE`], as
+/// the past releases are not edited anymore.
+pub fn lint(document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    for (index, release) in document.releases.iter().enumerate() {
+        let mut report = |section: Option<&str>, message: String| {
+            problems.push(Problem {
+                release: release.title.clone(),
+                section: section.map(Into::into),
+                message,
+            })
+        };
+        for message in lint_links(&release.items) {
+            report(None, message);
+        }
+        for section in &release.sections {
+            for message in lint_links(&section.items) {
+                report(Some(&section.title), message);
+            }
+        }
+
+        if release.title != NEXT_RELEASE {
+            continue;
+        }
+        if index != 0 {
+            report(None, format!("The {NEXT_RELEASE} must be the first release."));
+        }
+        if release.items.iter().any(Item::is_entry) {
+            report(None, "Entries must be listed under one of the sections.".into());
+        }
+        let mut seen = BTreeSet::new();
+        for section in &release.sections {
+            if SectionKind::from_title(&section.title).is_none() {
+                let expected = SectionKind::iter().map(|kind| kind.title()).join(", ");
+                report(
+                    Some(&section.title),
+                    format!("Unknown section, expected one of: {expected}."),
+                );
+            }
+            if !seen.insert(section.title.as_str()) {
+                report(Some(&section.title), "The section appears more than once.".into());
+            }
+        }
+    }
+    if document.releases.iter().filter(|release| release.title == NEXT_RELEASE).count() > 1 {
+        problems.push(Problem {
+            release: NEXT_RELEASE.into(),
+            section: None,
+            message: format!("There is more than one {NEXT_RELEASE}."),
+        });
+    }
+    problems
+}
+
+/// Check that the links referenced by the entries match the definitions among the items.
+fn lint_links(items: &[Item]) -> Vec<String> {
+    let mut problems = vec![];
+    let mut used = BTreeSet::new();
+    for item in items {
+        if let Item::Entry(entry) = item {
+            used.extend(entry.references());
+        }
+    }
+    let mut defined = BTreeSet::new();
+    for item in items {
+        let Item::LinkDefinition(link) = item else { continue };
+        let label = &link.label;
+        if !defined.insert(label.clone()) {
+            problems.push(format!("The link [{label}] is defined more than once."));
+            continue;
+        }
+        if !used.contains(label) {
+            problems.push(format!("The link [{label}] is defined but not used."));
+        }
+        if link.url.is_empty() {
+            problems.push(format!("The link [{label}] has no URL."));
+        } else if label.parse::<u64>().is_ok() && !link.url.ends_with(&format!("/pull/{label}")) {
+            problems.push(format!("The link [{label}] should point to the pull request #{label}."));
+        }
+    }
+    for label in used.difference(&defined) {
+        problems.push(format!("The link [{label}] is used but not defined in this section."));
+    }
+    problems
+}
+
+/// Lint the changelog file, failing if any problems are found.
+#[context("Changelog {} did not pass the lint.", path.as_ref().display())]
+pub fn lint_file(path: impl AsRef<Path>) -> Result {
+    let document = Document::read(&path)?;
+    let problems = lint(&document);
+    for problem in &problems {
+        if ide_ci::actions::workflow::is_in_env() {
+            ide_ci::actions::workflow::error(problem.to_string());
+        } else {
+            error!("{problem}");
+        }
+    }
+    ensure!(problems.is_empty(), "Found {} problem(s).", problems.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(text: &str) -> Result<Vec<String>> {
+        let document: Document = text.parse()?;
+        Ok(lint(&document).iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn valid_changelog() -> Result {
+        let text = r#"# Next Release
+
+#### Enso IDE
+
+- [Drilldown for XML][10824]
+- [Fixed an issue][10857], [twice][10857].
+
+[10824]: https://github.com/enso-org/enso/pull/10824
+[10857]: https://github.com/enso-org/enso/pull/10857
+
+# Enso 2024.3
+
+#### Anonymous Data Collection
+
+Please note that this release collects anonymous usage data:
+
+- Session length.
+"#;
+        assert_eq!(problems(text)?, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn mismatched_links() -> Result {
+        let text = r#"# Next Release
+
+#### Enso IDE
+
+- [Drilldown for XML][10824]
+
+[10857]: https://github.com/enso-org/enso/pull/10857
+[10857]: https://github.com/enso-org/enso/pull/10857
+
+#### Enso Standard Library
+
+- [Added `Text.to_decimal`.][10874]
+
+[10874]: https://github.com/enso-org/enso/pull/10875
+[debug-shortcuts]:
+"#;
+        assert_eq!(problems(text)?, vec![
+            "Next Release / Enso IDE: The link [10857] is defined but not used.",
+            "Next Release / Enso IDE: The link [10857] is defined more than once.",
+            "Next Release / Enso IDE: The link [10824] is used but not defined in this section.",
+            "Next Release / Enso Standard Library: The link [10874] should point to the pull \
+            request #10874.",
+            "Next Release / Enso Standard Library: The link [debug-shortcuts] is defined but not \
+            used.",
+            "Next Release / Enso Standard Library: The link [debug-shortcuts] has no URL.",
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn misplaced_entries() -> Result {
+        let text = r#"# Enso 2024.3
+
+# Next Release
+
+- [Drilldown for XML][10824]
+
+[10824]: https://github.com/enso-org/enso/pull/10824
+
+#### Enso GUI
+
+#### Enso IDE
+
+#### Enso IDE
+"#;
+        assert_eq!(problems(text)?, vec![
+            "Next Release: The Next Release must be the first release.",
+            "Next Release: Entries must be listed under one of the sections.",
+            "Next Release / Enso GUI: Unknown section, expected one of: Enso IDE, Enso Standard \
+            Library, Enso Language & Runtime.",
+            "Next Release / Enso IDE: The section appears more than once.",
+        ]);
+        Ok(())
+    }
+}
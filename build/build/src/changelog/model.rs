@@ -0,0 +1,534 @@
+//! Typed model of the `CHANGELOG.md` file.
+//!
+//! The changelog consists of releases (level one headers), each divided into sections (level four
+//! headers). A section lists the entries, followed by the definitions of the links they reference:
+//!
+//! ```markdown
+//! # Next Release
+//!
+//! #### Enso IDE
+//!
+//! - [Table Editor Widget][10774] displayed in `Table.new` component.
+//!
+//! [10774]: https://github.com/enso-org/enso/pull/10774
+//! ```
+//!
+//! The model is lossless: rendering a parsed [`Document`] yields back the original text, so it can
+//! be used to edit the file without disturbing the parts that are not understood.
+
+use crate::prelude::*;
+
+use regex::Regex;
+use std::sync::LazyLock;
+use strum::IntoEnumIterator;
+
+
+
+/// Title of the release that collects the changes made since the last release.
+pub const NEXT_RELEASE: &str = "Next Release";
+
+/// The line width that the Markdown files are wrapped to, as configured for Prettier.
+pub const LINE_WIDTH: usize = 80;
+
+/// Matches a link reference definition, like `[10774]: https://github.com/enso-org/enso/pull/10774`.
+static LINK_DEFINITION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[([^\[\]]+)\]:\s*(.*)$").unwrap());
+
+/// Matches the label part of a full reference link, like the `[10774]` in `[Widget][10774]`.
+static LINK_REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\]\[([^\[\]]+)\]").unwrap());
+
+/// The sections that the [`NEXT_RELEASE`] entries may be listed under.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, strum::EnumIter)]
+pub enum SectionKind {
+    #[clap(alias = "gui")]
+    Ide,
+    #[clap(alias = "stdlib")]
+    StandardLibrary,
+    #[clap(alias = "runtime")]
+    LanguageAndRuntime,
+}
+
+impl SectionKind {
+    /// The section header text.
+    pub fn title(self) -> &'static str {
+        match self {
+            SectionKind::Ide => "Enso IDE",
+            SectionKind::StandardLibrary => "Enso Standard Library",
+            SectionKind::LanguageAndRuntime => "Enso Language & Runtime",
+        }
+    }
+
+    pub fn from_title(title: &str) -> Option<Self> {
+        Self::iter().find(|kind| kind.title() == title)
+    }
+}
+
+
+
+// ================
+// === Document ===
+// ================
+
+/// The whole changelog.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Document {
+    /// Contents before the first release.
+    pub preamble: Vec<Item>,
+    pub releases: Vec<Release>,
+}
+
+impl FromStr for Document {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut document = Document::default();
+        for line in text.lines() {
+            if let Some(title) = line.strip_prefix("# ") {
+                document.releases.push(Release::new(title));
+            } else if let Some(title) = line.strip_prefix("#### ") {
+                let release = document.releases.last_mut().with_context(|| {
+                    format!("The section header `{line}` does not belong to any release.")
+                })?;
+                release.sections.push(Section::new(title));
+            } else {
+                let items = match document.releases.last_mut() {
+                    Some(release) => release.items_mut(),
+                    None => &mut document.preamble,
+                };
+                push_line(items, line);
+            }
+        }
+        Ok(document)
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for item in &self.preamble {
+            write!(f, "{item}")?;
+        }
+        for release in &self.releases {
+            write!(f, "{release}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Document {
+    #[context("Failed to read the changelog from {}.", path.as_ref().display())]
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        ide_ci::fs::read_to_string(&path)?.parse()
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result {
+        ide_ci::fs::write(&path, self.to_string())
+    }
+
+    /// The release collecting the unreleased changes, if present.
+    pub fn next_release(&self) -> Option<&Release> {
+        self.releases.iter().find(|release| release.title == NEXT_RELEASE)
+    }
+
+    /// Add the entry to the given section of the [`NEXT_RELEASE`], creating it if needed.
+    ///
+    /// The entry is placed after the section's existing entries, and the link definition after
+    /// the existing definitions. An already present definition of the same link is reused.
+    pub fn add_entry(
+        &mut self,
+        section: SectionKind,
+        entry: Entry,
+        link: LinkDefinition,
+    ) -> Result {
+        if self.releases.first().map_or(true, |release| release.title != NEXT_RELEASE) {
+            let mut release = Release::new(NEXT_RELEASE);
+            release.items.push(Item::blank());
+            self.releases.insert(0, release);
+        }
+        let release = &mut self.releases[0];
+        let section = match release.sections.iter().position(|s| s.title == section.title()) {
+            Some(index) => &mut release.sections[index],
+            None => {
+                ensure_trailing_blank(release.items_mut());
+                let mut new_section = Section::new(section.title());
+                new_section.items.push(Item::blank());
+                release.sections.push(new_section);
+                release.sections.last_mut().unwrap()
+            }
+        };
+        section.add_entry(entry, link)
+    }
+}
+
+
+
+// ===============
+// === Release ===
+// ===============
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    /// The header text, e.g. `Enso 2024.3`.
+    pub title:    String,
+    /// Contents between the header and the first section.
+    pub items:    Vec<Item>,
+    pub sections: Vec<Section>,
+}
+
+impl Release {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), items: default(), sections: default() }
+    }
+
+    /// The items that the following lines of the file belong to.
+    fn items_mut(&mut self) -> &mut Vec<Item> {
+        match self.sections.last_mut() {
+            Some(section) => &mut section.items,
+            None => &mut self.items,
+        }
+    }
+}
+
+impl Display for Release {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# {}", self.title)?;
+        for item in &self.items {
+            write!(f, "{item}")?;
+        }
+        for section in &self.sections {
+            write!(f, "{section}")?;
+        }
+        Ok(())
+    }
+}
+
+
+
+// ===============
+// === Section ===
+// ===============
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// The header text, e.g. `Enso IDE`.
+    pub title: String,
+    pub items: Vec<Item>,
+}
+
+impl Section {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), items: default() }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Entry(entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    pub fn link_definitions(&self) -> impl Iterator<Item = &LinkDefinition> {
+        self.items.iter().filter_map(|item| match item {
+            Item::LinkDefinition(link) => Some(link),
+            _ => None,
+        })
+    }
+
+    fn add_entry(&mut self, entry: Entry, link: LinkDefinition) -> Result {
+        let existing = self.link_definitions().find(|l| l.label == link.label);
+        if let Some(existing) = existing {
+            ensure!(
+                existing.url == link.url,
+                "The link [{}] is already defined in the section {} as {}.",
+                link.label,
+                self.title,
+                existing.url
+            );
+        }
+        let is_defined = existing.is_some();
+
+        let entry_index = match self.items.iter().rposition(Item::is_entry) {
+            Some(last_entry) => last_entry + 1,
+            None => {
+                // The first entry is separated from the header by a blank line.
+                let mut index = self.items.iter().take_while(|item| item.is_blank()).count();
+                if index == 0 {
+                    self.items.insert(0, Item::blank());
+                    index = 1;
+                }
+                index
+            }
+        };
+        self.items.insert(entry_index, Item::Entry(entry));
+        ensure_blank_at(&mut self.items, entry_index + 1);
+
+        if is_defined {
+            return Ok(());
+        }
+        if let Some(last_link) = self.items.iter().rposition(Item::is_link_definition) {
+            self.items.insert(last_link + 1, Item::LinkDefinition(link));
+        } else {
+            // The definitions are separated from the entries by a blank line.
+            let last_entry = self.items.iter().rposition(Item::is_entry).unwrap_or_default();
+            self.items.insert(last_entry + 1, Item::LinkDefinition(link));
+            self.items.insert(last_entry + 1, Item::blank());
+            ensure_blank_at(&mut self.items, last_entry + 3);
+        }
+        Ok(())
+    }
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "#### {}", self.title)?;
+        for item in &self.items {
+            write!(f, "{item}")?;
+        }
+        Ok(())
+    }
+}
+
+
+
+// ============
+// === Item ===
+// ============
+
+/// A piece of the release or section contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Entry(Entry),
+    LinkDefinition(LinkDefinition),
+    /// Any other line, like a blank line or a paragraph of text.
+    Text(String),
+}
+
+impl Item {
+    pub fn blank() -> Self {
+        Item::Text(default())
+    }
+
+    pub fn is_blank(&self) -> bool {
+        matches!(self, Item::Text(text) if text.trim().is_empty())
+    }
+
+    pub fn is_entry(&self) -> bool {
+        matches!(self, Item::Entry(_))
+    }
+
+    pub fn is_link_definition(&self) -> bool {
+        matches!(self, Item::LinkDefinition(_))
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Item::Entry(entry) => write!(f, "{entry}"),
+            Item::LinkDefinition(link) => writeln!(f, "{link}"),
+            Item::Text(text) => writeln!(f, "{text}"),
+        }
+    }
+}
+
+/// Append the line to the items, either continuing the last entry or starting a new item.
+fn push_line(items: &mut Vec<Item>, line: &str) {
+    if line.starts_with("  ") {
+        if let Some(Item::Entry(entry)) = items.last_mut() {
+            entry.lines.push(line.into());
+            return;
+        }
+    }
+    if line.starts_with("- ") {
+        items.push(Item::Entry(Entry { lines: vec![line.into()] }));
+    } else if let Some(captures) = LINK_DEFINITION.captures(line) {
+        items.push(Item::LinkDefinition(LinkDefinition {
+            label: captures[1].into(),
+            url:   captures[2].into(),
+        }));
+    } else {
+        items.push(Item::Text(line.into()));
+    }
+}
+
+/// Make sure that the items end with a blank line, so a header can follow them.
+fn ensure_trailing_blank(items: &mut Vec<Item>) {
+    if items.last().map_or(false, |item| !item.is_blank()) {
+        items.push(Item::blank());
+    }
+}
+
+/// Make sure that there is a blank line at the given index, inserting one if needed.
+fn ensure_blank_at(items: &mut Vec<Item>, index: usize) {
+    if items.get(index).map_or(true, |item| !item.is_blank()) {
+        items.insert(index, Item::blank());
+    }
+}
+
+/// A list item describing a single change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The lines of the item, the first one starting with `- `, the following indented.
+    pub lines: Vec<String>,
+}
+
+impl Entry {
+    /// Create an entry linking the description to the given label, wrapped to [`LINE_WIDTH`].
+    pub fn new(description: &str, label: &str) -> Self {
+        let text = format!("[{}][{label}]", description.trim());
+        let mut lines = Vec::<String>::new();
+        for word in text.split_whitespace() {
+            match lines.last_mut() {
+                Some(line) if line.len() + 1 + word.len() <= LINE_WIDTH => {
+                    line.push(' ');
+                    line.push_str(word);
+                }
+                Some(_) => lines.push(format!("  {word}")),
+                None => lines.push(format!("- {word}")),
+            }
+        }
+        Self { lines }
+    }
+
+    /// The text of the entry, without the list marker and line breaks.
+    pub fn text(&self) -> String {
+        let text = self.lines.iter().map(|line| line.trim()).join(" ");
+        text.strip_prefix("- ").map(Into::into).unwrap_or(text)
+    }
+
+    /// Labels of the links referenced by the entry, in the order of appearance.
+    pub fn references(&self) -> Vec<String> {
+        LINK_REFERENCE.captures_iter(&self.text()).map(|c| c[1].to_string()).collect()
+    }
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A link reference definition, like `[10774]: https://github.com/enso-org/enso/pull/10774`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkDefinition {
+    pub label: String,
+    pub url:   String,
+}
+
+impl LinkDefinition {
+    /// The definition of a link to the pull request, labeled with its number.
+    pub fn pull_request(repo: &impl IsRepo, number: u64) -> Result<Self> {
+        let url = repo.url()?.join(&format!("pull/{number}"))?;
+        Ok(Self { label: number.to_string(), url: url.to_string() })
+    }
+}
+
+impl Display for LinkDefinition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.url.is_empty() {
+            write!(f, "[{}]:", self.label)
+        } else {
+            write!(f, "[{}]: {}", self.label, self.url)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ide_ci::github::Repo;
+
+    const CHANGELOG: &str = r#"# Next Release
+
+#### Enso IDE
+
+- [Table Editor Widget][10774] displayed in `Table.new` component.
+- [Fixed issue where switching edited widget with <kbd>tab</kbd> key did not
+  updated actual code][10857]
+
+[10774]: https://github.com/enso-org/enso/pull/10774
+[10857]: https://github.com/enso-org/enso/pull/10857
+
+# Enso 2024.3
+
+<br/>![Release Notes](/docs/assets/tags/release_notes.svg)
+
+#### Enso Language & Runtime
+
+- [Enforce conversion method return type][10468]
+
+[10468]: https://github.com/enso-org/enso/pull/10468
+"#;
+
+    #[test]
+    fn round_trip() -> Result {
+        let document: Document = CHANGELOG.parse()?;
+        assert_eq!(document.to_string(), CHANGELOG);
+        assert_eq!(document.releases.len(), 2);
+        let ide = &document.releases[0].sections[0];
+        assert_eq!(ide.title, SectionKind::Ide.title());
+        assert_eq!(ide.entries().count(), 2);
+        let entry = ide.entries().nth(1).unwrap();
+        assert_eq!(
+            entry.text(),
+            "[Fixed issue where switching edited widget with <kbd>tab</kbd> key did not \
+            updated actual code][10857]"
+        );
+        assert_eq!(entry.references(), vec!["10857"]);
+        Ok(())
+    }
+
+    #[test]
+    fn adding_to_existing_section() -> Result {
+        let mut document: Document = CHANGELOG.parse()?;
+        let repo = Repo::new("enso-org", "enso");
+        let entry = Entry::new("Added a new widget.", "11000");
+        document.add_entry(SectionKind::Ide, entry, LinkDefinition::pull_request(&repo, 11000)?)?;
+        let expected = CHANGELOG
+            .replacen(
+                "  updated actual code][10857]\n",
+                "  updated actual code][10857]\n- [Added a new widget.][11000]\n",
+                1,
+            )
+            .replacen(
+                "[10857]: https://github.com/enso-org/enso/pull/10857\n",
+                "[10857]: https://github.com/enso-org/enso/pull/10857\n\
+                [11000]: https://github.com/enso-org/enso/pull/11000\n",
+                1,
+            );
+        assert_eq!(document.to_string(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn adding_new_section_and_release() -> Result {
+        let released = CHANGELOG.split_once("# Enso 2024.3").unwrap().1;
+        let mut document: Document = format!("# Enso 2024.3{released}").parse()?;
+        let repo = Repo::new("enso-org", "enso");
+        let entry = Entry::new("Added `Text.to_decimal`.", "10874");
+        let link = LinkDefinition::pull_request(&repo, 10874)?;
+        document.add_entry(SectionKind::StandardLibrary, entry, link)?;
+        let expected = format!(
+            "# Next Release\n\n#### Enso Standard Library\n\n- [Added `Text.to_decimal`.][10874]\n\n\
+            [10874]: https://github.com/enso-org/enso/pull/10874\n\n# Enso 2024.3{released}"
+        );
+        assert_eq!(document.to_string(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn wrapping_entries() {
+        let description =
+            "Fixed issue where picking a variant in some ports disallowed changing it \
+            again and again.";
+        let entry = Entry::new(description, "10337");
+        assert_eq!(entry.lines, vec![
+            "- [Fixed issue where picking a variant in some ports disallowed changing it",
+            "  again and again.][10337]",
+        ]);
+        assert!(entry.lines.iter().all(|line| line.len() <= LINE_WIDTH));
+        assert_eq!(entry.text(), format!("[{description}][10337]"));
+    }
+}
@@ -37,6 +37,7 @@ use ide_ci::actions::workflow::definition::WorkflowDispatch;
 use ide_ci::actions::workflow::definition::WorkflowDispatchInput;
 use ide_ci::actions::workflow::definition::WorkflowDispatchInputType;
 use ide_ci::actions::workflow::definition::WorkflowToWrite;
+use ide_ci::actions::workflow::validation;
 use ide_ci::cache::goodie::graalvm;
 use strum::IntoEnumIterator;
 
@@ -190,6 +191,9 @@ pub enum CleaningCondition {
     /// Clean only if the clean build was requested by the actor.
     #[default]
     OnRequest,
+    /// Clean only if the pull request has the clean build label. For the workflows that cannot be
+    /// run manually, so there is no input to request the clean build with.
+    OnLabel,
 }
 
 impl Display for CleaningCondition {
@@ -206,6 +210,11 @@ impl Display for CleaningCondition {
                 crate::ci::labels::CLEAN_BUILD_REQUIRED,
                 crate::ci::inputs::CLEAN_BUILD_REQUIRED
             ),
+            Self::OnLabel => write!(
+                f,
+                "contains(github.event.pull_request.labels.*.name, '{}')",
+                crate::ci::labels::CLEAN_BUILD_REQUIRED
+            ),
         }
     }
 }
@@ -484,7 +493,10 @@ pub fn changelog() -> Result<Workflow> {
         Opened,
         Reopened,
     ]));
-    ret.add_job(RunStepsBuilder::new("changelog-check").build_job("Changelog", RunnerLabel::X64));
+    let job = RunStepsBuilder::new("changelog-check")
+        .cleaning(CleaningCondition::OnLabel)
+        .build_job("Changelog", RunnerLabel::X64);
+    ret.add_job(job);
     Ok(ret)
 }
 
@@ -567,7 +579,7 @@ pub fn release() -> Result<Workflow> {
         true,
         None::<String>,
     );
-    let workflow_dispatch = WorkflowDispatch::default().with_input("version", version_input);
+    let workflow_dispatch = manual_workflow_dispatch().with_input("version", version_input);
     let workflow_call = WorkflowCall::try_from(workflow_dispatch.clone())?;
     let on = Event {
         workflow_dispatch: Some(workflow_dispatch),
@@ -598,7 +610,7 @@ pub fn promote() -> Result<Workflow> {
         None::<String>,
     )?;
     let workflow_dispatch =
-        WorkflowDispatch::default().with_input(DESIGNATOR_INPUT_NAME, designator);
+        manual_workflow_dispatch().with_input(DESIGNATOR_INPUT_NAME, designator);
     let on = Event {
         workflow_call: Some(WorkflowCall::try_from(workflow_dispatch.clone())?),
         workflow_dispatch: Some(workflow_dispatch),
@@ -795,6 +807,7 @@ pub fn generate(
     let workflows = workflows
         .into_iter()
         .map(|(path, workflow)| WorkflowToWrite { workflow, path, source: module_path!().into() })
-        .collect();
+        .collect_vec();
+    validation::validate(&workflows)?;
     Ok(workflows)
 }
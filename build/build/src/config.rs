@@ -5,6 +5,13 @@ use ide_ci::programs;
 use semver::VersionReq;
 
 
+// ==============
+// === Export ===
+// ==============
+
+pub mod provision;
+
+
 
 /// Load the build configuration, based on the `build-config.yaml` and `.node-version` files in
 /// the repo root.
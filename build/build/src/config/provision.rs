@@ -0,0 +1,300 @@
+//! Installing the programs required by the [build configuration](crate::config) into the cache.
+//!
+//! The exact versions of the provisioned programs and the digests of their packages are pinned in
+//! the [`LOCKFILE_NAME`] file in the repository root. Entries missing from it are filled in on the
+//! first local use, so the updated lockfile should be reviewed and committed. On CI, missing
+//! entries are an error, so the packages are never trusted without review.
+
+use crate::prelude::*;
+
+use crate::config::check_program;
+use crate::config::Config;
+use crate::config::RecognizedProgram;
+
+use ide_ci::cache::goodie;
+use ide_ci::cache::goodie::cargo_watch::CargoWatch;
+use ide_ci::cache::goodie::node::Node;
+use ide_ci::cache::goodie::wasm_pack::WasmPack;
+use ide_ci::cache::pinned::PinnedFile;
+use ide_ci::cache::Cache;
+use semver::Op;
+use semver::VersionReq;
+
+
+
+/// Name of the lockfile, placed in the repository root.
+pub const LOCKFILE_NAME: &str = "build-config.lock.yaml";
+
+/// Header of the generated lockfile.
+const LOCKFILE_HEADER: &str = "# Versions and package digests of the programs provisioned by the \
+build script.\n# Generated when running the build script with `--provision-tools`, should be \
+committed.\n";
+
+
+
+// ================
+// === Lockfile ===
+// ================
+
+/// A package of a tool for a single platform.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub url:    Url,
+    /// Hex-encoded SHA-256 digest of the package.
+    pub sha256: String,
+}
+
+/// The pinned version of a tool.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedTool {
+    pub version:  Version,
+    /// Packages by the platform name, see [`platform`].
+    #[serde(default)]
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+
+/// Contents of the [`LOCKFILE_NAME`] file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// Tools by the names used in the `required-versions` of the build configuration.
+    #[serde(default)]
+    pub tools: BTreeMap<String, LockedTool>,
+}
+
+impl Lockfile {
+    /// Read the lockfile. Missing file yields an empty one.
+    #[context("Failed to load the lockfile '{}'.", path.as_ref().display())]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(default());
+        }
+        Ok(serde_yaml::from_str(&ide_ci::fs::read_to_string(path)?)?)
+    }
+
+    #[context("Failed to write the lockfile '{}'.", path.as_ref().display())]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result {
+        let contents = format!("{LOCKFILE_HEADER}{}", serde_yaml::to_string(self)?);
+        ide_ci::fs::write(path, contents)
+    }
+
+    /// Exact version of the tool to be provisioned.
+    ///
+    /// The locked version is used as long as it meets the requirement. Otherwise, the lowest
+    /// version meeting the requirement is locked instead.
+    pub fn version(&mut self, tool: &str, requirement: &VersionReq) -> Result<Version> {
+        if let Some(locked) = self.tools.get(tool) {
+            if requirement.matches(&locked.version) {
+                return Ok(locked.version.clone());
+            }
+            info!("Locked {tool} {} does not meet requirement {requirement}.", locked.version);
+        }
+        let version = lowest_matching(requirement)?;
+        let locked = LockedTool { version: version.clone(), packages: default() };
+        self.tools.insert(tool.to_owned(), locked);
+        Ok(version)
+    }
+
+    /// Digest of the tool package for the current platform, if it is locked with the given URL.
+    pub fn sha256(&self, tool: &str, url: &Url) -> Option<&str> {
+        let package = self.tools.get(tool)?.packages.get(&platform())?;
+        (&package.url == url).then_some(package.sha256.as_str())
+    }
+
+    /// Lock the tool package for the current platform.
+    pub fn record(&mut self, tool: &str, url: Url, sha256: String) -> Result {
+        let locked = self.tools.get_mut(tool).with_context(|| format!("{tool} is not locked."))?;
+        locked.packages.insert(platform(), LockedPackage { url, sha256 });
+        Ok(())
+    }
+}
+
+/// Name of the current platform, used as the key of the locked packages.
+pub fn platform() -> String {
+    format!("{TARGET_OS}-{TARGET_ARCH}")
+}
+
+/// The lowest version meeting the requirement, based on its comparators.
+#[context("Cannot choose an exact version for requirement {requirement}.")]
+pub fn lowest_matching(requirement: &VersionReq) -> Result<Version> {
+    requirement
+        .comparators
+        .iter()
+        .filter(|comparator| {
+            matches!(comparator.op, Op::Exact | Op::Caret | Op::Tilde | Op::GreaterEq)
+        })
+        .map(|comparator| Version {
+            major: comparator.major,
+            minor: comparator.minor.unwrap_or(0),
+            patch: comparator.patch.unwrap_or(0),
+            pre:   comparator.pre.clone(),
+            build: default(),
+        })
+        .find(|version| requirement.matches(version))
+        .context("The requirement has no lower bound.")
+}
+
+
+
+// ============
+// === Tool ===
+// ============
+
+/// A program that can be provisioned.
+#[derive(Clone, Debug)]
+pub enum Tool {
+    CargoWatch(CargoWatch),
+    Node(Node),
+    WasmPack(WasmPack),
+}
+
+impl Tool {
+    /// The tool for the current platform, if the program can be provisioned.
+    pub fn new(program: &RecognizedProgram, version: Version) -> Option<Self> {
+        let (os, arch) = (TARGET_OS, TARGET_ARCH);
+        let RecognizedProgram::Other(name) = program;
+        match name.as_str() {
+            "cargo-watch" => Some(Tool::CargoWatch(CargoWatch { version, os, arch })),
+            "node" => Some(Tool::Node(Node { version, os, arch })),
+            "wasm-pack" => Some(Tool::WasmPack(WasmPack { version, os, arch })),
+            _ => None,
+        }
+    }
+
+    pub fn url(&self) -> Result<Url> {
+        match self {
+            Tool::CargoWatch(tool) => tool.url(),
+            Tool::Node(tool) => tool.url(),
+            Tool::WasmPack(tool) => tool.url(),
+        }
+    }
+
+    /// Prepend the binaries of the package to `PATH`, so they are used by the child processes.
+    pub fn activate(&self, package: &Path) -> Result {
+        match self {
+            Tool::CargoWatch(tool) => tool.activate(package),
+            Tool::Node(tool) => tool.activate(package),
+            Tool::WasmPack(tool) => tool.activate(package),
+        }
+    }
+}
+
+
+
+// =================
+// === Provision ===
+// =================
+
+/// Install the required programs that are missing or in wrong versions, and activate them.
+///
+/// The packages are taken from the `mirror` directory if they are present there, and downloaded
+/// otherwise. Fails if any requirement is still not fulfilled afterwards, or if a package is not
+/// locked when running on CI.
+pub async fn provision(
+    config: &Config,
+    repo_root: &Path,
+    cache: &Cache,
+    mirror: Option<PathBuf>,
+) -> Result {
+    let lockfile_path = repo_root.join(LOCKFILE_NAME);
+    let mut lockfile = Lockfile::load(&lockfile_path)?;
+    let initial_lockfile = lockfile.clone();
+    let requirements =
+        config.required_versions.iter().sorted_by_key(|(program, _)| program.to_string());
+    for (program, requirement) in requirements {
+        if check_program(program, requirement).await.is_ok() {
+            continue;
+        }
+        let name = program.to_string();
+        let version = lockfile.version(&name, requirement)?;
+        let Some(tool) = Tool::new(program, version.clone()) else {
+            warn!("Cannot provision {name}, it must be installed manually.");
+            continue;
+        };
+        info!("Provisioning {name} {version}.");
+        let url = tool.url()?;
+        let sha256 = match lockfile.sha256(&name, &url) {
+            Some(sha256) => sha256.to_owned(),
+            None => {
+                ensure!(
+                    !ide_ci::actions::workflow::is_in_env(),
+                    "The digest of {url} is not locked in {LOCKFILE_NAME}. Run the build script \
+                    with `--provision-tools` locally and commit the updated lockfile."
+                );
+                let package = PinnedFile::locate(url.clone(), mirror.clone(), cache).await?;
+                let sha256 = ide_ci::io::Sha256Digest::of_file_async(&package).await?.to_hex();
+                warn!("Locking {url} with previously unknown digest {sha256}.");
+                lockfile.record(&name, url.clone(), sha256.clone())?;
+                sha256
+            }
+        };
+        let package = goodie::download_pinned(url, sha256, mirror.clone(), cache).await?;
+        tool.activate(&package)?;
+    }
+    if lockfile != initial_lockfile {
+        lockfile.save(&lockfile_path)?;
+    }
+    config.check_programs().await
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_versions() -> Result {
+        let lowest = |requirement: &str| lowest_matching(&VersionReq::parse(requirement)?);
+        assert_eq!(lowest("^8.1.1")?, Version::new(8, 1, 1));
+        assert_eq!(lowest("18.14")?, Version::new(18, 14, 0));
+        assert_eq!(lowest(">=1.2, <2")?, Version::new(1, 2, 0));
+        assert!(lowest("<2").is_err());
+
+        let mut lockfile = Lockfile::default();
+        let requirement = VersionReq::parse("^0.12.1")?;
+        assert_eq!(lockfile.version("wasm-pack", &requirement)?, Version::new(0, 12, 1));
+        let url = Url::parse("https://example.com/wasm-pack.tar.gz")?;
+        lockfile.record("wasm-pack", url.clone(), "00".repeat(32))?;
+        assert_eq!(lockfile.sha256("wasm-pack", &url), Some("00".repeat(32).as_str()));
+
+        let lockfile: Lockfile = serde_yaml::from_str(&serde_yaml::to_string(&lockfile)?)?;
+        let mut bumped = lockfile.clone();
+        bumped.tools.get_mut("wasm-pack").unwrap().version = Version::new(0, 12, 5);
+        assert_eq!(bumped.clone().version("wasm-pack", &requirement)?, Version::new(0, 12, 5));
+        let requirement = VersionReq::parse("^0.13")?;
+        assert_eq!(bumped.version("wasm-pack", &requirement)?, Version::new(0, 13, 0));
+        assert_eq!(bumped.sha256("wasm-pack", &url), None);
+        Ok(())
+    }
+
+    #[test]
+    fn repo_lockfile() -> Result {
+        let config = crate::config::load()?;
+        let lockfile = include_str!("../../../../build-config.lock.yaml");
+        let lockfile = serde_yaml::from_str::<Lockfile>(lockfile)?;
+        for (program, requirement) in &config.required_versions {
+            let name = program.to_string();
+            let mut updated = lockfile.clone();
+            let version = updated.version(&name, requirement)?;
+            if Tool::new(program, version).is_some() {
+                assert_eq!(
+                    updated, lockfile,
+                    "{name} is not locked in a version meeting {requirement}."
+                );
+            }
+        }
+
+        // The Node requirement comes from `.node-version` rather than `build-config.yaml`.
+        let node = RecognizedProgram::Other("node".into());
+        let version = lockfile.tools["node"].version.clone();
+        assert_eq!(version.to_string(), include_str!("../../../../.node-version").trim());
+        assert!(config.required_versions[&node].matches(&version));
+        assert!(matches!(Tool::new(&node, version), Some(Tool::Node(_))));
+        Ok(())
+    }
+}
@@ -9,12 +9,14 @@ use crate::get_flatbuffers_version;
 use crate::get_graal_packages_version;
 use crate::get_graal_version;
 use crate::paths::generated;
+use crate::version::Versions;
 
 use artifact::IsArtifact;
 use bundle::IsBundle;
 use ide_ci::cache::goodie::graalvm::Edition;
 use ide_ci::future::AsyncPolicy;
 use ide_ci::github::Repo;
+use ide_ci::task;
 use package::IsPackage;
 
 
@@ -36,11 +38,14 @@ pub use context::RunContext;
 /// Whether pure Enso tests should be run in parallel.
 const PARALLEL_ENSO_TESTS: AsyncPolicy = AsyncPolicy::Sequential;
 
-/// Download template projects from GitHub.
+/// Download template projects from GitHub, or from [`env::ENSO_PROJECT_TEMPLATES_URL`] if set.
 pub async fn download_project_templates(client: reqwest::Client, enso_root: PathBuf) -> Result {
     // Download Project Template Files
     let output_base = enso_root.join("lib/scala/pkg/src/main/resources/");
-    let url_base = Url::parse("https://github.com/enso-org/project-templates/raw/main/")?;
+    let url_base = match env::ENSO_PROJECT_TEMPLATES_URL.get() {
+        Ok(url) => url,
+        Err(_) => Url::parse("https://github.com/enso-org/project-templates/raw/main/")?,
+    };
     let to_handle = [
         ("Orders", vec!["data/store_data.xlsx", "src/Main.enso"]),
         ("Restaurants", vec!["data/la_districts.csv", "data/restaurants.csv", "src/Main.enso"]),
@@ -358,3 +363,25 @@ pub async fn deduce_flatbuffers(build_sbt: &generated::RepoRootBuildSbt) -> Resu
     let build_sbt_content = ide_ci::fs::tokio::read_to_string(build_sbt).await?;
     get_flatbuffers_version(&build_sbt_content)
 }
+
+/// What the Engine builds depend on, used to skip the builds that are up to date.
+///
+/// The GraalVM version is not listed separately, as it is defined in the `build.sbt`.
+pub fn build_inputs(repo_root: &Path, versions: &Versions) -> Vec<task::Input> {
+    let sources = task::Input::files(repo_root, [
+        "build.sbt",
+        "project/**/*",
+        "engine/**/*",
+        "lib/java/**/*",
+        "lib/scala/**/*",
+        "lib/rust/parser/**/*",
+        "distribution/**/*",
+        "std-bits/**/*",
+    ])
+    .excluding(["**/target", "**/node_modules"]);
+    vec![
+        sources,
+        task::Input::value("version", &versions.version),
+        task::Input::env(&env::GRAAL_EDITION),
+    ]
+}
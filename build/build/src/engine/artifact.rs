@@ -37,7 +37,15 @@ pub trait IsArtifact: AsRef<Path> + Send + Sync {
     fn upload_as_asset(&self, release: release::Handle) -> BoxFuture<'static, Result<Asset>> {
         let path = self.as_ref().to_path_buf();
         let name = self.asset_file_stem();
-        async move { release.upload_compressed_dir_as(path, name?).await }.boxed()
+        async move {
+            let temp = tempfile::tempdir()?;
+            let archive_path =
+                temp.path().join(name?).with_appended_extension(release::archive_extension());
+            let mode = ide_ci::archive::Mode::deterministic()?;
+            ide_ci::archive::create_with_mode(&archive_path, [&path], mode).await?;
+            crate::release::checksum::upload_asset(&release, &archive_path).await
+        }
+        .boxed()
     }
 
     fn as_dyn_artifact(&self) -> &dyn IsArtifact;
@@ -574,8 +574,11 @@ impl RunContext {
                         bundle.upload_as_asset(release.clone()).await?;
                     }
                     if TARGET_OS == OS::Linux {
-                        release.upload_asset_file(self.paths.manifest_file()).await?;
-                        release.upload_asset_file(self.paths.launcher_manifest_file()).await?;
+                        let manifests =
+                            [self.paths.manifest_file(), self.paths.launcher_manifest_file()];
+                        for manifest in manifests {
+                            crate::release::checksum::upload_asset(&release, manifest).await?;
+                        }
                     }
                 }
             },
@@ -665,3 +668,86 @@ pub async fn runner_sanity_test(
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::BuildConfigurationFlags;
+    use crate::engine::StandardLibraryTestsSelection;
+    use crate::version::Versions;
+    use ide_ci::cache::Cache;
+    use ide_ci::program::command::executor;
+    use ide_ci::program::command::executor::Fixture;
+    use ide_ci::program::command::executor::Placeholders;
+    use ide_ci::program::command::executor::Replayer;
+    use wiremock::matchers::any;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+
+    const BUILD_SBT: &str = r#"
+val graalVersion              = "21.0.2"
+val graalMavenPackagesVersion = "24.0.0"
+val flatbuffersVersion        = "24.3.25"
+"#;
+
+    // The fixture uses the Unix shell to run the tests.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn build_and_test_standard_library() -> Result {
+        // Run the build as it would be run locally, without the Cloud tests.
+        ide_ci::actions::env::CI.remove();
+        ide_ci::actions::env::GITHUB_ACTIONS.remove();
+        crate::cloud_tests::env::ci_config::ENSO_CLOUD_TEST_ACCOUNT_USERNAME.remove();
+        env::GRAAL_EDITION.remove();
+
+        let root = tempfile::tempdir()?;
+        ide_ci::fs::write(root.path().join("build.sbt"), BUILD_SBT)?;
+        let templates = MockServer::start().await;
+        Mock::given(any()).respond_with(ResponseTemplate::new(200)).mount(&templates).await;
+        env::ENSO_PROJECT_TEMPLATES_URL.set(&Url::parse(&format!("{}/", templates.uri()))?)?;
+
+        let triple = TargetTriple::new(Versions::new(Version::new(0, 0, 0)));
+        let repo_root = crate::paths::new_repo_root(root.path(), &triple);
+        let cache = tempfile::tempdir()?;
+        let inner = crate::project::Context {
+            octocrab: default(),
+            cache: Cache::new(cache.path()).await?,
+            fingerprints: ide_ci::task::Store::new(repo_root.target.fingerprints.to_path_buf()),
+            repo_root,
+        };
+        let config = BuildConfigurationFlags {
+            test_standard_library: Some(StandardLibraryTestsSelection::Selected(vec![
+                "Base_Tests".into(),
+            ])),
+            ..default()
+        };
+        let context = RunContext::new(inner, config, triple, None)?;
+
+        let engine_package =
+            &context.repo_root.built_distribution.enso_engine_triple.engine_package;
+        let tasks = ["engine-runner/assembly", "buildEngineDistribution"];
+        let tasks = if crate::ci::big_memory_machine() {
+            Sbt::concurrent_tasks(tasks)
+        } else {
+            Sbt::sequential_tasks(tasks)
+        };
+        let placeholders = Placeholders::default()
+            .with("$ROOT", root.path())
+            .with("$ENGINE", engine_package.as_path())
+            .with("$TASKS", tasks);
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("engine-build.yaml");
+        let replayer = Arc::new(Replayer::new(Fixture::read(fixture)?, placeholders));
+        let artifacts = executor::scope(replayer.clone(), context.build()).await?;
+        replayer.finish()?;
+
+        let built_engine = artifacts.engine_package.map(|package| package.to_path_buf());
+        assert_eq!(built_engine, Some(engine_package.to_path_buf()));
+        assert!(artifacts.project_manager_package.is_none());
+        assert!(artifacts.launcher_bundle.is_none());
+        Ok(())
+    }
+}
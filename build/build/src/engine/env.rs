@@ -16,4 +16,8 @@ define_env_var! {
 
     /// GraalVM edition. Either Community or Enterprise.
     GRAAL_EDITION, graalvm::Edition;
+
+    /// Base URL of the project templates repository to download from, instead of GitHub. It
+    /// needs to end with a `/`.
+    ENSO_PROJECT_TEMPLATES_URL, url::Url;
 }
@@ -7,16 +7,12 @@ use crate::paths::ENSO_ENABLE_ASSERTIONS;
 use crate::paths::ENSO_META_TEST_ARGS;
 use crate::paths::ENSO_META_TEST_COMMAND;
 use crate::paths::ENSO_TEST_ANSI_COLORS;
-use crate::postgres;
-use crate::postgres::EndpointConfiguration as PostgresEndpointConfiguration;
-use crate::postgres::Postgresql;
-use crate::sqlserver;
-use crate::sqlserver::EndpointConfiguration as SQLServerEndpointConfiguration;
-use crate::sqlserver::SQLServer;
+use crate::test_service;
+use crate::test_service::postgres::Postgresql;
+use crate::test_service::sqlserver::SQLServer;
 
 use ide_ci::env::accessor::TypedVariable;
 use ide_ci::future::AsyncPolicy;
-use ide_ci::programs::docker::ContainerId;
 
 
 
@@ -188,48 +184,28 @@ impl BuiltEnso {
 
         let _postgres = match TARGET_OS {
             OS::Linux if may_need_postgres => {
-                let runner_context_string = crate::env::ENSO_RUNNER_CONTAINER_NAME
-                    .get_raw()
-                    .or_else(|_| ide_ci::actions::env::RUNNER_NAME.get())
-                    .unwrap_or_else(|_| Uuid::new_v4().to_string());
-                // GH-hosted runners are named like "GitHub Actions 10". Spaces are not allowed in
-                // the container name.
-                let container_name =
-                    format!("postgres-for-{runner_context_string}").replace(' ', "_");
-                let config = postgres::Configuration {
-                    postgres_container: ContainerId(container_name),
-                    database_name:      "enso_test_db".to_string(),
-                    user:               "enso_test_user".to_string(),
-                    password:           "enso_test_password".to_string(),
-                    endpoint:           PostgresEndpointConfiguration::deduce()?,
-                    version:            "latest".to_string(),
+                let postgres = Postgresql {
+                    database_name: "enso_test_db".to_string(),
+                    user:          "enso_test_user".to_string(),
+                    password:      "enso_test_password".to_string(),
+                    version:       "latest".to_string(),
                 };
-                let postgres = Postgresql::start(config).await?;
-                Some(postgres)
+                let mode = test_service::Mode::deduce(&postgres)?;
+                Some(test_service::start(postgres, mode).await?)
             }
             _ => None,
         };
 
         let _sqlserver = match TARGET_OS {
             OS::Linux if may_need_sqlserver => {
-                let runner_context_string = crate::env::ENSO_RUNNER_CONTAINER_NAME
-                    .get_raw()
-                    .or_else(|_| ide_ci::actions::env::RUNNER_NAME.get())
-                    .unwrap_or_else(|_| Uuid::new_v4().to_string());
-                // GH-hosted runners are named like "GitHub Actions 10". Spaces are not allowed in
-                // the container name.
-                let container_name =
-                    format!("sqlserver-for-{runner_context_string}").replace(' ', "_");
-                let config = sqlserver::Configuration {
-                    sqlserver_container: ContainerId(container_name),
-                    database_name:       "tempdb".to_string(),
-                    user:                "sa".to_string(),
-                    password:            "enso_test_password_<YourStrong@Passw0rd>".to_string(),
-                    endpoint:            SQLServerEndpointConfiguration::deduce()?,
-                    version:             "latest".to_string(),
+                let sqlserver = SQLServer {
+                    database_name: "tempdb".to_string(),
+                    user:          "sa".to_string(),
+                    password:      "enso_test_password_<YourStrong@Passw0rd>".to_string(),
+                    version:       "latest".to_string(),
                 };
-                let sqlserver = SQLServer::start(config).await?;
-                Some(sqlserver)
+                let mode = test_service::Mode::deduce(&sqlserver)?;
+                Some(test_service::start(sqlserver, mode).await?)
             }
             _ => None,
         };
@@ -16,4 +16,9 @@ define_env_var! {
 
     /// Static token for admin requests on our Lambdas.
     ENSO_ADMIN_TOKEN, String;
+
+    /// Path to the file with the Ed25519 key used to sign the release checksums.
+    ///
+    /// See [`crate::release::signature::SecretKey`] for the expected format.
+    ENSO_RELEASE_SIGNING_KEY, PathBuf;
 }
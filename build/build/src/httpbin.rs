@@ -1,7 +1,7 @@
 use crate::prelude::*;
 
 use ide_ci::extensions::child::ChildExt;
-use tokio::process::Child;
+use ide_ci::program::command::Child;
 
 
 
@@ -342,6 +342,20 @@ impl IdeDesktop {
             };
             enso_install_config::bundler::bundle(config).await?;
             store_sha256_checksum(&ide_artifacts.image, &ide_artifacts.image_checksum)?;
+        } else if TARGET_OS == OS::Linux {
+            // On Linux our user-level installer (bundling the uninstaller) is built alongside the
+            // AppImage.
+            let version = ENSO_VERSION.get()?;
+            let installer = format!("enso-linux-installer-{TARGET_ARCH}-{version}");
+            let config = enso_install_config::bundler::Config {
+                electron_builder_config:  electron_config,
+                unpacked_electron_bundle: unpacked_dir(output_path, target_os, TARGET_ARCH),
+                repo_root:                self.repo_root.to_path_buf(),
+                output_file:              output_path.join(installer),
+                intermediate_dir:         output_path.to_path_buf(),
+                certificate:              None,
+            };
+            enso_install_config::bundler::bundle(config).await?;
         }
         Ok(())
     }
@@ -31,7 +31,6 @@ pub mod httpbin;
 pub mod ide;
 pub mod libraries_tests;
 pub mod paths;
-pub mod postgres;
 pub mod programs;
 pub mod project;
 pub mod project_manager;
@@ -39,7 +38,7 @@ pub mod release;
 pub mod repo;
 pub mod rust;
 pub mod source;
-pub mod sqlserver;
+pub mod test_service;
 pub mod version;
 pub mod web;
 
@@ -19,6 +19,8 @@ pub mod snowflake {
             ENSO_SNOWFLAKE_DATABASE, String;
             ENSO_SNOWFLAKE_SCHEMA, String;
             ENSO_SNOWFLAKE_WAREHOUSE, String;
+            /// Server to connect to, with the port, instead of the one derived from the account.
+            ENSO_SNOWFLAKE_HOST, String;
         }
     }
 }
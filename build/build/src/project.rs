@@ -15,7 +15,9 @@ use ide_ci::actions::artifacts;
 use ide_ci::cache;
 use ide_ci::cache::Cache;
 use ide_ci::ok_ready_boxed;
+use ide_ci::program::command::Child;
 use ide_ci::programs::git;
+use ide_ci::task;
 use octocrab::models::repos::Asset;
 
 
@@ -90,6 +92,9 @@ pub struct Context {
     /// as well.
     #[derive_where(skip)]
     pub repo_root: crate::paths::generated::RepoRoot,
+
+    /// Records of the completed builds, used to skip the ones that are up to date.
+    pub fingerprints: task::Store,
 }
 
 impl Context {
@@ -100,6 +105,26 @@ impl Context {
     }
 }
 
+/// A target being produced by a [step](task::Step) of the build graph.
+#[derive_where(Debug)]
+pub struct TargetStep<Artifact> {
+    pub step:     task::Step,
+    /// Yields the artifact once the step has been run by the graph: either the one just produced,
+    /// or the one left in the destination by an earlier run, if the step was up to date.
+    #[derive_where(skip)]
+    pub artifact: BoxFuture<'static, Result<Artifact>>,
+}
+
+impl<Artifact: Send + 'static> TargetStep<Artifact> {
+    /// Run the step alone and get the artifact.
+    pub async fn run(self, fingerprints: task::Store) -> Result<Artifact> {
+        let mut graph = task::Graph::new(fingerprints);
+        graph.add(self.step);
+        graph.run().await?;
+        self.artifact.await
+    }
+}
+
 /// Build targets, like GUI or Project Manager.
 ///
 /// Built target generates artifacts that can be stored as a release asset or CI run artifacts.
@@ -160,7 +185,32 @@ pub trait IsTarget: Clone + Debug + Sized + Send + Sync + 'static {
         .boxed()
     }
 
+    /// Describe producing the target artifacts as a step of the build graph.
+    ///
+    /// The step is named after the [artifact](IsTarget::artifact_name). Fetching the artifacts from
+    /// an external source declares no inputs, so it is always run.
+    fn get_step(
+        &self,
+        context: Context,
+        job: GetTargetJob<Self>,
+    ) -> BoxFuture<'static, Result<TargetStep<Self::Artifact>>> {
+        let GetTargetJob { destination, inner } = job;
+        match inner {
+            Source::BuildLocally(local_build) =>
+                self.build_step(context, WithDestination::new(local_build, destination)),
+            Source::External(external) => {
+                let job = WithDestination::new(external, destination.clone());
+                let fetch = self.get_external(context, job);
+                let (step, artifact) = artifact_step(self.clone(), fetch, destination);
+                ok_ready_boxed(TargetStep { step, artifact })
+            }
+        }
+    }
+
     /// Produce an artifact from build inputs.
+    ///
+    /// The build is skipped if the destination already contains the artifact built from the same
+    /// [inputs](IsTarget::build_inputs).
     fn build(
         &self,
         context: Context,
@@ -168,10 +218,11 @@ pub trait IsTarget: Clone + Debug + Sized + Send + Sync + 'static {
     ) -> BoxFuture<'static, Result<Self::Artifact>> {
         let span = debug_span!("Building.", ?self, ?context, ?job).entered();
         let upload_artifacts = job.should_upload_artifact;
-        let artifact_fut = self.build_internal(context, job.map(|job| job.input));
+        let fingerprints = context.fingerprints.clone();
+        let step = self.build_step(context, job);
         let this = self.clone();
         async move {
-            let artifact = artifact_fut.await.context(format!("Failed to build {this:?}."))?;
+            let artifact = step.await?.run(fingerprints).await?;
             // We upload only built artifacts. There would be no point in uploading something that
             // we've just downloaded. That's why the uploading code is here.
             if upload_artifacts {
@@ -183,6 +234,31 @@ pub trait IsTarget: Clone + Debug + Sized + Send + Sync + 'static {
         .boxed()
     }
 
+    /// Describe building the target as a step of the build graph, with the declared
+    /// [inputs](IsTarget::build_inputs) and the destination as the output.
+    fn build_step(
+        &self,
+        context: Context,
+        job: BuildTargetJob<Self>,
+    ) -> BoxFuture<'static, Result<TargetStep<Self::Artifact>>> {
+        let destination = job.destination.clone();
+        let inputs = self.build_inputs(&context, &job.input);
+        let this = self.clone();
+        let build = self
+            .build_internal(context, job.map(|job| job.input))
+            .map(move |result| result.with_context(|| format!("Failed to build {this:?}.")));
+        let this = self.clone();
+        async move {
+            let mut inputs = inputs.await?;
+            if !inputs.is_empty() {
+                inputs.push(task::Input::value("destination", destination.display()));
+            }
+            let (step, artifact) = artifact_step(this, build, destination);
+            Ok(TargetStep { step: step.with_inputs(inputs), artifact })
+        }
+        .boxed()
+    }
+
     fn perhaps_upload_artifact(&self, artifact: &Self::Artifact) -> BoxFuture<'static, Result> {
         let should_upload_artifact = ide_ci::actions::workflow::is_in_env();
         trace!("Got target {:?}, should it be uploaded? {}", self, should_upload_artifact);
@@ -193,6 +269,17 @@ pub trait IsTarget: Clone + Debug + Sized + Send + Sync + 'static {
         }
     }
 
+    /// What the built artifact depends on, besides the target itself.
+    ///
+    /// If no inputs are declared, the target is always built.
+    fn build_inputs(
+        &self,
+        _context: &Context,
+        _input: &Self::BuildInput,
+    ) -> BoxFuture<'static, Result<Vec<task::Input>>> {
+        ok_ready_boxed(default())
+    }
+
     /// Produce an artifact from build inputs.
     fn build_internal(
         &self,
@@ -215,7 +302,7 @@ pub trait IsTarget: Clone + Debug + Sized + Send + Sync + 'static {
         ci_run: CiRunSource,
         output_path: impl AsRef<Path> + Send + Sync + 'static,
     ) -> BoxFuture<'static, Result<Self::Artifact>> {
-        let Context { octocrab, cache, repo_root: _ } = context;
+        let Context { octocrab, cache, .. } = context;
         let CiRunSource { run_id, artifact_name, repository } = ci_run;
         let repository = repository.handle(&octocrab);
         let span = info_span!("Downloading CI Artifact.", %artifact_name, %repository, target = output_path.as_ref().as_str());
@@ -260,7 +347,7 @@ pub trait IsTarget: Clone + Debug + Sized + Send + Sync + 'static {
         source: ReleaseSource,
         destination: PathBuf,
     ) -> BoxFuture<'static, Result<Self::Artifact>> {
-        let Context { octocrab, cache, repo_root: _ } = context;
+        let Context { octocrab, cache, .. } = context;
         let span = info_span!("Downloading built target from a release asset.",
             asset_id = source.asset_id.0,
             repo = %source.repository);
@@ -283,6 +370,31 @@ pub trait IsTarget: Clone + Debug + Sized + Send + Sync + 'static {
     }
 }
 
+/// A step running the job that places the target artifact in the destination.
+///
+/// If the step is not run, as it is up to date, the artifact is taken from the destination.
+fn artifact_step<Target: IsTarget>(
+    target: Target,
+    job: impl Future<Output = Result<Target::Artifact>> + Send + 'static,
+    destination: PathBuf,
+) -> (task::Step, BoxFuture<'static, Result<Target::Artifact>>) {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let action = async move {
+        // The receiver is dropped only when nobody waits for the artifact anymore.
+        let _ = sender.send(job.await?);
+        Ok(())
+    };
+    let step = task::Step::new(target.artifact_name(), action).with_output(&destination);
+    let artifact = async move {
+        match receiver.await {
+            Ok(artifact) => Ok(artifact),
+            Err(_) => target.adapt_artifact(destination).await,
+        }
+    }
+    .boxed();
+    (step, artifact)
+}
+
 #[derive(Debug)]
 pub enum PerhapsWatched<T: IsWatchable> {
     Watched(T::Watcher),
@@ -308,7 +420,7 @@ impl<T: IsWatchable> PerhapsWatched<T> {
 }
 
 pub trait ProcessWrapper {
-    fn inner(&mut self) -> &mut tokio::process::Child;
+    fn inner(&mut self) -> &mut Child;
 
     fn wait_ok(&mut self) -> BoxFuture<Result> {
         ide_ci::extensions::child::ChildExt::wait_ok(self.inner()).boxed()
@@ -319,8 +431,8 @@ pub trait ProcessWrapper {
     }
 }
 
-impl ProcessWrapper for tokio::process::Child {
-    fn inner(&mut self) -> &mut tokio::process::Child {
+impl ProcessWrapper for Child {
+    fn inner(&mut self) -> &mut Child {
         self
     }
 }
@@ -338,7 +450,7 @@ pub struct Watcher<Target: IsWatchable, Proc> {
 }
 
 impl<Target: IsWatchable, Proc: ProcessWrapper> ProcessWrapper for Watcher<Target, Proc> {
-    fn inner(&mut self) -> &mut tokio::process::Child {
+    fn inner(&mut self) -> &mut Child {
         self.watch_process.inner()
     }
 }
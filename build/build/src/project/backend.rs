@@ -9,6 +9,7 @@ use crate::source::WithDestination;
 use crate::version::Versions;
 
 use ide_ci::archive::is_archive_name;
+use ide_ci::task;
 use octocrab::models::repos::Asset;
 
 
@@ -136,6 +137,20 @@ impl IsTarget for Backend {
         .boxed()
     }
 
+    fn build_inputs(
+        &self,
+        context: &Context,
+        input: &Self::BuildInput,
+    ) -> BoxFuture<'static, Result<Vec<task::Input>>> {
+        // An externally provided runtime cannot be fingerprinted, so the bundle is always built.
+        let inputs = if input.external_runtime.is_some() {
+            default()
+        } else {
+            crate::engine::build_inputs(&context.repo_root, &input.versions)
+        };
+        ready(Ok(inputs)).boxed()
+    }
+
     fn build_internal(
         &self,
         context: Context,
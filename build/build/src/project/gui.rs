@@ -13,6 +13,9 @@ use crate::project::IsTarget;
 use crate::source::WithDestination;
 
 use ide_ci::ok_ready_boxed;
+use ide_ci::programs::Node;
+use ide_ci::programs::Pnpm;
+use ide_ci::task;
 
 // ================
 // === Artifact ===
@@ -56,6 +59,36 @@ impl IsTarget for Gui {
         ok_ready_boxed(Artifact::new(path))
     }
 
+    fn build_inputs(
+        &self,
+        context: &Context,
+        _input: &Self::BuildInput,
+    ) -> BoxFuture<'static, Result<Vec<task::Input>>> {
+        use crate::ide::web::env::*;
+        let repo_root = context.repo_root.to_path_buf();
+        async move {
+            let sources = sources(&repo_root);
+            let environment = [
+                task::Input::env(&ENSO_CLOUD_REDIRECT),
+                task::Input::env(&ENSO_CLOUD_ENVIRONMENT),
+                task::Input::env(&ENSO_CLOUD_API_URL),
+                task::Input::env(&ENSO_CLOUD_CHAT_URL),
+                task::Input::env(&ENSO_CLOUD_SENTRY_DSN),
+                task::Input::env(&ENSO_CLOUD_STRIPE_KEY),
+                task::Input::env(&ENSO_CLOUD_COGNITO_USER_POOL_ID),
+                task::Input::env(&ENSO_CLOUD_COGNITO_USER_POOL_WEB_CLIENT_ID),
+                task::Input::env(&ENSO_CLOUD_COGNITO_DOMAIN),
+                task::Input::env(&ENSO_CLOUD_COGNITO_REGION),
+                task::Input::env(&ENSO_CLOUD_GOOGLE_ANALYTICS_TAG),
+                task::Input::env(&VITE_ENSO_AG_GRID_LICENSE_KEY),
+                task::Input::env(&VITE_ENSO_MAPBOX_API_TOKEN),
+            ];
+            let tools = [task::Input::tool(&Node).await?, task::Input::tool(&Pnpm).await?];
+            Ok(once(sources).chain(environment).chain(tools).collect())
+        }
+        .boxed()
+    }
+
     fn build_internal(
         &self,
         context: Context,
@@ -103,6 +136,27 @@ impl IsTarget for Gui {
     }
 }
 
+/// The sources of the JS projects under `app`, shared by the GUI and the IDE.
+pub fn sources(repo_root: &Path) -> task::Input {
+    task::Input::files(repo_root, [
+        "app/**/*",
+        "package.json",
+        "pnpm-lock.yaml",
+        "pnpm-workspace.yaml",
+        crate::ide::web::BUILD_INFO.as_str(),
+    ])
+    // The fonts are downloaded into the sources by the build itself.
+    .excluding([
+        "**/node_modules",
+        "**/dist",
+        "**/playwright-report",
+        "app/gui2/public/font-*",
+        "app/gui2/src/assets/font-*.css",
+    ])
+}
+
+
+
 // =================
 // === BuildInfo ===
 // =================
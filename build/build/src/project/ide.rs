@@ -3,10 +3,14 @@ use crate::prelude::*;
 
 use crate::project::gui::ide_desktop_from_context;
 use crate::project::Context;
+use crate::project::TargetStep;
 
 use ide_ci::actions::artifacts::upload_compressed_directory;
 use ide_ci::actions::artifacts::upload_single_file;
 use ide_ci::actions::workflow::is_in_env;
+use ide_ci::programs::Node;
+use ide_ci::programs::Pnpm;
+use ide_ci::task;
 
 
 
@@ -122,6 +126,63 @@ impl Ide {
         }
         .boxed()
     }
+
+    /// Describe packaging the IDE as a step of the build graph, named after the
+    /// [artifact](BuildInput::artifact_name).
+    ///
+    /// The step should be made dependent on the steps producing the GUI and the Project Manager,
+    /// as they are packaged into the IDE.
+    pub fn build_step(
+        &self,
+        context: &Context,
+        input: BuildInput,
+        output_path: impl AsRef<Path>,
+    ) -> BoxFuture<'static, Result<TargetStep<Artifact>>> {
+        use crate::ide::web::env::*;
+        let output_path = output_path.as_ref().to_path_buf();
+        let sources = crate::project::gui::sources(&context.repo_root);
+        let values = [
+            task::Input::value("version", &input.version),
+            task::Input::value("target OS", self.target_os),
+            task::Input::value("target architecture", self.target_arch),
+            task::Input::value("electron target", format!("{:?}", input.electron_target)),
+            task::Input::value("destination", output_path.display()),
+        ];
+        let environment = [
+            task::Input::env(&CSC_LINK),
+            task::Input::env(&CSC_KEY_PASSWORD),
+            task::Input::env(&CSC_IDENTITY_AUTO_DISCOVERY),
+            task::Input::env(&WIN_CSC_LINK),
+            task::Input::env(&WIN_CSC_KEY_PASSWORD),
+            task::Input::env(&APPLEID),
+            task::Input::env(&APPLEIDPASS),
+            task::Input::env(&APPLETEAMID),
+        ];
+        let (target_os, target_arch) = (self.target_os, self.target_arch);
+        let name = input.artifact_name.clone();
+        let version = input.version.clone();
+        let build = self.build(context, input, output_path.clone());
+        async move {
+            let tools = [task::Input::tool(&Node).await?, task::Input::tool(&Pnpm).await?];
+            let inputs = once(sources).chain(values).chain(environment).chain(tools);
+            let (sender, receiver) = tokio::sync::oneshot::channel();
+            let action = async move {
+                // The receiver is dropped only when nobody waits for the artifact anymore.
+                let _ = sender.send(build.await?);
+                Ok(())
+            };
+            let step = task::Step::new(name, action).with_inputs(inputs).with_output(&output_path);
+            let artifact = async move {
+                match receiver.await {
+                    Ok(artifact) => Ok(artifact),
+                    Err(_) => Ok(Artifact::new(target_os, target_arch, &version, output_path)),
+                }
+            }
+            .boxed();
+            Ok(TargetStep { step, artifact })
+        }
+        .boxed()
+    }
 }
 
 /// Filename of the image that electron-builder will produce.
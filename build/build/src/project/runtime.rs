@@ -12,6 +12,8 @@ use crate::project::IsTarget;
 use crate::source::WithDestination;
 use crate::version::Versions;
 
+use ide_ci::task;
+
 
 
 const ARTIFACT_NAME: &str = "runtime";
@@ -37,6 +39,14 @@ impl IsTarget for Runtime {
         ready(Ok(Artifact::new(path.as_ref()))).boxed()
     }
 
+    fn build_inputs(
+        &self,
+        context: &Context,
+        input: &Self::BuildInput,
+    ) -> BoxFuture<'static, Result<Vec<task::Input>>> {
+        ready(Ok(crate::engine::build_inputs(&context.repo_root, &input.versions))).boxed()
+    }
+
     fn build_internal(
         &self,
         context: Context,
@@ -4,6 +4,8 @@ use crate::prelude::*;
 
 use ide_ci::programs::cargo;
 use ide_ci::programs::Cargo;
+use ide_ci::programs::WasmPack;
+use ide_ci::task;
 
 
 // ==============
@@ -90,3 +92,36 @@ pub async fn test(repo_root: PathBuf, wasm: &[test::Browser], native: bool) -> R
     maybe_run("wasm", !wasm.is_empty(), || test::test_all(repo_root.clone(), wasm)).await?;
     Ok(())
 }
+
+/// Name of the [step](test_step) running the Rust tests.
+pub const TEST_STEP: &str = "rust-test";
+
+/// Describe running the Rust tests as a step of the build graph.
+///
+/// The step is skipped if neither the Rust sources nor the test configuration changed since the
+/// tests last passed.
+pub async fn test_step(
+    repo_root: PathBuf,
+    wasm: Vec<test::Browser>,
+    native: bool,
+) -> Result<task::Step> {
+    let sources = task::Input::files(&repo_root, [
+        "**/*.rs",
+        "**/Cargo.toml",
+        "Cargo.lock",
+        "rust-toolchain.toml",
+        ".cargo/**/*",
+    ])
+    .excluding(["target", "**/target", "**/node_modules", "**/dist"]);
+    let mut inputs = vec![
+        sources,
+        task::Input::value("native", native),
+        task::Input::value("browsers", format!("{wasm:?}")),
+        task::Input::tool(&Cargo).await?,
+    ];
+    if !wasm.is_empty() {
+        inputs.push(task::Input::tool(&WasmPack).await?);
+    }
+    let action = async move { test(repo_root, &wasm, native).await };
+    Ok(task::Step::new(TEST_STEP, action).with_inputs(inputs))
+}
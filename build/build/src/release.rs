@@ -5,10 +5,18 @@ use crate::prelude::*;
 use crate::changelog::Changelog;
 use crate::context::BuildContext;
 use crate::env::ENSO_ADMIN_TOKEN;
+use crate::env::ENSO_RELEASE_SIGNING_KEY;
 use crate::paths::generated;
 use crate::paths::TargetTriple;
 use crate::paths::EDITION_FILE_ARTIFACT_NAME;
 use crate::project;
+use crate::release::checksum::Digest;
+use crate::release::checksum::Sums;
+use crate::release::checksum::SHA256SUMS_FILENAME;
+use crate::release::signature::signature_filename;
+use crate::release::signature::PublicKey;
+use crate::release::signature::SecretKey;
+use crate::release::signature::Signature;
 use crate::version;
 use crate::version::promote::Designation;
 use crate::version::Versions;
@@ -28,7 +36,10 @@ use tempfile::tempdir;
 // === Export ===
 // ==============
 
+pub mod checksum;
 pub mod manifest;
+pub mod notes;
+pub mod signature;
 
 
 
@@ -142,7 +153,35 @@ pub async fn publish_release(context: &BuildContext) -> Result {
     let release = release_handle.get().await?;
     ensure!(release.draft, "Release has been already published!");
 
-    debug!("Found the target release, will publish it.");
+    // Attach the assets manifest and checksums while the release is still a draft, so it is never
+    // public without them.
+    let digests = asset_digests(&release).await?;
+    let mut manifest = manifest::Assets::new(&remote_repo, &triple.versions.version);
+    manifest.set_digests(&digests)?;
+    let mut sums = Sums::default();
+    for (filename, digest) in &digests {
+        sums.insert(filename, digest);
+    }
+    let tempdir = tempdir()?;
+    let manifest_path = tempdir.path().join(manifest::ASSETS_MANIFEST_FILENAME);
+    ide_ci::fs::write_json(&manifest_path, &manifest)?;
+    let sums_path = tempdir.path().join(SHA256SUMS_FILENAME);
+    ide_ci::fs::write(&sums_path, sums.to_string())?;
+    let mut files = vec![manifest_path, sums_path];
+    if ENSO_RELEASE_SIGNING_KEY.is_set() {
+        let key = SecretKey::read(ENSO_RELEASE_SIGNING_KEY.get()?)?;
+        info!("Signing the release metadata, public key: {}", key.public_key().encode());
+        let signatures =
+            files.iter().map(|file| key.sign_file(file)).collect::<Result<Vec<_>>>()?;
+        files.extend(signatures);
+    } else {
+        warn!("{ENSO_RELEASE_SIGNING_KEY} is not set, the release metadata will not be signed.");
+    }
+    for file in files {
+        release_handle.upload_asset_file(&file).await?;
+    }
+
+    debug!("Attached the release metadata, will publish the release.");
     release_handle.publish().await?;
     debug!("Done. Release URL: {}", release.url);
 
@@ -163,13 +202,6 @@ pub async fn publish_release(context: &BuildContext) -> Result {
     debug!("Updating edition in the AWS S3.");
     crate::aws::update_manifest(&remote_repo, &edition_file_path).await?;
 
-    // Add assets manifest.
-    let manifest = manifest::Assets::new(&remote_repo, &triple.versions.version);
-    let tempdir = tempdir()?;
-    let manifest_path = tempdir.path().join(manifest::ASSETS_MANIFEST_FILENAME);
-    ide_ci::fs::write_json(&manifest_path, &manifest)?;
-    release_handle.upload_asset_file(&manifest_path).await?;
-
     // The validation step is performed to enable issue reporting and enhance issue visibility.
     // Currently, even if the validation fails, the release will not be retracted.
     validate_release(release_handle).await?;
@@ -203,6 +235,86 @@ pub async fn validate_release(release: github::release::Handle) -> Result {
     Ok(())
 }
 
+/// Get the digests of the release assets, by the asset names.
+///
+/// The digests are recorded as CI artifacts by the jobs [uploading the
+/// assets](checksum::upload_asset), so the assets are not downloaded.
+pub async fn asset_digests(release: &Release) -> Result<BTreeMap<String, Digest>> {
+    let mut ret = BTreeMap::new();
+    for asset in &release.assets {
+        ret.insert(asset.name.clone(), Digest::download_artifact(&asset.name).await?);
+    }
+    Ok(ret)
+}
+
+/// Location of the release assets to be verified.
+#[derive(Clone, Debug)]
+pub enum AssetSource {
+    /// Local directory with the downloaded assets.
+    Directory(PathBuf),
+    /// Published GitHub release, the assets are downloaded.
+    Release { repo: github::Repo, version: Version },
+}
+
+impl AssetSource {
+    /// Read the whole asset into memory. Meant for the small metadata files.
+    pub async fn read(&self, filename: &str) -> Result<Vec<u8>> {
+        match self {
+            AssetSource::Directory(directory) =>
+                ide_ci::fs::tokio::read(directory.join(filename)).await,
+            AssetSource::Release { repo, version } => {
+                let url = github::release::download_asset(repo, version, filename);
+                Ok(ide_ci::io::download_all(url).await?.to_vec())
+            }
+        }
+    }
+
+    /// Compute the digest of the asset. Remote assets are streamed, without being stored.
+    pub async fn digest(&self, filename: &str) -> Result<Digest> {
+        match self {
+            AssetSource::Directory(directory) => Digest::of_file(directory.join(filename)).await,
+            AssetSource::Release { repo, version } =>
+                Digest::of_download(&github::release::download_asset(repo, version, filename)).await,
+        }
+    }
+}
+
+/// Check the release assets against the manifest and the `SHA256SUMS` file.
+///
+/// If the public key is given, the signatures of both files are checked as well.
+#[context("Failed to verify the release assets from {source:?}.")]
+pub async fn verify_release_assets(source: &AssetSource, public_key: Option<&PublicKey>) -> Result {
+    let manifest_data = source.read(manifest::ASSETS_MANIFEST_FILENAME).await?;
+    let sums_data = source.read(SHA256SUMS_FILENAME).await?;
+    if let Some(public_key) = public_key {
+        for (filename, data) in [
+            (manifest::ASSETS_MANIFEST_FILENAME, &manifest_data),
+            (SHA256SUMS_FILENAME, &sums_data),
+        ] {
+            let signature = source.read(&signature_filename(filename)).await?;
+            let signature: Signature = String::from_utf8(signature)?.parse()?;
+            public_key
+                .verify(data, &signature)
+                .with_context(|| format!("Invalid signature of {filename}."))?;
+            info!("Verified the signature of {filename}: {}", signature.trusted_comment);
+        }
+    } else {
+        warn!("No public key given, the signatures will not be verified.");
+    }
+
+    let manifest: manifest::Assets =
+        serde_json::from_slice(&manifest_data).context("Failed to parse assets manifest.")?;
+    let sums: Sums = String::from_utf8(sums_data)?.parse()?;
+    for asset in manifest.assets() {
+        let filename = asset.filename()?;
+        let digest = source.digest(filename).await?;
+        asset.check(&digest)?;
+        sums.check(filename, &digest)?;
+        info!("Verified {filename}.");
+    }
+    Ok(())
+}
+
 /// Download the Enso Engine distribution from the GitHub release.
 pub async fn get_engine_package<R: IsRepo>(
     repo: &github::repo::Handle<R>,
@@ -343,11 +455,13 @@ mod tests {
         let repo_root = crate_dir.parent().unwrap().parent().unwrap();
         let version = Version::from_str("2024.1.1-nightly.2024.3.26")?;
         let triple = TargetTriple::new(Versions::new(version.clone()));
+        let repo_root = crate::paths::new_repo_root(repo_root, &triple);
         let context = BuildContext {
             inner:       project::Context {
-                repo_root: crate::paths::new_repo_root(repo_root, &triple),
-                octocrab:  setup_octocrab().await?,
-                cache:     Cache::new_default().await?,
+                fingerprints: ide_ci::task::Store::new(repo_root.target.fingerprints.to_path_buf()),
+                repo_root,
+                octocrab: setup_octocrab().await?,
+                cache: Cache::new_default().await?,
             },
             remote_repo: github::Repo::new("enso-org", "enso"),
             triple:      TargetTriple::new(Versions::new(version.clone())),
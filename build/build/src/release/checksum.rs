@@ -0,0 +1,205 @@
+//! SHA-256 digests of the release assets.
+//!
+//! The digests are published in two places: in the [assets manifest](crate::release::manifest),
+//! and in a `SHA256SUMS` file in the format understood by `sha256sum --check`.
+//!
+//! The assets are uploaded by separate CI jobs. Each job computes the digests of the assets it
+//! [uploads](upload_asset) and stores them as CI artifacts, so the job publishing the release does
+//! not need to download the assets.
+
+use crate::prelude::*;
+
+use ide_ci::github::release;
+use ide_ci::io::Sha256Digest;
+use octocrab::models::repos::Asset;
+use sha2::Digest as _;
+
+
+
+/// Name of the file listing the digests of all the release assets.
+pub const SHA256SUMS_FILENAME: &str = "SHA256SUMS";
+
+/// Prefix of the names of the CI artifacts with the digests of the release assets.
+pub const DIGEST_ARTIFACT_PREFIX: &str = "asset-digest-";
+
+/// SHA-256 digest and size of a file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Digest {
+    /// Hex-encoded SHA-256 digest of the file contents.
+    pub sha256: String,
+    /// Size of the file in bytes.
+    pub size:   u64,
+}
+
+impl From<Sha256Digest> for Digest {
+    fn from(digest: Sha256Digest) -> Self {
+        Self { sha256: digest.to_hex(), size: digest.size }
+    }
+}
+
+impl Digest {
+    /// Compute the digest of the data.
+    pub fn of_reader(data: impl Read) -> Result<Self> {
+        Ok(Sha256Digest::of_reader(data)?.into())
+    }
+
+    /// Compute the digest of the file contents.
+    pub async fn of_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Sha256Digest::of_file_async(path).await?.into())
+    }
+
+    /// Compute the digest of the data downloaded from the given URL, without storing it.
+    #[context("Failed to compute the digest of {url}.")]
+    pub async fn of_download(url: &Url) -> Result<Self> {
+        let mut stream = ide_ci::io::download(url.clone()).await?;
+        let mut hasher = sha2::Sha256::new();
+        let mut size = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+        }
+        Ok(Self { sha256: data_encoding::HEXLOWER.encode(&hasher.finalize()), size })
+    }
+
+    /// Name of the CI artifact with the digest of the given release asset.
+    pub fn artifact_name(asset_name: &str) -> String {
+        format!("{DIGEST_ARTIFACT_PREFIX}{asset_name}")
+    }
+
+    /// Store the digest of the release asset as a CI artifact.
+    pub async fn upload_as_artifact(&self, asset_name: &str) -> Result {
+        let temp = tempfile::tempdir()?;
+        let path = temp.path().join("digest.json");
+        ide_ci::fs::write_json(&path, self)?;
+        ide_ci::actions::artifacts::upload_single_file(&path, Self::artifact_name(asset_name)).await
+    }
+
+    /// Retrieve the digest of the release asset stored by [`Digest::upload_as_artifact`].
+    #[context("Failed to retrieve the digest of the release asset {asset_name}.")]
+    pub async fn download_artifact(asset_name: &str) -> Result<Self> {
+        let temp = tempfile::tempdir()?;
+        let path = temp.path().join("digest.json");
+        let artifact_name = Self::artifact_name(asset_name);
+        ide_ci::actions::artifacts::download_single_file_artifact(artifact_name, &path).await?;
+        ide_ci::fs::read_json(&path)
+    }
+}
+
+/// Upload the file as a release asset under its file name, recording its digest.
+pub async fn upload_asset(release: &release::Handle, path: impl AsRef<Path>) -> Result<Asset> {
+    let path = path.as_ref();
+    upload_asset_as(release, path, path.try_file_name()?.as_str()).await
+}
+
+/// Upload the file as a release asset under the given name, recording its digest.
+pub async fn upload_asset_as(
+    release: &release::Handle,
+    path: impl AsRef<Path>,
+    asset_name: &str,
+) -> Result<Asset> {
+    let path = path.as_ref();
+    let digest = Digest::of_file(path).await?;
+    let asset = release.upload_asset_file_as(path, asset_name).await?;
+    // GitHub may sanitize the name, so the one it reports is used.
+    digest.upload_as_artifact(&asset.name).await?;
+    Ok(asset)
+}
+
+/// Contents of the `SHA256SUMS` file: hex-encoded digests of the files, by their names.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sums {
+    pub digests: BTreeMap<String, String>,
+}
+
+impl Sums {
+    /// Record the digest of the file with given name.
+    pub fn insert(&mut self, filename: impl Into<String>, digest: &Digest) {
+        self.digests.insert(filename.into(), digest.sha256.clone());
+    }
+
+    /// Check that the file with given name is listed with the given digest.
+    pub fn check(&self, filename: &str, digest: &Digest) -> Result {
+        let expected = self
+            .digests
+            .get(filename)
+            .with_context(|| format!("{filename} is not listed in {SHA256SUMS_FILENAME}."))?;
+        ensure!(
+            expected == &digest.sha256,
+            "The SHA-256 digest of {filename} is {}, while {SHA256SUMS_FILENAME} lists {expected}.",
+            digest.sha256
+        );
+        Ok(())
+    }
+}
+
+impl Display for Sums {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (filename, sha256) in &self.digests {
+            writeln!(f, "{sha256}  {filename}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Sums {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ret = Self::default();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (sha256, filename) = line
+                .split_once(' ')
+                .with_context(|| format!("Invalid line in {SHA256SUMS_FILENAME}: {line}"))?;
+            // The filename is preceded by a space in text mode and by `*` in binary mode.
+            let filename = filename.strip_prefix([' ', '*']).unwrap_or(filename);
+            ret.digests.insert(filename.into(), sha256.to_lowercase());
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digest() -> Result {
+        let digest = Digest::of_reader("abc".as_bytes())?;
+        assert_eq!(digest, Digest {
+            sha256: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into(),
+            size:   3,
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn sums_round_trip() -> Result {
+        let mut sums = Sums::default();
+        sums.insert("enso-linux-x86_64-2024.4.1.AppImage", &Digest::of_reader("a".as_bytes())?);
+        sums.insert("assets.json", &Digest::of_reader("b".as_bytes())?);
+        let text = sums.to_string();
+        assert_eq!(
+            text,
+            "3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d  assets.json\n\
+            ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb  \
+            enso-linux-x86_64-2024.4.1.AppImage\n"
+        );
+        assert_eq!(text.parse::<Sums>()?, sums);
+        // Files hashed in binary mode are marked with `*`.
+        let binary: Sums = text.replace("  ", " *").parse()?;
+        assert_eq!(binary, sums);
+        Ok(())
+    }
+
+    #[test]
+    fn checking() -> Result {
+        let digest = Digest::of_reader("a".as_bytes())?;
+        let mut sums = Sums::default();
+        sums.insert("a.txt", &digest);
+        sums.check("a.txt", &digest)?;
+        assert!(sums.check("b.txt", &digest).is_err());
+        assert!(sums.check("a.txt", &Digest::of_reader("b".as_bytes())?).is_err());
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use crate::prelude::*;
 
 use crate::paths::TargetTriple;
 use crate::project;
+use crate::release::checksum::Digest;
 use crate::version::Versions;
 
 
@@ -23,6 +24,12 @@ pub struct Asset {
     pub url:           Url,
     /// User-friendly description of the target platform.
     pub target_pretty: String,
+    /// Hex-encoded SHA-256 digest of the asset. Missing in manifests of older releases.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256:        Option<String>,
+    /// Size of the asset in bytes. Missing in manifests of older releases.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size:          Option<u64>,
 }
 
 impl Asset {
@@ -34,7 +41,38 @@ impl Asset {
             (OS::MacOS, Arch::AArch64) => "macOS (Apple silicon)".into(),
             (os, arch) => format!("{os} {arch}"),
         };
-        Self { os: triple.os, arch: triple.arch, url, target_pretty }
+        Self { os: triple.os, arch: triple.arch, url, target_pretty, sha256: None, size: None }
+    }
+
+    /// Name of the release asset file, i.e. the last segment of its URL.
+    pub fn filename(&self) -> Result<&str> {
+        self.url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .with_context(|| format!("Failed to get the asset filename from {}.", self.url))
+    }
+
+    /// The recorded digest, if the manifest contains one.
+    pub fn digest(&self) -> Option<Digest> {
+        Some(Digest { sha256: self.sha256.clone()?, size: self.size? })
+    }
+
+    /// Check that the digest matches the one recorded in the manifest.
+    pub fn check(&self, digest: &Digest) -> Result {
+        let filename = self.filename()?;
+        let expected = self
+            .digest()
+            .with_context(|| format!("The manifest does not list the digest of {filename}."))?;
+        ensure!(
+            &expected == digest,
+            "{filename} has SHA-256 {} and size {}, while the manifest lists {} and {}.",
+            digest.sha256,
+            digest.size,
+            expected.sha256,
+            expected.size
+        );
+        Ok(())
     }
 
     /// Description od the asset with IDE image.
@@ -88,4 +126,53 @@ impl Assets {
     pub fn assets(&self) -> impl Iterator<Item = &Asset> {
         self.ide.iter().chain(&self.engine)
     }
+
+    /// Record the digests of the assets, given by the asset filenames.
+    ///
+    /// Fails if any of the described assets is missing from the given digests.
+    pub fn set_digests(&mut self, digests: &BTreeMap<String, Digest>) -> Result {
+        for asset in self.ide.iter_mut().chain(&mut self.engine) {
+            let filename = asset.filename()?;
+            let digest = digests
+                .get(filename)
+                .with_context(|| format!("Missing the digest of the release asset {filename}."))?;
+            asset.sha256 = Some(digest.sha256.clone());
+            asset.size = Some(digest.size);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_without_digests() -> Result {
+        // Manifests of the older releases do not contain the digests.
+        let json = r#"{
+            "ide": [{
+                "os": "Linux",
+                "arch": "X86_64",
+                "url": "https://github.com/enso-org/enso/releases/download/2024.3.1/enso-linux-x86_64-2024.3.1.AppImage",
+                "target_pretty": "Linux"
+            }],
+            "engine": [],
+            "version": "2024.3.1"
+        }"#;
+        let mut manifest: Assets = serde_json::from_str(json)?;
+        let asset = &manifest.ide[0];
+        assert_eq!(asset.filename()?, "enso-linux-x86_64-2024.3.1.AppImage");
+        assert_eq!(asset.digest(), None);
+        assert!(!serde_json::to_string(&manifest)?.contains("sha256"));
+
+        let digest = Digest { sha256: "00".repeat(32), size: 42 };
+        let digests = [(asset.filename()?.to_owned(), digest.clone())].into_iter().collect();
+        manifest.set_digests(&digests)?;
+        manifest.ide[0].check(&digest)?;
+        assert!(manifest.ide[0].check(&Digest { size: 43, ..digest }).is_err());
+        let manifest: Assets = serde_json::from_str(&serde_json::to_string(&manifest)?)?;
+        assert_eq!(manifest.ide[0].size, Some(42));
+        Ok(())
+    }
 }
@@ -0,0 +1,359 @@
+//! Release notes generated from the local git history.
+//!
+//! Unlike the [release body](crate::release::generate_release_body), which only copies the top
+//! changelog section, the notes list every commit between two revisions. The commits are grouped
+//! by their [conventional prefix](https://www.conventionalcommits.org), and cross-referenced with
+//! the changelog, so that pull requests missing from it can be spotted before the release.
+//!
+//! Everything is computed from the local clone, no network access is needed.
+
+use crate::prelude::*;
+
+use crate::changelog::model::Document;
+use crate::version;
+use crate::version::promote::Designation;
+use crate::version::promote::Releases;
+
+use ide_ci::programs::git;
+use ide_ci::programs::git::pretty_format::Placeholder;
+use regex::Regex;
+use std::sync::LazyLock;
+
+
+
+/// Matches a conventional commit prefix, like `fix(gui)!: `.
+static CONVENTIONAL_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\w+)(?:\([^)]*\))?!?:\s*(.*)$").unwrap());
+
+/// Matches the pull request number, as appended to the subject by GitHub when squash merging.
+static SQUASH_SUFFIX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s*\(#(\d+)\)\s*$").unwrap());
+
+/// Matches the pull request number in the subject of a merge commit.
+static MERGE_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^Merge pull request #(\d+)").unwrap());
+
+/// Title of the group of commits without a recognized conventional prefix.
+pub const OTHER_CHANGES: &str = "Other Changes";
+
+/// Headings of the groups for the known conventional prefixes, in the order of appearance.
+pub const GROUPS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "Build"),
+    ("chore", "Chores"),
+];
+
+/// One end of the commit range the notes are generated for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The latest local tag of a release with the given designation.
+    Latest(Designation),
+    /// Any git revision, e.g. a tag or `HEAD`.
+    Revision(String),
+}
+
+impl FromStr for Bound {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match Designation::from_str(s) {
+            Ok(designation) => Bound::Latest(designation),
+            Err(_) => Bound::Revision(s.into()),
+        })
+    }
+}
+
+impl Display for Bound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bound::Latest(designation) => write!(f, "latest {}", designation.as_ref()),
+            Bound::Revision(revision) => write!(f, "{revision}"),
+        }
+    }
+}
+
+impl Bound {
+    /// Get the git revision, looking up the local tags if needed.
+    pub async fn resolve(&self, git: &git::Context) -> Result<String> {
+        match self {
+            Bound::Revision(revision) => Ok(revision.clone()),
+            Bound::Latest(designation) => {
+                let kind = match designation {
+                    Designation::Stable | Designation::Patch => version::Kind::Stable,
+                    Designation::Rc => version::Kind::Rc,
+                    Designation::Nightly => version::Kind::Nightly,
+                };
+                let tags = git.list_tags().await?;
+                let releases = Releases::new_now(tags.iter().filter_map(|t| t.parse().ok()))?;
+                let latest = releases
+                    .latest_of_kind(kind)
+                    .with_context(|| format!("No local tag of a {kind} release was found."))?;
+                Ok(latest.to_string())
+            }
+        }
+    }
+}
+
+
+
+// ==============
+// === Commit ===
+// ==============
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Commit {
+    pub hash:              String,
+    pub author:            String,
+    /// The commit subject, without the conventional prefix and the pull request number.
+    pub summary:           String,
+    /// The conventional prefix type, like `fix`.
+    pub kind:              Option<String>,
+    pub pull_request:      Option<u64>,
+    /// Where the pull request is mentioned in the changelog, e.g. `Next Release / Enso IDE`.
+    pub changelog_section: Option<String>,
+}
+
+impl Commit {
+    pub fn new(hash: impl Into<String>, author: impl Into<String>, subject: &str) -> Self {
+        let pull_request = SQUASH_SUFFIX
+            .captures(subject)
+            .or_else(|| MERGE_PREFIX.captures(subject))
+            .and_then(|captures| captures[1].parse().ok());
+        let subject = SQUASH_SUFFIX.replace(subject, "");
+        let (kind, summary) = match CONVENTIONAL_PREFIX.captures(&subject) {
+            Some(captures) => (Some(captures[1].to_lowercase()), captures[2].to_string()),
+            None => (None, subject.to_string()),
+        };
+        Self {
+            hash: hash.into(),
+            author: author.into(),
+            summary,
+            kind,
+            pull_request,
+            changelog_section: None,
+        }
+    }
+
+    /// Heading of the group the commit belongs to.
+    pub fn group(&self) -> &'static str {
+        self.kind
+            .as_deref()
+            .and_then(|kind| GROUPS.iter().find(|(prefix, _)| *prefix == kind))
+            .map_or(OTHER_CHANGES, |(_, heading)| heading)
+    }
+}
+
+
+
+// ====================
+// === ReleaseNotes ===
+// ====================
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    pub title:   String,
+    pub commits: Vec<Commit>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotes {
+    pub from:                   String,
+    pub to:                     String,
+    pub groups:                 Vec<Group>,
+    /// Pull requests merged in the range that are not mentioned in the changelog.
+    pub missing_from_changelog: Vec<u64>,
+}
+
+impl ReleaseNotes {
+    /// Group the commits and cross-reference them with the changelog.
+    pub fn new(
+        from: impl Into<String>,
+        to: impl Into<String>,
+        commits: impl IntoIterator<Item = Commit>,
+        changelog: &Document,
+    ) -> Self {
+        let mentioned = pull_requests_in_changelog(changelog);
+        let mut groups: Vec<Group> = vec![];
+        let mut missing_from_changelog = vec![];
+        for mut commit in commits {
+            if let Some(number) = commit.pull_request {
+                commit.changelog_section = mentioned.get(&number).cloned();
+                if commit.changelog_section.is_none() {
+                    missing_from_changelog.push(number);
+                }
+            }
+            let title = commit.group();
+            match groups.iter_mut().find(|group| group.title == title) {
+                Some(group) => group.commits.push(commit),
+                None => groups.push(Group { title: title.into(), commits: vec![commit] }),
+            }
+        }
+        let order = |title: &str| {
+            GROUPS.iter().position(|(_, heading)| *heading == title).unwrap_or(GROUPS.len())
+        };
+        groups.sort_by_key(|group| order(&group.title));
+        missing_from_changelog.sort();
+        missing_from_changelog.dedup();
+        Self { from: from.into(), to: to.into(), groups, missing_from_changelog }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Render the notes as Markdown, linking the pull requests in the given repository.
+    pub fn to_markdown(&self, repo: &impl IsRepo) -> Result<String> {
+        let pull_url =
+            |number: u64| -> Result<Url> { Ok(repo.url()?.join(&format!("pull/{number}"))?) };
+        let mut text = format!("# Changes from {} to {}\n", self.from, self.to);
+        for group in &self.groups {
+            text += &format!("\n## {}\n\n", group.title);
+            for commit in &group.commits {
+                let hash = commit.hash.get(..10).unwrap_or(&commit.hash);
+                text += &format!("- {} ({hash}, {})", commit.summary, commit.author);
+                if let Some(number) = commit.pull_request {
+                    text += &format!(" [#{number}]({})", pull_url(number)?);
+                }
+                text += "\n";
+            }
+        }
+        if !self.missing_from_changelog.is_empty() {
+            text += "\n## Missing from the changelog\n\n";
+            for number in &self.missing_from_changelog {
+                text += &format!("- [#{number}]({})\n", pull_url(*number)?);
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Map the pull requests linked from the changelog to the sections they are listed in.
+pub fn pull_requests_in_changelog(changelog: &Document) -> BTreeMap<u64, String> {
+    let mut ret = BTreeMap::new();
+    for release in &changelog.releases {
+        for section in &release.sections {
+            for link in section.link_definitions() {
+                let number = link.url.trim_end_matches('/').rsplit_once("/pull/");
+                if let Some(Ok(number)) = number.map(|(_, number)| number.parse()) {
+                    ret.entry(number)
+                        .or_insert_with(|| format!("{} / {}", release.title, section.title));
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Generate the release notes for the commits between the given bounds.
+#[context("Failed to generate the release notes from {from} to {to}.")]
+pub async fn generate(
+    git: &git::Context,
+    changelog: &Document,
+    from: &Bound,
+    to: &Bound,
+) -> Result<ReleaseNotes> {
+    let from = from.resolve(git).await?;
+    let to = to.resolve(git).await?;
+    let fields = [Placeholder::Hash, Placeholder::AuthorName, Placeholder::Subject];
+    let commits = git.log_range_fields(&from, &to, &fields).await?.into_iter().map(|fields| {
+        let [hash, author, subject] = fields.as_slice() else {
+            unreachable!("The field count is checked by `log_range_fields`.")
+        };
+        Commit::new(hash, author, subject)
+    });
+    Ok(ReleaseNotes::new(from, to, commits.collect_vec(), changelog))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ide_ci::github::Repo;
+
+    const CHANGELOG: &str = r#"# Next Release
+
+#### Enso IDE
+
+- [Drilldown for XML][10824]
+
+[10824]: https://github.com/enso-org/enso/pull/10824
+"#;
+
+    #[test]
+    fn parsing_subjects() {
+        let commit = Commit::new("abc", "Jane", "fix(gui): Drilldown for XML (#10824)");
+        assert_eq!(commit.kind.as_deref(), Some("fix"));
+        assert_eq!(commit.summary, "Drilldown for XML");
+        assert_eq!(commit.pull_request, Some(10824));
+        assert_eq!(commit.group(), "Fixes");
+
+        let commit = Commit::new("abc", "Jane", "Merge pull request #10901 from enso-org/wip");
+        assert_eq!(commit.kind, None);
+        assert_eq!(commit.pull_request, Some(10901));
+        assert_eq!(commit.group(), OTHER_CHANGES);
+
+        let commit = Commit::new("abc", "Jane", "Bump the version: 2024.4 (notes)");
+        assert_eq!(commit.pull_request, None);
+        assert_eq!(commit.kind, None);
+    }
+
+    #[test]
+    fn bounds() {
+        assert_eq!(Bound::from_str("nightly").unwrap(), Bound::Latest(Designation::Nightly));
+        assert_eq!(Bound::from_str("2024.3.1").unwrap(), Bound::Revision("2024.3.1".into()));
+    }
+
+    #[test]
+    fn notes() -> Result {
+        let changelog: Document = CHANGELOG.parse()?;
+        let commits = [
+            Commit::new("1111111111aa", "Jane", "Add a CI job (#10900)"),
+            Commit::new("2222222222bb", "John", "fix: Drilldown for XML (#10824)"),
+            Commit::new("3333333333cc", "Jane", "feat!: Table widget (#10774)"),
+        ];
+        let notes = ReleaseNotes::new("2024.3.1", "HEAD", commits, &changelog);
+        assert_eq!(notes.groups.iter().map(|g| g.title.as_str()).collect_vec(), [
+            "Features",
+            "Fixes",
+            OTHER_CHANGES
+        ]);
+        assert_eq!(
+            notes.groups[1].commits[0].changelog_section.as_deref(),
+            Some("Next Release / Enso IDE")
+        );
+        assert_eq!(notes.missing_from_changelog, [10774, 10900]);
+
+        let markdown = notes.to_markdown(&Repo::new("enso-org", "enso"))?;
+        assert_eq!(
+            markdown,
+            "# Changes from 2024.3.1 to HEAD
+
+## Features
+
+- Table widget (3333333333, Jane) [#10774](https://github.com/enso-org/enso/pull/10774)
+
+## Fixes
+
+- Drilldown for XML (2222222222, John) [#10824](https://github.com/enso-org/enso/pull/10824)
+
+## Other Changes
+
+- Add a CI job (1111111111, Jane) [#10900](https://github.com/enso-org/enso/pull/10900)
+
+## Missing from the changelog
+
+- [#10774](https://github.com/enso-org/enso/pull/10774)
+- [#10900](https://github.com/enso-org/enso/pull/10900)
+"
+        );
+        let json = serde_json::to_value(&notes)?;
+        assert_eq!(json["missingFromChangelog"], serde_json::json!([10774, 10900]));
+        Ok(())
+    }
+}
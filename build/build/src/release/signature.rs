@@ -0,0 +1,339 @@
+//! Ed25519 signatures of the release metadata files.
+//!
+//! The signatures and public keys use the [minisign](https://jedisct1.github.io/minisign/) file
+//! formats, so the published files can be checked without our tooling, e.g.:
+//! ```text
+//! minisign -Vm SHA256SUMS -P <public key>
+//! ```
+//! Only the legacy (non-prehashed) signatures are supported. They require the whole signed file
+//! to be in memory, which is fine for the small files we sign, like `SHA256SUMS`.
+//!
+//! The secret key is not stored in the password-protected minisign format. Instead, the key file
+//! contains the base64-encoded 32-byte Ed25519 seed (optionally preceded by a comment line), so it
+//! can be provided by the CI secrets.
+
+use crate::prelude::*;
+
+use ring::signature::Ed25519KeyPair;
+use ring::signature::KeyPair;
+use ring::signature::UnparsedPublicKey;
+use ring::signature::ED25519;
+use sha2::Digest as _;
+
+
+
+/// Extension of the signature file, appended to the name of the signed file.
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+
+/// Identifier of the legacy Ed25519 signature algorithm.
+const ALGORITHM: &[u8; 2] = b"Ed";
+
+/// Identifier of the prehashed Ed25519 signature algorithm, which we do not support.
+const ALGORITHM_PREHASHED: &[u8; 2] = b"ED";
+
+const UNTRUSTED_COMMENT_PREFIX: &str = "untrusted comment: ";
+const TRUSTED_COMMENT_PREFIX: &str = "trusted comment: ";
+
+/// Name of the file with the signature of the given file.
+pub fn signature_filename(filename: &str) -> String {
+    format!("{filename}.{SIGNATURE_EXTENSION}")
+}
+
+/// Key identifier, embedded in public keys and signatures to tell which key should be used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyId(pub [u8; 8]);
+
+impl KeyId {
+    /// Derive the key identifier from the public key.
+    pub fn of(public_key: &[u8]) -> Self {
+        let digest = sha2::Sha256::digest(public_key);
+        let mut ret = [0; 8];
+        ret.copy_from_slice(&digest[..8]);
+        Self(ret)
+    }
+}
+
+impl Display for KeyId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Minisign stores the identifier as a little-endian number and prints it in hex.
+        write!(f, "{:016X}", u64::from_le_bytes(self.0))
+    }
+}
+
+/// Decode the base64-encoded payload line, checking its length.
+fn decode_line(line: &str, expected_len: usize, what: &str) -> Result<Vec<u8>> {
+    let bytes =
+        base64::decode(line.trim()).with_context(|| format!("Invalid base64 in {what}."))?;
+    ensure!(
+        bytes.len() == expected_len,
+        "Invalid {what}: expected {expected_len} bytes, got {}.",
+        bytes.len()
+    );
+    Ok(bytes)
+}
+
+/// Split the algorithm identifier and the key identifier from the decoded payload.
+fn split_header<'a>(bytes: &'a [u8], what: &str) -> Result<(KeyId, &'a [u8])> {
+    let (algorithm, rest) = bytes.split_at(ALGORITHM.len());
+    if algorithm == ALGORITHM_PREHASHED {
+        bail!("The {what} uses the prehashed algorithm, only legacy Ed25519 is supported.");
+    }
+    ensure!(algorithm == ALGORITHM, "Unknown algorithm in {what}.");
+    let (key_id, rest) = rest.split_at(8);
+    Ok((KeyId(key_id.try_into()?), rest))
+}
+
+/// Lines of the file that are not comments.
+fn payload_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .filter(|line| !line.starts_with(UNTRUSTED_COMMENT_PREFIX) && !line.trim().is_empty())
+}
+
+
+// =================
+// === SecretKey ===
+// =================
+
+/// A secret key used to sign the files.
+#[derive(Debug)]
+pub struct SecretKey {
+    key_pair: Ed25519KeyPair,
+    key_id:   KeyId,
+}
+
+impl SecretKey {
+    /// Create the key from the 32-byte Ed25519 seed.
+    pub fn from_seed(seed: &[u8]) -> Result<Self> {
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(seed)
+            .map_err(|e| anyhow!("Invalid Ed25519 seed: {e}"))?;
+        let key_id = KeyId::of(key_pair.public_key().as_ref());
+        Ok(Self { key_pair, key_id })
+    }
+
+    /// Read the key from the file with the base64-encoded seed.
+    #[context("Failed to read the secret key from {}.", path.as_ref().display())]
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        ide_ci::fs::read_to_string(path)?.parse()
+    }
+
+    /// The public key that verifies the signatures made with this key.
+    pub fn public_key(&self) -> PublicKey {
+        let mut key = [0; 32];
+        key.copy_from_slice(self.key_pair.public_key().as_ref());
+        PublicKey { key_id: self.key_id, key }
+    }
+
+    /// Sign the data. The trusted comment is signed as well, so it cannot be tampered with.
+    pub fn sign(&self, data: &[u8], trusted_comment: impl Into<String>) -> Signature {
+        let trusted_comment = trusted_comment.into();
+        let signature = to_array(self.key_pair.sign(data).as_ref());
+        let global_signature = to_array(
+            self.key_pair.sign(&Signature::global_message(&signature, &trusted_comment)).as_ref(),
+        );
+        Signature { key_id: self.key_id, signature, trusted_comment, global_signature }
+    }
+
+    /// Sign the file, writing the signature next to it. Returns the path to the signature file.
+    #[context("Failed to sign {}.", path.as_ref().display())]
+    pub fn sign_file(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        let path = path.as_ref();
+        let filename = path.try_file_name()?.as_str().to_owned();
+        let data = ide_ci::fs::read(path)?;
+        let timestamp = chrono::Utc::now().timestamp();
+        let signature = self.sign(&data, format!("timestamp:{timestamp}\tfile:{filename}"));
+        let signature_path = path.with_file_name(signature_filename(&filename));
+        ide_ci::fs::write(&signature_path, signature.to_string())?;
+        Ok(signature_path)
+    }
+}
+
+impl FromStr for SecretKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let line =
+            payload_lines(s).exactly_one().map_err(|_| anyhow!("Expected a single line."))?;
+        let seed = decode_line(line, 32, "secret key")?;
+        Self::from_seed(&seed)
+    }
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut ret = [0; N];
+    ret.copy_from_slice(bytes);
+    ret
+}
+
+
+// =================
+// === PublicKey ===
+// =================
+
+/// A public key used to verify the signatures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicKey {
+    pub key_id: KeyId,
+    pub key:    [u8; 32],
+}
+
+impl PublicKey {
+    /// Read the key from the file in the minisign format.
+    #[context("Failed to read the public key from {}.", path.as_ref().display())]
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        ide_ci::fs::read_to_string(path)?.parse()
+    }
+
+    /// The key as a single base64 line, as accepted by `minisign -P`.
+    pub fn encode(&self) -> String {
+        base64::encode([ALGORITHM.as_slice(), &self.key_id.0, &self.key].concat())
+    }
+
+    /// Check that the signature of the data was made with the matching secret key.
+    pub fn verify(&self, data: &[u8], signature: &Signature) -> Result {
+        ensure!(
+            signature.key_id == self.key_id,
+            "The signature was made with the key {}, expected {}.",
+            signature.key_id,
+            self.key_id
+        );
+        let key = UnparsedPublicKey::new(&ED25519, &self.key);
+        key.verify(data, &signature.signature)
+            .map_err(|_| anyhow!("The signature does not match the data."))?;
+        let global_message =
+            Signature::global_message(&signature.signature, &signature.trusted_comment);
+        key.verify(&global_message, &signature.global_signature)
+            .map_err(|_| anyhow!("The signature does not match the trusted comment."))?;
+        Ok(())
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{UNTRUSTED_COMMENT_PREFIX}minisign public key {}", self.key_id)?;
+        writeln!(f, "{}", self.encode())
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = anyhow::Error;
+
+    /// Accepts both the public key file contents and the bare base64 line.
+    fn from_str(s: &str) -> Result<Self> {
+        let line =
+            payload_lines(s).exactly_one().map_err(|_| anyhow!("Expected a single line."))?;
+        let bytes = decode_line(line, 42, "public key")?;
+        let (key_id, key) = split_header(&bytes, "public key")?;
+        Ok(Self { key_id, key: to_array(key) })
+    }
+}
+
+
+// =================
+// === Signature ===
+// =================
+
+/// A signature of a file, along with the signed trusted comment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub key_id:           KeyId,
+    pub signature:        [u8; 64],
+    pub trusted_comment:  String,
+    /// Signature of the file signature concatenated with the trusted comment.
+    pub global_signature: [u8; 64],
+}
+
+impl Signature {
+    fn global_message(signature: &[u8], trusted_comment: &str) -> Vec<u8> {
+        [signature, trusted_comment.as_bytes()].concat()
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{UNTRUSTED_COMMENT_PREFIX}signature from the Enso release key {}",
+            self.key_id
+        )?;
+        writeln!(
+            f,
+            "{}",
+            base64::encode([ALGORITHM.as_slice(), &self.key_id.0, &self.signature].concat())
+        )?;
+        writeln!(f, "{TRUSTED_COMMENT_PREFIX}{}", self.trusted_comment)?;
+        writeln!(f, "{}", base64::encode(self.global_signature))
+    }
+}
+
+impl FromStr for Signature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut lines = s.lines().filter(|line| !line.starts_with(UNTRUSTED_COMMENT_PREFIX));
+        let mut next = |what: &str| lines.next().with_context(|| format!("Missing the {what}."));
+        let bytes = decode_line(next("signature")?, 74, "signature")?;
+        let (key_id, signature) = split_header(&bytes, "signature")?;
+        let trusted_comment = next("trusted comment")?
+            .strip_prefix(TRUSTED_COMMENT_PREFIX)
+            .context("Missing the trusted comment.")?
+            .to_owned();
+        let global_signature = decode_line(next("global signature")?, 64, "global signature")?;
+        Ok(Self {
+            key_id,
+            signature: to_array(signature),
+            trusted_comment,
+            global_signature: to_array(&global_signature),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret_key() -> SecretKey {
+        SecretKey::from_seed(&[7; 32]).unwrap()
+    }
+
+    #[test]
+    fn key_round_trip() -> Result {
+        let key = secret_key();
+        let key_file = format!("untrusted comment: test key\n{}\n", base64::encode([7; 32]));
+        assert_eq!(key_file.parse::<SecretKey>()?.public_key(), key.public_key());
+
+        let public_key = key.public_key();
+        assert_eq!(public_key.to_string().parse::<PublicKey>()?, public_key);
+        assert_eq!(public_key.encode().parse::<PublicKey>()?, public_key);
+        Ok(())
+    }
+
+    #[test]
+    fn sign_and_verify() -> Result {
+        let key = secret_key();
+        let public_key = key.public_key();
+        let data = b"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  a.txt\n";
+        let signature = key.sign(data, "timestamp:0\tfile:SHA256SUMS");
+        let signature: Signature = signature.to_string().parse()?;
+        public_key.verify(data, &signature)?;
+
+        assert!(public_key.verify(b"tampered", &signature).is_err());
+        let mut tampered = signature.clone();
+        tampered.trusted_comment = "timestamp:1\tfile:SHA256SUMS".into();
+        assert!(public_key.verify(data, &tampered).is_err());
+        let other_key = SecretKey::from_seed(&[8; 32])?.public_key();
+        assert!(other_key.verify(data, &signature).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn reject_prehashed() -> Result {
+        let signature = secret_key().sign(b"data", "timestamp:0").to_string();
+        let mut lines = signature.lines().map(ToOwned::to_owned).collect_vec();
+        let mut bytes = base64::decode(&lines[1])?;
+        bytes[..2].copy_from_slice(ALGORITHM_PREHASHED);
+        lines[1] = base64::encode(bytes);
+        let error = lines.join("\n").parse::<Signature>().unwrap_err();
+        assert!(error.to_string().contains("prehashed"), "{error}");
+        Ok(())
+    }
+}
@@ -8,6 +8,7 @@ use ide_ci::programs::javac;
 use ide_ci::programs::Cargo;
 use ide_ci::programs::Java;
 use ide_ci::programs::Javac;
+use ide_ci::task;
 
 
 
@@ -16,7 +17,7 @@ const GENERATOR_BIN_NAME: &str = GENERATOR_CRATE_NAME;
 const TEST_GENERATOR_BIN_NAME: &str = "java-tests";
 const GENERATED_CODE_NAMESPACE: [&str; 3] = ["org", "enso", "syntax2"];
 const GENERATED_TEST_CLASS: &str = "GeneratedFormatTests";
-const JAVA_EXTENSION: &str = ".java";
+const JAVA_EXTENSION: &str = "java";
 
 pub fn cargo_run_generator_cmd(repo_root: &Path, binary_name: &str) -> Result<Command> {
     let mut ret = Cargo.cmd()?;
@@ -46,6 +47,24 @@ pub async fn generate_java(repo_root: &RepoRoot) -> Result {
     generate_java_to(repo_root, &output_path).await
 }
 
+/// Name of the [step](generate_java_step) generating the Java sources.
+pub const GENERATE_JAVA_STEP: &str = "generate-java";
+
+/// The [`generate_java`] job as a build step, skipped when the parser sources have not changed.
+pub fn generate_java_step(repo_root: &RepoRoot) -> task::Step {
+    let sources = task::Input::files(repo_root, [
+        "lib/rust/**/*",
+        "Cargo.toml",
+        "Cargo.lock",
+        "rust-toolchain.toml",
+    ])
+    .excluding(["**/target"]);
+    let output = repo_root.target.generated_java.join_iter(GENERATED_CODE_NAMESPACE);
+    let repo_root = repo_root.clone();
+    let job = async move { generate_java(&repo_root).await };
+    task::Step::new(GENERATE_JAVA_STEP, job).with_input(sources).with_output(output)
+}
+
 #[context("Running self-tests for the generated Java sources failed.")]
 pub async fn run_self_tests(repo_root: &RepoRoot) -> Result {
     let base = &repo_root.target.generated_java;
@@ -73,3 +92,42 @@ pub async fn run_self_tests(repo_root: &RepoRoot) -> Result {
 
     Ok(())
 }
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::TargetTriple;
+    use crate::version::Versions;
+    use ide_ci::program::command::executor;
+    use ide_ci::program::command::executor::Fixture;
+    use ide_ci::program::command::executor::Placeholders;
+    use ide_ci::program::command::executor::Replayer;
+
+    // The fixture uses the Unix path and classpath separators.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn self_tests_pipeline() -> Result {
+        let root = tempfile::tempdir()?;
+        let triple = TargetTriple::new(Versions::new(Version::new(0, 0, 0)));
+        let repo_root = crate::paths::new_repo_root(root.path(), &triple);
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("parser-self-tests.yaml");
+        let placeholders = Placeholders::default().with("$ROOT", root.path());
+        let replayer = Arc::new(Replayer::new(Fixture::read(fixture)?, placeholders));
+        executor::scope(replayer.clone(), run_self_tests(&repo_root)).await?;
+        replayer.finish()?;
+
+        let package = repo_root.target.generated_java.join_iter(GENERATED_CODE_NAMESPACE);
+        let test = ide_ci::fs::read_to_string(package.join("GeneratedFormatTests.java"))?;
+        assert!(test.contains("class GeneratedFormatTests"));
+        Ok(())
+    }
+}
@@ -0,0 +1,460 @@
+//! Services needed by the tests, like the database servers.
+//!
+//! Each service is described by a [`TestService`] implementation. The [`start`] function runs it
+//! either in a Docker container or, on machines without Docker, as a local process. Then it waits
+//! until the service is ready and exports the environment variables telling the tests how to
+//! connect. The returned [`RunningService`] stops the service and clears the environment when
+//! dropped.
+
+use crate::prelude::*;
+
+use ide_ci::env::accessor::RawVariable;
+use ide_ci::env::accessor::TypedVariable;
+use ide_ci::get_free_port;
+use ide_ci::program::command::Child;
+use ide_ci::programs::docker::ContainerId;
+use ide_ci::programs::docker::ImageId;
+use ide_ci::programs::docker::Network;
+use ide_ci::programs::docker::RunOptions;
+use ide_ci::programs::Docker;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::BufReader;
+use tokio::sync::oneshot;
+
+
+// ==============
+// === Export ===
+// ==============
+
+pub mod mysql;
+pub mod postgres;
+pub mod snowflake;
+pub mod sqlserver;
+
+
+
+/// How long to wait for the service to report being ready.
+const READINESS_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Number of the last log lines included in the error when the service fails to start.
+const LOG_TAIL_LENGTH: usize = 20;
+
+/// Environment variable name and value, exported for the tests.
+pub type EnvEntry = (String, String);
+
+/// Describe the value of a typed environment variable, to be exported for the tests.
+pub fn env_entry<T: TypedVariable>(
+    variable: &T,
+    value: impl Borrow<T::Borrowed>,
+) -> Result<EnvEntry> {
+    Ok((variable.name().into(), variable.generate(value.borrow())?))
+}
+
+/// Output stream of the service process, on which it writes its logs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// Address under which the tests can connect to the service.
+#[derive(Clone, Debug, PartialEq, Eq, Display)]
+#[display("{host}:{port}")]
+pub struct Address {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Address {
+    pub fn localhost(port: u16) -> Self {
+        Self { host: "localhost".into(), port }
+    }
+}
+
+
+// ===================
+// === TestService ===
+// ===================
+
+/// A service that the tests connect to, e.g. a database server.
+#[async_trait]
+pub trait TestService: Debug + Send + Sync {
+    /// Short name of the service, used in the logs and in the container name, e.g. `postgres`.
+    fn name(&self) -> &str;
+
+    /// Port the service listens on by default, also within its container.
+    fn default_port(&self) -> u16;
+
+    /// Image of the service container.
+    fn image(&self) -> ImageId;
+
+    /// Set the service-specific container options, like the environment.
+    fn configure_container(&self, _options: &mut RunOptions) -> Result {
+        Ok(())
+    }
+
+    /// The output stream on which the service reports being ready.
+    fn log_stream(&self) -> LogStream {
+        LogStream::Stderr
+    }
+
+    /// Readiness probe: whether the log line means that the service accepts connections.
+    fn is_ready(&self, line: &str) -> bool;
+
+    /// Environment variables telling the tests how to connect to the service.
+    fn test_env(&self, address: &Address) -> Result<Vec<EnvEntry>>;
+
+    /// Whether the service can be run as a local process, see [`TestService::native_command`].
+    fn supports_native(&self) -> bool {
+        false
+    }
+
+    /// Prepare the data directory and create the command running the service as a local process
+    /// listening on the given port.
+    async fn native_command(&self, _data_dir: &Path, _port: u16) -> Result<Command> {
+        bail!("The {} service can only be run in a Docker container.", self.name())
+    }
+
+    /// Initialize the service run as a local process, once it is ready, e.g. create the database.
+    async fn native_setup(&self, _data_dir: &Path, _port: u16) -> Result {
+        Ok(())
+    }
+}
+
+
+// ============
+// === Mode ===
+// ============
+
+#[derive(Clone, Debug)]
+pub enum EndpointConfiguration {
+    /// Used when the container is started directly from host (rather than Docker container). In
+    /// such case the service will be exposed to host network on a given port.
+    Host { port: u16 },
+    /// Used when the service is spawned from a container. In such case it will be spawned in a
+    /// owning container's network on the default port.
+    Container { owner: ContainerId },
+}
+
+impl EndpointConfiguration {
+    /// Tries to deduce what endpoint should be used for a spawned service container.
+    pub fn deduce(default_port: u16) -> Result<Self> {
+        if let Ok(container_name) = crate::env::ENSO_RUNNER_CONTAINER_NAME.get() {
+            debug!("Assuming that I am in the Docker container named {container_name}.");
+            Ok(Self::Container { owner: container_name })
+        } else {
+            // If we are running on the bare machine (i.e. not in container), we spawn the service
+            // and expose it on a free host port. Then we can directly consume.
+            Ok(Self::Host { port: preferred_port(default_port)? })
+        }
+    }
+}
+
+/// Use the given port if it is free, otherwise any free port.
+pub fn preferred_port(port: u16) -> Result<u16> {
+    if port_check::is_local_port_free(port) {
+        Ok(port)
+    } else {
+        get_free_port()
+    }
+}
+
+/// Name of the service container, unique for the runner, so parallel jobs do not collide.
+pub fn container_name(service_name: &str) -> ContainerId {
+    let runner_context_string = crate::env::ENSO_RUNNER_CONTAINER_NAME
+        .get_raw()
+        .or_else(|_| ide_ci::actions::env::RUNNER_NAME.get())
+        .unwrap_or_else(|_| Uuid::new_v4().to_string());
+    // GH-hosted runners are named like "GitHub Actions 10". Spaces are not allowed in the
+    // container name.
+    ContainerId(format!("{service_name}-for-{runner_context_string}").replace(' ', "_"))
+}
+
+/// How the service is run.
+#[derive(Clone, Debug)]
+pub enum Mode {
+    /// In a Docker container with the given name.
+    Docker { container: ContainerId, endpoint: EndpointConfiguration },
+    /// As a local process listening on the given port, for machines without Docker.
+    Native { port: u16 },
+}
+
+impl Mode {
+    /// Run the service in Docker when it is available. Otherwise, run it as a local process, if
+    /// the service supports it.
+    pub fn deduce(service: &dyn TestService) -> Result<Self> {
+        if Docker.lookup().is_err() && service.supports_native() {
+            info!("Docker is not available, the {} service will be run natively.", service.name());
+            Ok(Self::Native { port: preferred_port(service.default_port())? })
+        } else {
+            Ok(Self::Docker {
+                container: container_name(service.name()),
+                endpoint:  EndpointConfiguration::deduce(service.default_port())?,
+            })
+        }
+    }
+}
+
+
+// ============
+// === Logs ===
+// ============
+
+/// Log lines written by the service, collected in the background.
+#[derive(Clone, Debug, Default)]
+pub struct Logs {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Logs {
+    fn push(&self, line: String) {
+        self.lines.lock().unwrap().push(line);
+    }
+
+    /// All the lines logged so far.
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+
+    /// The last lines logged so far, joined.
+    pub fn tail(&self, count: usize) -> String {
+        let lines = self.lines.lock().unwrap();
+        lines[lines.len().saturating_sub(count)..].join("\n")
+    }
+}
+
+/// Read the lines from the service output into the logs, signalling when the service is ready.
+async fn capture_logs(
+    reader: impl AsyncRead + Unpin,
+    service: Arc<dyn TestService>,
+    logs: Logs,
+    ready: oneshot::Sender<()>,
+) -> Result {
+    let mut ready = Some(ready);
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        trace!("{}: {line}", service.name());
+        if ready.is_some() && service.is_ready(&line) {
+            // The receiver is gone only if the startup was abandoned, nothing to do then.
+            let _ = ready.take().map(|ready| ready.send(()));
+        }
+        logs.push(line);
+    }
+    Ok(())
+}
+
+
+// ======================
+// === RunningService ===
+// ======================
+
+/// What needs to be removed when the service is stopped.
+#[derive(Debug)]
+enum Cleanup {
+    Container(ContainerId),
+    DataDirectory(TempDir),
+}
+
+/// A started service. Dropping it stops the service and clears the exported environment.
+#[derive(Debug)]
+pub struct RunningService {
+    service: Arc<dyn TestService>,
+    address: Address,
+    env:     Vec<EnvEntry>,
+    logs:    Logs,
+    child:   Child,
+    cleanup: Cleanup,
+}
+
+impl RunningService {
+    /// Address under which the tests can connect to the service.
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Environment variables exported for the tests.
+    pub fn env(&self) -> &[EnvEntry] {
+        &self.env
+    }
+
+    /// The logs written by the service so far.
+    pub fn logs(&self) -> &Logs {
+        &self.logs
+    }
+}
+
+impl Drop for RunningService {
+    fn drop(&mut self) {
+        let name = self.service.name();
+        for (variable, _) in &self.env {
+            ide_ci::env::remove_var(variable);
+        }
+        if let Err(e) = self.child.start_kill() {
+            debug!("Failed to kill the {name} service process: {e}");
+        }
+        match &self.cleanup {
+            Cleanup::Container(container) => {
+                debug!("Will remove the {name} container.");
+                let cleanup_future = Docker.remove_container(container, true);
+                if let Err(e) = futures::executor::block_on(cleanup_future) {
+                    debug!("Failed to kill the {name} container named {container}: {e}");
+                } else {
+                    debug!("The {name} container killed.");
+                }
+            }
+            // The directory is removed when dropped.
+            Cleanup::DataDirectory(_) => {}
+        }
+    }
+}
+
+/// Start the service and wait until it is ready, then export the environment for the tests.
+#[context("Failed to start the {} service.", service.name())]
+pub async fn start(service: impl TestService + 'static, mode: Mode) -> Result<RunningService> {
+    let service: Arc<dyn TestService> = Arc::new(service);
+    let (mut command, address, cleanup) = match &mode {
+        Mode::Docker { container, endpoint } => {
+            // Attempt cleanup in case previous script run crashed in the middle of this.
+            // Otherwise, the container names could collide.
+            let _ = Docker.remove_container(container, true).await;
+            let mut opts = RunOptions::new(service.image());
+            service.configure_container(&mut opts)?;
+            let port = match endpoint {
+                EndpointConfiguration::Host { port } => {
+                    opts.publish_port(*port, service.default_port());
+                    *port
+                }
+                EndpointConfiguration::Container { owner } => {
+                    opts.network = Some(Network::Container(owner.clone()));
+                    service.default_port()
+                }
+            };
+            opts.sig_proxy = Some(true);
+            opts.name = Some(container.to_string());
+            let command = Docker.run_cmd(&opts)?;
+            (command, Address::localhost(port), Cleanup::Container(container.clone()))
+        }
+        Mode::Native { port } => {
+            let data_dir = tempfile::tempdir()?;
+            let command = service.native_command(data_dir.path(), *port).await?;
+            (command, Address::localhost(*port), Cleanup::DataDirectory(data_dir))
+        }
+    };
+
+    let log_stream = service.log_stream();
+    match log_stream {
+        LogStream::Stdout => command.stdout(Stdio::piped()),
+        LogStream::Stderr => command.stderr(Stdio::piped()),
+    };
+    command.kill_on_drop(true);
+    let mut child = command.spawn()?;
+    let output = match log_stream {
+        LogStream::Stdout => child.stdout.take().context("Missing standard output.")?,
+        LogStream::Stderr => child.stderr.take().context("Missing standard error.")?,
+    };
+
+    let logs = Logs::default();
+    let (ready_sender, ready_receiver) = oneshot::channel();
+    tokio::spawn(capture_logs(output, service.clone(), logs.clone(), ready_sender));
+    // From now on, the service is cleaned up on drop, even if it fails to become ready.
+    let mut running =
+        RunningService { service: service.clone(), address, env: default(), logs, child, cleanup };
+
+    let readiness = tokio::time::timeout(READINESS_TIMEOUT, ready_receiver).await;
+    if !matches!(readiness, Ok(Ok(()))) {
+        let reason = match readiness {
+            Err(_) => format!("did not become ready within {READINESS_TIMEOUT:?}"),
+            _ => "stopped without being ready".into(),
+        };
+        bail!(
+            "The {} service {reason}. Last logs:\n{}",
+            service.name(),
+            running.logs.tail(LOG_TAIL_LENGTH)
+        );
+    }
+    if let Cleanup::DataDirectory(data_dir) = &running.cleanup {
+        service.native_setup(data_dir.path(), running.address.port).await?;
+    }
+
+    info!("The {} service is ready at {}.", service.name(), running.address);
+    for (variable, value) in service.test_env(&running.address)? {
+        ide_ci::env::set_var(&variable, &value);
+        running.env.push((variable, value));
+    }
+    Ok(running)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A service run by the shell, printing the given script output.
+    #[derive(Debug)]
+    struct Echo {
+        script: &'static str,
+    }
+
+    #[async_trait]
+    impl TestService for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn default_port(&self) -> u16 {
+            0
+        }
+        fn image(&self) -> ImageId {
+            ImageId("busybox".into())
+        }
+        fn log_stream(&self) -> LogStream {
+            LogStream::Stdout
+        }
+        fn is_ready(&self, line: &str) -> bool {
+            line == "ready"
+        }
+        fn test_env(&self, address: &Address) -> Result<Vec<EnvEntry>> {
+            Ok(vec![("ENSO_ECHO_TEST_SERVICE_HOST".into(), address.to_string())])
+        }
+        fn supports_native(&self) -> bool {
+            true
+        }
+        async fn native_command(&self, _data_dir: &Path, _port: u16) -> Result<Command> {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(self.script);
+            Ok(command)
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn native_service() -> Result {
+        let service = Echo { script: "echo starting; echo ready; echo serving; sleep 60" };
+        let running = start(service, Mode::Native { port: 1234 }).await?;
+        assert_eq!(std::env::var("ENSO_ECHO_TEST_SERVICE_HOST")?, "localhost:1234");
+        assert_eq!(running.env(), [(
+            "ENSO_ECHO_TEST_SERVICE_HOST".into(),
+            "localhost:1234".into()
+        )]);
+        // Logs after the readiness are captured in the background.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(running.logs().lines(), ["starting", "ready", "serving"]);
+        drop(running);
+        assert!(std::env::var("ENSO_ECHO_TEST_SERVICE_HOST").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn service_not_ready() -> Result {
+        let service = Echo { script: "echo starting; echo failed >&1; exit 1" };
+        let error = start(service, Mode::Native { port: 1234 }).await.unwrap_err();
+        let message = format!("{error:#}");
+        assert!(message.contains("stopped without being ready"), "{message}");
+        assert!(message.contains("starting\nfailed"), "{message}");
+        Ok(())
+    }
+}
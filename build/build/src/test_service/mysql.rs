@@ -0,0 +1,109 @@
+//! MySQL database server.
+
+use crate::prelude::*;
+
+use crate::test_service::env_entry;
+use crate::test_service::Address;
+use crate::test_service::EnvEntry;
+use crate::test_service::TestService;
+
+use ide_ci::programs::docker::ImageId;
+use ide_ci::programs::docker::RunOptions;
+
+
+
+/// Port used by MySQL by default, also in its container.
+const MYSQL_DEFAULT_PORT: u16 = 3306;
+
+/// Environment variables used to configure the MySQL container.
+pub mod env {
+
+    pub mod container {
+        ide_ci::define_env_var! {
+            MYSQL_DATABASE, String;
+            MYSQL_USER, String;
+            MYSQL_PASSWORD, String;
+            MYSQL_ROOT_PASSWORD, String;
+        }
+    }
+    pub mod tests {
+        ide_ci::define_env_var! {
+            ENSO_MYSQL_DATABASE, String;
+            ENSO_MYSQL_HOST, String;
+            ENSO_MYSQL_PORT, String;
+            ENSO_MYSQL_USER, String;
+            ENSO_MYSQL_PASSWORD, String;
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MySql {
+    pub database_name: String,
+    /// Non-root user, granted all privileges on the database.
+    pub user:          String,
+    /// Password of both the user and `root`.
+    pub password:      String,
+    /// Tag of the Docker image.
+    pub version:       String,
+}
+
+#[async_trait]
+impl TestService for MySql {
+    fn name(&self) -> &str {
+        "mysql"
+    }
+
+    fn default_port(&self) -> u16 {
+        MYSQL_DEFAULT_PORT
+    }
+
+    fn image(&self) -> ImageId {
+        ImageId(format!("mysql:{}", &self.version))
+    }
+
+    fn configure_container(&self, options: &mut RunOptions) -> Result {
+        options.env(&env::container::MYSQL_DATABASE, &*self.database_name)?;
+        options.env(&env::container::MYSQL_USER, &*self.user)?;
+        options.env(&env::container::MYSQL_PASSWORD, &*self.password)?;
+        options.env(&env::container::MYSQL_ROOT_PASSWORD, &*self.password)?;
+        Ok(())
+    }
+
+    fn is_ready(&self, line: &str) -> bool {
+        // The container first starts a temporary server, not listening on the network, to create
+        // the database and the user.
+        line.contains("ready for connections")
+            && line.contains(&format!("port: {MYSQL_DEFAULT_PORT}"))
+    }
+
+    fn test_env(&self, address: &Address) -> Result<Vec<EnvEntry>> {
+        Ok(vec![
+            env_entry(&env::tests::ENSO_MYSQL_DATABASE, &*self.database_name)?,
+            env_entry(&env::tests::ENSO_MYSQL_HOST, &*address.host)?,
+            env_entry(&env::tests::ENSO_MYSQL_PORT, address.port.to_string())?,
+            env_entry(&env::tests::ENSO_MYSQL_USER, &*self.user)?,
+            env_entry(&env::tests::ENSO_MYSQL_PASSWORD, &*self.password)?,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readiness() {
+        let mysql = MySql {
+            database_name: "test".into(),
+            user:          "test".into(),
+            password:      "test".into(),
+            version:       "latest".into(),
+        };
+        let temporary = "2024-09-02T10:00:00.000000Z 0 [System] [MY-010931] [Server] \
+            /usr/sbin/mysqld: ready for connections. Version: '9.0.1'  socket: \
+            '/var/run/mysqld/mysqld.sock'  port: 0  MySQL Community Server - GPL.";
+        assert!(!mysql.is_ready(temporary));
+        assert!(mysql.is_ready(&temporary.replace("port: 0", "port: 3306")));
+    }
+}
@@ -0,0 +1,178 @@
+//! PostgreSQL database server, used by the `Table_Tests`.
+
+use crate::prelude::*;
+
+use crate::test_service::env_entry;
+use crate::test_service::Address;
+use crate::test_service::EnvEntry;
+use crate::test_service::TestService;
+
+use ide_ci::programs::docker::ImageId;
+use ide_ci::programs::docker::RunOptions;
+
+
+
+/// Port used by Postgres by default, also in its container.
+const POSTGRES_DEFAULT_PORT: u16 = 5432;
+
+/// Environment variables used to configure the Postgres container and its clients.
+pub mod env {
+
+    pub mod container {
+        ide_ci::define_env_var! {
+            POSTGRES_DB, String;
+            POSTGRES_USER, String;
+            POSTGRES_PASSWORD, String;
+        }
+    }
+    pub mod tests {
+        ide_ci::define_env_var! {
+            ENSO_POSTGRES_DATABASE, String;
+            ENSO_POSTGRES_HOST, String;
+            ENSO_POSTGRES_USER, String;
+            ENSO_POSTGRES_PASSWORD, String;
+        }
+    }
+    pub mod client {
+        ide_ci::define_env_var! {
+            /// Password used by the client programs, like `createdb`.
+            PGPASSWORD, String;
+        }
+    }
+}
+
+/// PostgreSQL programs used to run the server natively.
+#[derive(Clone, Copy, Debug)]
+pub enum Tool {
+    /// Creates a new database cluster.
+    InitDb,
+    /// The database server.
+    Postgres,
+    /// Creates a new database in the running server.
+    CreateDb,
+}
+
+impl Program for Tool {
+    type Command = Command;
+    type Version = Version;
+
+    fn executable_name(&self) -> &'static str {
+        match self {
+            Tool::InitDb => "initdb",
+            Tool::Postgres => "postgres",
+            Tool::CreateDb => "createdb",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Postgresql {
+    pub database_name: String,
+    pub user:          String,
+    pub password:      String,
+    /// Tag of the Docker image. Not used when the server is run natively.
+    pub version:       String,
+}
+
+#[async_trait]
+impl TestService for Postgresql {
+    fn name(&self) -> &str {
+        "postgres"
+    }
+
+    fn default_port(&self) -> u16 {
+        POSTGRES_DEFAULT_PORT
+    }
+
+    fn image(&self) -> ImageId {
+        ImageId(format!("postgres:{}", &self.version))
+    }
+
+    fn configure_container(&self, options: &mut RunOptions) -> Result {
+        options.env(&env::container::POSTGRES_DB, &*self.database_name)?;
+        options.env(&env::container::POSTGRES_USER, &*self.user)?;
+        options.env(&env::container::POSTGRES_PASSWORD, &*self.password)?;
+        Ok(())
+    }
+
+    fn is_ready(&self, line: &str) -> bool {
+        line.contains("database system is ready to accept connections")
+    }
+
+    fn test_env(&self, address: &Address) -> Result<Vec<EnvEntry>> {
+        Ok(vec![
+            env_entry(&env::tests::ENSO_POSTGRES_DATABASE, &*self.database_name)?,
+            env_entry(&env::tests::ENSO_POSTGRES_HOST, address.to_string())?,
+            env_entry(&env::tests::ENSO_POSTGRES_USER, &*self.user)?,
+            env_entry(&env::tests::ENSO_POSTGRES_PASSWORD, &*self.password)?,
+        ])
+    }
+
+    fn supports_native(&self) -> bool {
+        true
+    }
+
+    /// Initialize a new cluster in the data directory. The server binaries must be in `PATH`, and
+    /// the server refuses to run as `root`.
+    async fn native_command(&self, data_dir: &Path, port: u16) -> Result<Command> {
+        let cluster = data_dir.join("data");
+        let password_file = data_dir.join("password");
+        ide_ci::fs::write(&password_file, &self.password)?;
+        Tool::InitDb
+            .cmd()?
+            .arg("--pgdata")
+            .arg(&cluster)
+            .arg("--username")
+            .arg(&self.user)
+            .arg("--pwfile")
+            .arg(&password_file)
+            .args(["--auth", "scram-sha-256", "--encoding", "UTF8"])
+            .run_ok()
+            .await?;
+        let mut command = Tool::Postgres.cmd()?;
+        command
+            .arg("-D")
+            .arg(&cluster)
+            .arg("-p")
+            .arg(port.to_string())
+            // Keep the Unix socket in the data directory, the default one may be not writable.
+            .arg("-k")
+            .arg(data_dir)
+            .args(["-c", "listen_addresses=localhost"]);
+        Ok(command)
+    }
+
+    async fn native_setup(&self, _data_dir: &Path, port: u16) -> Result {
+        Tool::CreateDb
+            .cmd()?
+            .args(["--host", "localhost", "--port", &port.to_string()])
+            .arg("--username")
+            .arg(&self.user)
+            .set_env(env::client::PGPASSWORD, &self.password)?
+            .arg(&self.database_name)
+            .run_ok()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_service::Mode;
+
+    #[tokio::test]
+    #[ignore]
+    async fn start_postgres() -> Result {
+        let postgres = Postgresql {
+            version:       "latest".into(),
+            user:          "test".into(),
+            password:      "test".into(),
+            database_name: "test".into(),
+        };
+        let mode = Mode::deduce(&postgres)?;
+        let running = crate::test_service::start(postgres, mode).await?;
+        // drop(running);
+        std::mem::forget(running);
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+//! Local stand-in for Snowflake, using the LocalStack Snowflake emulator.
+//!
+//! The emulator requires a LocalStack license, passed through the `LOCALSTACK_AUTH_TOKEN`
+//! variable. It accepts the `test` credentials. The tests are pointed at it through
+//! `ENSO_SNOWFLAKE_HOST`, instead of the host derived from the account name.
+
+use crate::prelude::*;
+
+use crate::test_service::env_entry;
+use crate::test_service::Address;
+use crate::test_service::EnvEntry;
+use crate::test_service::LogStream;
+use crate::test_service::TestService;
+
+use ide_ci::programs::docker::ImageId;
+use ide_ci::programs::docker::RunOptions;
+
+
+
+/// Port used by the LocalStack gateway by default, also in its container.
+const LOCALSTACK_DEFAULT_PORT: u16 = 4566;
+
+/// Name used by the emulator for the account, user, password, database and warehouse.
+const TEST_CREDENTIAL: &str = "test";
+
+/// LocalStack routes the requests to the Snowflake emulator by this host name. It resolves to the
+/// local machine.
+const LOCALSTACK_SNOWFLAKE_HOST: &str = "snowflake.localhost.localstack.cloud";
+
+/// Environment variables used to configure the emulator container.
+pub mod env {
+
+    pub mod container {
+        ide_ci::define_env_var! {
+            /// LocalStack license token, required to run the Snowflake emulator.
+            LOCALSTACK_AUTH_TOKEN, String;
+        }
+    }
+    pub use crate::libraries_tests::snowflake::env as tests;
+}
+
+#[derive(Clone, Debug)]
+pub struct Snowflake {
+    /// Tag of the Docker image.
+    pub version: String,
+}
+
+#[async_trait]
+impl TestService for Snowflake {
+    fn name(&self) -> &str {
+        "snowflake"
+    }
+
+    fn default_port(&self) -> u16 {
+        LOCALSTACK_DEFAULT_PORT
+    }
+
+    fn image(&self) -> ImageId {
+        ImageId(format!("localstack/snowflake:{}", &self.version))
+    }
+
+    fn configure_container(&self, options: &mut RunOptions) -> Result {
+        options.env(
+            &env::container::LOCALSTACK_AUTH_TOKEN,
+            env::container::LOCALSTACK_AUTH_TOKEN.get()?,
+        )?;
+        Ok(())
+    }
+
+    fn log_stream(&self) -> LogStream {
+        LogStream::Stdout
+    }
+
+    fn is_ready(&self, line: &str) -> bool {
+        line.trim() == "Ready."
+    }
+
+    fn test_env(&self, address: &Address) -> Result<Vec<EnvEntry>> {
+        let host = Address { host: LOCALSTACK_SNOWFLAKE_HOST.into(), port: address.port };
+        Ok(vec![
+            env_entry(&env::tests::ENSO_SNOWFLAKE_HOST, host.to_string())?,
+            env_entry(&env::tests::ENSO_SNOWFLAKE_ACCOUNT, TEST_CREDENTIAL)?,
+            env_entry(&env::tests::ENSO_SNOWFLAKE_USER, TEST_CREDENTIAL)?,
+            env_entry(&env::tests::ENSO_SNOWFLAKE_PASSWORD, TEST_CREDENTIAL)?,
+            env_entry(&env::tests::ENSO_SNOWFLAKE_DATABASE, TEST_CREDENTIAL)?,
+            env_entry(&env::tests::ENSO_SNOWFLAKE_SCHEMA, "PUBLIC")?,
+            env_entry(&env::tests::ENSO_SNOWFLAKE_WAREHOUSE, TEST_CREDENTIAL)?,
+        ])
+    }
+}
@@ -0,0 +1,106 @@
+//! Microsoft SQL Server database server, used by the `Microsoft_Tests`.
+
+use crate::prelude::*;
+
+use crate::test_service::env_entry;
+use crate::test_service::Address;
+use crate::test_service::EnvEntry;
+use crate::test_service::LogStream;
+use crate::test_service::TestService;
+
+use ide_ci::programs::docker::ImageId;
+use ide_ci::programs::docker::RunOptions;
+
+
+
+/// Port used by SQLServer by default, also in its container.
+const SQLSERVER_DEFAULT_PORT: u16 = 1433;
+
+/// Environment variables used to configure the SQLServer container.
+pub mod env {
+
+    pub mod container {
+        ide_ci::define_env_var! {
+            ACCEPT_EULA, String;
+            MSSQL_SA_PASSWORD, String;
+        }
+    }
+    pub mod tests {
+        ide_ci::define_env_var! {
+            ENSO_SQLSERVER_DATABASE, String;
+            ENSO_SQLSERVER_HOST, String;
+            ENSO_SQLSERVER_PORT, String;
+            ENSO_SQLSERVER_USER, String;
+            ENSO_SQLSERVER_PASSWORD, String;
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SQLServer {
+    pub database_name: String,
+    pub user:          String,
+    pub password:      String,
+    /// Tag of the Docker image.
+    pub version:       String,
+}
+
+#[async_trait]
+impl TestService for SQLServer {
+    fn name(&self) -> &str {
+        "sqlserver"
+    }
+
+    fn default_port(&self) -> u16 {
+        SQLSERVER_DEFAULT_PORT
+    }
+
+    fn image(&self) -> ImageId {
+        ImageId(format!("mcr.microsoft.com/mssql/server:{}", &self.version))
+    }
+
+    fn configure_container(&self, options: &mut RunOptions) -> Result {
+        options.env(&env::container::ACCEPT_EULA, "Y")?;
+        options.env(&env::container::MSSQL_SA_PASSWORD, &*self.password)?;
+        Ok(())
+    }
+
+    fn log_stream(&self) -> LogStream {
+        LogStream::Stdout
+    }
+
+    fn is_ready(&self, line: &str) -> bool {
+        line.contains("The tempdb database has")
+    }
+
+    fn test_env(&self, address: &Address) -> Result<Vec<EnvEntry>> {
+        Ok(vec![
+            env_entry(&env::tests::ENSO_SQLSERVER_DATABASE, &*self.database_name)?,
+            env_entry(&env::tests::ENSO_SQLSERVER_HOST, &*address.host)?,
+            env_entry(&env::tests::ENSO_SQLSERVER_PORT, address.port.to_string())?,
+            env_entry(&env::tests::ENSO_SQLSERVER_USER, &*self.user)?,
+            env_entry(&env::tests::ENSO_SQLSERVER_PASSWORD, &*self.password)?,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_service::Mode;
+
+    #[tokio::test]
+    async fn start_sqlserver() -> Result {
+        let sqlserver = SQLServer {
+            version:       "latest".into(),
+            user:          "test".into(),
+            password:      "<YourStrong@Passw0rd>".into(),
+            database_name: "test".into(),
+        };
+        let mode = Mode::deduce(&sqlserver)?;
+        let running = crate::test_service::start(sqlserver, mode).await?;
+        // drop(running);
+        std::mem::forget(running);
+        Ok(())
+    }
+}
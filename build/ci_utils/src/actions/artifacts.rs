@@ -5,6 +5,7 @@ use crate::actions::artifacts::run_session::SessionClient;
 use crate::actions::artifacts::upload::ArtifactUploader;
 use crate::actions::artifacts::upload::FileToUpload;
 use crate::actions::artifacts::upload::UploadOptions;
+use crate::define_env_var;
 
 use anyhow::Context as Trait_anyhow_Context;
 use flume::Sender;
@@ -21,11 +22,162 @@ pub mod models;
 pub mod raw;
 pub mod run_session;
 pub mod upload;
+pub mod v4;
+
+#[cfg(test)]
+pub mod fake_server;
 
 
 
 pub const API_VERSION: &str = "6.0-preview";
 
+define_env_var! {
+    /// Overrides the choice of the artifact [`Protocol`], either `legacy` or `v4`.
+    ENSO_ARTIFACT_PROTOCOL, Protocol;
+}
+
+/// Version of the GitHub Actions artifact API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Protocol {
+    /// The container-based API, used by `actions/upload-artifact@v3` and earlier.
+    Legacy,
+    /// The API based on the results service and blob storage, used by
+    /// `actions/upload-artifact@v4`.
+    V4,
+}
+
+impl Protocol {
+    /// The protocol to use in the current environment.
+    ///
+    /// Unless overridden with [`ENSO_ARTIFACT_PROTOCOL`], the v4 protocol is used whenever the
+    /// runner exposes the results service URL.
+    pub fn from_env() -> Result<Self> {
+        if ENSO_ARTIFACT_PROTOCOL.is_set() {
+            ENSO_ARTIFACT_PROTOCOL.get()
+        } else if std::env::var_os("ACTIONS_RESULTS_URL").is_some() {
+            Ok(Protocol::V4)
+        } else {
+            Ok(Protocol::Legacy)
+        }
+    }
+}
+
+/// Client of the artifact API, using one of the [protocols](Protocol).
+///
+/// The free functions of this module use the backend described by the environment, see
+/// [`Backend::new_from_env`].
+#[derive(Clone, Debug)]
+pub enum Backend {
+    Legacy(SessionClient),
+    V4(v4::Client),
+}
+
+impl Backend {
+    pub fn new_from_env() -> Result<Self> {
+        let protocol = Protocol::from_env()?;
+        debug!("Using the {protocol} artifact protocol.");
+        match protocol {
+            Protocol::Legacy => Ok(Backend::Legacy(SessionClient::new_from_env()?)),
+            Protocol::V4 => Ok(Backend::V4(v4::Client::new_from_env()?)),
+        }
+    }
+
+    /// Upload the files as a new artifact.
+    ///
+    /// The options affect only the legacy protocol, the v4 protocol uploads a single archive.
+    pub fn upload(
+        &self,
+        file_provider: impl Stream<Item = FileToUpload> + Send + 'static,
+        artifact_name: impl Into<String>,
+        options: UploadOptions,
+    ) -> BoxFuture<'static, Result> {
+        let artifact_name = artifact_name.into();
+        let span = info_span!("Artifact upload", artifact_name);
+        let this = self.clone();
+        async move {
+            match this {
+                Backend::Legacy(client) => {
+                    let handler = ArtifactUploader::new(client, artifact_name).await?;
+                    let result =
+                        handler.upload_artifact_to_file_container(file_provider, &options).await;
+                    // We want to patch size even if there were some failures.
+                    handler.patch_artifact_size().await?;
+                    result
+                }
+                Backend::V4(client) => client.upload(file_provider, &artifact_name).await,
+            }
+        }
+        .instrument(span)
+        .boxed()
+    }
+
+    #[tracing::instrument(skip_all , fields(artifact_name = %artifact_name.as_ref(), target = %target.as_ref().display()), err)]
+    pub async fn download_single_file_artifact(
+        &self,
+        artifact_name: impl AsRef<str>,
+        target: impl AsRef<Path>,
+    ) -> Result {
+        let client = match self {
+            Backend::Legacy(client) => client.clone(),
+            Backend::V4(client) =>
+                return client
+                    .download_single_file_artifact(artifact_name.as_ref(), target.as_ref())
+                    .await,
+        };
+        let downloader = download::ArtifactDownloader::new(client, artifact_name.as_ref()).await?;
+        match downloader.file_items().collect_vec().as_slice() {
+            [item] => {
+                let file = FileToDownload {
+                    target:                 target.as_ref().into(),
+                    remote_source_location: item.content_location.clone(),
+                };
+                downloader.download_file_item(&file).await?;
+            }
+            _ => bail!(
+                "The artifact {} does not contain only a single file.",
+                artifact_name.as_ref()
+            ),
+        };
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all , fields(path = %path_to_upload.as_ref().display(), artifact = artifact_name.as_ref()), err)]
+    pub async fn upload_compressed_directory(
+        &self,
+        path_to_upload: impl AsRef<Path> + Send,
+        artifact_name: impl AsRef<str> + Send,
+    ) -> Result {
+        let artifact_name = artifact_name.as_ref();
+        let tempdir = tempdir()?;
+        let archive_path = tempdir.path().join(format!("{artifact_name}.tar.gz"));
+
+        info!("Packing {} to {}", path_to_upload.as_ref().display(), archive_path.display());
+        crate::archive::compress_directory_contents(&archive_path, path_to_upload).await?;
+
+        info!("Starting upload of {artifact_name}.");
+        self.upload(single_file_provider(&archive_path)?, artifact_name, default()).await?;
+        info!("Completed upload of {artifact_name}.");
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all , fields(path = %path_to_extract.as_ref().display(), artifact = artifact_name.as_ref()), err)]
+    pub async fn retrieve_compressed_directory(
+        &self,
+        artifact_name: impl AsRef<str> + Send,
+        path_to_extract: impl AsRef<Path> + Send,
+    ) -> Result {
+        let artifact_name = artifact_name.as_ref();
+        let tempdir = tempdir()?;
+        let archive_path = tempdir.path().join(format!("{artifact_name}.tar.gz"));
+
+        self.download_single_file_artifact(&artifact_name, &archive_path).await?;
+        crate::archive::extract_to(&archive_path, &path_to_extract).await?;
+        Ok(())
+    }
+}
+
+
 
 pub async fn execute_dbg<T: DeserializeOwned + Debug>(
     client: &reqwest::Client,
@@ -72,16 +224,8 @@ pub fn upload(
     options: UploadOptions,
 ) -> BoxFuture<'static, Result> {
     let artifact_name = artifact_name.into();
-    let span = info_span!("Artifact upload", artifact_name);
-    async move {
-        let handler = ArtifactUploader::new(SessionClient::new_from_env()?, artifact_name).await?;
-        let result = handler.upload_artifact_to_file_container(file_provider, &options).await;
-        // We want to patch size even if there were some failures.
-        handler.patch_artifact_size().await?;
-        result
-    }
-    .instrument(span)
-    .boxed()
+    async move { Backend::new_from_env()?.upload(file_provider, artifact_name, options).await }
+        .boxed()
 }
 
 pub fn upload_single_file(
@@ -106,25 +250,11 @@ pub async fn upload_directory_if_exists(
     upload(single_dir_provider(&dir)?, artifact_name, default()).await
 }
 
-#[tracing::instrument(skip_all , fields(artifact_name = %artifact_name.as_ref(), target = %target.as_ref().display()), err)]
 pub async fn download_single_file_artifact(
     artifact_name: impl AsRef<str>,
     target: impl AsRef<Path>,
 ) -> Result {
-    let downloader =
-        download::ArtifactDownloader::new(SessionClient::new_from_env()?, artifact_name.as_ref())
-            .await?;
-    match downloader.file_items().collect_vec().as_slice() {
-        [item] => {
-            let file = FileToDownload {
-                target:                 target.as_ref().into(),
-                remote_source_location: item.content_location.clone(),
-            };
-            downloader.download_file_item(&file).await?;
-        }
-        _ => bail!("The artifact {} does not contain only a single file.", artifact_name.as_ref()),
-    };
-    Ok(())
+    Backend::new_from_env()?.download_single_file_artifact(artifact_name, target).await
 }
 
 pub fn single_file_provider(
@@ -198,42 +328,25 @@ pub fn single_dir_provider(path: &Path) -> Result<impl Stream<Item = FileToUploa
     Ok(futures::stream::iter(files))
 }
 
-#[tracing::instrument(skip_all , fields(path = %path_to_upload.as_ref().display(), artifact = artifact_name.as_ref()), err)]
 pub async fn upload_compressed_directory(
     path_to_upload: impl AsRef<Path> + Send,
     artifact_name: impl AsRef<str> + Send,
 ) -> Result {
-    let artifact_name = artifact_name.as_ref();
-    let tempdir = tempdir()?;
-    let archive_path = tempdir.path().join(format!("{artifact_name}.tar.gz"));
-
-    info!("Packing {} to {}", path_to_upload.as_ref().display(), archive_path.display());
-    crate::archive::compress_directory_contents(&archive_path, path_to_upload).await?;
-
-    info!("Starting upload of {artifact_name}.");
-    upload_single_file(&archive_path, artifact_name).await?;
-    info!("Completed upload of {artifact_name}.");
-    Ok(())
+    Backend::new_from_env()?.upload_compressed_directory(path_to_upload, artifact_name).await
 }
 
-#[tracing::instrument(skip_all , fields(path = %path_to_extract.as_ref().display(), artifact = artifact_name.as_ref()), err)]
 pub async fn retrieve_compressed_directory(
     artifact_name: impl AsRef<str> + Send,
     path_to_extract: impl AsRef<Path> + Send,
 ) -> Result {
-    let artifact_name = artifact_name.as_ref();
-    let tempdir = tempdir()?;
-    let archive_path = tempdir.path().join(format!("{artifact_name}.tar.gz"));
-
-    download_single_file_artifact(&artifact_name, &archive_path).await?;
-    crate::archive::extract_to(&archive_path, &path_to_extract).await?;
-    Ok(())
+    Backend::new_from_env()?.retrieve_compressed_directory(artifact_name, path_to_extract).await
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::actions::artifacts::fake_server::FakeArtifactServer;
     use crate::actions::artifacts::models::CreateArtifactResponse;
     use reqwest::StatusCode;
     use wiremock::matchers::method;
@@ -278,6 +391,54 @@ mod tests {
         //let client = reqwest::Client::builder().default_headers().
     }
 
+    /// Upload artifacts with the given protocol and download them back.
+    async fn round_trip(protocol: Protocol) -> Result {
+        let server = FakeArtifactServer::start().await;
+        let backend = server.backend(protocol)?;
+        let temp = tempdir()?;
+
+        let file = temp.path().join("file.txt");
+        crate::fs::write(&file, "contents")?;
+        backend.upload(single_file_provider(&file)?, "file", default()).await?;
+        let downloaded = temp.path().join("downloaded.txt");
+        backend.download_single_file_artifact("file", &downloaded).await?;
+        assert_eq!(crate::fs::read_to_string(&downloaded)?, "contents");
+
+        let dir = temp.path().join("dir");
+        crate::fs::write(dir.join("a.txt"), "a")?;
+        crate::fs::write(dir.join("sub/b.txt"), "b")?;
+        backend.upload_compressed_directory(&dir, "dir").await?;
+        let extracted = temp.path().join("extracted");
+        backend.retrieve_compressed_directory("dir", &extracted).await?;
+        assert_eq!(crate::fs::read_to_string(extracted.join("a.txt"))?, "a");
+        assert_eq!(crate::fs::read_to_string(extracted.join("sub/b.txt"))?, "b");
+
+        backend.upload(single_dir_provider(&dir)?, "many", default()).await?;
+        let target = temp.path().join("target");
+        assert!(backend.download_single_file_artifact("many", &target).await.is_err());
+        assert!(backend.download_single_file_artifact("missing", &target).await.is_err());
+
+        assert_eq!(server.artifact_names(protocol), ["dir", "file", "many"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn legacy_round_trip() -> Result {
+        round_trip(Protocol::Legacy).await
+    }
+
+    #[tokio::test]
+    async fn v4_round_trip() -> Result {
+        round_trip(Protocol::V4).await
+    }
+
+    #[test]
+    fn protocol_names() -> Result {
+        assert_eq!("v4".parse::<Protocol>()?, Protocol::V4);
+        assert_eq!(Protocol::Legacy.to_string(), "legacy");
+        Ok(())
+    }
+
     #[test]
     fn deserialize_response() -> Result {
         let text = r#"{"containerId":11099678,"size":-1,"signedContent":null,"fileContainerResourceUrl":"https://pipelines.actions.githubusercontent.com/VYS7uSE1JB12MkavBOHvD6nounefzg1s5vHmQvfbiLmuvFuM6c/_apis/resources/Containers/11099678","type":"actions_storage","name":"SomeFile","url":"https://pipelines.actions.githubusercontent.com/VYS7uSE1JB12MkavBOHvD6nounefzg1s5vHmQvfbiLmuvFuM6c/_apis/pipelines/1/runs/75/artifacts?artifactName=SomeFile","expiresOn":"2022-01-29T04:07:24.5807079Z","items":null}"#;
@@ -0,0 +1,469 @@
+//! In-process stand-in for the GitHub Actions artifact services, for testing without a runner.
+//!
+//! A single [`FakeArtifactServer`] serves both the [legacy](Protocol::Legacy) and the
+//! [v4](Protocol::V4) protocol, including the file container and blob storage transfers. Artifacts
+//! uploaded with one protocol are visible only through the same protocol.
+
+use crate::prelude::*;
+
+use crate::actions::artifacts::context;
+use crate::actions::artifacts::models as legacy;
+use crate::actions::artifacts::models::ItemType;
+use crate::actions::artifacts::run_session::SessionClient;
+use crate::actions::artifacts::v4;
+use crate::actions::artifacts::v4::models::*;
+use crate::actions::artifacts::Backend;
+use crate::actions::artifacts::Protocol;
+use crate::actions::artifacts::API_VERSION;
+
+use sha2::Digest;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use wiremock::http::Method;
+use wiremock::matchers::any;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::Request;
+use wiremock::Respond;
+use wiremock::ResponseTemplate;
+
+
+
+/// The runtime token accepted by the server.
+///
+/// It is a JWT carrying the `Actions.Results` scope, so the v4 client can extract the backend IDs.
+pub fn runtime_token() -> String {
+    let claims = format!(
+        r#"{{"scp":"Actions.GenericRead Actions.Results:{RUN_BACKEND_ID}:{JOB_BACKEND_ID}"}}"#
+    );
+    let encode = |text: &str| data_encoding::BASE64URL_NOPAD.encode(text.as_bytes());
+    format!("{}.{}.signature", encode(r#"{"alg":"none"}"#), encode(&claims))
+}
+
+/// The workflow run ID used by the legacy protocol.
+pub const RUN_ID: &str = "1234";
+
+/// The workflow run ID used by the v4 protocol.
+pub const RUN_BACKEND_ID: &str = "fd0a9d72-8e84-4f4c-9a0e-7d5b1cde6f11";
+
+/// The workflow job ID used by the v4 protocol.
+pub const JOB_BACKEND_ID: &str = "1b3a50c4-3d5e-4bd8-8a05-2f8ec1a90b52";
+
+/// Artifact uploaded with the legacy protocol.
+#[derive(Clone, Debug, Default)]
+pub struct LegacyArtifact {
+    pub container_id: u64,
+    /// Files by their path in the container, which starts with the artifact name.
+    pub files:        BTreeMap<String, Vec<u8>>,
+    /// Set once the client patches the artifact size, which completes the upload.
+    pub size:         Option<usize>,
+}
+
+/// Artifact uploaded with the v4 protocol.
+#[derive(Clone, Debug, Default)]
+pub struct V4Artifact {
+    pub id:        u64,
+    /// The uploaded zip archive.
+    pub blob:      Option<Vec<u8>>,
+    /// Whether the client finalized the artifact, which completes the upload.
+    pub finalized: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct State {
+    pub legacy:  BTreeMap<String, LegacyArtifact>,
+    pub v4:      BTreeMap<String, V4Artifact>,
+    pub last_id: u64,
+}
+
+impl State {
+    fn next_id(&mut self) -> u64 {
+        self.last_id += 1;
+        self.last_id
+    }
+}
+
+/// The fake artifact server, running until dropped.
+#[derive(Debug)]
+pub struct FakeArtifactServer {
+    pub server: MockServer,
+    pub state:  Arc<Mutex<State>>,
+}
+
+impl FakeArtifactServer {
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        let state = Arc::<Mutex<State>>::default();
+        // The URLs of the incoming requests do not carry the port, so the service needs to know it.
+        let base = format!("{}/", server.uri()).parse().expect("Invalid mock server URI.");
+        Mock::given(any())
+            .respond_with(Service { base, state: state.clone() })
+            .mount(&server)
+            .await;
+        Self { server, state }
+    }
+
+    pub fn url(&self) -> Result<Url> {
+        Ok(format!("{}/", self.server.uri()).parse()?)
+    }
+
+    /// Backend talking to this server with the given protocol.
+    pub fn backend(&self, protocol: Protocol) -> Result<Backend> {
+        match protocol {
+            Protocol::Legacy => {
+                let context = context::Context {
+                    runtime_url:   self.url()?,
+                    runtime_token: runtime_token(),
+                    run_id:        RUN_ID.into(),
+                    api_version:   API_VERSION.into(),
+                };
+                Ok(Backend::Legacy(SessionClient::new(&context)?))
+            }
+            Protocol::V4 => {
+                let runtime_token = runtime_token();
+                let context = v4::Context {
+                    results_url: self.url()?,
+                    backend_ids: BackendIds::from_token(&runtime_token)?,
+                    runtime_token,
+                };
+                Ok(Backend::V4(v4::Client::new(context)?))
+            }
+        }
+    }
+
+    /// Names of the completely uploaded artifacts of the given protocol.
+    pub fn artifact_names(&self, protocol: Protocol) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        match protocol {
+            Protocol::Legacy => state
+                .legacy
+                .iter()
+                .filter(|(_, artifact)| artifact.size.is_some())
+                .map(|(name, _)| name.clone())
+                .collect(),
+            Protocol::V4 => state
+                .v4
+                .iter()
+                .filter(|(_, artifact)| artifact.finalized)
+                .map(|(name, _)| name.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Handles all requests made to the [`FakeArtifactServer`].
+#[derive(Debug)]
+struct Service {
+    /// The URL of the server, which the URLs given to the client are based on.
+    base:  Url,
+    state: Arc<Mutex<State>>,
+}
+
+impl Respond for Service {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        trace!("{} {}", request.method, request.url);
+        self.handle(request).unwrap_or_else(|err| {
+            ResponseTemplate::new(500).set_body_string(format!("Fake server failure: {err:?}"))
+        })
+    }
+}
+
+impl Service {
+    fn handle(&self, request: &Request) -> Result<ResponseTemplate> {
+        let base = &self.base;
+        let segments = request.url.path_segments().into_iter().flatten().collect_vec();
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        // The blob storage is authorized by the signed URLs, everything else by the token.
+        let is_blob = segments.first() == Some(&"blob");
+        if !is_blob
+            && header(request, "authorization") != Some(format!("Bearer {}", runtime_token()))
+        {
+            return Ok(ResponseTemplate::new(401));
+        }
+        match (request.method, segments.as_slice()) {
+            // === Legacy ===
+            (Method::Post, ["_apis", "pipelines", "workflows", RUN_ID, "artifacts"]) => {
+                let body: serde_json::Value = serde_json::from_slice(&request.body)?;
+                let name = body["Name"].as_str().context("Missing artifact name.")?.to_owned();
+                let container_id = state.next_id();
+                state.legacy.insert(name.clone(), LegacyArtifact { container_id, ..default() });
+                let response = legacy::CreateArtifactResponse {
+                    container_id,
+                    size: -1,
+                    signed_content: None,
+                    file_container_resource_url: container_url(base, container_id)?,
+                    r#type: "actions_storage".into(),
+                    name: name.clone(),
+                    url: artifact_url(base, &name)?,
+                    expires_on: "2100-01-01T00:00:00Z".into(),
+                };
+                Ok(ResponseTemplate::new(201).set_body_json(response))
+            }
+            (Method::Put, ["_apis", "resources", "Containers", id]) => {
+                let path = query(request, "itemPath").context("Missing item path.")?;
+                let range = header(request, "content-range").context("Missing content range.")?;
+                let start: usize = range
+                    .trim_start_matches("bytes ")
+                    .split('-')
+                    .next()
+                    .context("Invalid content range.")?
+                    .parse()?;
+                let Some(artifact) = legacy_artifact(state, id)? else {
+                    return Ok(ResponseTemplate::new(404));
+                };
+                let file = artifact.files.entry(path).or_default();
+                file.truncate(start);
+                ensure!(file.len() == start, "Chunks were not uploaded in order.");
+                file.extend_from_slice(&request.body);
+                Ok(ResponseTemplate::new(200))
+            }
+            (Method::Patch, ["_apis", "pipelines", "workflows", RUN_ID, "artifacts"]) => {
+                let name = query(request, "artifactName").context("Missing artifact name.")?;
+                let body: serde_json::Value = serde_json::from_slice(&request.body)?;
+                let size = body["Size"].as_u64().context("Missing artifact size.")?;
+                let Some(artifact) = state.legacy.get_mut(&name) else {
+                    return Ok(ResponseTemplate::new(404));
+                };
+                artifact.size = Some(size as usize);
+                let response = legacy::PatchArtifactSizeResponse {
+                    container_id:   artifact.container_id,
+                    size:           size as i64,
+                    signed_content: None,
+                    r#type:         "actions_storage".into(),
+                    name:           name.clone(),
+                    url:            artifact_url(base, &name)?,
+                };
+                Ok(ResponseTemplate::new(200).set_body_json(response))
+            }
+            (Method::Get, ["_apis", "pipelines", "workflows", RUN_ID, "artifacts"]) => {
+                let value = state
+                    .legacy
+                    .iter()
+                    .filter_map(|(name, artifact)| {
+                        let size = artifact.size?;
+                        Some(legacy_artifact_response(base, name, artifact.container_id, size))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let response = legacy::ListArtifactsResponse { count: value.len() as i64, value };
+                Ok(ResponseTemplate::new(200).set_body_json(response))
+            }
+            (Method::Get, ["_apis", "resources", "Containers", id]) => {
+                let prefix = query(request, "itemPath").context("Missing item path.")?;
+                let Some(artifact) = legacy_artifact(state, id)? else {
+                    return Ok(ResponseTemplate::new(404));
+                };
+                let container_id = artifact.container_id;
+                let folder = container_entry(base, container_id, &prefix, ItemType::Folder, 0)?;
+                let files_prefix = format!("{prefix}/");
+                let files =
+                    artifact.files.iter().filter(|(path, _)| path.starts_with(&files_prefix));
+                let files = files.map(|(path, data)| {
+                    container_entry(base, container_id, path, ItemType::File, data.len())
+                });
+                let value = once(Ok(folder)).chain(files).collect::<Result<Vec<_>>>()?;
+                let response = legacy::QueryArtifactResponse { count: value.len() as i64, value };
+                Ok(ResponseTemplate::new(200).set_body_json(response))
+            }
+            (Method::Get, ["download", id]) => {
+                let path = query(request, "itemPath").context("Missing item path.")?;
+                let data =
+                    legacy_artifact(state, id)?.and_then(|artifact| artifact.files.get(&path));
+                Ok(match data {
+                    Some(data) => ResponseTemplate::new(200).set_body_bytes(data.clone()),
+                    None => ResponseTemplate::new(404),
+                })
+            }
+
+            // === V4 ===
+            (Method::Post, ["twirp", "github.actions.results.api.v1.ArtifactService", method]) =>
+                handle_twirp(state, base, method, &request.body),
+            (Method::Put, ["blob", id]) => {
+                if header(request, v4::BLOB_TYPE_HEADER).as_deref() != Some("BlockBlob") {
+                    return Ok(ResponseTemplate::new(400)
+                        .set_body_string(format!("Missing {} header.", v4::BLOB_TYPE_HEADER)));
+                }
+                let Some(artifact) = v4_artifact(state, id)? else {
+                    return Ok(ResponseTemplate::new(404));
+                };
+                artifact.blob = Some(request.body.clone());
+                Ok(ResponseTemplate::new(201))
+            }
+            (Method::Get, ["blob", id]) => {
+                let blob = v4_artifact(state, id)?.and_then(|artifact| artifact.blob.as_ref());
+                Ok(match blob {
+                    Some(blob) => ResponseTemplate::new(200).set_body_bytes(blob.clone()),
+                    None => ResponseTemplate::new(404),
+                })
+            }
+            _ => Ok(ResponseTemplate::new(404)),
+        }
+    }
+}
+
+/// Handle a call to the Twirp artifact service.
+fn handle_twirp(
+    state: &mut State,
+    base: &Url,
+    method: &str,
+    body: &[u8],
+) -> Result<ResponseTemplate> {
+    let check_ids = |backend_ids: &BackendIds| {
+        ensure!(
+            backend_ids.workflow_run_backend_id == RUN_BACKEND_ID
+                && backend_ids.workflow_job_run_backend_id == JOB_BACKEND_ID,
+            "Unexpected backend IDs: {backend_ids:?}"
+        );
+        Ok(())
+    };
+    match method {
+        "CreateArtifact" => {
+            let request: CreateArtifactRequest = serde_json::from_slice(body)?;
+            check_ids(&request.backend_ids)?;
+            ensure!(request.version == v4::PROTOCOL_VERSION, "Unsupported artifact version.");
+            if state.v4.get(&request.name).is_some_and(|artifact| artifact.finalized) {
+                return Ok(twirp_error(409, "already_exists", "Artifact already exists."));
+            }
+            let id = state.next_id();
+            state.v4.insert(request.name, V4Artifact { id, ..default() });
+            let response = CreateArtifactResponse {
+                ok:                true,
+                signed_upload_url: blob_url(base, id)?.to_string(),
+            };
+            Ok(ResponseTemplate::new(200).set_body_json(response))
+        }
+        "FinalizeArtifact" => {
+            let request: FinalizeArtifactRequest = serde_json::from_slice(body)?;
+            check_ids(&request.backend_ids)?;
+            let Some(artifact) = state.v4.get_mut(&request.name) else {
+                return Ok(twirp_error(404, "not_found", "Artifact not found."));
+            };
+            let blob = artifact.blob.as_ref().context("Artifact was not uploaded.")?;
+            ensure!(blob.len() as u64 == request.size, "Artifact size mismatch.");
+            let digest = data_encoding::HEXLOWER.encode(&sha2::Sha256::digest(blob));
+            ensure!(request.hash == Some(format!("sha256:{digest}")), "Artifact hash mismatch.");
+            artifact.finalized = true;
+            let response = FinalizeArtifactResponse { ok: true, artifact_id: artifact.id };
+            Ok(ResponseTemplate::new(200).set_body_json(response))
+        }
+        "ListArtifacts" => {
+            let request: ListArtifactsRequest = serde_json::from_slice(body)?;
+            check_ids(&request.backend_ids)?;
+            let artifacts = state
+                .v4
+                .iter()
+                .filter(|(name, artifact)| {
+                    artifact.finalized
+                        && request.name_filter.as_ref().map_or(true, |filter| filter == *name)
+                })
+                .map(|(name, artifact)| Artifact {
+                    backend_ids: request.backend_ids.clone(),
+                    database_id: artifact.id,
+                    name:        name.clone(),
+                    size:        artifact.blob.as_ref().map_or(0, |blob| blob.len() as u64),
+                })
+                .collect();
+            let response = ListArtifactsResponse { artifacts };
+            Ok(ResponseTemplate::new(200).set_body_json(response))
+        }
+        "GetSignedArtifactURL" => {
+            let request: GetSignedArtifactUrlRequest = serde_json::from_slice(body)?;
+            check_ids(&request.backend_ids)?;
+            match state.v4.get(&request.name).filter(|artifact| artifact.finalized) {
+                Some(artifact) => {
+                    let response =
+                        GetSignedArtifactUrlResponse { signed_url: blob_url(base, artifact.id)? };
+                    Ok(ResponseTemplate::new(200).set_body_json(response))
+                }
+                None => Ok(twirp_error(404, "not_found", "Artifact not found.")),
+            }
+        }
+        _ => Ok(twirp_error(404, "bad_route", "Unknown method.")),
+    }
+}
+
+fn twirp_error(status: u16, code: &str, msg: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(serde_json::json!({ "code": code, "msg": msg }))
+}
+
+fn header(request: &Request, name: &str) -> Option<String> {
+    request
+        .headers
+        .iter()
+        .find(|(header, _)| header.as_str().eq_ignore_ascii_case(name))
+        .map(|(_, values)| values.last().as_str().to_owned())
+}
+
+fn query(request: &Request, name: &str) -> Option<String> {
+    request.url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned())
+}
+
+fn legacy_artifact<'a>(state: &'a mut State, id: &str) -> Result<Option<&'a mut LegacyArtifact>> {
+    let id: u64 = id.parse()?;
+    Ok(state.legacy.values_mut().find(|artifact| artifact.container_id == id))
+}
+
+fn v4_artifact<'a>(state: &'a mut State, id: &str) -> Result<Option<&'a mut V4Artifact>> {
+    let id: u64 = id.parse()?;
+    Ok(state.v4.values_mut().find(|artifact| artifact.id == id))
+}
+
+fn container_url(base: &Url, container_id: u64) -> Result<Url> {
+    Ok(base.join(&format!("_apis/resources/Containers/{container_id}"))?)
+}
+
+fn artifact_url(base: &Url, name: &str) -> Result<Url> {
+    let mut url = base.join(&format!("_apis/pipelines/workflows/{RUN_ID}/artifacts"))?;
+    url.query_pairs_mut().append_pair("artifactName", name);
+    Ok(url)
+}
+
+fn blob_url(base: &Url, id: u64) -> Result<Url> {
+    Ok(base.join(&format!("blob/{id}?sig=signature"))?)
+}
+
+fn legacy_artifact_response(
+    base: &Url,
+    name: &str,
+    container_id: u64,
+    size: usize,
+) -> Result<legacy::ArtifactResponse> {
+    Ok(legacy::ArtifactResponse {
+        container_id,
+        size: size as i64,
+        signed_content: None,
+        file_container_resource_url: container_url(base, container_id)?,
+        r#type: "actions_storage".into(),
+        name: name.into(),
+        url: artifact_url(base, name)?,
+    })
+}
+
+fn container_entry(
+    base: &Url,
+    container_id: u64,
+    path: &str,
+    item_type: ItemType,
+    length: usize,
+) -> Result<legacy::ContainerEntry> {
+    let mut content_location = base.join(&format!("download/{container_id}"))?;
+    content_location.query_pairs_mut().append_pair("itemPath", path);
+    Ok(legacy::ContainerEntry {
+        container_id,
+        scope_identifier: Uuid::nil(),
+        path: path.into(),
+        item_type,
+        status: legacy::EntryStatus::Created,
+        file_length: (item_type == ItemType::File).then_some(length as i64),
+        file_encoding: None,
+        file_type: None,
+        date_created: chrono::Utc::now(),
+        date_last_modified: chrono::Utc::now(),
+        created_by: Uuid::nil(),
+        last_modified_by: Uuid::nil(),
+        item_location: container_url(base, container_id)?,
+        content_location,
+        file_id: None,
+        content_id: String::new(),
+    })
+}
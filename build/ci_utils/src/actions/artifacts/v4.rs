@@ -0,0 +1,254 @@
+//! Client of the artifact API used by `actions/upload-artifact@v4` and later.
+//!
+//! Unlike in the [legacy API](crate::actions::artifacts::run_session), an artifact is a single zip
+//! archive. Its lifecycle is managed through the Twirp (JSON over HTTP) service of the runner's
+//! results backend, while the archive itself is transferred directly to and from the blob storage,
+//! using the signed URLs provided by the service.
+
+use crate::prelude::*;
+
+use crate::actions::artifacts::raw::check_response_json;
+use crate::actions::artifacts::upload::FileToUpload;
+use crate::actions::artifacts::v4::models::*;
+use crate::env::expect_var;
+use crate::extensions::reqwest::ClientBuilderExt;
+
+use reqwest::header::HeaderValue;
+use reqwest::StatusCode;
+use tempfile::tempdir;
+
+
+// ==============
+// === Export ===
+// ==============
+
+pub mod models;
+
+
+
+/// Path of the artifact service, relative to the results service URL.
+pub const SERVICE_PATH: &str = "twirp/github.actions.results.api.v1.ArtifactService";
+
+/// The version of the artifact protocol declared when creating an artifact.
+pub const PROTOCOL_VERSION: u32 = 4;
+
+/// Header required by the blob storage when uploading the whole blob in a single request.
+pub const BLOB_TYPE_HEADER: &str = "x-ms-blob-type";
+
+/// Data needed to access the artifact service.
+#[derive(Clone, Debug)]
+pub struct Context {
+    /// URL of the runner's results service (`ACTIONS_RESULTS_URL`).
+    pub results_url:   Url,
+    /// Token authorizing the access to the service (`ACTIONS_RUNTIME_TOKEN`).
+    pub runtime_token: String,
+    /// Identifiers of the current run and job, as known to the service.
+    pub backend_ids:   BackendIds,
+}
+
+impl Context {
+    pub fn new_from_env() -> Result<Self> {
+        let results_url = expect_var("ACTIONS_RESULTS_URL")?.parse()?;
+        let runtime_token = expect_var("ACTIONS_RUNTIME_TOKEN")?;
+        let backend_ids = BackendIds::from_token(&runtime_token)?;
+        Ok(Self { results_url, runtime_token, backend_ids })
+    }
+
+    /// URL of the given method of the artifact service.
+    pub fn method_url(&self, method: &str) -> Result<Url> {
+        let results_url = self.results_url.as_str().trim_end_matches('/');
+        Ok(Url::parse(&format!("{results_url}/{SERVICE_PATH}/{method}"))?)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Client {
+    pub context:     Context,
+    /// Client for the artifact service, authorized with the runtime token.
+    pub json_client: reqwest::Client,
+    /// Client for the blob storage. The signed URLs carry the authorization, so no token is sent.
+    pub blob_client: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(context: Context) -> Result<Self> {
+        let json_client = reqwest::ClientBuilder::new()
+            .user_agent(crate::USER_AGENT)
+            .default_content_type(mime::APPLICATION_JSON)
+            .default_header(
+                reqwest::header::AUTHORIZATION,
+                HeaderValue::try_from(format!("Bearer {}", context.runtime_token))?,
+            )
+            .build()?;
+        let blob_client = reqwest::ClientBuilder::new().user_agent(crate::USER_AGENT).build()?;
+        Ok(Self { context, json_client, blob_client })
+    }
+
+    pub fn new_from_env() -> Result<Self> {
+        Self::new(Context::new_from_env()?)
+    }
+
+    /// Call the given method of the artifact service.
+    pub async fn call<Response: DeserializeOwned>(
+        &self,
+        method: &str,
+        request: &impl Serialize,
+    ) -> Result<Response> {
+        let url = self.context.method_url(method)?;
+        let response = self.json_client.post(url).json(request).send().await?;
+        check_response_json(response, |status, err| match status {
+            StatusCode::NOT_FOUND => err.context(format!(
+                "Artifact service method {method} failed. Does the artifact exist?"
+            )),
+            _ => err.context(format!("Artifact service method {method} failed.")),
+        })
+        .await
+    }
+
+    /// Create a new artifact and get the URL, where its archive should be uploaded.
+    #[context("Failed to create the artifact `{name}`.")]
+    pub async fn create_artifact(&self, name: &str) -> Result<Url> {
+        let request = CreateArtifactRequest {
+            backend_ids: self.context.backend_ids.clone(),
+            name:        name.into(),
+            version:     PROTOCOL_VERSION,
+        };
+        let response: CreateArtifactResponse = self.call("CreateArtifact", &request).await?;
+        ensure!(response.ok, "The service refused to create the artifact.");
+        Ok(response.signed_upload_url.parse()?)
+    }
+
+    /// Mark the artifact as complete, after its archive has been uploaded.
+    ///
+    /// Returns the identifier of the artifact.
+    #[context("Failed to finalize the artifact `{name}`.")]
+    pub async fn finalize_artifact(&self, name: &str, size: u64, sha256: &str) -> Result<u64> {
+        let request = FinalizeArtifactRequest {
+            backend_ids: self.context.backend_ids.clone(),
+            name: name.into(),
+            size,
+            hash: Some(format!("sha256:{sha256}")),
+        };
+        let response: FinalizeArtifactResponse = self.call("FinalizeArtifact", &request).await?;
+        ensure!(response.ok, "The service refused to finalize the artifact.");
+        Ok(response.artifact_id)
+    }
+
+    /// List the artifacts of the current workflow run, optionally only the ones with given name.
+    #[context("Failed to list artifacts for the current run.")]
+    pub async fn list_artifacts(&self, name_filter: Option<&str>) -> Result<Vec<Artifact>> {
+        let request = ListArtifactsRequest {
+            backend_ids: self.context.backend_ids.clone(),
+            name_filter: name_filter.map(Into::into),
+        };
+        let response: ListArtifactsResponse = self.call("ListArtifacts", &request).await?;
+        Ok(response.artifacts)
+    }
+
+    /// Get the URL, where the archive of the given artifact can be downloaded from.
+    #[context("Failed to get the download URL of the artifact `{name}`.")]
+    pub async fn get_signed_artifact_url(&self, name: &str) -> Result<Url> {
+        let request = GetSignedArtifactUrlRequest {
+            backend_ids: self.context.backend_ids.clone(),
+            name:        name.into(),
+        };
+        let response: GetSignedArtifactUrlResponse =
+            self.call("GetSignedArtifactURL", &request).await?;
+        Ok(response.signed_url)
+    }
+
+    /// Upload the file as a blob, in a single request.
+    ///
+    /// A single request can carry up to 5000 MiB, which is more than enough for our artifacts.
+    #[context("Failed to upload {} to the blob storage.", path.display())]
+    pub async fn upload_blob(&self, url: Url, path: &Path) -> Result {
+        let file = crate::fs::tokio::open(path).await?;
+        let length = file.metadata().await?.len();
+        let body = reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(file));
+        let request = self
+            .blob_client
+            .put(url)
+            .header(BLOB_TYPE_HEADER, "BlockBlob")
+            .header(reqwest::header::CONTENT_LENGTH, length)
+            .body(body);
+        crate::io::web::execute(request).await?;
+        Ok(())
+    }
+
+    /// Download the blob to the given file.
+    pub async fn download_blob(&self, url: Url, output: &Path) -> Result {
+        let response = crate::io::web::execute(self.blob_client.get(url)).await?;
+        crate::io::web::stream_response_to_file(response, output).await
+    }
+
+    /// Upload the files as a new artifact.
+    ///
+    /// The files are packed into a zip archive, each under its remote path.
+    pub async fn upload(
+        &self,
+        files: impl Stream<Item = FileToUpload> + Send,
+        artifact_name: &str,
+    ) -> Result {
+        let files = files.collect::<Vec<_>>().await;
+        let tempdir = tempdir()?;
+        let archive_path = tempdir.path().join(format!("{artifact_name}.zip"));
+        let (size, sha256) = {
+            let archive_path = archive_path.clone();
+            tokio::task::spawn_blocking(move || pack(&archive_path, &files)).await??
+        };
+        let upload_url = self.create_artifact(artifact_name).await?;
+        self.upload_blob(upload_url, &archive_path).await?;
+        let id = self.finalize_artifact(artifact_name, size, &sha256).await?;
+        info!("Uploaded artifact `{artifact_name}` with id {id}, {size} bytes.");
+        Ok(())
+    }
+
+    /// Download the artifact that consists of a single file to the given path.
+    pub async fn download_single_file_artifact(
+        &self,
+        artifact_name: &str,
+        target: &Path,
+    ) -> Result {
+        let artifacts = self.list_artifacts(Some(artifact_name)).await?;
+        ensure!(
+            artifacts.iter().any(|artifact| artifact.name == artifact_name),
+            "Failed to find artifact by name {artifact_name}."
+        );
+        let url = self.get_signed_artifact_url(artifact_name).await?;
+        let tempdir = tempdir()?;
+        let archive_path = tempdir.path().join(format!("{artifact_name}.zip"));
+        self.download_blob(url, &archive_path).await?;
+        let target = target.to_owned();
+        let artifact_name = artifact_name.to_owned();
+        tokio::task::spawn_blocking(move || {
+            let mut archive = crate::archive::zip::open(&archive_path)?;
+            let files = (0..archive.len())
+                .filter(|index| archive.by_index(*index).is_ok_and(|file| !file.is_dir()))
+                .collect_vec();
+            let [index] = files.as_slice() else {
+                bail!("The artifact {artifact_name} does not contain only a single file.");
+            };
+            crate::fs::create_parent_dir_if_missing(&target)?;
+            let mut file = archive.by_index(*index)?;
+            crate::archive::zip::extract_file(&mut file, &target)
+        })
+        .await?
+    }
+}
+
+/// Pack the files into a zip archive, each under its remote path.
+///
+/// Returns the size of the archive and its hex-encoded SHA-256 digest.
+#[context("Failed to pack the artifact files into {}.", output.display())]
+pub fn pack(output: &Path, files: &[FileToUpload]) -> Result<(u64, String)> {
+    use path_slash::PathExt;
+    let mut writer = zip::ZipWriter::new(crate::fs::create(output)?);
+    for file in files {
+        let name = file.remote_path.to_slash_lossy();
+        writer.start_file(name, zip::write::FileOptions::default())?;
+        std::io::copy(&mut crate::fs::open(&file.local_path)?, &mut writer)?;
+    }
+    writer.finish()?;
+    let digest = crate::io::Sha256Digest::of_file(output)?;
+    Ok((digest.size, digest.to_hex()))
+}
@@ -0,0 +1,162 @@
+//! Messages of the `github.actions.results.api.v1.ArtifactService` Twirp service.
+//!
+//! Twirp uses the canonical protobuf JSON mapping: field names are kept in `snake_case`, 64-bit
+//! integers are encoded as strings and fields with default values may be omitted.
+
+use crate::prelude::*;
+
+use crate::serde::via_string;
+
+
+
+/// Identifiers of the workflow run and job in the results service.
+///
+/// They are not the same as the run and job identifiers known from the GitHub API. The runner
+/// passes them in the claims of the runtime token, see [`BackendIds::from_token`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendIds {
+    pub workflow_run_backend_id:     String,
+    pub workflow_job_run_backend_id: String,
+}
+
+impl BackendIds {
+    /// The scope of the runtime token that grants access to the results of the current job.
+    pub const SCOPE: &'static str = "Actions.Results";
+
+    /// Extract the identifiers from the runtime token (`ACTIONS_RUNTIME_TOKEN`).
+    ///
+    /// The token is a JWT, whose `scp` claim is a space-separated list of scopes. One of them is
+    /// `Actions.Results:<workflow run backend id>:<workflow job run backend id>`.
+    #[context("Failed to get the backend identifiers from the runtime token.")]
+    pub fn from_token(token: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Claims {
+            scp: String,
+        }
+
+        let payload = token.split('.').nth(1).context("The token is not a JWT.")?;
+        let payload =
+            data_encoding::BASE64URL_NOPAD.decode(payload.trim_end_matches('=').as_bytes())?;
+        let claims: Claims = serde_json::from_slice(&payload)?;
+        claims
+            .scp
+            .split(' ')
+            .find_map(|scope| match scope.split(':').collect_vec().as_slice() {
+                [Self::SCOPE, run, job] => Some(Self {
+                    workflow_run_backend_id:     run.to_string(),
+                    workflow_job_run_backend_id: job.to_string(),
+                }),
+                _ => None,
+            })
+            .with_context(|| format!("No `{}` scope in the token claims.", Self::SCOPE))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateArtifactRequest {
+    #[serde(flatten)]
+    pub backend_ids: BackendIds,
+    pub name:        String,
+    /// Version of the artifact protocol, this module implements the version 4.
+    pub version:     u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateArtifactResponse {
+    #[serde(default)]
+    pub ok:                bool,
+    #[serde(default)]
+    pub signed_upload_url: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FinalizeArtifactRequest {
+    #[serde(flatten)]
+    pub backend_ids: BackendIds,
+    pub name:        String,
+    #[serde(with = "via_string")]
+    pub size:        u64,
+    /// Digest of the uploaded archive, in the `sha256:<hex digest>` form.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash:        Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct FinalizeArtifactResponse {
+    #[serde(default)]
+    pub ok:          bool,
+    #[serde(with = "via_string", default)]
+    pub artifact_id: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListArtifactsRequest {
+    #[serde(flatten)]
+    pub backend_ids: BackendIds,
+    /// Only artifacts with this exact name are listed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_filter: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListArtifactsResponse {
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Artifact {
+    #[serde(flatten)]
+    pub backend_ids: BackendIds,
+    #[serde(with = "via_string")]
+    pub database_id: u64,
+    pub name:        String,
+    #[serde(with = "via_string")]
+    pub size:        u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetSignedArtifactUrlRequest {
+    #[serde(flatten)]
+    pub backend_ids: BackendIds,
+    pub name:        String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetSignedArtifactUrlResponse {
+    pub signed_url: Url,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_ids_from_token() -> Result {
+        let claims = r#"{"scp":"Actions.ExampleScope Actions.Results:ce7f54c7:ca395085","exp":1}"#;
+        let payload = data_encoding::BASE64URL_NOPAD.encode(claims.as_bytes());
+        let token = format!("eyJhbGciOiJIUzI1NiJ9.{payload}.signature");
+        let ids = BackendIds::from_token(&token)?;
+        assert_eq!(ids.workflow_run_backend_id, "ce7f54c7");
+        assert_eq!(ids.workflow_job_run_backend_id, "ca395085");
+        assert!(BackendIds::from_token("not a token").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn int64_as_string() -> Result {
+        let backend_ids = BackendIds {
+            workflow_run_backend_id:     "run".into(),
+            workflow_job_run_backend_id: "job".into(),
+        };
+        let request =
+            FinalizeArtifactRequest { backend_ids, name: "a".into(), size: 1024, hash: None };
+        let json = serde_json::to_value(&request)?;
+        assert_eq!(json["size"], "1024");
+        assert_eq!(json["workflow_run_backend_id"], "run");
+        let response: FinalizeArtifactResponse =
+            serde_json::from_str(r#"{"ok":true,"artifact_id":"42"}"#)?;
+        assert_eq!(response.artifact_id, 42);
+        Ok(())
+    }
+}
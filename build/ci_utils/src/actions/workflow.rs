@@ -9,6 +9,8 @@ use crate::actions::env_file;
 // ==============
 
 pub mod definition;
+pub mod local;
+pub mod validation;
 
 
 
@@ -100,6 +100,7 @@ pub fn setup_artifact_api() -> Step {
     let script = r#"
     core.exportVariable("ACTIONS_RUNTIME_TOKEN", process.env["ACTIONS_RUNTIME_TOKEN"])
     core.exportVariable("ACTIONS_RUNTIME_URL", process.env["ACTIONS_RUNTIME_URL"])
+    core.exportVariable("ACTIONS_RESULTS_URL", process.env["ACTIONS_RESULTS_URL"])
     core.exportVariable("GITHUB_RETENTION_DAYS", process.env["GITHUB_RETENTION_DAYS"])
     console.log(context)
     "#;
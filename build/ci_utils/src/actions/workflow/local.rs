@@ -0,0 +1,652 @@
+//! Running the [workflow definitions](crate::actions::workflow::definition) on the local machine,
+//! without GitHub.
+//!
+//! The jobs are run one after another, in the order of their `needs`. The `run` steps are executed
+//! in the local shell, while the `uses` steps are replaced with [fakes](ActionFake), as the actions
+//! themselves are not available locally. Secrets and variables are taken from the environment
+//! variables of the same name.
+
+use crate::prelude::*;
+
+use crate::actions::workflow::definition::Job;
+use crate::actions::workflow::definition::Shell;
+use crate::actions::workflow::definition::Step;
+use crate::actions::workflow::definition::Workflow;
+use crate::program::Shell as _;
+use crate::programs::Bash;
+use crate::programs::Cmd;
+use crate::programs::PwSh;
+
+use expression::Context;
+use expression::Status;
+use serde_json::json;
+use serde_json::Value;
+use std::time::Duration;
+
+
+// ==============
+// === Export ===
+// ==============
+
+pub mod expression;
+
+
+
+// =============
+// === Fakes ===
+// =============
+
+/// Invocation of an action by a `uses` step.
+#[derive(Clone, Debug)]
+pub struct ActionCall {
+    /// The action reference, like `actions/checkout@v4`.
+    pub uses:              String,
+    /// The step arguments, with the expressions already evaluated.
+    pub with:              BTreeMap<String, String>,
+    /// The environment of the step.
+    pub env:               BTreeMap<String, String>,
+    /// The directory the workflow is run in.
+    pub working_directory: PathBuf,
+}
+
+/// Local replacement of an action.
+pub trait ActionFake: Debug + Send + Sync {
+    /// Perform the action's work, returning the step outputs.
+    fn run(&self, call: ActionCall) -> BoxFuture<'static, Result<BTreeMap<String, String>>>;
+}
+
+/// Fake that does nothing, for the actions that are not needed when running locally.
+#[derive(Clone, Copy, Debug)]
+pub struct Skip {
+    /// Why the action is not needed.
+    pub reason: &'static str,
+}
+
+impl ActionFake for Skip {
+    fn run(&self, call: ActionCall) -> BoxFuture<'static, Result<BTreeMap<String, String>>> {
+        info!("Skipping {}: {}.", call.uses, self.reason);
+        ready(Ok(default())).boxed()
+    }
+}
+
+/// Fakes of the actions used by our workflows, by the action name without the version.
+pub fn default_fakes() -> BTreeMap<String, Arc<dyn ActionFake>> {
+    let skip = |name: &str, reason| -> (String, Arc<dyn ActionFake>) {
+        (name.into(), Arc::new(Skip { reason }))
+    };
+    BTreeMap::from_iter([
+        skip("actions/checkout", "the local working copy is used as is"),
+        skip("actions/github-script", "the GitHub Actions runtime is not available locally"),
+        skip("jetli/wasm-pack-action", "wasm-pack is expected to be installed"),
+        skip("styfle/cancel-workflow-action", "there are no other runs to cancel"),
+        skip("dorny/test-reporter", "the test results are left in place"),
+    ])
+}
+
+
+
+// ==============
+// === Report ===
+// ==============
+
+/// The result of a job or a step, as exposed in the `needs` and `steps` contexts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failure,
+    Skipped,
+}
+
+/// Outcomes of the jobs that were run, in the order they were run.
+pub type Report = Vec<(String, Outcome)>;
+
+
+
+// ================
+// === Ordering ===
+// ================
+
+/// Order the jobs so that each comes after the jobs it needs.
+///
+/// If the `selected` job is given, only it and the jobs it (transitively) needs are included.
+pub fn job_order(workflow: &Workflow, selected: Option<&str>) -> Result<Vec<String>> {
+    fn visit<'a>(
+        workflow: &'a Workflow,
+        id: &'a str,
+        visiting: &mut Vec<&'a str>,
+        order: &mut Vec<String>,
+    ) -> Result {
+        if order.iter().any(|done| done == id) {
+            return Ok(());
+        }
+        if visiting.contains(&id) {
+            bail!("Jobs have a dependency cycle: {} -> {id}.", visiting.iter().join(" -> "));
+        }
+        let job = workflow.jobs.get(id).with_context(|| format!("There is no job `{id}`."))?;
+        visiting.push(id);
+        for need in &job.needs {
+            visit(workflow, need, visiting, order)?;
+        }
+        visiting.pop();
+        order.push(id.to_owned());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    let roots = match selected {
+        Some(job) => vec![job],
+        None => workflow.jobs.keys().map(String::as_str).collect(),
+    };
+    for root in roots {
+        visit(workflow, root, &mut default(), &mut order)?;
+    }
+    Ok(order)
+}
+
+
+
+// ===============
+// === Runtime ===
+// ===============
+
+/// Parse the contents of the `GITHUB_OUTPUT` or `GITHUB_ENV` file.
+///
+/// Each entry is either a `name=value` line or a `name<<DELIMITER` line followed by the value
+/// lines and the delimiter line.
+pub fn parse_environment_file(contents: &str) -> Result<Vec<(String, String)>> {
+    let mut ret = Vec::new();
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some((name, delimiter)) = line.split_once("<<") {
+            let mut value = Vec::new();
+            loop {
+                match lines.next() {
+                    Some(line) if line == delimiter => break,
+                    Some(line) => value.push(line),
+                    None => bail!("Value of `{name}` is not terminated with `{delimiter}`."),
+                }
+            }
+            ret.push((name.to_owned(), value.join("\n")));
+        } else if let Some((name, value)) = line.split_once('=') {
+            ret.push((name.to_owned(), value.to_owned()));
+        } else {
+            bail!("Invalid line in the environment file: `{line}`.");
+        }
+    }
+    Ok(ret)
+}
+
+/// Name of the operating system, as in the `runner.os` context.
+fn runner_os() -> &'static str {
+    match TARGET_OS {
+        OS::Windows => "Windows",
+        OS::MacOS => "macOS",
+        _ => "Linux",
+    }
+}
+
+/// The shell used by the steps that do not specify one, like on GitHub.
+fn default_shell() -> Shell {
+    if TARGET_OS == OS::Windows {
+        Shell::Pwsh
+    } else {
+        Shell::Bash
+    }
+}
+
+/// Evaluate the expressions in the environment definitions, adding them to the `env` context.
+fn extend_env(
+    context: &mut Context,
+    env: &mut BTreeMap<String, String>,
+    definitions: &BTreeMap<String, String>,
+) -> Result {
+    for (name, value) in definitions {
+        let value = context.interpolate(value)?;
+        env.insert(name.clone(), value);
+        context.set("env", json!(env));
+    }
+    Ok(())
+}
+
+/// Runs a workflow on the local machine.
+#[derive(Debug)]
+pub struct LocalRun {
+    /// The workflow to run.
+    pub workflow:          Workflow,
+    /// The directory the steps are run in, i.e. the repository root.
+    pub working_directory: PathBuf,
+    /// Values of the workflow inputs. The inputs that are not given take their default values.
+    pub inputs:            BTreeMap<String, String>,
+    /// Values of the matrix parameters. By default, the first value of each is used.
+    pub matrix:            BTreeMap<String, String>,
+    /// The `github` context, like `ref` or `sha`. Workspace and event properties are filled in.
+    pub github:            serde_json::Map<String, Value>,
+    /// Fakes of the actions, by the action name without the version.
+    pub fakes:             BTreeMap<String, Arc<dyn ActionFake>>,
+}
+
+impl LocalRun {
+    /// Prepare running the workflow in the given directory, with the [default
+    /// fakes](default_fakes).
+    pub fn new(workflow: Workflow, working_directory: impl Into<PathBuf>) -> Self {
+        Self {
+            workflow,
+            working_directory: working_directory.into(),
+            inputs: default(),
+            matrix: default(),
+            github: default(),
+            fakes: default_fakes(),
+        }
+    }
+
+    /// Register a fake for the action, replacing any previous one.
+    pub fn with_fake(mut self, action: impl Into<String>, fake: impl ActionFake + 'static) -> Self {
+        self.fakes.insert(action.into(), Arc::new(fake));
+        self
+    }
+
+    /// The `inputs` context: the given values, typed like the inputs, or the defaults.
+    fn inputs_context(&self) -> Result<Value> {
+        let on = &self.workflow.on;
+        let dispatch = on.workflow_dispatch.iter().flat_map(|dispatch| &dispatch.inputs);
+        let dispatch =
+            dispatch.map(|(name, input)| Result::Ok((name, serde_json::to_value(&input.r#type)?)));
+        let call = on.workflow_call.iter().flat_map(|call| &call.inputs);
+        let call =
+            call.map(|(name, input)| Result::Ok((name, serde_json::to_value(&input.r#type)?)));
+        let mut inputs = serde_json::Map::new();
+        for declaration in dispatch.chain(call) {
+            let (name, declaration) = declaration?;
+            let value = match self.inputs.get(name) {
+                Some(value) => match declaration["type"].as_str() {
+                    Some("boolean" | "number") => serde_json::from_str(value)
+                        .with_context(|| format!("Invalid value of input `{name}`: {value}."))?,
+                    _ => Value::String(value.clone()),
+                },
+                None => declaration["default"].clone(),
+            };
+            inputs.insert(name.clone(), value);
+        }
+        for name in self.inputs.keys() {
+            ensure!(inputs.contains_key(name), "The workflow has no input `{name}`.");
+        }
+        Ok(Value::Object(inputs))
+    }
+
+    /// The `matrix` context: the chosen combination of the job's matrix parameters.
+    fn matrix_context(&self, job: &Job) -> Value {
+        let parameters = job.strategy.iter().flat_map(|strategy| &strategy.matrix);
+        let matrix =
+            parameters.filter(|(name, _)| !matches!(name.as_str(), "include" | "exclude")).map(
+                |(name, values)| {
+                    let value = match self.matrix.get(name) {
+                        Some(value) => serde_json::from_str(value)
+                            .unwrap_or_else(|_| Value::String(value.clone())),
+                        None => match values {
+                            Value::Array(values) => values.first().cloned().unwrap_or_default(),
+                            value => value.clone(),
+                        },
+                    };
+                    (name.clone(), value)
+                },
+            );
+        Value::Object(matrix.collect())
+    }
+
+    /// Run the jobs, or only the `selected` job with the jobs it needs.
+    ///
+    /// Jobs whose needs did not succeed are skipped, unless their conditions say otherwise. Fails
+    /// only if a job could not be run at all; failed jobs are reported in the outcome.
+    pub async fn run(&self, selected: Option<&str>) -> Result<Report> {
+        let mut context = Context::default();
+        let inputs = self.inputs_context()?;
+        let mut github = self.github.clone();
+        github.insert("workspace".into(), json!(self.working_directory));
+        github.entry("event_name").or_insert_with(|| json!("workflow_dispatch"));
+        github.entry("event").or_insert_with(|| json!({ "inputs": inputs }));
+        github.entry("token").or_insert_with(|| json!(std::env::var("GITHUB_TOKEN").ok()));
+        let process_env: serde_json::Map<String, Value> =
+            std::env::vars().map(|(name, value)| (name, Value::String(value))).collect();
+        context.set("github", github);
+        context.set("inputs", inputs);
+        context.set("secrets", process_env.clone());
+        context.set("vars", process_env);
+        context.set(
+            "runner",
+            json!({
+                "os": runner_os(),
+                "arch": if TARGET_ARCH == Arch::AArch64 { "ARM64" } else { "X64" },
+                "name": "local",
+                "temp": std::env::temp_dir(),
+            }),
+        );
+
+        let mut report = Report::new();
+        let mut needs = serde_json::Map::new();
+        for id in job_order(&self.workflow, selected)? {
+            let job = &self.workflow.jobs[&id];
+            let span = info_span!("Running job.", job = %id, name = %job.name);
+            let (outcome, outputs) = self.run_job(job, &context, &needs).instrument(span).await?;
+            info!("Job {id} finished: {outcome}.");
+            needs.insert(id.clone(), json!({ "result": outcome.as_ref(), "outputs": outputs }));
+            report.push((id, outcome));
+        }
+        Ok(report)
+    }
+
+    /// Run the job's steps, returning its outcome and outputs.
+    async fn run_job(
+        &self,
+        job: &Job,
+        context: &Context,
+        needs: &serde_json::Map<String, Value>,
+    ) -> Result<(Outcome, BTreeMap<String, String>)> {
+        ensure!(job.uses.is_none(), "Reusable workflow calls are not supported in local runs.");
+        let mut context = context.clone();
+        let job_needs = needs.iter().filter(|(id, _)| job.needs.contains(*id));
+        let job_needs: serde_json::Map<String, Value> =
+            job_needs.map(|(id, need)| (id.clone(), need.clone())).collect();
+        let needs_succeeded = job_needs.values().all(|need| need["result"] == "success");
+        context.status = if needs_succeeded { Status::Success } else { Status::Failure };
+        context.set("needs", job_needs);
+        context.set("matrix", self.matrix_context(job));
+        context.set("steps", json!({}));
+
+        let mut env = BTreeMap::new();
+        context.set("env", json!(env));
+        extend_env(&mut context, &mut env, &self.workflow.env)?;
+        extend_env(&mut context, &mut env, &job.env)?;
+        if !context.condition(job.r#if.as_deref().unwrap_or("success()"))? {
+            return Ok((Outcome::Skipped, default()));
+        }
+        context.status = Status::Success;
+
+        let mut steps = serde_json::Map::new();
+        let mut paths = Vec::<PathBuf>::new();
+        for (index, step) in job.steps.iter().enumerate() {
+            let name = step.name.clone().or_else(|| step.uses.clone());
+            let name = name.unwrap_or_else(|| format!("Step {}", index + 1));
+            let mut step_context = context.clone();
+            let mut step_env = env.clone();
+            extend_env(&mut step_context, &mut step_env, &step.env)?;
+            let (outcome, outputs) =
+                if step_context.condition(step.r#if.as_deref().unwrap_or("success()"))? {
+                    info!("Running step: {name}.");
+                    let run = self.run_step(step, &step_context, step_env, &paths);
+                    match run.await {
+                        Ok(StepResult { outputs, env: new_env, paths: new_paths }) => {
+                            env.extend(new_env);
+                            context.set("env", json!(env));
+                            // Like on GitHub, the latest additions take precedence.
+                            for path in new_paths {
+                                paths.insert(0, path);
+                            }
+                            (Outcome::Success, outputs)
+                        }
+                        Err(error) => {
+                            error!("Step {name} failed: {error:?}");
+                            (Outcome::Failure, default())
+                        }
+                    }
+                } else {
+                    debug!("Skipping step: {name}.");
+                    (Outcome::Skipped, default())
+                };
+            let continues = outcome == Outcome::Failure && step.continue_on_error == Some(true);
+            let conclusion = if continues { Outcome::Success } else { outcome };
+            if conclusion == Outcome::Failure {
+                context.status = Status::Failure;
+            }
+            if let Some(id) = &step.id {
+                steps.insert(
+                    id.clone(),
+                    json!({
+                        "outputs": outputs,
+                        "outcome": outcome.as_ref(),
+                        "conclusion": conclusion.as_ref(),
+                    }),
+                );
+                context.set("steps", steps.clone());
+            }
+        }
+
+        let outcome = match context.status {
+            Status::Success => Outcome::Success,
+            Status::Failure => Outcome::Failure,
+        };
+        let outputs = job
+            .outputs
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), context.interpolate(value)?)))
+            .collect::<Result<_>>()?;
+        Ok((outcome, outputs))
+    }
+
+    /// Run a single step, with its own environment files.
+    async fn run_step(
+        &self,
+        step: &Step,
+        context: &Context,
+        env: BTreeMap<String, String>,
+        paths: &[PathBuf],
+    ) -> Result<StepResult> {
+        let files = tempfile::tempdir()?;
+        let output_file = files.path().join("output");
+        let env_file = files.path().join("env");
+        let path_file = files.path().join("path");
+        let summary_file = files.path().join("summary");
+        for file in [&output_file, &env_file, &path_file, &summary_file] {
+            crate::fs::write(file, "")?;
+        }
+
+        let run = if let Some(script) = &step.run {
+            let script = context.interpolate(script)?;
+            let shell = step.shell.unwrap_or_else(default_shell);
+            let mut command = match shell {
+                Shell::Bash => {
+                    let script_path = files.path().join("script.sh");
+                    crate::fs::write(&script_path, script)?;
+                    let mut command = Bash.cmd()?;
+                    command.args(["--noprofile", "--norc", "-eo", "pipefail"]).arg(script_path);
+                    command
+                }
+                Shell::Cmd => {
+                    let script_path = files.path().join("script.cmd");
+                    crate::fs::write(&script_path, script)?;
+                    Cmd.run_script(script_path)?
+                }
+                Shell::Pwsh => {
+                    let script_path = files.path().join("script.ps1");
+                    crate::fs::write(&script_path, script)?;
+                    PwSh.run_script(script_path)?
+                }
+            };
+            if !paths.is_empty() {
+                let path = std::env::var_os("PATH").unwrap_or_default();
+                let path = paths.iter().cloned().chain(std::env::split_paths(&path));
+                command.env("PATH", std::env::join_paths(path)?);
+            }
+            command
+                .current_dir(&self.working_directory)
+                .envs(&env)
+                .env("GITHUB_WORKSPACE", &self.working_directory)
+                .env("GITHUB_OUTPUT", &output_file)
+                .env("GITHUB_ENV", &env_file)
+                .env("GITHUB_PATH", &path_file)
+                .env("GITHUB_STEP_SUMMARY", &summary_file)
+                .run_ok()
+                .map_ok(|()| default())
+                .boxed()
+        } else if let Some(uses) = &step.uses {
+            ensure!(!uses.starts_with("./"), "Local actions are not supported in local runs.");
+            let name = uses.split_once('@').map_or(uses.as_str(), |(name, _)| name);
+            let fake = self.fakes.get(name).with_context(|| {
+                format!("There is no local fake of the action {uses}, so the step cannot be run.")
+            })?;
+            let with = match &step.with {
+                Some(with) => match serde_json::to_value(with)? {
+                    Value::Object(arguments) => arguments,
+                    other => bail!("Invalid step arguments: {other}."),
+                },
+                None => default(),
+            };
+            let with = with
+                .into_iter()
+                .map(|(name, value)| {
+                    Ok((name, context.interpolate(&expression::to_string(&value))?))
+                })
+                .collect::<Result<_>>()?;
+            let call = ActionCall {
+                uses: uses.clone(),
+                with,
+                env,
+                working_directory: self.working_directory.clone(),
+            };
+            fake.run(call)
+        } else {
+            bail!("The step neither runs a command nor uses an action.");
+        };
+        let mut outputs = match step.timeout_minutes {
+            Some(minutes) => {
+                let timeout = Duration::from_secs(u64::from(minutes) * 60);
+                tokio::time::timeout(timeout, run)
+                    .await
+                    .with_context(|| format!("Step timed out after {minutes} minutes."))??
+            }
+            None => run.await?,
+        };
+
+        outputs.extend(parse_environment_file(&crate::fs::read_to_string(&output_file)?)?);
+        let env = parse_environment_file(&crate::fs::read_to_string(&env_file)?)?;
+        let paths = crate::fs::read_to_string(&path_file)?;
+        let paths = paths.lines().filter(|line| !line.trim().is_empty()).map(PathBuf::from);
+        Ok(StepResult { outputs, env: env.into_iter().collect(), paths: paths.collect() })
+    }
+}
+
+/// Effects of a successful step on the rest of the job.
+#[derive(Clone, Debug, Default)]
+struct StepResult {
+    /// The step outputs.
+    outputs: BTreeMap<String, String>,
+    /// Environment variables set for the subsequent steps.
+    env:     BTreeMap<String, String>,
+    /// Directories prepended to `PATH` for the subsequent steps.
+    paths:   Vec<PathBuf>,
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::workflow::definition::checkout_repo_step;
+    use crate::actions::workflow::definition::get_input_expression;
+    use crate::actions::workflow::definition::wrap_expression;
+    use crate::actions::workflow::definition::WorkflowDispatch;
+    use crate::actions::workflow::definition::WorkflowDispatchInput;
+
+    fn job(needs: &[&str], steps: Vec<Step>) -> Job {
+        Job { needs: needs.iter().map(|need| need.to_string()).collect(), steps, ..default() }
+    }
+
+    fn run(script: &str) -> Step {
+        Step { run: Some(script.into()), shell: Some(Shell::Bash), ..default() }
+    }
+
+    #[test]
+    fn ordering() -> Result {
+        let mut workflow = Workflow::default();
+        workflow.jobs.insert("build".into(), job(&["prepare"], default()));
+        workflow.jobs.insert("test".into(), job(&["build", "prepare"], default()));
+        workflow.jobs.insert("prepare".into(), job(&[], default()));
+        workflow.jobs.insert("lint".into(), job(&[], default()));
+        assert_eq!(job_order(&workflow, None)?, ["prepare", "build", "lint", "test"]);
+        assert_eq!(job_order(&workflow, Some("test"))?, ["prepare", "build", "test"]);
+        assert!(job_order(&workflow, Some("deploy")).is_err());
+
+        workflow.jobs.insert("prepare".into(), job(&["test"], default()));
+        let error = job_order(&workflow, Some("test")).unwrap_err();
+        assert!(error.to_string().contains("cycle"), "{error}");
+        Ok(())
+    }
+
+    #[test]
+    fn environment_files() -> Result {
+        let contents = "name=value=1\n\ntext<<EOF\nfirst\nsecond\nEOF\nempty=\n";
+        assert_eq!(parse_environment_file(contents)?, [
+            ("name".into(), "value=1".into()),
+            ("text".into(), "first\nsecond".into()),
+            ("empty".into(), String::new()),
+        ]);
+        assert!(parse_environment_file("text<<EOF\nunterminated\n").is_err());
+        assert!(parse_environment_file("no separator\n").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn running_jobs() -> Result {
+        let temp = tempfile::tempdir()?;
+        let mut workflow = Workflow::default();
+        workflow.on.workflow_dispatch = Some(WorkflowDispatch::default().with_input(
+            "greeting",
+            WorkflowDispatchInput::new_string("Greeting.", false, Some("hello")),
+        ));
+        workflow.env.insert("GREETING".into(), get_input_expression("greeting"));
+        let steps = checkout_repo_step().into_iter().chain([
+            Step {
+                id: Some("version".into()),
+                ..run("echo \"version=1.0\" >> \"$GITHUB_OUTPUT\"\necho \"FROM_STEP=yes\" >> \"$GITHUB_ENV\"")
+            },
+            run("echo \"$GREETING $FROM_STEP\" > prepare.txt"),
+        ]);
+        let mut prepare = job(&[], steps.collect());
+        prepare.outputs.insert("version".into(), wrap_expression("steps.version.outputs.version"));
+        workflow.jobs.insert("prepare".into(), prepare);
+        workflow.jobs.insert(
+            "build".into(),
+            job(&["prepare"], vec![
+                Step { continue_on_error: Some(true), ..run("exit 1") },
+                run("echo \"${{ needs.prepare.outputs.version }}\" > build.txt"),
+                run("exit 2"),
+                run("touch unreachable.txt"),
+                Step { r#if: Some("failure()".into()), ..run("touch cleanup.txt") },
+            ]),
+        );
+        workflow.jobs.insert("test".into(), job(&["build"], vec![run("touch test.txt")]));
+
+        let mut local_run = LocalRun::new(workflow, temp.path());
+        local_run.inputs.insert("greeting".into(), "hi".into());
+        let report = local_run.run(None).await?;
+        assert_eq!(report, [
+            ("prepare".to_owned(), Outcome::Success),
+            ("build".to_owned(), Outcome::Failure),
+            ("test".to_owned(), Outcome::Skipped),
+        ]);
+        let read = |name: &str| crate::fs::read_to_string(temp.path().join(name));
+        assert_eq!(read("prepare.txt")?, "hi yes\n");
+        assert_eq!(read("build.txt")?, "1.0\n");
+        assert!(temp.path().join("cleanup.txt").exists());
+        assert!(!temp.path().join("unreachable.txt").exists());
+        assert!(!temp.path().join("test.txt").exists());
+
+        let mut unknown_action =
+            job(&[], vec![Step { uses: Some("foo/bar@v1".into()), ..default() }]);
+        unknown_action.steps.push(run("touch after.txt"));
+        let mut workflow = Workflow::default();
+        workflow.jobs.insert("unknown".into(), unknown_action);
+        let report = LocalRun::new(workflow, temp.path()).run(None).await?;
+        assert_eq!(report, [("unknown".to_owned(), Outcome::Failure)]);
+        assert!(!temp.path().join("after.txt").exists());
+        Ok(())
+    }
+}
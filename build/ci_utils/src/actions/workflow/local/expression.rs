@@ -0,0 +1,638 @@
+//! Evaluation of the GitHub Actions expressions, as used in the `${{ }}` placeholders and in the
+//! `if` conditions.
+//!
+//! Only a subset of the language is supported: the one that our generated workflows use. This
+//! covers literals, context property access (including the `*` object filter), the comparison and
+//! logical operators, the status functions and a few string functions.
+//!
+//! See <https://docs.github.com/en/actions/learn-github-actions/expressions>.
+
+use crate::prelude::*;
+
+use serde_json::Value;
+
+
+
+// ===============
+// === Context ===
+// ===============
+
+/// Outcome of the job steps run so far, as checked by the status functions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Status {
+    /// All the steps so far have succeeded.
+    #[default]
+    Success,
+    /// Some previous step has failed.
+    Failure,
+}
+
+/// Everything an expression can refer to.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    /// Contexts by their names, like `env`, `inputs` or `needs`.
+    pub contexts: serde_json::Map<String, Value>,
+    /// The outcome of the steps run so far.
+    pub status:   Status,
+}
+
+impl Context {
+    /// Set the context with the given name, e.g. `inputs`.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<Value>) {
+        self.contexts.insert(name.into(), value.into());
+    }
+
+    /// Evaluate the expression, given without the `${{ }}` braces.
+    pub fn evaluate(&self, expression: &str) -> Result<Value> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser { tokens: &tokens, position: 0, context: self };
+        let value = parser.expression()?;
+        match parser.peek() {
+            None => Result::Ok(value),
+            Some(token) => bail!("Unexpected {token:?} in expression `{expression}`."),
+        }
+        .with_context(|| format!("Failed to evaluate expression `{expression}`."))
+    }
+
+    /// Replace all the `${{ }}` placeholders in the text with the values of their expressions.
+    pub fn interpolate(&self, text: &str) -> Result<String> {
+        let mut ret = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("${{") {
+            ret.push_str(&rest[..start]);
+            let after_start = &rest[start + 3..];
+            let end = after_start
+                .find("}}")
+                .with_context(|| format!("Unterminated `${{{{` placeholder in `{text}`."))?;
+            let value = self.evaluate(&after_start[..end])?;
+            ret.push_str(&to_string(&value));
+            rest = &after_start[end + 2..];
+        }
+        ret.push_str(rest);
+        Ok(ret)
+    }
+
+    /// Evaluate the `if` condition of a job or step.
+    ///
+    /// The condition may or may not be wrapped in `${{ }}`. Unless it calls one of the status
+    /// functions, it is implicitly combined with `success()`, like on GitHub.
+    pub fn condition(&self, condition: &str) -> Result<bool> {
+        let expression = condition_expression(condition);
+        if !checks_status(expression) && self.status != Status::Success {
+            return Ok(false);
+        }
+        Ok(is_truthy(&self.evaluate(expression)?))
+    }
+}
+
+
+
+// ==============
+// === Values ===
+// ==============
+
+/// Whether the value counts as `true` in a condition.
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64().is_some_and(|number| number != 0.0),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
+    }
+}
+
+/// Text that the value is replaced with in the interpolated strings.
+pub fn to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Bool(_) | Value::Number(_) => value.to_string(),
+        Value::Array(_) | Value::Object(_) =>
+            serde_json::to_string_pretty(value).unwrap_or_default(),
+    }
+}
+
+/// Numeric value used when comparing values of different types.
+fn to_number(value: &Value) -> f64 {
+    match value {
+        Value::Null => 0.0,
+        Value::Bool(value) => f64::from(u8::from(*value)),
+        Value::Number(number) => number.as_f64().unwrap_or(f64::NAN),
+        Value::String(text) if text.trim().is_empty() => 0.0,
+        Value::String(text) => text.trim().parse().unwrap_or(f64::NAN),
+        Value::Array(_) | Value::Object(_) => f64::NAN,
+    }
+}
+
+/// Loose equality: strings are compared ignoring case and values of different types are compared
+/// as numbers.
+pub fn loosely_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::String(left), Value::String(right)) => left.to_lowercase() == right.to_lowercase(),
+        (Value::Array(_), Value::Array(_)) | (Value::Object(_), Value::Object(_)) =>
+            std::ptr::eq(left, right) || left == right,
+        _ => to_number(left) == to_number(right),
+    }
+}
+
+
+
+// ==================
+// === References ===
+// ==================
+
+/// The expression of an `if` condition, which may or may not be wrapped in `${{ }}`.
+pub fn condition_expression(condition: &str) -> &str {
+    let condition = condition.trim();
+    condition.strip_prefix("${{").and_then(|inner| inner.strip_suffix("}}")).unwrap_or(condition)
+}
+
+/// Whether the condition calls one of the status functions, so it is not implicitly combined with
+/// `success()`.
+pub fn checks_status(expression: &str) -> bool {
+    ["always(", "success(", "failure(", "cancelled("]
+        .iter()
+        .any(|function| expression.contains(function))
+}
+
+/// The expressions of all the `${{ }}` placeholders in the text.
+pub fn placeholders(text: &str) -> Result<Vec<&str>> {
+    let mut ret = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${{") {
+        let after_start = &rest[start + 3..];
+        let end = after_start
+            .find("}}")
+            .with_context(|| format!("Unterminated `${{{{` placeholder in `{text}`."))?;
+        ret.push(&after_start[..end]);
+        rest = &after_start[end + 2..];
+    }
+    Ok(ret)
+}
+
+/// The context properties that the expression refers to, each given as the path of property names,
+/// e.g. `["needs", "build", "outputs", "version"]`. The `*` filter is given as `*`.
+///
+/// The expression is not evaluated, so all the references are listed, even the ones in the branches
+/// that would be short-circuited.
+pub fn references(expression: &str) -> Result<Vec<Vec<String>>> {
+    let tokens = tokenize(expression)?;
+    let mut ret = Vec::new();
+    for (position, token) in tokens.iter().enumerate() {
+        let Token::Identifier(name) = token else { continue };
+        let is_property = position > 0 && tokens[position - 1] == Token::Dot;
+        let is_call = tokens.get(position + 1) == Some(&Token::OpenParen);
+        if is_property || is_call || matches!(name.as_str(), "true" | "false" | "null") {
+            continue;
+        }
+        let mut path = vec![name.clone()];
+        let mut rest = &tokens[position + 1..];
+        loop {
+            match rest {
+                [Token::Dot, Token::Identifier(name), ..] => path.push(name.clone()),
+                [Token::Dot, Token::Star, ..] => path.push("*".into()),
+                [Token::OpenBracket, Token::String(name), Token::CloseBracket, ..] => {
+                    path.push(name.clone());
+                    rest = &rest[1..];
+                }
+                _ => break,
+            }
+            rest = &rest[2..];
+        }
+        ret.push(path);
+    }
+    Ok(ret)
+}
+
+
+
+// =================
+// === Tokenizer ===
+// =================
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    String(String),
+    Number(f64),
+    Dot,
+    Star,
+    Comma,
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    Not,
+    And,
+    Or,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(char) = chars.next() {
+        let mut followed_by = |next: char| chars.next_if_eq(&next).is_some();
+        let token = match char {
+            _ if char.is_whitespace() => continue,
+            '.' => Token::Dot,
+            '*' => Token::Star,
+            ',' => Token::Comma,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '!' if followed_by('=') => Token::NotEqual,
+            '!' => Token::Not,
+            '=' if followed_by('=') => Token::Equal,
+            '&' if followed_by('&') => Token::And,
+            '|' if followed_by('|') => Token::Or,
+            '<' if followed_by('=') => Token::LessEqual,
+            '<' => Token::Less,
+            '>' if followed_by('=') => Token::GreaterEqual,
+            '>' => Token::Greater,
+            '\'' => {
+                // Quotes within the string literals are escaped by doubling them.
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.next_if_eq(&'\'').is_some() => text.push('\''),
+                        Some('\'') => break,
+                        Some(char) => text.push(char),
+                        None => bail!("Unterminated string literal in `{expression}`."),
+                    }
+                }
+                Token::String(text)
+            }
+            _ if char.is_ascii_digit() || char == '-' => {
+                let mut text = String::from(char);
+                while let Some(char) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.') {
+                    text.push(char);
+                }
+                let number = text.parse().with_context(|| format!("Invalid number `{text}`."))?;
+                Token::Number(number)
+            }
+            _ if char.is_alphabetic() || char == '_' => {
+                let mut text = String::from(char);
+                while let Some(char) =
+                    chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    text.push(char);
+                }
+                Token::Identifier(text)
+            }
+            _ => bail!("Unexpected character `{char}` in `{expression}`."),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+
+
+// ==============
+// === Parser ===
+// ==============
+
+/// Recursive descent parser that evaluates the expression as it goes.
+struct Parser<'a> {
+    tokens:   &'a [Token],
+    position: usize,
+    context:  &'a Context,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn accept(&mut self, token: &Token) -> bool {
+        let accepted = self.peek() == Some(token);
+        if accepted {
+            self.position += 1;
+        }
+        accepted
+    }
+
+    fn expect(&mut self, token: &Token) -> Result {
+        ensure!(self.accept(token), "Expected {token:?}, found {:?}.", self.peek());
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<Value> {
+        let mut left = self.and()?;
+        while self.accept(&Token::Or) {
+            let right = self.and()?;
+            left = if is_truthy(&left) { left } else { right };
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Value> {
+        let mut left = self.comparison()?;
+        while self.accept(&Token::And) {
+            let right = self.comparison()?;
+            left = if is_truthy(&left) { right } else { left };
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Value> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = match self.peek() {
+                Some(
+                    operator @ (Token::Equal
+                    | Token::NotEqual
+                    | Token::Less
+                    | Token::LessEqual
+                    | Token::Greater
+                    | Token::GreaterEqual),
+                ) => operator,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            let right = self.unary()?;
+            let ordering = || match (&left, &right) {
+                (Value::String(left), Value::String(right)) =>
+                    Some(left.to_lowercase().cmp(&right.to_lowercase())),
+                _ => to_number(&left).partial_cmp(&to_number(&right)),
+            };
+            let result = match operator {
+                Token::Equal => loosely_equal(&left, &right),
+                Token::NotEqual => !loosely_equal(&left, &right),
+                Token::Less => ordering().is_some_and(|ordering| ordering.is_lt()),
+                Token::LessEqual => ordering().is_some_and(|ordering| ordering.is_le()),
+                Token::Greater => ordering().is_some_and(|ordering| ordering.is_gt()),
+                _ => ordering().is_some_and(|ordering| ordering.is_ge()),
+            };
+            left = Value::Bool(result);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value> {
+        if self.accept(&Token::Not) {
+            Ok(Value::Bool(!is_truthy(&self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value> {
+        let value = match self.next() {
+            Some(Token::OpenParen) => {
+                let value = self.expression()?;
+                self.expect(&Token::CloseParen)?;
+                value
+            }
+            Some(Token::String(text)) => Value::String(text.clone()),
+            // Integral numbers are kept as integers, so they are interpolated without a fraction.
+            Some(Token::Number(number)) if number.fract() == 0.0 => Value::from(*number as i64),
+            Some(Token::Number(number)) => serde_json::Number::from_f64(*number).into(),
+            Some(Token::Identifier(name)) => match name.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ if self.accept(&Token::OpenParen) => {
+                    let mut arguments = Vec::new();
+                    if !self.accept(&Token::CloseParen) {
+                        loop {
+                            arguments.push(self.expression()?);
+                            if self.accept(&Token::CloseParen) {
+                                break;
+                            }
+                            self.expect(&Token::Comma)?;
+                        }
+                    }
+                    self.call(name, &arguments)?
+                }
+                _ => self.context.contexts.get(name).cloned().unwrap_or_default(),
+            },
+            token => bail!("Unexpected {token:?}."),
+        };
+        self.properties(value)
+    }
+
+    /// Apply the property dereferences following the value, like `.name`, `['name']` or `.*`.
+    fn properties(&mut self, mut value: Value) -> Result<Value> {
+        // After the `*` filter, the properties are looked up in each of the filtered elements.
+        let mut filtered = false;
+        loop {
+            let key = if self.accept(&Token::Dot) {
+                match self.next() {
+                    Some(Token::Identifier(name)) => Some(Value::String(name.clone())),
+                    Some(Token::Star) => None,
+                    token => bail!("Expected a property name, found {token:?}."),
+                }
+            } else if self.accept(&Token::OpenBracket) {
+                let key = if self.accept(&Token::Star) { None } else { Some(self.expression()?) };
+                self.expect(&Token::CloseBracket)?;
+                key
+            } else {
+                return Ok(value);
+            };
+            value = match key {
+                None => {
+                    filtered = true;
+                    match value {
+                        Value::Array(elements) => Value::Array(elements),
+                        Value::Object(object) =>
+                            Value::Array(object.into_iter().map(|(_, value)| value).collect()),
+                        _ => Value::Array(default()),
+                    }
+                }
+                Some(key) if filtered => match value {
+                    Value::Array(elements) => Value::Array(
+                        elements
+                            .iter()
+                            .map(|element| index(element, &key))
+                            .filter(|value| !value.is_null())
+                            .collect(),
+                    ),
+                    _ => Value::Null,
+                },
+                Some(key) => index(&value, &key),
+            };
+        }
+    }
+
+    fn call(&self, name: &str, arguments: &[Value]) -> Result<Value> {
+        let argument = |position: usize| arguments.get(position).cloned().unwrap_or_default();
+        let text = |position: usize| to_string(&argument(position)).to_lowercase();
+        let status = self.context.status;
+        Ok(match name.to_lowercase().as_str() {
+            "always" => Value::Bool(true),
+            "success" => Value::Bool(status == Status::Success),
+            "failure" => Value::Bool(status == Status::Failure),
+            "cancelled" => Value::Bool(false),
+            "contains" => Value::Bool(match argument(0) {
+                Value::Array(elements) =>
+                    elements.iter().any(|element| loosely_equal(element, &argument(1))),
+                _ => text(0).contains(&text(1)),
+            }),
+            "startswith" => Value::Bool(text(0).starts_with(&text(1))),
+            "endswith" => Value::Bool(text(0).ends_with(&text(1))),
+            "format" => {
+                let mut ret = to_string(&argument(0));
+                for (position, value) in arguments.iter().enumerate().skip(1) {
+                    ret = ret.replace(&format!("{{{}}}", position - 1), &to_string(value));
+                }
+                Value::String(ret)
+            }
+            "join" => {
+                let separator = arguments.get(1).map_or_else(|| ",".into(), to_string);
+                match argument(0) {
+                    Value::Array(elements) =>
+                        Value::String(elements.iter().map(to_string).join(&separator)),
+                    value => Value::String(to_string(&value)),
+                }
+            }
+            "tojson" => Value::String(serde_json::to_string_pretty(&argument(0))?),
+            "fromjson" => serde_json::from_str(&to_string(&argument(0)))?,
+            _ => bail!("Function `{name}` is not supported in local runs."),
+        })
+    }
+}
+
+/// Look up the property of the object or the element of the array.
+fn index(value: &Value, key: &Value) -> Value {
+    match (value, key) {
+        (Value::Object(object), key) => {
+            let key = to_string(key);
+            object
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&key))
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default()
+        }
+        (Value::Array(elements), Value::Number(position)) => position
+            .as_f64()
+            .and_then(|position| elements.get(position as usize))
+            .cloned()
+            .unwrap_or_default(),
+        _ => Value::Null,
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::workflow::definition::env_expression;
+    use crate::actions::workflow::definition::get_input_expression;
+    use crate::actions::workflow::definition::is_github_hosted;
+    use crate::actions::workflow::definition::secret_expression;
+    use crate::actions::workflow::definition::wrap_expression;
+    use serde_json::json;
+
+    fn context() -> Context {
+        let mut context = Context::default();
+        context.set("env", json!({"ENSO_RELEASE_ID": "42", "GITHUB_REF": "refs/heads/develop"}));
+        context.set("inputs", json!({"clean": true, "version": "2024.1.1"}));
+        context.set("secrets", json!({"TOKEN": "hunter2"}));
+        context.set("runner", json!({"os": "Linux", "name": "local"}));
+        context.set("needs", json!({"prepare": {"outputs": {"version": "1.0"}}}));
+        context.set(
+            "github",
+            json!({"ref": "refs/heads/develop", "event": {"pull_request": {"labels": [
+                {"name": "CI: Clean build required"},
+                {"name": "CI: No changelog needed"},
+            ]}}}),
+        );
+        context
+    }
+
+    #[test]
+    fn interpolation() -> Result {
+        let context = context();
+        let text = format!(
+            "{} {} {} {}",
+            env_expression(&crate::actions::env::GITHUB_REF),
+            get_input_expression("version"),
+            secret_expression("TOKEN"),
+            wrap_expression("needs.prepare.outputs.version"),
+        );
+        assert_eq!(context.interpolate(&text)?, "refs/heads/develop 2024.1.1 hunter2 1.0");
+        assert_eq!(context.interpolate("${{env.ENSO_RELEASE_ID}}")?, "42");
+        assert_eq!(context.interpolate("${{ inputs.missing }}!")?, "!");
+        assert_eq!(context.interpolate("${{ format('{0}-{1}', 'a', 1) }}")?, "a-1");
+        assert_eq!(context.interpolate("no placeholders")?, "no placeholders");
+        assert!(context.interpolate("${{ env.FOO").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn conditions() -> Result {
+        let mut context = context();
+        assert!(context.condition("github.ref == 'refs/heads/develop'")?);
+        assert!(context.condition("github.ref == 'REFS/HEADS/DEVELOP'")?);
+        assert!(!context.condition("github.ref != 'refs/heads/develop'")?);
+        assert!(context.condition(
+            "contains(github.event.pull_request.labels.*.name, 'CI: Clean build required') \
+            || inputs.clean"
+        )?);
+        assert!(!context.condition("contains(github.event.pull_request.labels.*.name, 'CI')")?);
+        assert!(context.condition("true == inputs.clean")?);
+        assert!(context.condition("${{ !(inputs.missing) && runner.os == 'Linux' }}")?);
+        assert!(!context.condition(&is_github_hosted())?);
+        assert!(context.condition("startsWith(runner.name, 'LOC')")?);
+        assert!(context.condition("inputs['version'] == '2024.1.1'")?);
+        assert!(context.condition("1 < 2 && '1' == 1")?);
+
+        context.status = Status::Failure;
+        assert!(!context.condition("runner.os == 'Linux'")?);
+        assert!(context.condition("failure() && runner.os == 'Linux'")?);
+        assert!(context.condition("(success() || failure()) && inputs.clean")?);
+        assert!(context.condition("always()")?);
+        Ok(())
+    }
+
+    #[test]
+    fn listing_references() -> Result {
+        let text = "${{ needs.build.outputs.version }}-${{ format('{0}', inputs['name']) }}";
+        let expressions = placeholders(text)?;
+        assert_eq!(expressions, [
+            " needs.build.outputs.version ",
+            " format('{0}', inputs['name']) "
+        ]);
+        assert_eq!(references(expressions[0])?, [["needs", "build", "outputs", "version"]]);
+        assert_eq!(references(expressions[1])?, [["inputs", "name"]]);
+        let condition = condition_expression(
+            "${{ always() && contains(github.event.pull_request.labels.*.name, 'x') || !env.FOO }}",
+        );
+        assert!(checks_status(condition));
+        assert_eq!(references(condition)?, [
+            vec!["github", "event", "pull_request", "labels", "*", "name"],
+            vec!["env", "FOO"],
+        ]);
+        assert!(placeholders("${{ env.FOO").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        let context = context();
+        assert!(context.evaluate("env.").is_err());
+        assert!(context.evaluate("'unterminated").is_err());
+        assert!(context.evaluate("(true").is_err());
+        assert!(context.evaluate("true false").is_err());
+        assert!(context.evaluate("hashFiles('**/Cargo.lock')").is_err());
+    }
+}
@@ -0,0 +1,608 @@
+//! Static checks of the [workflow definitions](crate::actions::workflow::definition).
+//!
+//! The model allows constructing workflows that GitHub rejects only when they are run, or that
+//! silently misbehave, like reading an output of a step that does not exist. These checks catch
+//! such mistakes before the workflows are written out.
+
+use crate::prelude::*;
+
+use crate::actions::workflow::definition::Job;
+use crate::actions::workflow::definition::JobSecrets;
+use crate::actions::workflow::definition::Workflow;
+use crate::actions::workflow::definition::WorkflowToWrite;
+use crate::actions::workflow::local::expression;
+use crate::actions::workflow::local::expression::Context;
+
+
+
+// =============
+// === Issue ===
+// =============
+
+/// A mistake found in a workflow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    /// Name of the workflow.
+    pub workflow: String,
+    /// Identifier of the job, unless the issue concerns the whole workflow.
+    pub job:      Option<String>,
+    /// Description of the mistake.
+    pub message:  String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Workflow `{}`", self.workflow)?;
+        if let Some(job) = &self.job {
+            write!(f, ", job `{job}`")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+
+
+// ============
+// === Text ===
+// ============
+
+/// A workflow string that may contain expressions.
+#[derive(Clone, Debug)]
+struct Text<'a> {
+    /// The job the text belongs to, if any.
+    job:          Option<&'a str>,
+    /// Where the text is, e.g. ``step `Build`, condition``.
+    location:     String,
+    /// The text itself.
+    text:         &'a str,
+    /// Whether the text is an `if` condition, i.e. an expression that does not need the `${{ }}`.
+    is_condition: bool,
+    /// Identifiers of the steps whose outputs can be read, if the text belongs to a job.
+    steps:        BTreeSet<&'a str>,
+}
+
+impl<'a> Text<'a> {
+    /// The expressions in the text.
+    fn expressions(&self) -> Result<Vec<&'a str>> {
+        if self.is_condition {
+            Ok(vec![expression::condition_expression(self.text)])
+        } else {
+            expression::placeholders(self.text)
+        }
+    }
+}
+
+/// All the strings of the step arguments.
+fn argument_strings(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::String(text) => vec![text.clone()],
+        serde_json::Value::Array(values) => values.iter().flat_map(argument_strings).collect(),
+        serde_json::Value::Object(map) => map.values().flat_map(argument_strings).collect(),
+        _ => default(),
+    }
+}
+
+/// All the texts of the workflow that may contain expressions.
+///
+/// The step arguments are serialized first, so they are returned separately as owned strings,
+/// together with their job and location.
+fn texts(workflow: &Workflow) -> (Vec<Text>, Vec<(&str, String, String)>) {
+    let mut texts = Vec::new();
+    let mut arguments = Vec::new();
+    for (name, value) in &workflow.env {
+        let location = format!("environment variable `{name}`");
+        let text = Text { job: None, location, text: value, is_condition: false, steps: default() };
+        texts.push(text);
+    }
+    for (id, job) in &workflow.jobs {
+        let all_steps: BTreeSet<&str> = job.steps.iter().filter_map(|s| s.id.as_deref()).collect();
+        let job_text = |location: String, text, is_condition| Text {
+            job: Some(id.as_str()),
+            location,
+            text,
+            is_condition,
+            steps: all_steps.clone(),
+        };
+        if let Some(condition) = &job.r#if {
+            texts.push(job_text("condition".into(), condition, true));
+        }
+        for (name, value) in &job.env {
+            texts.push(job_text(format!("environment variable `{name}`"), value, false));
+        }
+        for (name, value) in &job.outputs {
+            texts.push(job_text(format!("output `{name}`"), value, false));
+        }
+        for (name, value) in &job.with {
+            texts.push(job_text(format!("input `{name}` of the called workflow"), value, false));
+        }
+
+        let mut previous_steps = BTreeSet::new();
+        for (index, step) in job.steps.iter().enumerate() {
+            let step_name = step.name.clone().or_else(|| step.id.clone());
+            let step_name = step_name.unwrap_or_else(|| format!("#{}", index + 1));
+            let step_text = |location: &str, text, is_condition| Text {
+                job: Some(id.as_str()),
+                location: format!("step `{step_name}`, {location}"),
+                text,
+                is_condition,
+                steps: previous_steps.clone(),
+            };
+            if let Some(condition) = &step.r#if {
+                texts.push(step_text("condition", condition, true));
+            }
+            if let Some(run) = &step.run {
+                texts.push(step_text("command", run, false));
+            }
+            for (name, value) in &step.env {
+                texts.push(step_text(&format!("environment variable `{name}`"), value, false));
+            }
+            if let Some(with) = &step.with {
+                let location = format!("step `{step_name}`, arguments");
+                let value = serde_json::to_value(with).unwrap_or_default();
+                for text in argument_strings(&value) {
+                    arguments.push((id.as_str(), location.clone(), text));
+                }
+            }
+            if let Some(step_id) = &step.id {
+                previous_steps.insert(step_id.as_str());
+            }
+        }
+    }
+    (texts, arguments)
+}
+
+
+
+// ==============
+// === Checks ===
+// ==============
+
+/// Find the dependency cycles among the jobs. Each cycle is listed once, starting with its
+/// alphabetically first job.
+fn cycles(workflow: &Workflow) -> BTreeSet<Vec<String>> {
+    fn visit<'a>(
+        workflow: &'a Workflow,
+        id: &'a str,
+        stack: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+        cycles: &mut BTreeSet<Vec<String>>,
+    ) {
+        if let Some(position) = stack.iter().position(|visited| *visited == id) {
+            let mut cycle = stack[position..].iter().map(|id| id.to_string()).collect_vec();
+            let first = cycle.iter().position_min().unwrap_or_default();
+            cycle.rotate_left(first);
+            cycles.insert(cycle);
+            return;
+        }
+        if !done.insert(id) {
+            return;
+        }
+        let Some(job) = workflow.jobs.get(id) else { return };
+        stack.push(id);
+        for need in &job.needs {
+            visit(workflow, need, stack, done, cycles);
+        }
+        stack.pop();
+    }
+
+    let mut ret = BTreeSet::new();
+    let mut done = BTreeSet::new();
+    for id in workflow.jobs.keys() {
+        visit(workflow, id, &mut default(), &mut done, &mut ret);
+    }
+    ret
+}
+
+/// Whether the condition is always false, as it depends on nothing that could make it true.
+fn is_never_true(condition: &str) -> bool {
+    let expression = expression::condition_expression(condition);
+    let is_constant = expression::references(expression).is_ok_and(|paths| paths.is_empty());
+    is_constant
+        && !expression::checks_status(expression)
+        && Context::default().condition(condition).is_ok_and(|value| !value)
+}
+
+/// Describe the problem with the context property reference, if there is one.
+fn check_reference(
+    workflow: &Workflow,
+    job: Option<&Job>,
+    steps: &BTreeSet<&str>,
+    path: &[String],
+) -> Option<String> {
+    let on = &workflow.on;
+    let segment = |index: usize| path.get(index).map(String::as_str);
+    match (segment(0)?, segment(1)?) {
+        ("inputs", input) => {
+            let dispatch = on.workflow_dispatch.iter().flat_map(|dispatch| dispatch.inputs.keys());
+            let call = on.workflow_call.iter().flat_map(|call| call.inputs.keys());
+            let mut declared = dispatch.chain(call);
+            (!declared.any(|declared| declared == input)).then(|| {
+                format!("reads input `{input}`, which is not declared by the workflow's triggers.")
+            })
+        }
+        ("secrets", secret) => {
+            let declared = &on.workflow_call.as_ref()?.secrets;
+            let is_declared = declared.is_empty()
+                || declared.contains_key(secret)
+                || secret == crate::github::GITHUB_TOKEN.name();
+            (!is_declared).then(|| {
+                format!(
+                    "uses secret `{secret}`, which is not declared by the workflow_call trigger."
+                )
+            })
+        }
+        ("needs", needed) => {
+            let Some(job) = job else {
+                return Some(format!("refers to job `{needed}` outside of any job."));
+            };
+            if !job.needs.contains(needed) {
+                return Some(format!("refers to job `{needed}`, which it does not need."));
+            }
+            let needed_job = workflow.jobs.get(needed)?;
+            let output = match (segment(2), segment(3)) {
+                (Some("outputs"), Some(output)) => output,
+                _ => return None,
+            };
+            let is_defined = needed_job.uses.is_some() || needed_job.outputs.contains_key(output);
+            (!is_defined).then(|| {
+                format!("reads output `{output}` of job `{needed}`, which does not define it.")
+            })
+        }
+        ("steps", step) => {
+            let Some(job) = job else {
+                return Some(format!("refers to step `{step}` outside of any job."));
+            };
+            if steps.contains(step) {
+                None
+            } else if job.steps.iter().any(|candidate| candidate.id.as_deref() == Some(step)) {
+                Some(format!("refers to step `{step}`, which does not run before it."))
+            } else {
+                Some(format!("refers to step `{step}`, which is not in the job."))
+            }
+        }
+        ("matrix", parameter) => {
+            let job = job?;
+            let strategy = job.strategy.as_ref();
+            let is_defined = strategy.is_some_and(|strategy| {
+                strategy.matrix.contains_key(parameter) || strategy.matrix.contains_key("include")
+            });
+            (!is_defined).then(|| {
+                format!("reads matrix parameter `{parameter}`, which the job does not define.")
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Check the workflow, returning all the mistakes found.
+///
+/// Calls of other workflows are not checked, see [`validate`] for that.
+pub fn check(workflow: &Workflow) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut report = |job: Option<&str>, message: String| {
+        let job = job.map(ToString::to_string);
+        issues.push(Issue { workflow: workflow.name.clone(), job, message });
+    };
+
+    for (id, job) in &workflow.jobs {
+        for need in &job.needs {
+            if !workflow.jobs.contains_key(need) {
+                report(Some(id), format!("needs `{need}`, which is not a job of the workflow."));
+            }
+        }
+    }
+
+    let cycles = cycles(workflow);
+    for cycle in &cycles {
+        let path = cycle.iter().chain(cycle.first()).join(" -> ");
+        report(None, format!("Jobs form a dependency cycle: {path}."));
+    }
+
+    // Jobs that never run, either due to their own condition or because they need such a job.
+    // The ones in cycles never run either, but these are reported above.
+    let in_cycle: BTreeSet<&str> = cycles.iter().flatten().map(String::as_str).collect();
+    let mut never_run = BTreeMap::<&str, String>::new();
+    for (id, job) in &workflow.jobs {
+        if job.r#if.as_deref().is_some_and(is_never_true) {
+            never_run.insert(id, "never runs, as its condition is always false.".into());
+        }
+    }
+    loop {
+        let newly_found = workflow
+            .jobs
+            .iter()
+            .filter(|(id, _)| {
+                !never_run.contains_key(id.as_str()) && !in_cycle.contains(id.as_str())
+            })
+            .filter(|(_, job)| !job.r#if.as_deref().is_some_and(expression::checks_status))
+            .filter_map(|(id, job)| {
+                let need = job.needs.iter().find(|need| {
+                    never_run.contains_key(need.as_str()) || in_cycle.contains(need.as_str())
+                })?;
+                Some((
+                    id.as_str(),
+                    format!("never runs, as it needs job `{need}`, which never runs."),
+                ))
+            })
+            .collect_vec();
+        if newly_found.is_empty() {
+            break;
+        }
+        never_run.extend(newly_found);
+    }
+    for (id, message) in never_run {
+        report(Some(id), message);
+    }
+
+    let (texts, arguments) = texts(workflow);
+    let arguments = arguments.iter().map(|(job, location, text)| Text {
+        job: Some(job),
+        location: location.clone(),
+        text,
+        is_condition: false,
+        steps: default(),
+    });
+    for text in texts.into_iter().chain(arguments) {
+        let job = text.job.and_then(|id| workflow.jobs.get(id));
+        let expressions = match text.expressions() {
+            Ok(expressions) => expressions,
+            Err(error) => {
+                report(text.job, format!("{}: {error}", text.location));
+                continue;
+            }
+        };
+        for expression in expressions {
+            match expression::references(expression) {
+                Ok(paths) =>
+                    for path in paths {
+                        if let Some(problem) = check_reference(workflow, job, &text.steps, &path) {
+                            report(text.job, format!("{}: {problem}", text.location));
+                        }
+                    },
+                Err(error) => report(
+                    text.job,
+                    format!("{}: invalid expression `{expression}`: {error}", text.location),
+                ),
+            }
+        }
+    }
+    issues
+}
+
+/// Secrets used by the workflow, other than the always available `GITHUB_TOKEN`.
+fn used_secrets(workflow: &Workflow) -> BTreeSet<String> {
+    let (texts, arguments) = texts(workflow);
+    let texts = texts.iter().map(|text| (text.text, text.is_condition));
+    let arguments = arguments.iter().map(|(_, _, text)| (text.as_str(), false));
+    let mut ret = BTreeSet::new();
+    for (text, is_condition) in texts.chain(arguments) {
+        let expressions = if is_condition {
+            vec![expression::condition_expression(text)]
+        } else {
+            expression::placeholders(text).unwrap_or_default()
+        };
+        for expression in expressions {
+            for path in expression::references(expression).unwrap_or_default() {
+                if let [context, secret, ..] = path.as_slice() {
+                    if context == "secrets" && secret != crate::github::GITHUB_TOKEN.name() {
+                        ret.insert(secret.clone());
+                    }
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Check the calls from the job to the other generated workflows.
+fn check_calls(workflows: &[WorkflowToWrite], caller: &Workflow) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for (id, job) in &caller.jobs {
+        let Some(uses) = job.uses.as_deref() else { continue };
+        let mut report = |message: String| {
+            let job = Some(id.clone());
+            issues.push(Issue { workflow: caller.name.clone(), job, message });
+        };
+        if !uses.starts_with("./") {
+            continue;
+        }
+        let file_name = Path::new(uses).file_name();
+        let Some(callee) = workflows.iter().find(|w| w.path.file_name() == file_name) else {
+            report(format!("calls `{uses}`, which is not one of the generated workflows."));
+            continue;
+        };
+        let callee = &callee.workflow;
+        let Some(call) = &callee.on.workflow_call else {
+            report(format!(
+                "calls workflow `{}`, which has no workflow_call trigger.",
+                callee.name
+            ));
+            continue;
+        };
+        for input in job.with.keys() {
+            if !call.inputs.contains_key(input) {
+                report(format!(
+                    "passes input `{input}`, which workflow `{}` does not declare.",
+                    callee.name
+                ));
+            }
+        }
+        for (name, input) in &call.inputs {
+            if input.required && !job.with.contains_key(name) {
+                report(format!(
+                    "does not pass the required input `{name}` of workflow `{}`.",
+                    callee.name
+                ));
+            }
+        }
+        for secret in used_secrets(callee) {
+            let is_passed = match &job.secrets {
+                Some(JobSecrets::Inherit) => true,
+                Some(JobSecrets::Map(secrets)) => secrets.contains_key(&secret),
+                None => false,
+            };
+            if !is_passed {
+                report(format!(
+                    "does not pass secret `{secret}`, which workflow `{}` uses.",
+                    callee.name
+                ));
+            }
+        }
+    }
+    issues
+}
+
+/// Check the workflows and the calls between them. Fails with the list of all the mistakes found.
+pub fn validate(workflows: &[WorkflowToWrite]) -> Result {
+    let issues = workflows
+        .iter()
+        .flat_map(|WorkflowToWrite { workflow, .. }| {
+            check(workflow).into_iter().chain(check_calls(workflows, workflow))
+        })
+        .collect_vec();
+    ensure!(
+        issues.is_empty(),
+        "Found {} mistakes in the workflows:\n{}",
+        issues.len(),
+        issues.iter().map(|issue| format!(" * {issue}")).join("\n")
+    );
+    Ok(())
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::workflow::definition::get_input_expression;
+    use crate::actions::workflow::definition::secret_expression;
+    use crate::actions::workflow::definition::wrap_expression;
+    use crate::actions::workflow::definition::Step;
+    use crate::actions::workflow::definition::WorkflowCall;
+    use crate::actions::workflow::definition::WorkflowDispatch;
+    use crate::actions::workflow::definition::WorkflowDispatchInput;
+
+    fn job(needs: &[&str], steps: Vec<Step>) -> Job {
+        Job { needs: needs.iter().map(|need| need.to_string()).collect(), steps, ..default() }
+    }
+
+    fn run(script: impl Into<String>) -> Step {
+        Step { run: Some(script.into()), ..default() }
+    }
+
+    fn messages(workflow: &Workflow) -> Vec<(Option<String>, String)> {
+        check(workflow).into_iter().map(|issue| (issue.job, issue.message)).collect()
+    }
+
+    #[test]
+    fn job_graph() {
+        let mut workflow = Workflow { name: "Test".into(), ..default() };
+        workflow.jobs.insert("build".into(), job(&["prepare"], default()));
+        workflow.jobs.insert("prepare".into(), job(&[], default()));
+        assert!(check(&workflow).is_empty());
+
+        workflow.jobs.insert("test".into(), job(&["compile"], default()));
+        workflow.jobs.insert("a".into(), job(&["b"], default()));
+        workflow.jobs.insert("b".into(), job(&["a"], default()));
+        workflow.jobs.insert("c".into(), job(&["a"], default()));
+        workflow.jobs.insert("disabled".into(), Job { r#if: Some("false".into()), ..default() });
+        workflow.jobs.insert("report".into(), Job {
+            r#if: Some("always()".into()),
+            ..job(&["disabled"], default())
+        });
+        workflow.jobs.insert("deploy".into(), job(&["disabled"], default()));
+        assert_eq!(messages(&workflow), [
+            (Some("test".into()), "needs `compile`, which is not a job of the workflow.".into()),
+            (None, "Jobs form a dependency cycle: a -> b -> a.".into()),
+            (Some("c".into()), "never runs, as it needs job `a`, which never runs.".into()),
+            (
+                Some("deploy".into()),
+                "never runs, as it needs job `disabled`, which never runs.".into()
+            ),
+            (Some("disabled".into()), "never runs, as its condition is always false.".into()),
+        ]);
+        let issue = check(&workflow).remove(0);
+        assert_eq!(
+            issue.to_string(),
+            "Workflow `Test`, job `test`: needs `compile`, which is not a job of the workflow."
+        );
+    }
+
+    #[test]
+    fn references() {
+        let mut workflow = Workflow::default();
+        workflow.on.workflow_dispatch = Some(WorkflowDispatch::default().with_input(
+            "version",
+            WorkflowDispatchInput::new_string("Version.", false, None::<String>),
+        ));
+        workflow.env.insert("VERSION".into(), get_input_expression("version"));
+        workflow.env.insert("CLEAN".into(), get_input_expression("clean"));
+        let mut producer = job(&[], vec![
+            Step {
+                id: Some("build".into()),
+                ..run(format!("echo {}", wrap_expression("steps.package.outputs.path")))
+            },
+            Step { id: Some("package".into()), ..run("echo path=out >> $GITHUB_OUTPUT") },
+            run(format!("echo {}", wrap_expression("steps.setup.outcome"))),
+        ]);
+        producer.outputs.insert("path".into(), wrap_expression("steps.package.outputs.path"));
+        workflow.jobs.insert("producer".into(), producer);
+        let consumer = job(&["producer"], vec![
+            run(format!("echo {}", wrap_expression("needs.producer.outputs.path"))),
+            run(format!("echo {}", wrap_expression("needs.producer.outputs.size"))),
+            run(format!("echo {}", wrap_expression("needs.other.result"))),
+            Step { r#if: Some("matrix.os == 'linux'".into()), ..run("true") },
+        ]);
+        workflow.jobs.insert("consumer".into(), consumer);
+        assert_eq!(messages(&workflow), [
+            (None, "environment variable `CLEAN`: reads input `clean`, which is not declared by the workflow's triggers.".into()),
+            (Some("consumer".into()), "step `#2`, command: reads output `size` of job `producer`, which does not define it.".into()),
+            (Some("consumer".into()), "step `#3`, command: refers to job `other`, which it does not need.".into()),
+            (Some("consumer".into()), "step `#4`, condition: reads matrix parameter `os`, which the job does not define.".into()),
+            (Some("producer".into()), "step `build`, command: refers to step `package`, which does not run before it.".into()),
+            (Some("producer".into()), "step `#3`, command: refers to step `setup`, which is not in the job.".into()),
+        ]);
+    }
+
+    #[test]
+    fn calls() -> Result {
+        let dispatch = WorkflowDispatch::default().with_input(
+            "version",
+            WorkflowDispatchInput::new_string("Version.", true, None::<String>),
+        );
+        let mut callee = Workflow { name: "Release".into(), ..default() };
+        callee.on.workflow_call = Some(WorkflowCall::try_from(dispatch)?);
+        callee.jobs.insert(
+            "upload".into(),
+            job(&[], vec![run(format!("echo {}", secret_expression("UPLOAD_TOKEN")))]),
+        );
+        let mut caller = Workflow { name: "Nightly".into(), ..default() };
+        caller.jobs.insert("release".into(), Job {
+            uses: Some("./.github/workflows/release.yml".into()),
+            with: [("designator".into(), "nightly".into())].into(),
+            ..default()
+        });
+        let to_write = |workflow: &Workflow, file: &str| WorkflowToWrite {
+            workflow: workflow.clone(),
+            path:     PathBuf::from(".github/workflows").join(file),
+            source:   "test".into(),
+        };
+        let mut workflows =
+            vec![to_write(&callee, "release.yml"), to_write(&caller, "nightly.yml")];
+        let error = validate(&workflows).unwrap_err().to_string();
+        assert!(error.starts_with("Found 3 mistakes in the workflows:"), "{error}");
+        assert!(error.contains("passes input `designator`"), "{error}");
+        assert!(error.contains("does not pass the required input `version`"), "{error}");
+        assert!(error.contains("does not pass secret `UPLOAD_TOKEN`"), "{error}");
+
+        let release = caller.jobs.get_mut("release").unwrap();
+        release.with = [("version".into(), "1.0.0".into())].into();
+        release.secrets = Some(JobSecrets::Inherit);
+        workflows[1] = to_write(&caller, "nightly.yml");
+        validate(&workflows)
+    }
+}
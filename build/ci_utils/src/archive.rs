@@ -5,6 +5,9 @@ use crate::programs::tar::Compression;
 use crate::programs::tar::Tar;
 use crate::programs::SevenZip;
 
+use std::io::Write;
+use std::time::Duration;
+use std::time::Instant;
 use tracing::Span;
 
 
@@ -13,11 +16,197 @@ use tracing::Span;
 // ==============
 
 pub mod extract_files;
+pub mod reproducible;
 pub mod tar;
 pub mod zip;
 
 
 
+// =================
+// === Constants ===
+// =================
+
+/// How often the progress of packing or unpacking a large archive is logged.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+
+
+// ============
+// === Mode ===
+// ============
+
+/// How the metadata of the packed files is stored in the archive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Store the metadata of the files as they are on the disk.
+    #[default]
+    Preserve,
+    /// Normalize the metadata, so packing the same file tree always yields the same bytes: all
+    /// entries get the given modification time (in seconds since the Unix epoch), no ownership
+    /// and either `755` (directories and executables) or `644` permissions.
+    ///
+    /// The entries are always stored in the order of their names, in both modes.
+    Deterministic { mtime: u64 },
+}
+
+impl Mode {
+    /// The deterministic mode, with the modification time taken from the `SOURCE_DATE_EPOCH`
+    /// environment variable, or the Unix epoch if it is not set.
+    pub fn deterministic() -> Result<Self> {
+        let variable = crate::env::known::SOURCE_DATE_EPOCH;
+        let mtime = if variable.is_set() { variable.get()? } else { 0 };
+        Ok(Mode::Deterministic { mtime })
+    }
+
+    /// Normalized Unix permissions of an entry, for the deterministic mode.
+    pub(crate) fn normalized_permissions(metadata: &std::fs::Metadata) -> u32 {
+        #[cfg(unix)]
+        let executable = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode() & 0o100 != 0
+        };
+        #[cfg(not(unix))]
+        let executable = false;
+        if metadata.is_dir() || executable {
+            0o755
+        } else {
+            0o644
+        }
+    }
+}
+
+
+
+// ================
+// === Progress ===
+// ================
+
+/// Reports the progress of packing or unpacking an archive through `tracing`.
+#[derive(Debug)]
+pub(crate) struct Progress {
+    action:      &'static str,
+    entries:     usize,
+    bytes:       u64,
+    started:     Instant,
+    last_report: Instant,
+}
+
+impl Progress {
+    pub(crate) fn new(action: &'static str) -> Self {
+        let now = Instant::now();
+        Self { action, entries: 0, bytes: 0, started: now, last_report: now }
+    }
+
+    /// Record a processed entry of the given size.
+    pub(crate) fn entry(&mut self, path: &Path, size: u64) {
+        trace!("{} {}.", self.action, path.display());
+        self.entries += 1;
+        self.bytes += size;
+        if self.last_report.elapsed() >= PROGRESS_INTERVAL {
+            self.last_report = Instant::now();
+            info!("{} {} entries ({}) so far.", self.action, self.entries, self.human_bytes());
+        }
+    }
+
+    /// Log the summary of the whole operation.
+    pub(crate) fn finish(self) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        debug!(
+            "{} {} entries ({}) in {elapsed:.1}s.",
+            self.action,
+            self.entries,
+            self.human_bytes()
+        );
+    }
+
+    fn human_bytes(&self) -> indicatif::HumanBytes {
+        indicatif::HumanBytes(self.bytes)
+    }
+}
+
+
+
+// =============
+// === Paths ===
+// =============
+
+/// Validate the path of an archive entry, so it cannot be extracted outside the output directory.
+///
+/// Absolute paths and paths with `..` components are rejected. The returned path is relative and
+/// consists only of normal components (it is empty for the root entry, e.g. `./`).
+pub fn enclosed_path(path_in_archive: impl AsRef<Path>) -> Result<PathBuf> {
+    use std::path::Component;
+    let path_in_archive = path_in_archive.as_ref();
+    let mut ret = PathBuf::new();
+    for component in path_in_archive.components() {
+        match component {
+            Component::Normal(name) => ret.push(name),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => bail!(
+                "The archive entry {} points outside the output directory.",
+                path_in_archive.display()
+            ),
+        }
+    }
+    Ok(ret)
+}
+
+/// Check that the parent directory of a path being extracted does not lead outside the output
+/// directory, e.g. through a symlink extracted earlier from the same archive.
+///
+/// The `output_dir` must be canonical. The parent directory does not need to exist yet, the deepest
+/// existing ancestor is checked instead, so this should be called before creating it.
+pub(crate) fn ensure_parent_inside(output_dir: &Path, path: &Path) -> Result {
+    let existing = path.ancestors().skip(1).find(|ancestor| ancestor.symlink_metadata().is_ok());
+    if let Some(existing) = existing {
+        let existing = crate::fs::canonicalize(existing)?;
+        ensure!(
+            existing.starts_with(output_dir),
+            "The archive entry {} would be extracted outside the output directory {}.",
+            path.display(),
+            output_dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// List the files and directories to be packed from a directory tree, paired with their paths in
+/// the archive (under the given prefix).
+///
+/// The entries are ordered by name, each directory directly followed by its contents. Symlinks
+/// inside the tree are not followed, but the root itself is.
+pub(crate) fn directory_entries(root: &Path, prefix: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let min_depth = if prefix.as_os_str().is_empty() { 1 } else { 0 };
+    let walker = walkdir::WalkDir::new(root).min_depth(min_depth).sort_by_file_name();
+    walker
+        .into_iter()
+        .map(|entry| {
+            let entry = entry?;
+            let relative_path = entry.path().strip_prefix(root)?;
+            Ok((entry.path().to_path_buf(), prefix.join(relative_path)))
+        })
+        .collect()
+}
+
+/// List the entries to be packed for the given path, which is placed in the archive root under its
+/// file name.
+pub(crate) fn path_entries(path: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    // The file name is missing for paths like `..`, which the canonicalization resolves.
+    let name = match path.file_name() {
+        Some(name) => PathBuf::from(name),
+        None => crate::fs::canonicalize(path)?
+            .file_name()
+            .map(PathBuf::from)
+            .with_context(|| format!("Cannot pack {} under its file name.", path.display()))?,
+    };
+    directory_entries(path, &name)
+}
+
+
+// ==============
+// === Format ===
+// ==============
+
 /// Archive formats that we handle.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Format {
@@ -36,8 +225,10 @@ impl Format {
         match extension.to_str().unwrap() {
             "zip" => Ok(Format::Zip),
             "7z" => Ok(Format::SevenZip),
+            "tar" => Ok(Format::Tar(None)),
             "tgz" => Ok(Format::Tar(Some(Compression::Gzip))),
             "txz" => Ok(Format::Tar(Some(Compression::Xz))),
+            "tzst" => Ok(Format::Tar(Some(Compression::Zstd))),
             other =>
                 if let Ok(compression) = Compression::deduce_from_extension(other) {
                     let secondary_extension =
@@ -53,7 +244,19 @@ impl Format {
         }
     }
 
+    /// Whether the archive of this format can be packed and unpacked in-process, without using
+    /// external programs.
+    pub fn is_native(self) -> bool {
+        match self {
+            Format::Zip => true,
+            Format::SevenZip => false,
+            Format::Tar(compression) => tar::is_native(compression),
+        }
+    }
+
     /// Extract an archive of this format into a given output directory.
+    ///
+    /// Only the [native](Self::is_native) formats are supported.
     #[tracing::instrument(
         name="Unpacking archive.",
         skip_all,
@@ -68,30 +271,88 @@ impl Format {
         match self {
             Format::Zip => {
                 let mut archive = zip::ZipArchive::new(compressed_data)?;
-                archive.extract(output_dir)?;
+                zip::extract(&mut archive, output_dir)?;
             }
-            Format::Tar(Some(Compression::Gzip)) => {
-                let tar_stream = flate2::read::GzDecoder::new(compressed_data);
-                let mut archive = ::tar::Archive::new(tar_stream);
-                archive.unpack(output_dir)?;
+            Format::Tar(compression) => {
+                let tar_stream = tar::decompress(compression, compressed_data)?;
+                tar::extract(::tar::Archive::new(tar_stream), output_dir)?;
             }
-            _ => todo!("Not supported!"),
+            Format::SevenZip =>
+                bail!("7-Zip archives can be extracted only by an external program."),
         }
         Ok(())
     }
+
+    /// Pack the contents of a directory into an archive of this format.
+    ///
+    /// Only the [native](Self::is_native) formats are supported.
+    pub fn pack_directory_contents(
+        self,
+        output: impl Write + Seek,
+        root_directory: impl AsRef<Path>,
+        mode: Mode,
+    ) -> Result {
+        match self {
+            Format::Zip => zip::pack_directory_contents(output, root_directory, mode),
+            Format::Tar(compression) =>
+                tar::pack_directory_contents(compression, output, root_directory, mode),
+            Format::SevenZip => bail!("7-Zip archives can be created only by an external program."),
+        }
+    }
+
+    /// Pack the given files and directories into an archive of this format. Each of them is placed
+    /// in the archive root under its file name.
+    ///
+    /// Only the [native](Self::is_native) formats are supported.
+    pub fn pack(
+        self,
+        output: impl Write + Seek,
+        paths_to_pack: impl IntoIterator<Item: AsRef<Path>>,
+        mode: Mode,
+    ) -> Result {
+        match self {
+            Format::Zip => zip::pack(output, paths_to_pack, mode),
+            Format::Tar(compression) => tar::pack(compression, output, paths_to_pack, mode),
+            Format::SevenZip => bail!("7-Zip archives can be created only by an external program."),
+        }
+    }
 }
 
+/// Run a blocking job on a thread where blocking is allowed, keeping the current tracing span.
+async fn blocking<T: Send + 'static>(
+    job: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(job).instrument(Span::current()).await?
+}
 
 pub async fn create(
     output_archive: impl AsRef<Path>,
     paths_to_pack: impl IntoIterator<Item: AsRef<Path>>,
+) -> Result {
+    create_with_mode(output_archive, paths_to_pack, Mode::Preserve).await
+}
+
+/// Create an archive with the given files and directories, storing their metadata in the given
+/// [`Mode`]. The deterministic mode is not supported for formats packed by external programs.
+pub async fn create_with_mode(
+    output_archive: impl AsRef<Path>,
+    paths_to_pack: impl IntoIterator<Item: AsRef<Path>>,
+    mode: Mode,
 ) -> Result {
     let span = info_span!("Creating an archive", target = output_archive.as_ref().as_str());
     let format = Format::from_filename(&output_archive)?;
-    match format {
-        Format::Zip | Format::SevenZip =>
-            SevenZip.pack(output_archive, paths_to_pack).instrument(span).await,
-        Format::Tar(_) => Tar.pack(output_archive, paths_to_pack).instrument(span).await,
+    if format.is_native() {
+        let output_archive = output_archive.as_ref().to_path_buf();
+        let paths = paths_to_pack.into_iter().map(|path| path.as_ref().to_path_buf()).collect_vec();
+        blocking(move || format.pack(crate::fs::create(output_archive)?, paths, mode))
+            .instrument(span)
+            .await
+    } else {
+        ensure!(mode == Mode::Preserve, "Cannot deterministically pack {format:?} archives.");
+        match format {
+            Format::Tar(_) => Tar.pack(output_archive, paths_to_pack).instrument(span).await,
+            _ => SevenZip.pack(output_archive, paths_to_pack).instrument(span).await,
+        }
     }
 }
 
@@ -111,13 +372,34 @@ pub fn is_archive_name(path: impl AsRef<Path>) -> bool {
 pub async fn compress_directory_contents(
     output_archive: impl AsRef<Path>,
     root_directory: impl AsRef<Path>,
+) -> Result {
+    compress_directory_contents_with_mode(output_archive, root_directory, Mode::Preserve).await
+}
+
+/// Create an archive with directory contents, storing their metadata in the given [`Mode`].
+///
+/// The deterministic mode is not supported for formats packed by external programs.
+pub async fn compress_directory_contents_with_mode(
+    output_archive: impl AsRef<Path>,
+    root_directory: impl AsRef<Path>,
+    mode: Mode,
 ) -> Result {
     let format = Format::from_filename(&output_archive)?;
-    match format {
-        Format::Zip | Format::SevenZip =>
-            SevenZip.pack_directory_contents(output_archive, root_directory).await,
-        Format::Tar(compression) =>
-            Tar.pack_directory_contents(compression, output_archive, root_directory).await,
+    if format.is_native() {
+        let output_archive = output_archive.as_ref().to_path_buf();
+        let root_directory = root_directory.as_ref().to_path_buf();
+        blocking(move || {
+            let output = crate::fs::create(output_archive)?;
+            format.pack_directory_contents(output, root_directory, mode)
+        })
+        .await
+    } else {
+        ensure!(mode == Mode::Preserve, "Cannot deterministically pack {format:?} archives.");
+        match format {
+            Format::Tar(compression) =>
+                Tar.pack_directory_contents(compression, output_archive, root_directory).await,
+            _ => SevenZip.pack_directory_contents(output_archive, root_directory).await,
+        }
     }
 }
 
@@ -142,17 +424,23 @@ pub async fn extract_item(
     match format {
         Format::Zip => {
             let mut archive = zip::open(&archive_path)?;
-            tokio::task::spawn_blocking(move || {
-                zip::extract_subtree(&mut archive, item_path, output_path)
-            })
-            .instrument(Span::current())
-            .await??;
-        }
-        Format::Tar(Some(Compression::Gzip)) => {
-            let archive = tar::Archive::open_tar_gz(&archive_path).await?;
+            blocking(move || zip::extract_subtree(&mut archive, item_path, output_path)).await?;
+        }
+        Format::Tar(compression) if tar::is_native(compression) => {
+            let archive = tar::Archive::open(&archive_path, compression).await?;
             archive.extract_subtree(item_path, output_path).await?;
         }
-        _ => todo!(),
+        _ => {
+            // The external programs can't extract a subtree without also recreating its parent
+            // directories, so we extract everything and move the requested item.
+            // The temporary directory is placed next to the output, so the item can be renamed.
+            let parent = output_path.try_parent()?;
+            crate::fs::tokio::create_dir_if_missing(parent).await?;
+            let temp = tempfile::tempdir_in(parent)?;
+            extract_to(&archive_path, temp.path()).await?;
+            let item = temp.path().join(enclosed_path(&item_path)?);
+            crate::fs::rename(item, output_path)?;
+        }
     };
 
     Ok(())
@@ -170,10 +458,18 @@ pub async fn extract_to(
         target = output_directory.as_ref().as_str()
     );
     let format = Format::from_filename(&archive_path)?;
-    match format {
-        Format::Zip | Format::SevenZip =>
-            SevenZip.unpack_cmd(archive_path, output_directory)?.run_ok().instrument(span).await,
-        Format::Tar(_) => Tar.unpack(archive_path, output_directory).instrument(span).await,
+    if format.is_native() {
+        let archive_path = archive_path.as_ref().to_path_buf();
+        let output_directory = output_directory.as_ref().to_path_buf();
+        blocking(move || format.extract(crate::fs::open(archive_path)?, output_directory))
+            .instrument(span)
+            .await
+    } else {
+        match format {
+            Format::Tar(_) => Tar.unpack(archive_path, output_directory).instrument(span).await,
+            _ =>
+                SevenZip.unpack_cmd(archive_path, output_directory)?.run_ok().instrument(span).await,
+        }
     }
 }
 
@@ -194,5 +490,173 @@ mod tests {
     fn archive_checker() {
         assert!(is_archive_name("enso-project-manager-0.2.31-linux-amd64.tar.gz"));
         assert!(is_archive_name("enso-project-manager-0.2.31-windows-amd64.zip"));
+        assert!(is_archive_name("enso-project-manager-0.2.31-linux-amd64.tar.zst"));
+    }
+
+    #[test]
+    fn enclosed_paths() -> Result {
+        assert_eq!(enclosed_path("./bin/enso")?, Path::new("bin/enso"));
+        assert_eq!(enclosed_path("./")?, Path::new(""));
+        assert!(enclosed_path("../evil").is_err());
+        assert!(enclosed_path("bin/../../evil").is_err());
+        assert!(enclosed_path("/etc/passwd").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn native_round_trip() -> Result {
+        let source = tempfile::tempdir()?;
+        crate::fs::write(source.path().join("bin/enso"), "#!/bin/sh")?;
+        crate::fs::write(source.path().join("README.md"), "readme")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let permissions = std::fs::Permissions::from_mode(0o755);
+            crate::fs::set_permissions(source.path().join("bin/enso"), permissions)?;
+            std::os::unix::fs::symlink("bin/enso", source.path().join("enso"))?;
+        }
+
+        for name in
+            ["archive.zip", "archive.tar", "archive.tar.gz", "archive.tar.xz", "archive.tar.zst"]
+        {
+            let temp = tempfile::tempdir()?;
+            let archive = temp.path().join(name);
+            compress_directory_contents(&archive, source.path()).await?;
+            let output = temp.path().join("output");
+            extract_to(&archive, &output).await?;
+
+            assert_eq!(crate::fs::read_to_string(output.join("bin/enso"))?, "#!/bin/sh", "{name}");
+            assert_eq!(crate::fs::read_to_string(output.join("README.md"))?, "readme", "{name}");
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = crate::fs::metadata(output.join("bin/enso"))?.permissions().mode();
+                assert_eq!(mode & 0o777, 0o755, "{name}");
+                assert_eq!(
+                    std::fs::read_link(output.join("enso"))?,
+                    Path::new("bin/enso"),
+                    "{name}"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deterministic_packing() -> Result {
+        let source = tempfile::tempdir()?;
+        crate::fs::write(source.path().join("bin/enso"), "#!/bin/sh")?;
+        crate::fs::write(source.path().join("README.md"), "readme")?;
+        let mode = Mode::Deterministic { mtime: 1700000000 };
+
+        for name in ["archive.zip", "archive.tar.gz"] {
+            let temp = tempfile::tempdir()?;
+            let first = temp.path().join("first").join(name);
+            let second = temp.path().join("second").join(name);
+            crate::fs::create_parent_dir_if_missing(&first)?;
+            crate::fs::create_parent_dir_if_missing(&second)?;
+            compress_directory_contents_with_mode(&first, source.path(), mode).await?;
+            let later = std::time::SystemTime::now() + Duration::from_secs(3600);
+            let readme =
+                std::fs::OpenOptions::new().write(true).open(source.path().join("README.md"))?;
+            readme.set_modified(later)?;
+            compress_directory_contents_with_mode(&second, source.path(), mode).await?;
+            assert_eq!(reproducible::compare(&first, &second)?, None, "{name}");
+
+            crate::fs::write(source.path().join("README.md"), "changed")?;
+            compress_directory_contents_with_mode(&second, source.path(), mode).await?;
+            let difference = reproducible::compare(&first, &second)?.unwrap_or_default();
+            assert!(difference.contains("README.md"), "{name}: {difference}");
+            crate::fs::write(source.path().join("README.md"), "readme")?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify_reproducible() -> Result {
+        let temp = tempfile::tempdir()?;
+        let bundle = temp.path().join("enso-bundle");
+        crate::fs::write(bundle.join("bin/enso"), "#!/bin/sh")?;
+        let expected = temp.path().join("expected.tar.gz");
+        create_with_mode(&expected, [&bundle], Mode::deterministic()?).await?;
+        reproducible::verify(&bundle, "bundle.tar.gz", Some(&expected)).await?;
+
+        crate::fs::write(bundle.join("bin/enso"), "#!/bin/bash")?;
+        assert!(reproducible::verify(&bundle, "bundle.tar.gz", Some(&expected)).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn zip_path_traversal_is_rejected() -> Result {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("../evil.txt", default())?;
+        writer.write_all(b"evil")?;
+        let data = writer.finish()?;
+
+        let temp = tempfile::tempdir()?;
+        let output = temp.path().join("output");
+        assert!(Format::Zip.extract(data, &output).is_err());
+        assert!(!temp.path().join("evil.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn tar_path_traversal_is_rejected() -> Result {
+        let mut header = ::tar::Header::new_old();
+        // `Header::set_path` refuses `..`, so the name has to be written directly.
+        header.as_old_mut().name[..11].copy_from_slice(b"../evil.txt");
+        header.set_size(4);
+        header.set_cksum();
+        let mut builder = ::tar::Builder::new(Vec::new());
+        builder.append(&header, "evil".as_bytes())?;
+        let data = builder.into_inner()?;
+
+        let temp = tempfile::tempdir()?;
+        let output = temp.path().join("output");
+        assert!(Format::Tar(None).extract(std::io::Cursor::new(data), &output).is_err());
+        assert!(!temp.path().join("evil.txt").exists());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zip_symlink_escape_is_rejected() -> Result {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.add_symlink("link", "..", default())?;
+        writer.start_file("link/nested/evil.txt", default())?;
+        writer.write_all(b"evil")?;
+        let data = writer.finish()?;
+
+        let temp = tempfile::tempdir()?;
+        let output = temp.path().join("output");
+        assert!(Format::Zip.extract(data, &output).is_err());
+        assert!(!temp.path().join("nested").exists());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn tar_subtree_extraction() -> Result {
+        let mut builder = ::tar::Builder::new(Vec::new());
+        for name in ["enso/bin/enso", "enso/../../evil.txt"] {
+            let mut header = ::tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(4);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, "data".as_bytes())?;
+        }
+        let temp = tempfile::tempdir()?;
+        let archive = temp.path().join("archive.tar");
+        crate::fs::write(&archive, builder.into_inner()?)?;
+
+        let output = temp.path().join("nested/output");
+        assert!(extract_item(&archive, "enso", &output).await.is_err());
+        assert_eq!(crate::fs::read_to_string(output.join("bin/enso"))?, "data");
+        assert!(!temp.path().join("evil.txt").exists());
+
+        let output = temp.path().join("enso");
+        extract_item(&archive, "enso/bin/enso", &output).await?;
+        assert_eq!(crate::fs::read_to_string(&output)?, "data");
+        Ok(())
     }
 }
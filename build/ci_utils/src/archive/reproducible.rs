@@ -0,0 +1,167 @@
+//! Checking that packing a file tree in the [deterministic mode](Mode::Deterministic) always yields
+//! the same archive, so the released archives can be attested by rebuilding them.
+
+use crate::prelude::*;
+
+use crate::archive::create_with_mode;
+use crate::archive::Format;
+use crate::archive::Mode;
+use crate::io::Sha256Digest;
+
+use itertools::EitherOrBoth;
+
+
+
+// ===============
+// === Entries ===
+// ===============
+
+/// Describe each entry of the archive: its path, metadata and contents digest. Comparing the
+/// descriptions of two archives tells which entry makes them differ.
+pub fn describe_entries(archive: impl AsRef<Path>) -> Result<Vec<String>> {
+    let archive = archive.as_ref();
+    let format = Format::from_filename(archive)?;
+    let file = crate::fs::open(archive)?;
+    match format {
+        Format::Zip => {
+            let mut archive = crate::archive::zip::ZipArchive::new(file)?;
+            (0..archive.len())
+                .map(|index| {
+                    let file = archive.by_index(index)?;
+                    let name = file.name().to_owned();
+                    let mode = file.unix_mode().map(|mode| format!("{mode:o}"));
+                    let mtime = file.last_modified();
+                    let method = file.compression();
+                    Ok(format!(
+                        "{name} mode={mode:?} mtime={mtime:?} method={method} sha256={}",
+                        Sha256Digest::of_reader(file)?.to_hex()
+                    ))
+                })
+                .collect()
+        }
+        Format::Tar(compression) => {
+            let stream = crate::archive::tar::decompress(compression, file)?;
+            let mut archive = tar::Archive::new(stream);
+            archive
+                .entries()?
+                .map(|entry| {
+                    let entry = entry?;
+                    let header = entry.header();
+                    let path = entry.path()?.display().to_string();
+                    let kind = header.entry_type();
+                    let mode = header.mode()?;
+                    let owner =
+                        (header.uid()?, header.gid()?, header.username(), header.groupname());
+                    let owner = format!("{owner:?}");
+                    let mtime = header.mtime()?;
+                    let link = entry.link_name()?.map(|link| link.display().to_string());
+                    Ok(format!(
+                        "{path} type={kind:?} mode={mode:o} owner={owner} mtime={mtime} \
+                        link={link:?} sha256={}",
+                        Sha256Digest::of_reader(entry)?.to_hex()
+                    ))
+                })
+                .collect()
+        }
+        Format::SevenZip => bail!("Cannot inspect the entries of 7-Zip archives."),
+    }
+}
+
+/// Compare two archives. Returns `None` if they are identical, otherwise a description of the
+/// first difference.
+pub fn compare(first: impl AsRef<Path>, second: impl AsRef<Path>) -> Result<Option<String>> {
+    let (first, second) = (first.as_ref(), second.as_ref());
+    let first_digest = Sha256Digest::of_file(first)?.to_hex();
+    let second_digest = Sha256Digest::of_file(second)?.to_hex();
+    if first_digest == second_digest {
+        return Ok(None);
+    }
+    let first_entries = describe_entries(first)?;
+    let second_entries = describe_entries(second)?;
+    let difference = first_entries
+        .iter()
+        .zip_longest(&second_entries)
+        .find(|pair| !pair.clone().both().is_some_and(|(first, second)| first == second));
+    let description = match difference {
+        Some(pair) => {
+            let (first_entry, second_entry) = match pair {
+                EitherOrBoth::Both(first, second) => (first.as_str(), second.as_str()),
+                EitherOrBoth::Left(first) => (first.as_str(), "<none>"),
+                EitherOrBoth::Right(second) => ("<none>", second.as_str()),
+            };
+            format!("The first differing entry is:\n  {first_entry}\n  {second_entry}")
+        }
+        None => "The entries are identical, so the archives differ only in their encoding.".into(),
+    };
+    Ok(Some(format!(
+        "{} (SHA-256 {first_digest}) and {} (SHA-256 {second_digest}) differ. {description}",
+        first.display(),
+        second.display()
+    )))
+}
+
+
+
+// ====================
+// === Verification ===
+// ====================
+
+/// Copy the file tree. The copied files get new modification times, and directory entries may be
+/// listed in a different order, so packing the copy reveals any dependency on these.
+fn copy_tree(source: &Path, target: &Path) -> Result {
+    for entry in walkdir::WalkDir::new(source) {
+        let entry = entry?;
+        let target = target.join(entry.path().strip_prefix(source)?);
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            crate::fs::symlink_auto(std::fs::read_link(entry.path())?, &target)?;
+        } else if file_type.is_dir() {
+            crate::fs::create_dir_if_missing(&target)?;
+        } else {
+            crate::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Pack the directory deterministically twice (the second time from a fresh copy) and check that
+/// the archives are identical. If an `expected` archive is given (e.g. a downloaded release asset),
+/// it must be identical as well.
+///
+/// Like the release assets, the archive contains the directory itself, not just its contents.
+///
+/// The format is deduced from the `archive_name`. Returns the SHA-256 digest of the archive.
+pub async fn verify(
+    directory: impl AsRef<Path>,
+    archive_name: impl AsRef<Path>,
+    expected: Option<&Path>,
+) -> Result<String> {
+    let directory = directory.as_ref();
+    let archive_name = archive_name.as_ref();
+    let archive_name = archive_name.try_file_name()?;
+    let mode = Mode::deterministic()?;
+    info!("Packing {} in {mode:?}.", directory.display());
+    let temp = tempfile::tempdir()?;
+    let first = temp.path().join("first").join(archive_name);
+    let second = temp.path().join("second").join(archive_name);
+    crate::fs::create_parent_dir_if_missing(&first)?;
+    crate::fs::create_parent_dir_if_missing(&second)?;
+    create_with_mode(&first, [directory], mode).await?;
+
+    let directory_name = crate::fs::canonicalize(directory)?.try_file_name()?.to_owned();
+    let copy = temp.path().join("copy").join(directory_name);
+    copy_tree(directory, &copy)?;
+    create_with_mode(&second, [&copy], mode).await?;
+
+    if let Some(difference) = compare(&first, &second)? {
+        bail!("Packing {} is not reproducible. {difference}", directory.display());
+    }
+    if let Some(expected) = expected {
+        if let Some(difference) = compare(expected, &first)? {
+            bail!("The archive does not match {}. {difference}", expected.display());
+        }
+    }
+    let digest = Sha256Digest::of_file(&first)?.to_hex();
+    info!("Packing {} is reproducible, SHA-256 {digest}.", directory.display());
+    Ok(digest)
+}
@@ -1,13 +1,178 @@
 use crate::prelude::*;
 
+use crate::archive::enclosed_path;
+use crate::archive::ensure_parent_inside;
 use crate::archive::extract_files::ExtractFiles;
+use crate::archive::Mode;
+use crate::archive::Progress;
+use crate::fs::create_dir_if_missing;
+use crate::programs::tar::Compression;
 
 use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
 use std::fs::File;
+use std::io::BufReader;
+use std::io::Write;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
 
 
 
+// =================
+// === Constants ===
+// =================
+
+/// Compression level used for gzip-compressed archives.
+const GZIP_LEVEL: u32 = 6;
+
+/// Compression level used for xz-compressed archives.
+const XZ_LEVEL: u32 = 6;
+
+/// Compression level used for zstd-compressed archives.
+const ZSTD_LEVEL: i32 = 3;
+
+
+
+// ===================
+// === Compression ===
+// ===================
+
+/// Whether tar archives with the given compression are handled in-process. The remaining ones are
+/// handled by the external `tar` program.
+pub fn is_native(compression: Option<Compression>) -> bool {
+    matches!(compression, None | Some(Compression::Gzip | Compression::Xz | Compression::Zstd))
+}
+
+/// A reader decompressing the tar stream.
+#[derive_where(Debug)]
+pub enum Decoder<R: Read> {
+    Plain(#[derive_where(skip)] R),
+    Gzip(#[derive_where(skip)] GzDecoder<R>),
+    Xz(#[derive_where(skip)] XzDecoder<R>),
+    Zstd(#[derive_where(skip)] zstd::Decoder<'static, BufReader<R>>),
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Decoder::Plain(reader) => reader.read(buf),
+            Decoder::Gzip(reader) => reader.read(buf),
+            Decoder::Xz(reader) => reader.read(buf),
+            Decoder::Zstd(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Wrap the reader of a compressed tar stream, so it yields the uncompressed data.
+pub fn decompress<R: Read>(compression: Option<Compression>, reader: R) -> Result<Decoder<R>> {
+    Ok(match compression {
+        None => Decoder::Plain(reader),
+        Some(Compression::Gzip) => Decoder::Gzip(GzDecoder::new(reader)),
+        Some(Compression::Xz) => Decoder::Xz(XzDecoder::new(reader)),
+        Some(Compression::Zstd) => Decoder::Zstd(zstd::Decoder::new(reader)?),
+        Some(other) => bail!("The {other} compression is not supported in-process."),
+    })
+}
+
+/// A writer compressing the tar stream.
+#[derive_where(Debug)]
+pub enum Encoder<W: Write> {
+    Plain(#[derive_where(skip)] W),
+    Gzip(#[derive_where(skip)] GzEncoder<W>),
+    Xz(#[derive_where(skip)] XzEncoder<W>),
+    Zstd(#[derive_where(skip)] zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Write the remaining compressed data and return the underlying writer.
+    pub fn finish(self) -> Result<W> {
+        let mut writer = match self {
+            Encoder::Plain(writer) => writer,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Xz(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(buf),
+            Encoder::Gzip(writer) => writer.write(buf),
+            Encoder::Xz(writer) => writer.write(buf),
+            Encoder::Zstd(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(writer) => writer.flush(),
+            Encoder::Xz(writer) => writer.flush(),
+            Encoder::Zstd(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Wrap the writer, so the tar stream written to it gets compressed.
+pub fn compress<W: Write>(compression: Option<Compression>, writer: W) -> Result<Encoder<W>> {
+    Ok(match compression {
+        None => Encoder::Plain(writer),
+        Some(Compression::Gzip) =>
+            Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::new(GZIP_LEVEL))),
+        Some(Compression::Xz) => Encoder::Xz(XzEncoder::new(writer, XZ_LEVEL)),
+        Some(Compression::Zstd) => Encoder::Zstd(zstd::Encoder::new(writer, ZSTD_LEVEL)?),
+        Some(other) => bail!("The {other} compression is not supported in-process."),
+    })
+}
+
+
+
+// ==================
+// === Extraction ===
+// ==================
+
+/// Extract all entries of the archive into the output directory.
+///
+/// File permissions, modification times and symlinks are preserved. An entry that would be placed
+/// outside the output directory fails the whole extraction.
+pub fn extract<R: Read>(mut archive: tar::Archive<R>, output_dir: impl AsRef<Path>) -> Result {
+    create_dir_if_missing(&output_dir)?;
+    let output_dir = crate::fs::canonicalize(&output_dir)?;
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(true);
+    archive.set_overwrite(true);
+    let mut progress = Progress::new("Extracted");
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path_in_archive = entry.path()?.to_path_buf();
+        let relative_path = enclosed_path(&path_in_archive)?;
+        if relative_path.as_os_str().is_empty() {
+            continue;
+        }
+        let size = entry.size();
+        // Besides the entry path, `unpack_in` checks that no symlink extracted earlier leads the
+        // entry outside the output directory.
+        let unpacked = entry
+            .unpack_in(&output_dir)
+            .with_context(|| format!("Failed to extract {} entry.", path_in_archive.display()))?;
+        ensure!(
+            unpacked,
+            "The archive entry {} points outside the output directory.",
+            path_in_archive.display()
+        );
+        progress.entry(&relative_path, size);
+    }
+    progress.finish();
+    Ok(())
+}
+
 /// Synchronous version of [`extract_files`].
+///
+/// Archives with entries pointing outside the archive root are rejected, see [`enclosed_path`].
 #[context("Failed to extract files from the archive.")]
 pub fn extract_files_sync<R: Read>(
     mut archive: tar::Archive<R>,
@@ -17,6 +182,7 @@ pub fn extract_files_sync<R: Read>(
     for entry in entries {
         let mut entry = entry?;
         let path_in_archive = entry.path()?.to_path_buf();
+        enclosed_path(&path_in_archive)?;
         if let Some(output_path) = filter(&entry) {
             let entry_type = entry.header().entry_type();
             let make_message = |prefix, path: &Path| {
@@ -38,6 +204,103 @@ pub fn extract_files_sync<R: Read>(
     Ok(())
 }
 
+
+
+// ===============
+// === Packing ===
+// ===============
+
+/// Append a file with normalized metadata, as described by [`Mode::Deterministic`].
+fn append_deterministic<W: Write>(
+    builder: &mut tar::Builder<W>,
+    source: &Path,
+    path_in_archive: &Path,
+    metadata: &std::fs::Metadata,
+    mtime: u64,
+) -> Result {
+    let mut header = tar::Header::new_gnu();
+    header.set_metadata_in_mode(metadata, tar::HeaderMode::Deterministic);
+    header.set_mode(Mode::normalized_permissions(metadata));
+    header.set_mtime(mtime);
+    if metadata.is_symlink() {
+        let target = std::fs::read_link(source)
+            .with_context(|| format!("Failed to read the symlink {}.", source.display()))?;
+        header.set_size(0);
+        builder.append_link(&mut header, path_in_archive, target)?;
+    } else if metadata.is_dir() {
+        builder.append_data(&mut header, path_in_archive, std::io::empty())?;
+    } else {
+        builder.append_data(&mut header, path_in_archive, crate::fs::open(source)?)?;
+    }
+    Ok(())
+}
+
+/// Append the files to the tar stream. Symlinks are stored as such, not followed.
+fn append_all<W: Write>(
+    builder: &mut tar::Builder<W>,
+    entries: impl IntoIterator<Item = (PathBuf, PathBuf)>,
+    mode: Mode,
+    progress: &mut Progress,
+) -> Result {
+    for (source, path_in_archive) in entries {
+        let metadata = crate::fs::symlink_metadata(&source)?;
+        match mode {
+            Mode::Preserve =>
+                builder.append_path_with_name(&source, &path_in_archive).map_err(Into::into),
+            Mode::Deterministic { mtime } =>
+                append_deterministic(builder, &source, &path_in_archive, &metadata, mtime),
+        }
+        .with_context(|| format!("Failed to add {} to the archive.", source.display()))?;
+        progress.entry(&path_in_archive, if metadata.is_file() { metadata.len() } else { 0 });
+    }
+    Ok(())
+}
+
+fn new_builder<W: Write>(
+    compression: Option<Compression>,
+    output: W,
+) -> Result<tar::Builder<Encoder<W>>> {
+    let mut builder = tar::Builder::new(compress(compression, output)?);
+    builder.follow_symlinks(false);
+    Ok(builder)
+}
+
+fn finish_builder<W: Write>(builder: tar::Builder<Encoder<W>>, progress: Progress) -> Result {
+    builder.into_inner()?.finish()?;
+    progress.finish();
+    Ok(())
+}
+
+/// Pack the contents of the directory (but not the directory itself) into a tar stream.
+pub fn pack_directory_contents(
+    compression: Option<Compression>,
+    output: impl Write,
+    root_directory: impl AsRef<Path>,
+    mode: Mode,
+) -> Result {
+    let mut builder = new_builder(compression, output)?;
+    let mut progress = Progress::new("Packed");
+    let entries = crate::archive::directory_entries(root_directory.as_ref(), Path::new(""))?;
+    append_all(&mut builder, entries, mode, &mut progress)?;
+    finish_builder(builder, progress)
+}
+
+/// Pack the files and directories into a tar stream, each under its file name.
+pub fn pack(
+    compression: Option<Compression>,
+    output: impl Write,
+    paths_to_pack: impl IntoIterator<Item: AsRef<Path>>,
+    mode: Mode,
+) -> Result {
+    let mut builder = new_builder(compression, output)?;
+    let mut progress = Progress::new("Packed");
+    for path in paths_to_pack {
+        let entries = crate::archive::path_entries(path.as_ref())?;
+        append_all(&mut builder, entries, mode, &mut progress)?;
+    }
+    finish_builder(builder, progress)
+}
+
 // ===============
 // === Archive ===
 // ===============
@@ -48,27 +311,32 @@ pub struct Archive {
     /// The path that the `file` originated from. This is stored for error reporting.
     path: Box<Path>,
     #[derive_where(skip)]
-    file: tar::Archive<GzDecoder<File>>,
+    file: tar::Archive<Decoder<File>>,
 }
 
 impl Archive {
-    /// Open a gzip-compressed tar archive.
+    /// Open a tar archive with the given compression.
     #[context("Failed to open archive: {}", path.as_ref().display())]
-    pub async fn open_tar_gz(path: impl AsRef<Path>) -> Result<Self> {
+    pub async fn open(path: impl AsRef<Path>, compression: Option<Compression>) -> Result<Self> {
         let file = crate::fs::tokio::open(&path).await?;
         let file = file
             .try_into_std()
             .map_err(|_| anyhow!("Failed to convert tokio::fs::File to std::fs::File"))?;
-        let tar_stream = GzDecoder::new(file);
+        let tar_stream = decompress(compression, file)?;
         let archive = tar::Archive::new(tar_stream);
         let path = path.as_ref().to_owned().into_boxed_path();
         Ok(Self { path, file: archive })
     }
 
+    /// Open a gzip-compressed tar archive.
+    pub async fn open_tar_gz(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open(path, Some(Compression::Gzip)).await
+    }
+
     /// Synchronous version of [`extract_files`].
     pub fn extract_files_sync(
         self,
-        filter: impl FnMut(&tar::Entry<GzDecoder<File>>) -> Option<PathBuf>,
+        filter: impl FnMut(&tar::Entry<Decoder<File>>) -> Option<PathBuf>,
     ) -> Result {
         extract_files_sync(self.file, filter).with_context(|| {
             format!("Failed to extract files from archive {}", self.path.display())
@@ -77,31 +345,62 @@ impl Archive {
 
     /// Extract all files from the specified subtree in the archive, placing them in the specified
     /// output directory.
+    ///
+    /// If the prefix denotes a single file, it is extracted to the output path. An entry that would
+    /// be placed outside the output fails the whole extraction.
     pub async fn extract_subtree(
         self,
         prefix: impl AsRef<Path>,
         output: impl AsRef<Path>,
     ) -> Result {
-        let path = self.path.clone();
+        let Self { path, file: mut archive } = self;
+        let prefix = prefix.as_ref();
+        let output = output.as_ref();
         debug!(
             "Extracting subtree '{}' from archive {} to {}",
-            prefix.as_ref().display(),
-            self.path.display(),
-            output.as_ref().display()
+            prefix.display(),
+            path.display(),
+            output.display()
         );
-        self.extract_files(|path_in_archive| {
-            path_in_archive
-                .strip_prefix(&prefix)
-                .ok()
-                .map(|relative_path| output.as_ref().join(relative_path))
-        })
-        .await
-        .with_context(|| {
+        let job = || -> Result {
+            crate::fs::create_parent_dir_if_missing(output)?;
+            // Canonical output directory, known once the first entry inside the subtree is found.
+            let mut output_dir = None;
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                let path_in_archive = entry.path()?.to_path_buf();
+                let Ok(relative_path) = path_in_archive.strip_prefix(prefix) else {
+                    continue;
+                };
+                let relative_path = enclosed_path(relative_path)?;
+                let target = if relative_path.as_os_str().is_empty() {
+                    output.to_path_buf()
+                } else {
+                    let output_dir = match &output_dir {
+                        Some(output_dir) => output_dir,
+                        None => {
+                            create_dir_if_missing(output)?;
+                            output_dir.insert(crate::fs::canonicalize(output)?)
+                        }
+                    };
+                    let target = output_dir.join(&relative_path);
+                    ensure_parent_inside(output_dir, &target)?;
+                    crate::fs::create_parent_dir_if_missing(&target)?;
+                    target
+                };
+                trace!("Extracting {}", target.display());
+                entry
+                    .unpack(&target)
+                    .with_context(|| format!("Failed to extract {}.", path_in_archive.display()))?;
+            }
+            Ok(())
+        };
+        tokio::task::block_in_place(job).with_context(|| {
             format!(
                 "Failed to extract subtree '{}' from archive {} to {}",
-                prefix.as_ref().display(),
+                prefix.display(),
                 path.display(),
-                output.as_ref().display()
+                output.display()
             )
         })
     }
@@ -109,7 +408,7 @@ impl Archive {
 
 impl ExtractFiles for Archive {
     async fn extract_files(self, mut filter: impl FnMut(&Path) -> Option<PathBuf>) -> Result {
-        let filter = move |entry: &tar::Entry<GzDecoder<File>>| filter(entry.path().ok()?.as_ref());
+        let filter = move |entry: &tar::Entry<Decoder<File>>| filter(entry.path().ok()?.as_ref());
         let job = move || self.extract_files_sync(filter);
         tokio::task::block_in_place(job)
     }
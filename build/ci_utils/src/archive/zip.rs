@@ -1,10 +1,17 @@
 use crate::prelude::*;
 
+use crate::archive::enclosed_path;
+use crate::archive::ensure_parent_inside;
 use crate::archive::extract_files::ExtractFiles;
+use crate::archive::Mode;
+use crate::archive::Progress;
+use crate::fs::create_dir_if_missing;
 
 use anyhow::Context;
 use std::io::Cursor;
+use std::io::Write;
 use zip::read::ZipFile;
+use zip::write::FileOptions;
 
 
 
@@ -17,13 +24,31 @@ pub fn open(path: impl AsRef<Path>) -> Result<ZipArchive<std::fs::File>> {
 #[context("Failed to extract in-memory archive to {}.", output_dir.as_ref().display())]
 pub fn extract_bytes(bytes: Bytes, output_dir: impl AsRef<Path>) -> Result {
     let mut archive = ZipArchive::new(Cursor::new(&bytes))?;
-    archive.extract(&output_dir)?;
-    Ok(())
+    extract(&mut archive, &output_dir)
+}
+
+/// File type bits of the Unix mode.
+const FILE_TYPE_MASK: u32 = 0o170000;
+
+/// File type bits of a symlink.
+const SYMLINK_TYPE: u32 = 0o120000;
+
+/// Whether the entry is a symlink, whose target is stored as the file contents.
+pub fn is_symlink(file: &ZipFile) -> bool {
+    file.unix_mode().is_some_and(|mode| mode & FILE_TYPE_MASK == SYMLINK_TYPE)
 }
 
 pub fn extract_file(file: &mut ZipFile, output: impl AsRef<Path>) -> Result {
+    // Never write through a symlink that is already in place, e.g. extracted from the same archive.
+    if crate::fs::symlink_metadata(&output).is_ok_and(|metadata| metadata.is_symlink()) {
+        crate::fs::remove_file_if_exists(&output)?;
+    }
     if file.is_dir() {
         crate::fs::create_dir_if_missing(&output)?;
+    } else if is_symlink(file) {
+        let mut target = String::new();
+        file.read_to_string(&mut target)?;
+        return crate::fs::symlink_auto(target, &output);
     } else {
         let mut output_file = crate::fs::create(&output)?;
         std::io::copy(file, &mut output_file)?;
@@ -44,6 +69,31 @@ pub fn extract_file(file: &mut ZipFile, output: impl AsRef<Path>) -> Result {
 }
 
 
+/// Extract all entries of the archive into the output directory.
+///
+/// Unix permissions and symlinks are preserved. An entry that would be placed outside the output
+/// directory fails the whole extraction.
+pub fn extract(archive: &mut ZipArchive<impl Read + Seek>, output_dir: impl AsRef<Path>) -> Result {
+    create_dir_if_missing(&output_dir)?;
+    let output_dir = crate::fs::canonicalize(&output_dir)?;
+    let mut progress = Progress::new("Extracted");
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let relative_path = enclosed_path(file.name())?;
+        if relative_path.as_os_str().is_empty() {
+            continue;
+        }
+        let output = output_dir.join(&relative_path);
+        ensure_parent_inside(&output_dir, &output)?;
+        crate::fs::create_parent_dir_if_missing(&output)?;
+        extract_file(&mut file, &output)
+            .with_context(|| format!("Failed to extract {} entry.", file.name()))?;
+        progress.entry(&relative_path, file.size());
+    }
+    progress.finish();
+    Ok(())
+}
+
 #[tracing::instrument(
     name="Extracting subtree from archive.",
     skip_all,
@@ -56,15 +106,18 @@ pub fn extract_subtree(
     prefix: impl AsRef<Path>,
     output: impl AsRef<Path>,
 ) -> Result {
-    // let bar = crate::global::new_spinner("Extracting archive.");
+    create_dir_if_missing(&output)?;
+    let output_dir = crate::fs::canonicalize(&output)?;
     for index in 0..archive.len() {
         let mut file = archive.by_index(index)?;
         let path_in_archive = file
             .enclosed_name()
             .context(format!("Illegal path in the archive: {}", file.name()))?;
         if let Ok(relative_path) = path_in_archive.strip_prefix(&prefix) {
-            let output = output.as_ref().join(relative_path);
+            let output = output_dir.join(relative_path);
             trace!("Extracting {}", output.display());
+            ensure_parent_inside(&output_dir, &output)?;
+            crate::fs::create_parent_dir_if_missing(&output)?;
             extract_file(&mut file, output)?;
         }
     }
@@ -110,3 +163,115 @@ impl ExtractFiles for &mut ZipArchive<std::fs::File> {
         tokio::task::block_in_place(job)
     }
 }
+
+
+
+// ===============
+// === Packing ===
+// ===============
+
+/// Store the permissions of the file. The modification time is not stored, as it requires the
+/// `time` feature of the `zip` crate (and is not reliable anyway, see [`extract_file`]).
+fn preserved_metadata(options: FileOptions, metadata: &std::fs::Metadata) -> FileOptions {
+    #[cfg(unix)]
+    let options = {
+        use std::os::unix::fs::PermissionsExt;
+        options.unix_permissions(metadata.permissions().mode())
+    };
+    #[cfg(not(unix))]
+    let _ = metadata;
+    options
+}
+
+/// Convert the time in seconds since the Unix epoch to the MS-DOS time used by zip. Times outside
+/// of the representable range (1980-2107) are replaced with its start.
+fn dos_time(seconds: u64) -> DateTime {
+    use chrono::Datelike;
+    use chrono::Timelike;
+    let Some(time) =
+        i64::try_from(seconds).ok().and_then(|s| chrono::DateTime::from_timestamp(s, 0))
+    else {
+        return DateTime::default();
+    };
+    let Ok(year) = u16::try_from(time.year()) else {
+        return DateTime::default();
+    };
+    DateTime::from_date_and_time(
+        year,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .unwrap_or_default()
+}
+
+/// Add the files to the archive. Symlinks are stored as such, not followed.
+fn append_all<W: Write + Seek>(
+    writer: &mut ZipWriter<W>,
+    entries: impl IntoIterator<Item = (PathBuf, PathBuf)>,
+    mode: Mode,
+    progress: &mut Progress,
+) -> Result {
+    for (source, path_in_archive) in entries {
+        // Zip archives use forward slashes, whatever the platform.
+        let name = path_in_archive.components().map(|c| c.as_os_str().to_string_lossy()).join("/");
+        let metadata = crate::fs::symlink_metadata(&source)?;
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let options = match mode {
+            Mode::Preserve => preserved_metadata(options, &metadata),
+            Mode::Deterministic { mtime } => options
+                .unix_permissions(Mode::normalized_permissions(&metadata))
+                .last_modified_time(dos_time(mtime)),
+        };
+        let size = if metadata.is_symlink() {
+            let target = std::fs::read_link(&source)
+                .with_context(|| format!("Failed to read the symlink {}.", source.display()))?;
+            writer.add_symlink(name, target.to_string_lossy(), options)?;
+            0
+        } else if metadata.is_dir() {
+            writer.add_directory(name, options)?;
+            0
+        } else {
+            let options = options.large_file(metadata.len() >= u32::MAX as u64);
+            writer.start_file(name, options)?;
+            std::io::copy(&mut crate::fs::open(&source)?, writer)
+                .with_context(|| format!("Failed to add {} to the archive.", source.display()))?
+        };
+        progress.entry(&path_in_archive, size);
+    }
+    Ok(())
+}
+
+/// Pack the contents of the directory (but not the directory itself) into a zip archive.
+pub fn pack_directory_contents(
+    output: impl Write + Seek,
+    root_directory: impl AsRef<Path>,
+    mode: Mode,
+) -> Result {
+    let mut writer = ZipWriter::new(output);
+    let mut progress = Progress::new("Packed");
+    let entries = crate::archive::directory_entries(root_directory.as_ref(), Path::new(""))?;
+    append_all(&mut writer, entries, mode, &mut progress)?;
+    writer.finish()?;
+    progress.finish();
+    Ok(())
+}
+
+/// Pack the files and directories into a zip archive, each under its file name.
+pub fn pack(
+    output: impl Write + Seek,
+    paths_to_pack: impl IntoIterator<Item: AsRef<Path>>,
+    mode: Mode,
+) -> Result {
+    let mut writer = ZipWriter::new(output);
+    let mut progress = Progress::new("Packed");
+    for path in paths_to_pack {
+        let entries = crate::archive::path_entries(path.as_ref())?;
+        append_all(&mut writer, entries, mode, &mut progress)?;
+    }
+    writer.finish()?;
+    progress.finish();
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use crate::prelude::*;
 
 use anyhow::Context;
+use chrono::DateTime;
+use chrono::Utc;
 use enso_build_base::extensions::future::TryFutureExt;
 use sha2::Digest;
 use std::hash::Hasher;
@@ -15,6 +17,9 @@ pub mod artifact;
 pub mod asset;
 pub mod download;
 pub mod goodie;
+pub mod lock;
+pub mod maintenance;
+pub mod pinned;
 
 pub use goodie::Goodie;
 
@@ -62,6 +67,22 @@ pub struct EntryIndexRequired<S: Storable> {
     pub metadata: S::Metadata,
 }
 
+/// Bookkeeping information about a cache entry, used by the [cache maintenance](maintenance).
+///
+/// All fields are optional, as entries created by older versions of the build script lack them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EntryUsage {
+    /// When the entry was generated.
+    pub created:        Option<DateTime<Utc>>,
+    /// When the entry was last retrieved from the cache.
+    pub last_access:    Option<DateTime<Utc>>,
+    /// Total size of the entry files, in bytes.
+    pub size:           Option<u64>,
+    /// Digest of the entry files, recorded by the first verification of the entry. See
+    /// [`maintenance::content_digest`].
+    pub content_digest: Option<String>,
+}
+
 /// The metadata for a cache entry with additional information to help debugging.
 #[derive(Clone, Debug, Serialize, Deserialize, derive_more::Deref, derive_more::DerefMut)]
 #[serde(bound = "S:")]
@@ -74,16 +95,19 @@ pub struct EntryIndexExtended<S: Storable> {
     pub r#type:         Option<String>,
     pub key_type:       Option<String>,
     pub schema_version: Option<u8>,
+    #[serde(flatten)]
+    pub usage:          EntryUsage,
 }
 
 impl<S: Storable> EntryIndexExtended<S> {
-    pub fn new(metadata: S::Metadata, key: S::Key) -> Self {
+    pub fn new(metadata: S::Metadata, key: S::Key, usage: EntryUsage) -> Self {
         Self {
-            inner:          EntryIndexRequired { metadata },
-            key:            Some(key),
-            r#type:         Some(std::any::type_name::<S>().into()),
-            key_type:       Some(std::any::type_name::<S::Key>().into()),
+            inner: EntryIndexRequired { metadata },
+            key: Some(key),
+            r#type: Some(std::any::type_name::<S>().into()),
+            key_type: Some(std::any::type_name::<S::Key>().into()),
             schema_version: Some(VERSION),
+            usage,
         }
     }
 }
@@ -128,6 +152,21 @@ impl Cache {
         &self.root
     }
 
+    /// Path to the directory with the files of the entry with the given digest.
+    pub fn entry_dir(&self, digest: &str) -> PathBuf {
+        self.root.join(digest)
+    }
+
+    /// Path to the index file of the entry with the given digest.
+    pub fn entry_index(&self, digest: &str) -> PathBuf {
+        self.entry_dir(digest).with_appended_extension("json")
+    }
+
+    /// Path to the lock file of the entry with the given digest, see [`lock`].
+    pub fn entry_lock(&self, digest: &str) -> PathBuf {
+        self.entry_dir(digest).with_appended_extension("lock")
+    }
+
     pub async fn new(path: impl Into<PathBuf>) -> Result<Self> {
         let root = path.into();
         crate::fs::tokio::create_dir_if_missing(&root).await?;
@@ -135,41 +174,72 @@ impl Cache {
         Ok(Self { root })
     }
 
+    /// Get the value from the cache, generating it if needed.
+    ///
+    /// The entry is generated into a temporary directory, which is moved into place once complete.
+    /// The index is written last, so an entry with an index is always complete. If another process
+    /// is already generating the entry, this waits for it to finish instead of generating again.
     pub fn get<S>(&self, storable: S) -> BoxFuture<'static, Result<S::Output>>
     where S: Storable {
         let this = self.clone();
         async move {
             let digest = digest(&storable)?;
             tracing::Span::current().record("digest", digest.as_str());
-            let entry_dir = this.root.join(&digest);
-            let entry_meta = entry_dir.with_appended_extension("json");
+            let entry_dir = this.entry_dir(&digest);
+            let entry_meta = this.entry_index(&digest);
 
-            let retrieve = async {
-                let info = entry_meta.read_to_json::<EntryIndexRequired<S>>()?;
-                crate::fs::require_exist(&entry_dir)?;
-                storable.adapt(entry_dir.clone(), info.metadata).await
-            };
+            if let Ok(out) = retrieve(&storable, &entry_dir, &entry_meta).await {
+                trace!("Found in cache, skipping generation.");
+                // Without the lock, the index could be written back after another process removed
+                // the entry. If the lock is taken, the access is simply not recorded.
+                match lock::EntryLock::try_acquire(this.entry_lock(&digest)) {
+                    Ok(Some(_lock)) => record_access(&entry_meta),
+                    Ok(None) => trace!("The entry is locked, not recording the access."),
+                    Err(e) => warn!("Failed to record the cache entry access: {e:?}"),
+                }
+                return Ok(out);
+            }
 
-            match retrieve.await {
+            let _lock = lock::EntryLock::acquire(this.entry_lock(&digest)).await?;
+            // Another process might have filled the entry while we were waiting for the lock.
+            match retrieve(&storable, &entry_dir, &entry_meta).await {
                 Ok(out) => {
-                    trace!("Found in cache, skipping generation.");
-                    Ok(out)
+                    trace!("Found in cache after acquiring the lock, skipping generation.");
+                    record_access(&entry_meta);
+                    return Ok(out);
                 }
+                Err(e) => trace!("Value cannot be retrieved from cache because: {e}"),
+            }
+
+            // Remove the leftovers of a broken entry, the index first.
+            crate::fs::remove_file_if_exists(&entry_meta)?;
+            crate::fs::remove_dir_if_exists(&entry_dir)?;
+            let key = storable.key();
+            tracing::Span::current().record("key", tracing::field::debug(&key));
+            let temporary_dir = this.root.join(format!("{digest}.{}.tmp", Uuid::new_v4()));
+            crate::fs::reset_dir(&temporary_dir)?;
+            let generated = async {
+                let metadata = storable
+                    .generate(this.clone(), temporary_dir.clone())
+                    .instrument(info_span!("Generating value to fill the cache."))
+                    .context("Failed to generate the cache entry.")
+                    .await?;
+                let usage = maintenance::measure(temporary_dir.clone()).await?;
+                Result::Ok((metadata, usage))
+            };
+            let (metadata, usage) = match generated.await {
+                Ok(generated) => generated,
                 Err(e) => {
-                    trace!("Value cannot be retrieved from cache because: {e}");
-                    crate::fs::reset_dir(&entry_dir)?;
-                    let key = storable.key();
-                    tracing::Span::current().record("key", tracing::field::debug(&key));
-                    let metadata = storable
-                        .generate(this, entry_dir.clone())
-                        .instrument(info_span!("Generating value to fill the cache."))
-                        .context("Failed to generate the cache entry.")
-                        .await?;
-                    let info = EntryIndexExtended::<S>::new(metadata, key);
-                    entry_meta.write_as_json(&info)?;
-                    storable.adapt(entry_dir, info.inner.metadata).await
+                    if let Err(cleanup_error) = crate::fs::remove_dir_if_exists(&temporary_dir) {
+                        warn!("Failed to clean up the cache entry: {cleanup_error:?}");
+                    }
+                    return Err(e);
                 }
-            }
+            };
+            crate::fs::rename(&temporary_dir, &entry_dir)?;
+            let info = EntryIndexExtended::<S>::new(metadata, key, usage);
+            write_index(&entry_meta, &info)?;
+            storable.adapt(entry_dir, info.inner.metadata).await
         }
         .instrument(trace_span!(
             "Getting a value from cache.",
@@ -181,6 +251,34 @@ impl Cache {
 }
 
 
+/// Get the value of a complete cache entry.
+async fn retrieve<S: Storable>(
+    storable: &S,
+    entry_dir: &Path,
+    entry_meta: &Path,
+) -> Result<S::Output> {
+    let info = entry_meta.read_to_json::<EntryIndexRequired<S>>()?;
+    crate::fs::require_exist(entry_dir)?;
+    storable.adapt(entry_dir.to_path_buf(), info.metadata).await
+}
+
+/// Update the last access time of the entry. The caller must hold the entry lock. Failure is not an
+/// error, as it only affects the cache maintenance.
+fn record_access(entry_meta: &Path) {
+    if let Err(e) = maintenance::record_access(entry_meta) {
+        warn!("Failed to record the cache entry access: {e:?}");
+    }
+}
+
+/// Write the entry index. The index is written to a temporary file first and then renamed, so that
+/// concurrent readers never observe a partially written index.
+fn write_index(path: &Path, index: &impl Serialize) -> Result {
+    let temporary = path.with_appended_extension(format!("{}.tmp", Uuid::new_v4()));
+    temporary.write_as_json(index)?;
+    crate::fs::rename(&temporary, path)
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,4 +294,73 @@ mod tests {
         cache.get(download_task).await?;
         Ok(())
     }
+
+    /// Environment variable with the cache root, set for the processes spawned by
+    /// [`concurrent_processes_generate_once`].
+    const STRESS_TEST_CACHE: &str = "ENSO_BUILD_CACHE_STRESS_TEST_ROOT";
+
+    /// Entry that takes a while to generate and counts its generations in a marker file.
+    #[derive(Clone, Debug)]
+    struct SlowEntry {
+        marker: PathBuf,
+    }
+
+    impl Storable for SlowEntry {
+        type Metadata = PathBuf;
+        type Output = PathBuf;
+        type Key = String;
+
+        fn generate(&self, _cache: Cache, store: PathBuf) -> BoxFuture<'static, Result<PathBuf>> {
+            let marker = self.marker.clone();
+            async move {
+                crate::fs::append(&marker, "generated\n")?;
+                for index in 0..10 {
+                    crate::fs::write(store.join(format!("{index}.txt")), index.to_string())?;
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                }
+                Ok(PathBuf::from("9.txt"))
+            }
+            .boxed()
+        }
+
+        fn adapt(&self, store: PathBuf, file: PathBuf) -> BoxFuture<'static, Result<PathBuf>> {
+            ready(Ok(store.join(file))).boxed()
+        }
+
+        fn key(&self) -> Self::Key {
+            "stress test".into()
+        }
+    }
+
+    /// Body of the processes spawned by [`concurrent_processes_generate_once`]. Does nothing when
+    /// run directly.
+    #[tokio::test]
+    async fn concurrent_get_process() -> Result {
+        let Ok(root) = std::env::var(STRESS_TEST_CACHE) else { return Ok(()) };
+        let root = PathBuf::from(root);
+        let cache = Cache::new(&root).await?;
+        let file = cache.get(SlowEntry { marker: root.join("generations") }).await?;
+        assert_eq!(crate::fs::read_to_string(file)?, "9");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_processes_generate_once() -> Result {
+        let root = tempfile::tempdir()?;
+        let test_binary = std::env::current_exe()?;
+        let processes = (0..8).map(|_| {
+            tokio::process::Command::new(&test_binary)
+                .args(["--exact", "cache::tests::concurrent_get_process"])
+                .env(STRESS_TEST_CACHE, root.path())
+                .output()
+        });
+        for output in futures::future::join_all(processes).await {
+            let output = output?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            ensure!(output.status.success(), "A process failed to get the cache entry:\n{stdout}");
+        }
+        let generations = crate::fs::read_to_string(root.path().join("generations"))?;
+        assert_eq!(generations.lines().count(), 1);
+        Ok(())
+    }
 }
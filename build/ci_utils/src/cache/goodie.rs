@@ -8,10 +8,13 @@ use crate::cache::Cache;
 // === Export ===
 // ==============
 
+pub mod cargo_watch;
 pub mod flatc;
 pub mod graalpy;
 pub mod graalvm;
+pub mod node;
 pub mod sbt;
+pub mod wasm_pack;
 
 
 
@@ -96,3 +99,17 @@ pub fn download_try_future_url(
     }
     .boxed()
 }
+
+
+/// Download the archive with known SHA-256 digest, possibly from a local mirror directory, and
+/// extract it. See [`cache::pinned::PinnedFile`].
+pub fn download_pinned(
+    url: Url,
+    sha256: String,
+    mirror: Option<PathBuf>,
+    cache: &Cache,
+) -> BoxFuture<'static, Result<PathBuf>> {
+    let archive_source = cache::pinned::PinnedFile { url, sha256, mirror };
+    let package = cache::archive::ExtractedArchive { archive_source, path_to_extract: None };
+    cache.get(package)
+}
@@ -0,0 +1,81 @@
+use crate::prelude::*;
+
+use crate::cache::goodie;
+use crate::cache::Cache;
+use crate::env::known::PATH;
+use crate::env::Modification;
+use crate::github::RepoRef;
+use crate::programs;
+
+
+
+/// The repository of the `cargo-watch` project.
+pub const REPO: RepoRef = RepoRef { owner: "watchexec", name: "cargo-watch" };
+
+/// Prebuilt `cargo-watch` binary.
+///
+/// Cargo finds its subcommands in `PATH`, so activating this makes `cargo watch` available.
+#[derive(Clone, Debug)]
+pub struct CargoWatch {
+    pub version: Version,
+    /// Operating system for which the binary is built.
+    pub os:      OS,
+    /// Architecture for which the binary is built.
+    pub arch:    Arch,
+}
+
+impl CargoWatch {
+    /// Name of the release package, which is also the name of the directory inside it.
+    pub fn package_name(&self) -> Result<String> {
+        let triple = match (self.os, self.arch) {
+            (OS::Linux, Arch::X86_64) => "x86_64-unknown-linux-gnu",
+            (OS::Linux, Arch::AArch64) => "aarch64-unknown-linux-gnu",
+            (OS::MacOS, Arch::X86_64) => "x86_64-apple-darwin",
+            (OS::MacOS, Arch::AArch64) => "aarch64-apple-darwin",
+            (OS::Windows, Arch::X86_64) => "x86_64-pc-windows-msvc",
+            (os, arch) => bail!("There is no prebuilt cargo-watch for {os} on {arch}."),
+        };
+        Ok(format!("cargo-watch-v{}-{triple}", self.version))
+    }
+
+    /// Get the download URL.
+    ///
+    /// ```
+    /// use ide_ci::prelude::*;
+    /// use ide_ci::cache::goodie::cargo_watch::CargoWatch;
+    ///
+    /// # fn main() -> Result {
+    /// let version = Version::from_str("8.4.0")?;
+    /// let cargo_watch = CargoWatch { version, os: OS::MacOS, arch: Arch::AArch64 };
+    /// let url = cargo_watch.url()?;
+    /// assert_eq!(url.as_str(), "https://github.com/watchexec/cargo-watch/releases/download/v8.4.0/cargo-watch-v8.4.0-aarch64-apple-darwin.tar.xz");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn url(&self) -> Result<Url> {
+        let extension = if self.os == OS::Windows { "zip" } else { "tar.xz" };
+        let filename = format!("{}.{extension}", self.package_name()?);
+        let tag = format!("v{}", self.version);
+        Ok(crate::github::release::download_asset(&REPO, &tag, filename))
+    }
+}
+
+impl Goodie for CargoWatch {
+    fn get(&self, cache: &Cache) -> BoxFuture<'static, Result<PathBuf>> {
+        goodie::download_try_url(self.url(), cache)
+    }
+
+    fn is_active(&self) -> BoxFuture<'static, Result<bool>> {
+        let version = self.version.clone();
+        async move {
+            let output = programs::Cargo.cmd()?.arg("watch").arg("--version").run_stdout().await?;
+            Ok(Version::find_in_text(&output)? == version)
+        }
+        .boxed()
+    }
+
+    fn activation_env_changes(&self, package_path: &Path) -> Result<Vec<Modification>> {
+        let bin = package_path.join(self.package_name()?);
+        Ok(vec![Modification::prepend_path(&PATH, bin)])
+    }
+}
@@ -0,0 +1,89 @@
+use crate::prelude::*;
+
+use crate::cache::goodie;
+use crate::cache::Cache;
+use crate::env::known::PATH;
+use crate::env::Modification;
+use crate::programs;
+
+
+
+/// Location of the official Node.js binary distributions.
+pub const DIST_URL: &str = "https://nodejs.org/dist";
+
+/// Node.js binary distribution, including `npm` and `npx`.
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub version: Version,
+    /// Operating system for which the binaries are built.
+    pub os:      OS,
+    /// Architecture for which the binaries are built.
+    pub arch:    Arch,
+}
+
+impl Node {
+    /// Name of the distribution, which is also the name of the directory inside the package.
+    ///
+    /// ```
+    /// use ide_ci::cache::goodie::node::Node;
+    /// use ide_ci::prelude::*;
+    ///
+    /// # fn main() -> Result {
+    /// let version = Version::from_str("18.14.1")?;
+    /// let node = Node { version, os: OS::Linux, arch: Arch::X86_64 };
+    /// assert_eq!(node.distribution_name()?, "node-v18.14.1-linux-x64");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn distribution_name(&self) -> Result<String> {
+        let os = match self.os {
+            OS::Linux => "linux",
+            OS::MacOS => "darwin",
+            OS::Windows => "win",
+        };
+        let arch = match self.arch {
+            Arch::X86_64 => "x64",
+            Arch::AArch64 => "arm64",
+            arch => bail!("Node.js is not distributed for {arch}."),
+        };
+        Ok(format!("node-v{}-{os}-{arch}", self.version))
+    }
+
+    /// Get the download URL.
+    ///
+    /// ```
+    /// use ide_ci::cache::goodie::node::Node;
+    /// use ide_ci::prelude::*;
+    ///
+    /// # fn main() -> Result {
+    /// let version = Version::from_str("18.14.1")?;
+    /// let node = Node { version, os: OS::Windows, arch: Arch::X86_64 };
+    /// let url = node.url()?;
+    /// assert_eq!(url.as_str(), "https://nodejs.org/dist/v18.14.1/node-v18.14.1-win-x64.zip");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn url(&self) -> Result<Url> {
+        let extension = if self.os == OS::Windows { "zip" } else { "tar.gz" };
+        let name = self.distribution_name()?;
+        Ok(Url::parse(&format!("{DIST_URL}/v{}/{name}.{extension}", self.version))?)
+    }
+}
+
+impl Goodie for Node {
+    fn get(&self, cache: &Cache) -> BoxFuture<'static, Result<PathBuf>> {
+        goodie::download_try_url(self.url(), cache)
+    }
+
+    fn is_active(&self) -> BoxFuture<'static, Result<bool>> {
+        let version = self.version.clone();
+        async move { Ok(programs::Node.version().await? == version) }.boxed()
+    }
+
+    fn activation_env_changes(&self, package_path: &Path) -> Result<Vec<Modification>> {
+        let root = package_path.join(self.distribution_name()?);
+        // Windows packages have the executables directly in the root directory.
+        let bin = if self.os == OS::Windows { root } else { root.join("bin") };
+        Ok(vec![Modification::prepend_path(&PATH, bin)])
+    }
+}
@@ -0,0 +1,74 @@
+use crate::prelude::*;
+
+use crate::cache::goodie;
+use crate::cache::Cache;
+use crate::env::known::PATH;
+use crate::env::Modification;
+use crate::github::RepoRef;
+use crate::programs;
+
+
+
+/// The repository of the `wasm-pack` project.
+pub const REPO: RepoRef = RepoRef { owner: "rustwasm", name: "wasm-pack" };
+
+/// Prebuilt `wasm-pack` binary.
+#[derive(Clone, Debug)]
+pub struct WasmPack {
+    pub version: Version,
+    /// Operating system for which the binary is built.
+    pub os:      OS,
+    /// Architecture for which the binary is built.
+    pub arch:    Arch,
+}
+
+impl WasmPack {
+    /// Name of the release package, which is also the name of the directory inside it.
+    pub fn package_name(&self) -> Result<String> {
+        let triple = match (self.os, self.arch) {
+            (OS::Linux, Arch::X86_64) => "x86_64-unknown-linux-musl",
+            (OS::Linux, Arch::AArch64) => "aarch64-unknown-linux-musl",
+            // There are no native binaries for Apple Silicon, Rosetta runs the x86-64 ones.
+            (OS::MacOS, _) => "x86_64-apple-darwin",
+            (OS::Windows, Arch::X86_64) => "x86_64-pc-windows-msvc",
+            (os, arch) => bail!("There is no prebuilt wasm-pack for {os} on {arch}."),
+        };
+        Ok(format!("wasm-pack-v{}-{triple}", self.version))
+    }
+
+    /// Get the download URL.
+    ///
+    /// ```
+    /// use ide_ci::prelude::*;
+    /// use ide_ci::cache::goodie::wasm_pack::WasmPack;
+    ///
+    /// # fn main() -> Result {
+    /// let version = Version::from_str("0.12.1")?;
+    /// let wasm_pack = WasmPack { version, os: OS::Linux, arch: Arch::X86_64 };
+    /// let url = wasm_pack.url()?;
+    /// assert_eq!(url.as_str(), "https://github.com/rustwasm/wasm-pack/releases/download/v0.12.1/wasm-pack-v0.12.1-x86_64-unknown-linux-musl.tar.gz");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn url(&self) -> Result<Url> {
+        let filename = format!("{}.tar.gz", self.package_name()?);
+        let tag = format!("v{}", self.version);
+        Ok(crate::github::release::download_asset(&REPO, &tag, filename))
+    }
+}
+
+impl Goodie for WasmPack {
+    fn get(&self, cache: &Cache) -> BoxFuture<'static, Result<PathBuf>> {
+        goodie::download_try_url(self.url(), cache)
+    }
+
+    fn is_active(&self) -> BoxFuture<'static, Result<bool>> {
+        let version = self.version.clone();
+        async move { Ok(programs::WasmPack.version().await? == version) }.boxed()
+    }
+
+    fn activation_env_changes(&self, package_path: &Path) -> Result<Vec<Modification>> {
+        let bin = package_path.join(self.package_name()?);
+        Ok(vec![Modification::prepend_path(&PATH, bin)])
+    }
+}
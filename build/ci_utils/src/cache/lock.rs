@@ -0,0 +1,69 @@
+//! Cross-process locking of cache entries.
+//!
+//! Each entry has a `<digest>.lock` file next to its index. A process filling or removing the
+//! entry holds an exclusive lock on that file, so concurrent build script invocations (e.g.
+//! parallel jobs on a self-hosted runner) wait for each other instead of generating into the same
+//! directory. The lock files are never removed, as removing a lock file while another process
+//! waits on it would let a third process lock a new file under the same path.
+
+use crate::prelude::*;
+
+use fs2::FileExt;
+use std::fs::File;
+use std::fs::OpenOptions;
+
+
+
+/// An exclusive lock on a cache entry, shared between processes. The lock is released when this
+/// value is dropped (or the process exits).
+#[derive(Debug)]
+pub struct EntryLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl EntryLock {
+    fn open(path: &Path) -> Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open the lock file {}.", path.display()))
+    }
+
+    /// Acquire the lock, waiting while another process holds it.
+    pub async fn acquire(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(lock) = Self::try_acquire(&path)? {
+            return Ok(lock);
+        }
+        info!("Waiting for another process to release the cache entry lock {}.", path.display());
+        let file = Self::open(&path)?;
+        tokio::task::spawn_blocking(move || {
+            file.lock_exclusive().with_context(|| format!("Failed to lock {}.", path.display()))?;
+            Ok(Self { file, path })
+        })
+        .await?
+    }
+
+    /// Acquire the lock if no other process holds it.
+    pub fn try_acquire(path: impl Into<PathBuf>) -> Result<Option<Self>> {
+        let path = path.into();
+        let file = Self::open(&path)?;
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Self { file, path })),
+            Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to lock {}.", path.display())),
+        }
+    }
+}
+
+impl Drop for EntryLock {
+    fn drop(&mut self) {
+        if let Err(e) = FileExt::unlock(&self.file) {
+            warn!("Failed to unlock {}: {e}", self.path.display());
+        }
+    }
+}
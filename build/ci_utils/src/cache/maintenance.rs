@@ -0,0 +1,509 @@
+//! Inspection and cleanup of the cache contents.
+//!
+//! Each cache entry consists of a `<digest>` directory with the entry files and a `<digest>.json`
+//! index. Besides the data needed to use the entry, the index records when the entry was created
+//! and last accessed, its size and the digest of its files (see [`EntryUsage`]). This allows
+//! removing the stale entries and detecting the damaged ones.
+//!
+//! Hashing large entries (like a GraalVM distribution) is costly, so the digest is not computed
+//! when the entry is generated, but when it is verified for the first time.
+
+use crate::prelude::*;
+
+use crate::cache::lock::EntryLock;
+use crate::cache::write_index;
+use crate::cache::Cache;
+use crate::cache::EntryUsage;
+
+use chrono::DateTime;
+use chrono::Utc;
+use sha2::Digest;
+use std::time::Duration;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Time after the last modification of an incomplete entry (i.e. one lacking the index or the
+/// files) or a temporary file, after which it is considered abandoned.
+pub const INCOMPLETE_ENTRY_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+
+
+// =====================
+// === Usage Records ===
+// =====================
+
+/// Total size of the files in the directory, in bytes.
+pub fn size(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in walkdir::WalkDir::new(path) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+/// Digest of the directory contents: relative paths, file contents and symlink targets.
+///
+/// The directory tree is traversed in a sorted order, so the digest does not depend on the order
+/// in which the file system lists the files.
+#[context("Failed to compute the digest of {}.", path.display())]
+pub fn content_digest(path: &Path) -> Result<String> {
+    let mut digest = sha2::Sha256::default();
+    for entry in walkdir::WalkDir::new(path).sort_by_file_name() {
+        let entry = entry?;
+        let relative_path = entry.path().strip_prefix(path)?;
+        Digest::update(&mut digest, relative_path.as_str().as_bytes());
+        Digest::update(&mut digest, [0]);
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            let target = std::fs::read_link(entry.path())?;
+            Digest::update(&mut digest, b"l");
+            Digest::update(&mut digest, target.as_str().as_bytes());
+        } else if file_type.is_file() {
+            Digest::update(&mut digest, b"f");
+            let mut file = crate::fs::open(entry.path())?;
+            std::io::copy(&mut file, &mut digest)?;
+        } else {
+            Digest::update(&mut digest, b"d");
+        }
+        Digest::update(&mut digest, [0]);
+    }
+    Ok(data_encoding::BASE64URL_NOPAD.encode(&digest.finalize()))
+}
+
+/// Describe a freshly generated entry. The content digest is left to the first verification.
+pub async fn measure(entry_dir: PathBuf) -> Result<EntryUsage> {
+    tokio::task::spawn_blocking(move || {
+        let now = Utc::now();
+        Ok(EntryUsage {
+            created:        Some(now),
+            last_access:    Some(now),
+            size:           Some(size(&entry_dir)?),
+            content_digest: None,
+        })
+    })
+    .await?
+}
+
+/// Set a field of the entry index.
+///
+/// The index is updated as a JSON value, so this does not need to know the type of the stored
+/// entity.
+fn update_index(index: &Path, field: &str, value: impl Serialize) -> Result {
+    let mut json = index.read_to_json::<serde_json::Value>()?;
+    let object = json.as_object_mut().context("The cache entry index is not a JSON object.")?;
+    object.insert(field.into(), serde_json::to_value(value)?);
+    write_index(index, &json)
+}
+
+/// Update the last access time in the entry index. The caller must hold the entry lock.
+///
+/// Does nothing if the index does not exist anymore, e.g. because the entry was removed since it
+/// was retrieved.
+pub fn record_access(index: &Path) -> Result {
+    if !index.exists() {
+        return Ok(());
+    }
+    update_index(index, "last_access", Utc::now())
+}
+
+
+
+// =============
+// === Entry ===
+// =============
+
+/// The part of the entry index that does not depend on the type of the stored entity.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EntryIndex {
+    /// The key of the entry.
+    pub key:            Option<serde_json::Value>,
+    /// Name of the stored entity type.
+    pub r#type:         Option<String>,
+    /// Version of the hashing scheme, see [`crate::cache::VERSION`].
+    pub schema_version: Option<u8>,
+    #[serde(flatten)]
+    pub usage:          EntryUsage,
+}
+
+/// A cache entry, as found on the disk.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// Digest of the entry key, naming the entry files.
+    pub digest:   String,
+    /// The entry index, unless it is missing or cannot be read.
+    pub index:    Option<EntryIndex>,
+    /// Whether the directory with the entry files exists.
+    pub has_data: bool,
+    /// Size of the entry files and index on the disk, in bytes.
+    pub size:     u64,
+    /// Last modification time of the entry files or index.
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl Entry {
+    /// Check if both the index and the files of the entry are present.
+    pub fn is_complete(&self) -> bool {
+        self.index.is_some() && self.has_data
+    }
+
+    /// When the entry was last used. If the index does not record it, the modification time is
+    /// used.
+    pub fn last_used(&self) -> Option<DateTime<Utc>> {
+        let usage = self.index.as_ref().map(|index| &index.usage);
+        let recorded = usage.and_then(|usage| usage.last_access.or(usage.created));
+        recorded.or(self.modified)
+    }
+
+    /// Human-readable description of the entry key.
+    pub fn describe(&self) -> String {
+        match &self.index {
+            Some(EntryIndex { key: Some(key), .. }) => key.to_string(),
+            Some(EntryIndex { r#type: Some(r#type), .. }) => r#type.clone(),
+            Some(_) => "<unknown key>".into(),
+            None => "<incomplete entry>".into(),
+        }
+    }
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let size = indicatif::HumanBytes(self.size).to_string();
+        let last_used = match self.last_used() {
+            Some(last_used) => last_used.format("%Y-%m-%d %H:%M").to_string(),
+            None => "unknown".into(),
+        };
+        write!(f, "{}  {size:>10}  {last_used:>16}  {}", self.digest, self.describe())
+    }
+}
+
+
+
+// ====================
+// === Verification ===
+// ====================
+
+/// Result of an entry integrity check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verification {
+    /// The entry files match the recorded digest.
+    Valid,
+    /// The entry was not verified before, so its digest was computed and recorded for the later
+    /// checks.
+    Recorded,
+    /// The index is missing or cannot be read.
+    MissingIndex,
+    /// The entry files are missing.
+    MissingData,
+    /// The entry files do not match the recorded digest.
+    Corrupted,
+}
+
+impl Verification {
+    /// Check if the entry should not be used.
+    pub fn is_invalid(self) -> bool {
+        matches!(self, Self::MissingIndex | Self::MissingData | Self::Corrupted)
+    }
+}
+
+impl Display for Verification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Self::Valid => "valid",
+            Self::Recorded => "digest recorded",
+            Self::MissingIndex => "missing or unreadable index",
+            Self::MissingData => "missing files",
+            Self::Corrupted => "files modified or damaged",
+        };
+        f.write_str(description)
+    }
+}
+
+
+
+// ===============
+// === Pruning ===
+// ===============
+
+/// Rules for selecting the entries to remove from the cache.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrunePolicy {
+    /// Remove the entries that were not used for longer than this.
+    pub max_age:  Option<Duration>,
+    /// Remove the least recently used entries, until the total size of the cache (in bytes) does
+    /// not exceed this.
+    pub max_size: Option<u64>,
+}
+
+impl PrunePolicy {
+    /// Select the entries to remove.
+    ///
+    /// Regardless of the policy, the incomplete entries older than
+    /// [`INCOMPLETE_ENTRY_GRACE_PERIOD`] are selected.
+    pub fn select<'a>(&self, entries: &'a [Entry], now: DateTime<Utc>) -> Vec<&'a Entry> {
+        let unused_for = |entry: &Entry, age: Duration| match chrono::Duration::from_std(age) {
+            Ok(age) => entry.last_used().map_or(true, |last_used| now - last_used > age),
+            Err(_) => false,
+        };
+        let (mut selected, mut kept): (Vec<_>, Vec<_>) = entries.iter().partition(|entry| {
+            if entry.is_complete() {
+                self.max_age.is_some_and(|age| unused_for(entry, age))
+            } else {
+                unused_for(entry, INCOMPLETE_ENTRY_GRACE_PERIOD)
+            }
+        });
+        if let Some(max_size) = self.max_size {
+            kept.sort_by_key(|entry| entry.last_used());
+            let mut total_size = kept.iter().map(|entry| entry.size).sum::<u64>();
+            for entry in kept {
+                if total_size <= max_size {
+                    break;
+                }
+                total_size -= entry.size;
+                selected.push(entry);
+            }
+        }
+        selected
+    }
+}
+
+
+
+// =============
+// === Cache ===
+// =============
+
+impl Cache {
+    /// List the entries in the cache, including the incomplete ones.
+    pub async fn entries(&self) -> Result<Vec<Entry>> {
+        let cache = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut digests = BTreeSet::new();
+            for item in crate::fs::read_dir(cache.path())? {
+                let name = item?.file_name();
+                let Some(name) = name.to_str() else { continue };
+                let digest = name.strip_suffix(".json").unwrap_or(name);
+                // Digests are base64url-encoded, so anything with a dot is not an entry (e.g. a
+                // temporary index file).
+                if !digest.contains('.') {
+                    digests.insert(digest.to_owned());
+                }
+            }
+            digests.into_iter().map(|digest| cache.read_entry(digest)).collect()
+        })
+        .await?
+    }
+
+    /// Temporary files and directories left by the processes that were interrupted while filling
+    /// the cache.
+    fn abandoned_temporaries(&self, now: DateTime<Utc>) -> Result<Vec<PathBuf>> {
+        let mut abandoned = Vec::new();
+        for item in crate::fs::read_dir(self.path())? {
+            let item = item?;
+            if !item.file_name().to_string_lossy().ends_with(".tmp") {
+                continue;
+            }
+            let modified = item.metadata()?.modified()?;
+            let age = (now - DateTime::<Utc>::from(modified)).to_std().unwrap_or_default();
+            if age > INCOMPLETE_ENTRY_GRACE_PERIOD {
+                abandoned.push(item.path());
+            }
+        }
+        Ok(abandoned)
+    }
+
+    fn read_entry(&self, digest: String) -> Result<Entry> {
+        let entry_dir = self.entry_dir(&digest);
+        let entry_index = self.entry_index(&digest);
+        let index = entry_index.read_to_json::<EntryIndex>().ok();
+        let has_data = entry_dir.is_dir();
+        let mut size = 0;
+        let mut modified = None;
+        for path in [&entry_dir, &entry_index] {
+            if let Ok(metadata) = std::fs::symlink_metadata(path) {
+                modified = modified.max(metadata.modified().ok().map(DateTime::<Utc>::from));
+                size += if metadata.is_dir() { self::size(path)? } else { metadata.len() };
+            }
+        }
+        Ok(Entry { digest, index, has_data, size, modified })
+    }
+
+    /// Remove the entry with the given digest. Waits if another process is filling the entry.
+    pub async fn remove(&self, digest: &str) -> Result {
+        ensure!(
+            !digest.is_empty() && !digest.contains(['/', '\\', '.']),
+            "Invalid cache entry digest: {digest}."
+        );
+        let _lock = EntryLock::acquire(self.entry_lock(digest)).await?;
+        self.remove_locked(digest).await
+    }
+
+    /// Remove the entry files. The caller must hold the entry lock.
+    async fn remove_locked(&self, digest: &str) -> Result {
+        let entry_dir = self.entry_dir(digest);
+        let entry_index = self.entry_index(digest);
+        ensure!(
+            entry_dir.exists() || entry_index.exists(),
+            "There is no cache entry with digest {digest}."
+        );
+        // The index goes first, so an interrupted removal leaves an incomplete entry, rather than
+        // an index describing partially removed files.
+        crate::fs::tokio::remove_file_if_exists(&entry_index).await?;
+        crate::fs::tokio::remove_dir_if_exists(&entry_dir).await
+    }
+
+    /// Check the integrity of the entry. If the entry has no recorded digest yet, it is computed
+    /// and recorded.
+    pub async fn verify(&self, entry: &Entry) -> Result<Verification> {
+        let Some(index) = &entry.index else { return Ok(Verification::MissingIndex) };
+        if !entry.has_data {
+            return Ok(Verification::MissingData);
+        }
+        let entry_dir = self.entry_dir(&entry.digest);
+        let Some(expected) = index.usage.content_digest.clone() else {
+            // The lock keeps other processes from removing or refilling the entry meanwhile.
+            let _lock = EntryLock::acquire(self.entry_lock(&entry.digest)).await?;
+            let entry_index = self.entry_index(&entry.digest);
+            if !entry_index.exists() {
+                return Ok(Verification::MissingIndex);
+            }
+            let digest = tokio::task::spawn_blocking(move || content_digest(&entry_dir)).await??;
+            update_index(&entry_index, "content_digest", digest)?;
+            return Ok(Verification::Recorded);
+        };
+        let actual = tokio::task::spawn_blocking(move || content_digest(&entry_dir)).await??;
+        Ok(if actual == expected { Verification::Valid } else { Verification::Corrupted })
+    }
+
+    /// Remove the entries selected by the policy, and the abandoned temporary files. Returns the
+    /// removed entries.
+    ///
+    /// The entries being filled by other processes are skipped. If `dry_run` is set, the entries
+    /// are only selected.
+    pub async fn prune(&self, policy: &PrunePolicy, dry_run: bool) -> Result<Vec<Entry>> {
+        let now = Utc::now();
+        let entries = self.entries().await?;
+        let selected = policy.select(&entries, now).into_iter().cloned().collect_vec();
+        if dry_run {
+            return Ok(selected);
+        }
+        let mut removed = Vec::new();
+        for entry in selected {
+            let Some(_lock) = EntryLock::try_acquire(self.entry_lock(&entry.digest))? else {
+                debug!("Skipping cache entry {}, as it is in use.", entry.digest);
+                continue;
+            };
+            debug!("Removing cache entry {}: {}", entry.digest, entry.describe());
+            self.remove_locked(&entry.digest).await?;
+            removed.push(entry);
+        }
+        for path in self.abandoned_temporaries(now)? {
+            debug!("Removing abandoned temporary {}.", path.display());
+            crate::fs::remove_if_exists(&path)?;
+        }
+        Ok(removed)
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cache::Storable;
+
+    /// Entry consisting of a single file with the given contents.
+    #[derive(Clone, Debug)]
+    struct TextEntry(&'static str);
+
+    impl Storable for TextEntry {
+        type Metadata = ();
+        type Output = PathBuf;
+        type Key = String;
+
+        fn generate(&self, _cache: Cache, store: PathBuf) -> BoxFuture<'static, Result> {
+            ready(crate::fs::write(store.join("entry.txt"), self.0)).boxed()
+        }
+
+        fn adapt(&self, store: PathBuf, _metadata: ()) -> BoxFuture<'static, Result<PathBuf>> {
+            ready(Ok(store.join("entry.txt"))).boxed()
+        }
+
+        fn key(&self) -> Self::Key {
+            self.0.into()
+        }
+    }
+
+    fn entry(digest: &str, size: u64, days_unused: i64, complete: bool) -> Entry {
+        let last_access = Utc::now() - chrono::Duration::days(days_unused);
+        let usage = EntryUsage { last_access: Some(last_access), ..default() };
+        let index = EntryIndex { key: None, r#type: None, schema_version: None, usage };
+        Entry {
+            digest: digest.into(),
+            index: Some(index),
+            has_data: complete,
+            size,
+            modified: Some(last_access),
+        }
+    }
+
+    fn selected(policy: PrunePolicy, entries: &[Entry]) -> Vec<&str> {
+        let selected = policy.select(entries, Utc::now());
+        selected.into_iter().map(|entry| entry.digest.as_str()).sorted().collect()
+    }
+
+    #[test]
+    fn prune_selection() {
+        let entries = [
+            entry("old", 10, 40, true),
+            entry("recent", 10, 1, true),
+            entry("older", 10, 20, true),
+            entry("abandoned", 10, 2, false),
+        ];
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(selected(default(), &entries), ["abandoned"]);
+        let by_age = PrunePolicy { max_age: Some(30 * day), max_size: None };
+        assert_eq!(selected(by_age, &entries), ["abandoned", "old"]);
+        let by_size = PrunePolicy { max_age: None, max_size: Some(15) };
+        assert_eq!(selected(by_size, &entries), ["abandoned", "old", "older"]);
+    }
+
+    #[test]
+    fn content_digest_detects_changes() -> Result {
+        let dir = tempfile::tempdir()?;
+        crate::fs::write(dir.path().join("a.txt"), "a")?;
+        crate::fs::write(dir.path().join("sub").join("b.txt"), "b")?;
+        let digest = content_digest(dir.path())?;
+        assert_eq!(digest, content_digest(dir.path())?);
+        crate::fs::write(dir.path().join("sub").join("b.txt"), "c")?;
+        assert_ne!(digest, content_digest(dir.path())?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn digest_recorded_on_first_verification() -> Result {
+        let root = tempfile::tempdir()?;
+        let cache = Cache::new(root.path()).await?;
+        let file = cache.get(TextEntry("contents")).await?;
+        let entry = || async { Result::Ok(cache.entries().await?.into_iter().exactly_one()?) };
+        let usage = entry().await?.index.context("Missing index.")?.usage;
+        assert!(usage.size.is_some() && usage.content_digest.is_none());
+
+        assert_eq!(cache.verify(&entry().await?).await?, Verification::Recorded);
+        assert_eq!(cache.verify(&entry().await?).await?, Verification::Valid);
+        crate::fs::write(&file, "damaged")?;
+        assert_eq!(cache.verify(&entry().await?).await?, Verification::Corrupted);
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! Files with a known SHA-256 digest.
+//!
+//! The cache entry is identified by the file name and the digest, not by the URL, so the same file
+//! can be obtained either from the network or from a local mirror directory.
+
+use crate::prelude::*;
+
+use crate::cache::download::DownloadFile;
+use crate::cache::Cache;
+use crate::cache::Storable;
+use crate::io::filename_from_url;
+use crate::io::Sha256Digest;
+
+
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Key {
+    pub filename: PathBuf,
+    /// Hex-encoded SHA-256 digest of the file contents.
+    pub sha256:   String,
+}
+
+/// A file that is downloaded or taken from a mirror, and verified against the expected digest.
+#[derive(Clone, Debug)]
+pub struct PinnedFile {
+    pub url:    Url,
+    /// Hex-encoded SHA-256 digest of the file contents.
+    pub sha256: String,
+    /// Directory with the files to be used instead of downloading them, looked up by file name.
+    pub mirror: Option<PathBuf>,
+}
+
+impl PinnedFile {
+    /// Get the path to the file, preferring the mirror, and downloading it into the cache
+    /// otherwise. The file is not verified.
+    pub fn locate(
+        url: Url,
+        mirror: Option<PathBuf>,
+        cache: &Cache,
+    ) -> BoxFuture<'static, Result<PathBuf>> {
+        let cache = cache.clone();
+        async move {
+            let filename = filename_from_url(&url)?;
+            if let Some(mirror) = mirror {
+                let mirrored = mirror.join(&filename);
+                if mirrored.is_file() {
+                    debug!("Using {} from the mirror.", mirrored.display());
+                    return Ok(mirrored);
+                }
+                debug!("{} is not present in the mirror {}.", filename.display(), mirror.display());
+            }
+            cache.get(DownloadFile::new(url)?).await
+        }
+        .boxed()
+    }
+}
+
+impl Storable for PinnedFile {
+    type Metadata = PathBuf;
+    type Output = PathBuf;
+    type Key = Key;
+
+    fn generate(&self, cache: Cache, store: PathBuf) -> BoxFuture<'static, Result<Self::Metadata>> {
+        let Self { url, sha256, mirror } = self.clone();
+        async move {
+            let filename = filename_from_url(&url)?;
+            let source = Self::locate(url, mirror, &cache).await?;
+            let found = Sha256Digest::of_file_async(&source).await?.to_hex();
+            ensure!(
+                found == sha256,
+                "The SHA-256 digest of {} is {found}, while {sha256} was expected.",
+                source.display()
+            );
+            crate::fs::tokio::copy(&source, store.join(&filename)).await?;
+            Ok(filename)
+        }
+        .boxed()
+    }
+
+    fn adapt(
+        &self,
+        cache: PathBuf,
+        metadata: Self::Metadata,
+    ) -> BoxFuture<'static, Result<Self::Output>> {
+        async move { Ok(cache.join(metadata)) }.boxed()
+    }
+
+    fn key(&self) -> Self::Key {
+        Key {
+            filename: filename_from_url(&self.url).unwrap_or_default(),
+            sha256:   self.sha256.clone(),
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn mirrored_file() -> Result {
+        let mirror = tempfile::tempdir()?;
+        let cache = Cache::new(tempfile::tempdir()?.into_path()).await?;
+        crate::fs::write(mirror.path().join("tool.tar.gz"), "contents")?;
+        let url = Url::parse("https://example.com/releases/tool.tar.gz")?;
+        let sha256 = Sha256Digest::of_file_async(mirror.path().join("tool.tar.gz")).await?.to_hex();
+        assert_eq!(sha256, "d1b2a59fbea7e20077af9f91b27e95e865061b270be03ff539ab3b73587882e8");
+
+        let mirror = Some(mirror.path().to_owned());
+        let pinned = PinnedFile { url: url.clone(), sha256, mirror: mirror.clone() };
+        let path = cache.get(pinned).await?;
+        assert_eq!(crate::fs::read_to_string(&path)?, "contents");
+
+        let wrong = PinnedFile { url, sha256: "00".repeat(32), mirror };
+        assert!(cache.get(wrong).await.is_err());
+        Ok(())
+    }
+}
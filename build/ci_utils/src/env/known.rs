@@ -76,6 +76,14 @@ define_env_var! {
     LC_MONETARY, String;
 }
 
+define_env_var! {
+    /// Time (in seconds since the Unix epoch) that tools aiming for reproducible output use instead
+    /// of the current time or file modification times.
+    ///
+    /// See: <https://reproducible-builds.org/specs/source-date-epoch/>
+    SOURCE_DATE_EPOCH, u64;
+}
+
 /// The `C.UTF-8` locale, when used as a value for [`LC_ALL`] or other `LC_*` environment variables
 /// in Unix-like systems, combines the minimalistic behavior of the default C locale with UTF-8
 /// character encoding.
@@ -1,10 +1,12 @@
 use crate::prelude::*;
 
+use crate::program::command::Child;
+
 use sysinfo::Pid;
 
 
 
-/// Extension methods for [`tokio::process::Child`].
+/// Extension methods for [`Child`].
 pub trait ChildExt {
     /// Wait for the process completion and represent non-zero exit code as an error.
     fn wait_ok(&mut self) -> BoxFuture<Result>;
@@ -15,7 +17,7 @@ pub trait ChildExt {
     fn kill_subtree(&self);
 }
 
-impl ChildExt for tokio::process::Child {
+impl ChildExt for Child {
     fn wait_ok(&mut self) -> BoxFuture<Result> {
         async move { default_status_checker(self.wait().await?) }.boxed()
     }
@@ -204,7 +204,8 @@ pub trait IsReleaseExt: IsRelease + Sync {
     /// Upload given directory as a release asset.
     ///
     /// The given filename will be used, with appended [platform-specific
-    /// extension](ARCHIVE_EXTENSION).
+    /// extension](ARCHIVE_EXTENSION). The archive is packed in the [deterministic
+    /// mode](crate::archive::Mode::Deterministic), so it can be verified by rebuilding.
     async fn upload_compressed_dir_as(
         &self,
         dir_to_upload: impl AsRef<Path> + Send,
@@ -214,7 +215,8 @@ pub trait IsReleaseExt: IsRelease + Sync {
         let temp_dir = tempfile::tempdir()?;
         let archive_path =
             custom_name.with_parent(temp_dir.path()).with_appended_extension(archive_extension());
-        crate::archive::create(&archive_path, [&dir_to_upload]).await?;
+        let mode = crate::archive::Mode::deterministic()?;
+        crate::archive::create_with_mode(&archive_path, [&dir_to_upload], mode).await?;
         self.upload_asset_file(archive_path).await
     }
 
@@ -104,7 +104,12 @@ pub fn println(msg: impl AsRef<str>) {
 
 pub fn spawn(name: impl AsRef<str>, f: impl Future<Output = Result> + Send + 'static) {
     // info!("Spawning a new global task named '{}'.", name.as_ref());
-    let join_handle = tokio::task::spawn(f.instrument(info_span!("task", name = name.as_ref())));
+    let f = f.instrument(info_span!("task", name = name.as_ref()));
+    // The task inherits the command executor, so that its commands are recorded or replayed too.
+    let join_handle = match crate::program::command::executor::current() {
+        Some(executor) => tokio::task::spawn(crate::program::command::executor::scope(executor, f)),
+        None => tokio::task::spawn(f),
+    };
     // let join_handle = tokio::task::Builder::new().name(name.as_ref()).spawn(f);
     GLOBAL.lock().unwrap().ongoing_tasks.push(join_handle);
 }
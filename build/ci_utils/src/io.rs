@@ -4,6 +4,7 @@ use crate::io::web::filename_from_response;
 use crate::io::web::stream_response_to_file;
 
 use reqwest::IntoUrl;
+use sha2::Digest;
 use tokio::io::AsyncRead;
 use web::client;
 
@@ -24,6 +25,41 @@ pub async fn read_length(mut read: impl AsyncRead + Unpin) -> Result<u64> {
     Ok(tokio::io::copy(&mut read, &mut sink).await?)
 }
 
+/// SHA-256 digest of some data, like a file's contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sha256Digest {
+    /// The raw digest.
+    pub bytes: [u8; 32],
+    /// Size of the digested data in bytes.
+    pub size:  u64,
+}
+
+impl Sha256Digest {
+    /// Digest the data, reading it until its end.
+    pub fn of_reader(mut data: impl Read) -> Result<Self> {
+        let mut hasher = sha2::Sha256::new();
+        let size = std::io::copy(&mut data, &mut hasher)?;
+        Ok(Self { bytes: hasher.finalize().into(), size })
+    }
+
+    /// Digest the file contents.
+    #[context("Failed to compute the digest of {}.", path.as_ref().display())]
+    pub fn of_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::of_reader(crate::fs::open(path.as_ref())?)
+    }
+
+    /// Digest the file contents on a blocking thread.
+    pub async fn of_file_async(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        tokio::task::spawn_blocking(move || Self::of_file(path)).await?
+    }
+
+    /// Lowercase hex encoding of the digest, as used by `sha256sum`.
+    pub fn to_hex(&self) -> String {
+        data_encoding::HEXLOWER.encode(&self.bytes)
+    }
+}
+
 /// Get the the response body as a byte stream.
 pub async fn download(url: impl IntoUrl) -> Result<impl Stream<Item = reqwest::Result<Bytes>>> {
     client::download(&default(), url).await
@@ -120,6 +156,22 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn sha256_digest() -> Result {
+        let dir = tempdir()?;
+        let path = dir.path().join("abc.txt");
+        std::fs::write(&path, "abc")?;
+        let digest = Sha256Digest::of_file_async(&path).await?;
+        assert_eq!(digest, Sha256Digest::of_reader("abc".as_bytes())?);
+        assert_eq!(digest.size, 3);
+        assert_eq!(
+            digest.to_hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(Sha256Digest::of_file(dir.path().join("missing.txt")).is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     #[ignore]
     async fn copy_dir_with_symlink() -> Result {
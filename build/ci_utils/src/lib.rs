@@ -31,6 +31,7 @@ pub mod program;
 pub mod programs;
 pub mod reqwest;
 pub mod serde;
+pub mod task;
 
 
 
@@ -14,6 +14,13 @@ use tracing_subscriber::Layer;
 use tracing_subscriber::Registry;
 
 
+// ==============
+// === Export ===
+// ==============
+
+pub mod trace;
+
+
 
 pub fn is_our_module_path(path: impl AsRef<str>) -> bool {
     ["ide_ci::", "enso"].into_iter().any(|prefix| path.as_ref().starts_with(prefix))
@@ -62,12 +69,18 @@ where S: Subscriber + for<'a> LookupSpan<'a> + Debug + Send + Sync + 'static {
 
 /// Install global `tracing` subscriber that logs to stderr.
 ///
+/// If requested through the environment, the spans are also [recorded](trace::Recorder), to be
+/// written out by [`trace::finish`].
+///
 /// Should be called only once, otherwise it will fail.
 ///
 /// When using this function in unit tests, the result should be ignored, to allow multiple tests
 /// to be run in a single batch.
 pub fn setup_logging() -> Result {
-    let registry = Registry::default().with(GlobalFilteringLayer).with(stderr_log_layer());
+    let registry = Registry::default()
+        .with(GlobalFilteringLayer)
+        .with(stderr_log_layer())
+        .with(trace::layer());
     tracing::subscriber::set_global_default(registry)
         .context("Failed to set global default subscriber.")
 }
@@ -0,0 +1,390 @@
+//! Recording of the build's `tracing` spans, to see where the build time is spent.
+//!
+//! When enabled (see [`ENSO_BUILD_TRACE`] and [`ENSO_BUILD_OTLP_ENDPOINT`]), the [`Recorder`]
+//! layer keeps every closed span. At exit, [`finish`] writes them as a [Chrome trace](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU),
+//! that can be opened in `chrome://tracing` or [Perfetto](https://ui.perfetto.dev), sends them
+//! to an OpenTelemetry collector and logs the summary of the slowest spans.
+
+use crate::prelude::*;
+
+use crate::define_env_var;
+
+use serde_json::json;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use tracing::field::Field;
+use tracing::field::Visit;
+use tracing::span;
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+define_env_var! {
+    /// Path to the file where the Chrome trace of the build is written at exit.
+    ENSO_BUILD_TRACE, PathBuf;
+
+    /// Base URL of the OpenTelemetry collector accepting OTLP over HTTP, to which the build spans
+    /// are sent at exit, e.g. `http://localhost:4318`.
+    ENSO_BUILD_OTLP_ENDPOINT, Url;
+}
+
+/// How many span names are listed in the summary logged at exit.
+pub const SUMMARY_LENGTH: usize = 20;
+
+/// The recorder installed by [`crate::log::setup_logging`], if any.
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+
+
+// ==============
+// === Record ===
+// ==============
+
+/// A closed span.
+#[derive(Clone, Debug)]
+pub struct SpanRecord {
+    /// Identifier unique within the recording, unlike the `tracing` span identifiers that are
+    /// reused.
+    pub id:       u64,
+    pub parent:   Option<u64>,
+    /// Identifier of the outermost span containing this one, possibly the span itself.
+    pub root:     u64,
+    pub name:     &'static str,
+    pub target:   &'static str,
+    pub fields:   BTreeMap<String, String>,
+    pub start:    SystemTime,
+    pub duration: Duration,
+}
+
+/// Data attached to each open span.
+#[derive(Debug)]
+struct OpenSpan {
+    id:         u64,
+    parent:     Option<u64>,
+    root:       u64,
+    fields:     BTreeMap<String, String>,
+    start:      SystemTime,
+    started_at: Instant,
+}
+
+/// Stores the span fields' values as text.
+#[derive(Debug)]
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().into(), format!("{value:?}"));
+    }
+}
+
+
+
+// ================
+// === Recorder ===
+// ================
+
+/// A layer keeping all the closed spans.
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    /// The moment the recording started, used as the zero point of the Chrome trace.
+    pub started: SystemTime,
+    next_id:     Arc<AtomicU64>,
+    spans:       Arc<Mutex<Vec<SpanRecord>>>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self { started: SystemTime::now(), next_id: default(), spans: default() }
+    }
+}
+
+impl<S> Layer<S> for Recorder
+where S: Subscriber + for<'a> LookupSpan<'a>
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let parent = span.parent().and_then(|parent| {
+            let extensions = parent.extensions();
+            extensions.get::<OpenSpan>().map(|open| (open.id, open.root))
+        });
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        span.extensions_mut().insert(OpenSpan {
+            id,
+            parent: parent.map(|(parent, _)| parent),
+            root: parent.map_or(id, |(_, root)| root),
+            fields,
+            start: SystemTime::now(),
+            started_at: Instant::now(),
+        });
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(open) = extensions.get_mut::<OpenSpan>() {
+            values.record(&mut FieldVisitor(&mut open.fields));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(open) = span.extensions_mut().remove::<OpenSpan>() else { return };
+        let record = SpanRecord {
+            id:       open.id,
+            parent:   open.parent,
+            root:     open.root,
+            name:     span.metadata().name(),
+            target:   span.metadata().target(),
+            fields:   open.fields,
+            start:    open.start,
+            duration: open.started_at.elapsed(),
+        };
+        self.spans.lock().unwrap().push(record);
+    }
+}
+
+impl Recorder {
+    /// The spans closed so far, in the order of closing.
+    pub fn spans(&self) -> Vec<SpanRecord> {
+        self.spans.lock().unwrap().clone()
+    }
+
+    /// The spans in the Chrome trace event format.
+    ///
+    /// Spans are described as nestable asynchronous events, as the spans of concurrent futures
+    /// overlap without nesting. Each outermost span with its descendants forms a separate track.
+    pub fn chrome_trace(&self) -> serde_json::Value {
+        let micros = |time: SystemTime| {
+            time.duration_since(self.started).unwrap_or_default().as_micros() as u64
+        };
+        let events = self.spans().into_iter().flat_map(|span| {
+            let start = micros(span.start);
+            let end = start + span.duration.as_micros() as u64;
+            let id = format!("{:#x}", span.root);
+            let begin = json!({
+                "ph": "b", "name": span.name, "cat": span.target, "id": id, "pid": 1,
+                "ts": start, "args": span.fields,
+            });
+            let end = json!({
+                "ph": "e", "name": span.name, "cat": span.target, "id": id, "pid": 1, "ts": end,
+            });
+            [begin, end]
+        });
+        json!({ "traceEvents": events.collect_vec(), "displayTimeUnit": "ms" })
+    }
+
+    /// The spans as an OTLP/HTTP [export request](https://opentelemetry.io/docs/specs/otlp/#otlphttp-request)
+    /// in the JSON encoding.
+    ///
+    /// Each outermost span starts a separate trace.
+    pub fn otlp_request(&self) -> serde_json::Value {
+        // Trace identifiers must be unique across the runs, so they are derived from the time.
+        let run_id = self.started.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let run_id = run_id.as_nanos() as u64;
+        let nanos = |time: SystemTime| {
+            time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+        };
+        let attribute =
+            |key: &str, value: &str| json!({ "key": key, "value": { "stringValue": value } });
+        // Span identifiers must not be zero, hence the offset.
+        let span_id = |id: u64| format!("{:016x}", id + 1);
+        let spans = self.spans().into_iter().map(|span| {
+            let fields = span.fields.iter().map(|(key, value)| attribute(key, value));
+            let attributes = fields.chain([attribute("target", span.target)]).collect_vec();
+            json!({
+                "traceId": format!("{run_id:016x}{:016x}", span.root),
+                "spanId": span_id(span.id),
+                "parentSpanId": span.parent.map(span_id).unwrap_or_default(),
+                "name": span.name,
+                "kind": 1,
+                "startTimeUnixNano": nanos(span.start),
+                "endTimeUnixNano": nanos(span.start + span.duration),
+                "attributes": attributes,
+            })
+        });
+        let resource = json!({ "attributes": [attribute("service.name", "enso-build")] });
+        let scope = json!({ "name": "enso-build" });
+        json!({
+            "resourceSpans": [{
+                "resource": resource,
+                "scopeSpans": [{ "scope": scope, "spans": spans.collect_vec() }]
+            }]
+        })
+    }
+
+    /// Total and longest durations of the spans, grouped by the name, from the longest total.
+    pub fn summary(&self) -> Vec<SpanSummary> {
+        let mut by_name = BTreeMap::<(&str, &str), SpanSummary>::new();
+        for span in self.spans() {
+            let summary = by_name.entry((span.target, span.name)).or_insert_with(|| SpanSummary {
+                name:    format!("{}::{}", span.target, span.name),
+                count:   0,
+                total:   default(),
+                longest: default(),
+            });
+            summary.count += 1;
+            summary.total += span.duration;
+            summary.longest = summary.longest.max(span.duration);
+        }
+        by_name.into_values().sorted_by_key(|summary| std::cmp::Reverse(summary.total)).collect()
+    }
+}
+
+/// Durations of the spans with the same name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanSummary {
+    /// The span's target and name.
+    pub name:    String,
+    pub count:   usize,
+    pub total:   Duration,
+    pub longest: Duration,
+}
+
+/// Format the summaries as a table.
+pub fn summary_table(summaries: &[SpanSummary]) -> String {
+    let width = summaries.iter().map(|summary| summary.name.len()).max().unwrap_or_default();
+    let header = format!("{:width$}  {:>6}  {:>10}  {:>10}", "Span", "Count", "Total", "Longest");
+    let rows = summaries.iter().map(|summary| {
+        let SpanSummary { name, count, total, longest } = summary;
+        let total = format!("{:.3}s", total.as_secs_f64());
+        let longest = format!("{:.3}s", longest.as_secs_f64());
+        format!("{name:width$}  {count:>6}  {total:>10}  {longest:>10}")
+    });
+    once(header).chain(rows).join("\n")
+}
+
+
+
+// ===================
+// === Entry Point ===
+// ===================
+
+/// Create the recorder, if the recording was requested through the environment.
+///
+/// The recorder is stored globally, so it can be [finished](finish) at exit.
+pub fn layer() -> Option<Recorder> {
+    let is_requested = ENSO_BUILD_TRACE.is_set() || ENSO_BUILD_OTLP_ENDPOINT.is_set();
+    is_requested.then(|| RECORDER.get_or_init(default).clone())
+}
+
+/// Write out the recorded spans, if the recording was requested.
+pub async fn finish() -> Result {
+    let Some(recorder) = RECORDER.get() else { return Ok(()) };
+    let summaries = recorder.summary();
+    let summaries = &summaries[..summaries.len().min(SUMMARY_LENGTH)];
+    info!("The longest spans:\n{}", summary_table(summaries));
+    if let Ok(path) = ENSO_BUILD_TRACE.get() {
+        path.write_as_json(&recorder.chrome_trace())?;
+        info!("Chrome trace written to {}.", path.display());
+    }
+    if let Ok(endpoint) = ENSO_BUILD_OTLP_ENDPOINT.get() {
+        let url = endpoint.join("v1/traces")?;
+        reqwest::Client::new()
+            .post(url.clone())
+            .json(&recorder.otlp_request())
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("Failed to send the spans to {url}."))?;
+        info!("Spans sent to {url}.");
+    }
+    Ok(())
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::Registry;
+
+    fn record(f: impl FnOnce()) -> Recorder {
+        let recorder = Recorder::default();
+        let subscriber = Registry::default().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, f);
+        recorder
+    }
+
+    #[test]
+    fn recording_spans() {
+        let recorder = record(|| {
+            let build = info_span!("build", target = "gui").entered();
+            for _ in 0..2 {
+                let step = debug_span!("step", done = tracing::field::Empty).entered();
+                step.record("done", true);
+            }
+            drop(build);
+        });
+        let spans = recorder.spans();
+        assert_eq!(spans.iter().map(|span| span.name).collect_vec(), ["step", "step", "build"]);
+        let build = &spans[2];
+        assert_eq!(build.parent, None);
+        assert_eq!(build.root, build.id);
+        assert_eq!(build.fields, [("target".into(), "gui".into())].into());
+        for step in &spans[..2] {
+            assert_eq!(step.parent, Some(build.id));
+            assert_eq!(step.root, build.id);
+            assert_eq!(step.fields, [("done".into(), "true".into())].into());
+            assert!(step.duration <= build.duration);
+        }
+        assert_ne!(spans[0].id, spans[1].id);
+
+        let summary = recorder.summary();
+        let names = summary.iter().map(|summary| (summary.name.as_str(), summary.count));
+        let target = module_path!();
+        assert_eq!(names.collect_vec(), [
+            (format!("{target}::build").as_str(), 1),
+            (format!("{target}::step").as_str(), 2)
+        ]);
+        let table = summary_table(&summary);
+        assert_eq!(table.lines().count(), 3);
+        assert!(table.starts_with("Span"));
+    }
+
+    #[test]
+    fn exporting() {
+        let recorder = record(|| {
+            let _outer = info_span!("outer").entered();
+            let _inner = info_span!("inner").entered();
+        });
+        let trace = recorder.chrome_trace();
+        let events = trace["traceEvents"].as_array().unwrap();
+        let phases = events.iter().map(|event| event["ph"].as_str().unwrap()).collect_vec();
+        assert_eq!(phases, ["b", "e", "b", "e"]);
+        assert!(events.iter().all(|event| event["id"] == events[0]["id"]));
+
+        let request = recorder.otlp_request();
+        let spans = request["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap();
+        let (inner, outer) = (&spans[0], &spans[1]);
+        assert_eq!(inner["name"], "inner");
+        assert_eq!(inner["parentSpanId"], outer["spanId"]);
+        assert_eq!(inner["traceId"], outer["traceId"]);
+        assert_eq!(outer["parentSpanId"], "");
+        assert_eq!(outer["spanId"].as_str().unwrap().len(), 16);
+        assert_eq!(outer["traceId"].as_str().unwrap().len(), 32);
+    }
+}
@@ -63,7 +63,15 @@ pub trait Program: Sized + 'static {
     ///
     /// The lookup locations are program-defined, they typically include Path environment variable
     /// and program-specific default locations.
+    ///
+    /// When a [command executor](command::executor) is active, it may provide the location
+    /// instead, so the programs that are not really run do not need to be installed.
     fn lookup(&self) -> Result<Location<Self>> {
+        if let Some(path) = command::executor::current()
+            .and_then(|executor| executor.lookup(self.executable_name()))
+        {
+            return Ok(Location::new(path));
+        }
         Resolver::<Self>::new(self.executable_names(), self.default_locations())?
             .lookup()
             .map(Location::new)
@@ -9,7 +9,6 @@ use std::process::Stdio;
 use tokio::io::AsyncBufReadExt;
 use tokio::io::AsyncRead;
 use tokio::io::BufReader;
-use tokio::process::Child;
 use tokio::task::JoinHandle;
 use tracing::field;
 
@@ -18,8 +17,12 @@ use tracing::field;
 // === Export ===
 // ==============
 
+pub mod child;
+pub mod executor;
 pub mod provider;
 
+pub use child::Child;
+
 
 
 #[macro_export]
@@ -345,8 +348,18 @@ impl Command {
             command = field::Empty,
         )
         .entered();
-        let child = self.spawn_intercepting();
         let status_checker = self.status_checker.clone();
+        if let Some(executor) = executor::current() {
+            let output = executor.execute(self);
+            return async move {
+                let status = output.await?.status;
+                tracing::Span::current().record("status", status.code());
+                status_checker(status).context(format!("Command failed: {pretty}"))
+            }
+            .instrument(span.exit())
+            .boxed();
+        }
+        let child = self.spawn_intercepting();
         async move {
             let mut child = child?;
             let status = child
@@ -371,14 +384,21 @@ impl Command {
         )
         .entered();
 
-        self.stdout(Stdio::piped());
-        self.stderr(Stdio::piped());
-        let child = self.spawn();
+        let output = match executor::current() {
+            Some(executor) => executor.execute(self),
+            None => {
+                self.stdout(Stdio::piped());
+                self.stderr(Stdio::piped());
+                let child = self.spawn();
+                async move {
+                    child?.wait_with_output().await.context("Failed while waiting for output.")
+                }
+                .boxed()
+            }
+        };
         let status_checker = self.status_checker.clone();
         async move {
-            let child = child?;
-            let output =
-                child.wait_with_output().await.context("Failed while waiting for output.")?;
+            let output = output.await?;
             tracing::Span::current().record("status", output.status.code());
             status_checker(output.status).with_context(|| {
                 format!(
@@ -405,7 +425,18 @@ impl Command {
         .boxed()
     }
 
+    /// Spawn the process, without waiting for it.
+    ///
+    /// When a [command executor](executor) is active, the process is spawned by it.
     pub fn spawn(&mut self) -> Result<Child> {
+        match executor::current() {
+            Some(executor) => executor.spawn(self),
+            None => self.spawn_process().map(Child::from),
+        }
+    }
+
+    /// Spawn the real process, regardless of the active executor.
+    fn spawn_process(&mut self) -> Result<tokio::process::Child> {
         let pretty = self.describe();
 
         let current_span = tracing::Span::current();
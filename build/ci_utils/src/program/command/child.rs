@@ -0,0 +1,134 @@
+//! Handle to a spawned process.
+
+use crate::prelude::*;
+
+use std::process::ExitStatus;
+use std::process::Output;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+
+
+
+/// Standard input of a [`Child`].
+pub type InputStream = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Standard output or error of a [`Child`].
+pub type OutputStream = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Called with the exit status of the process, once it is known.
+type ExitHook = Box<dyn FnOnce(&ExitStatus) + Send + Sync>;
+
+#[derive(Debug)]
+enum Process {
+    Real(tokio::process::Child),
+    /// A process that does not really run, like one replayed by a
+    /// [command executor](super::executor).
+    Exited(ExitStatus),
+}
+
+/// A spawned process.
+///
+/// It is either a real process, or one replayed by a [command executor](super::executor), which
+/// has its output served from memory and exits as soon as it is waited for.
+#[derive_where(Debug)]
+pub struct Child {
+    /// Standard input, if piped. Writes to a replayed process are discarded.
+    #[derive_where(skip)]
+    pub stdin:  Option<InputStream>,
+    /// Standard output, if piped. A replayed process always has it.
+    #[derive_where(skip)]
+    pub stdout: Option<OutputStream>,
+    /// Standard error, if piped. A replayed process always has it.
+    #[derive_where(skip)]
+    pub stderr: Option<OutputStream>,
+    process:    Process,
+    #[derive_where(skip)]
+    on_exit:    Option<ExitHook>,
+}
+
+impl From<tokio::process::Child> for Child {
+    fn from(mut child: tokio::process::Child) -> Self {
+        Self {
+            stdin:   child.stdin.take().map(|stream| Box::new(stream) as InputStream),
+            stdout:  child.stdout.take().map(|stream| Box::new(stream) as OutputStream),
+            stderr:  child.stderr.take().map(|stream| Box::new(stream) as OutputStream),
+            process: Process::Real(child),
+            on_exit: None,
+        }
+    }
+}
+
+impl Child {
+    /// A process that is not really run: it has the given output and exits with the given status.
+    pub fn exited(status: ExitStatus, stdout: Vec<u8>, stderr: Vec<u8>) -> Self {
+        Self {
+            stdin:   Some(Box::new(tokio::io::sink())),
+            stdout:  Some(Box::new(std::io::Cursor::new(stdout))),
+            stderr:  Some(Box::new(std::io::Cursor::new(stderr))),
+            process: Process::Exited(status),
+            on_exit: None,
+        }
+    }
+
+    /// Call the function with the exit status, once the process is [waited for](Child::wait).
+    pub fn on_exit(mut self, f: impl FnOnce(&ExitStatus) + Send + Sync + 'static) -> Self {
+        self.on_exit = Some(Box::new(f));
+        self
+    }
+
+    /// The OS identifier of the process. It is not available once the process has exited, or if
+    /// it is not a real one.
+    pub fn id(&self) -> Option<u32> {
+        match &self.process {
+            Process::Real(child) => child.id(),
+            Process::Exited(_) => None,
+        }
+    }
+
+    /// Wait for the process to exit.
+    ///
+    /// The standard input is closed first, so the process does not wait for more of it.
+    pub async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        drop(self.stdin.take());
+        let status = match &mut self.process {
+            Process::Real(child) => child.wait().await?,
+            Process::Exited(status) => *status,
+        };
+        if let Some(on_exit) = self.on_exit.take() {
+            on_exit(&status);
+        }
+        Ok(status)
+    }
+
+    /// Wait for the process to exit, collecting the whole standard output and error, if piped.
+    pub async fn wait_with_output(mut self) -> std::io::Result<Output> {
+        async fn read_to_end(stream: Option<OutputStream>) -> std::io::Result<Vec<u8>> {
+            let mut ret = Vec::new();
+            if let Some(mut stream) = stream {
+                stream.read_to_end(&mut ret).await?;
+            }
+            Ok(ret)
+        }
+        let stdout = read_to_end(self.stdout.take());
+        let stderr = read_to_end(self.stderr.take());
+        let (status, stdout, stderr) = try_join!(self.wait(), stdout, stderr)?;
+        Ok(Output { status, stdout, stderr })
+    }
+
+    /// Request the process to be killed, without waiting for it.
+    pub fn start_kill(&mut self) -> std::io::Result<()> {
+        match &mut self.process {
+            Process::Real(child) => child.start_kill(),
+            Process::Exited(_) => Ok(()),
+        }
+    }
+
+    /// Kill the process and wait for it to exit.
+    pub async fn kill(&mut self) -> std::io::Result<()> {
+        match &mut self.process {
+            Process::Real(child) => child.kill().await,
+            Process::Exited(_) => Ok(()),
+        }
+    }
+}
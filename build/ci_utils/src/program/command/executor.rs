@@ -0,0 +1,644 @@
+//! Pluggable execution of [`Command`]s, allowing the build logic to be tested without the real
+//! programs.
+//!
+//! By default, commands spawn the real processes. Within a [`scope`], they are passed to the
+//! given [`Executor`] instead:
+//! * [`Recorder`] runs the real processes and records each invocation with its outcome, so they can
+//!   be stored in a [`Fixture`] file;
+//! * [`Replayer`] does not run anything. It checks that the commands are invoked in the order of
+//!   the fixture and serves the recorded outcomes.
+//!
+//! Processes that are [spawned](Command::spawn) rather than run to completion are recorded and
+//! replayed as well. A replayed process serves the recorded output and exits with the recorded
+//! status as soon as it is waited for.
+//!
+//! The executor is bound to the current task. Tasks spawned with [`crate::global::spawn`] inherit
+//! it, tasks spawned directly with [`tokio::spawn`] do not.
+
+use crate::prelude::*;
+
+use crate::program::command::child;
+use crate::program::command::Child;
+use crate::program::command::Command;
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::process::ExitStatus;
+use std::process::Output;
+use std::process::Stdio;
+use std::sync::Mutex;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_util::io::ReaderStream;
+use tokio_util::io::StreamReader;
+
+
+
+tokio::task_local! {
+    static EXECUTOR: Arc<dyn Executor>;
+}
+
+/// The executor of the current task, if any.
+pub fn current() -> Option<Arc<dyn Executor>> {
+    EXECUTOR.try_with(Arc::clone).ok()
+}
+
+/// Run the future with all its commands passed to the given executor.
+pub async fn scope<F: Future>(executor: Arc<dyn Executor>, f: F) -> F::Output {
+    EXECUTOR.scope(executor, f).await
+}
+
+/// Runs commands on behalf of [`Command`].
+pub trait Executor: Debug + Send + Sync {
+    /// Run the command to completion, capturing its output.
+    ///
+    /// The exit status is checked by the caller, so an unsuccessful command should still yield
+    /// its output.
+    fn execute(&self, command: &mut Command) -> BoxFuture<'static, Result<Output>>;
+
+    /// Spawn the process, without waiting for it.
+    fn spawn(&self, command: &mut Command) -> Result<Child>;
+
+    /// Path to be used for the program, instead of looking it up in the system.
+    ///
+    /// This allows running programs that are not installed, when they are not really run.
+    fn lookup(&self, _executable_name: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+
+
+// ==================
+// === Invocation ===
+// ==================
+
+/// Matches any text in the invocations of a fixture.
+///
+/// It is meant for values that differ between runs, like free port numbers. Recorded invocations
+/// never contain it, it needs to be put in the fixture by hand.
+pub const WILDCARD: &str = "$ANY";
+
+/// Whether the text matches the pattern, which may contain [`WILDCARD`]s.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split(WILDCARD);
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts = parts.collect_vec();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        let Some(index) = rest.find(part) else {
+            return false;
+        };
+        rest = &rest[index + part.len()..];
+    }
+    rest.ends_with(last)
+}
+
+/// A program invocation, as described by the [`Command`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Invocation {
+    /// The program name, without the directory and the extension, e.g. `cargo`.
+    ///
+    /// The location of the program depends on the machine, so it is not a part of the invocation.
+    pub program:     String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args:        Vec<String>,
+    /// The environment variables set for the command. `None` means that the variable is removed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env:         BTreeMap<String, Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_dir: Option<PathBuf>,
+}
+
+impl Invocation {
+    pub fn new(command: &Command) -> Self {
+        let command = command.inner.as_std();
+        let text = |text: &OsStr| text.to_string_lossy().into_owned();
+        Self {
+            program:     Path::new(command.get_program()).file_stem().map(text).unwrap_or_default(),
+            args:        command.get_args().map(text).collect(),
+            env:         command
+                .get_envs()
+                .map(|(name, value)| (text(name), value.map(text)))
+                .collect(),
+            current_dir: command.get_current_dir().map(Into::into),
+        }
+    }
+
+    /// Replace the occurrences of the placeholders' values with the placeholders' names.
+    ///
+    /// This allows the invocation to be matched regardless of machine-specific paths.
+    pub fn redact(&self, placeholders: &Placeholders) -> Self {
+        let redact = |text: &str| placeholders.redact(text);
+        Self {
+            program:     redact(&self.program),
+            args:        self.args.iter().map(|arg| redact(arg)).collect(),
+            env:         self
+                .env
+                .iter()
+                .map(|(name, value)| (name.clone(), value.as_deref().map(redact)))
+                .collect(),
+            current_dir: self.current_dir.as_ref().map(|dir| redact(&dir.to_string_lossy()).into()),
+        }
+    }
+}
+
+impl Invocation {
+    /// Whether the actual invocation is the same as this one, which may contain [`WILDCARD`]s.
+    pub fn matches(&self, actual: &Invocation) -> bool {
+        let matches_optional = |pattern: Option<&str>, text: Option<&str>| match (pattern, text) {
+            (Some(pattern), Some(text)) => matches_pattern(pattern, text),
+            (pattern, text) => pattern == text,
+        };
+        let args_match = self.args.len() == actual.args.len()
+            && self
+                .args
+                .iter()
+                .zip(&actual.args)
+                .all(|(pattern, arg)| matches_pattern(pattern, arg));
+        let env_match = self.env.len() == actual.env.len()
+            && self.env.iter().zip(&actual.env).all(|((name, pattern), (actual_name, value))| {
+                name == actual_name && matches_optional(pattern.as_deref(), value.as_deref())
+            });
+        let current_dir =
+            |dir: &Option<PathBuf>| dir.as_ref().map(|dir| dir.to_string_lossy().into_owned());
+        matches_pattern(&self.program, &actual.program)
+            && args_match
+            && env_match
+            && matches_optional(
+                current_dir(&self.current_dir).as_deref(),
+                current_dir(&actual.current_dir).as_deref(),
+            )
+    }
+}
+
+impl Display for Invocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {arg:?}")?;
+        }
+        for (name, value) in &self.env {
+            match value {
+                Some(value) => write!(f, " [{name}={value:?}]")?,
+                None => write!(f, " [unset {name}]")?,
+            }
+        }
+        if let Some(dir) = &self.current_dir {
+            write!(f, " [in {}]", dir.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Named values that are replaced with their names in the recorded invocations, like the temporary
+/// directory a test runs in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Placeholders {
+    pub values: BTreeMap<String, String>,
+}
+
+impl Placeholders {
+    /// Add a placeholder, e.g. `$ROOT` for the repository root path.
+    pub fn with(mut self, name: impl Into<String>, value: impl AsRef<OsStr>) -> Self {
+        self.values.insert(name.into(), value.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    /// Replace the values with the placeholders' names.
+    ///
+    /// The longest values are replaced first, so a placeholder for a nested path takes precedence
+    /// over the one for its parent.
+    pub fn redact(&self, text: &str) -> String {
+        self.values
+            .iter()
+            .sorted_by_key(|(_, value)| std::cmp::Reverse(value.len()))
+            .fold(text.to_owned(), |text, (name, value)| text.replace(value, name))
+    }
+
+    /// Replace the placeholders' names with their values.
+    pub fn expand(&self, text: &str) -> String {
+        self.values
+            .iter()
+            .sorted_by_key(|(name, _)| std::cmp::Reverse(name.len()))
+            .fold(text.to_owned(), |text, (name, value)| text.replace(name, value))
+    }
+}
+
+
+
+// ===============
+// === Fixture ===
+// ===============
+
+/// What the program did when invoked.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outcome {
+    /// The exit code. Termination by a signal is recorded as `-1`.
+    pub code:   i32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stdout: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+}
+
+impl Outcome {
+    /// A successful outcome with the given standard output.
+    pub fn success(stdout: impl Into<String>) -> Self {
+        Self { code: 0, stdout: stdout.into(), stderr: default() }
+    }
+
+    pub fn new(output: &Output) -> Self {
+        Self {
+            code:   output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+
+    pub fn to_output(&self) -> Output {
+        Output {
+            status: exit_status(self.code),
+            stdout: self.stdout.clone().into_bytes(),
+            stderr: self.stderr.clone().into_bytes(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    // The raw value is the wait status, which stores the exit code in the second byte.
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+/// A recorded invocation with its outcome.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub invocation: Invocation,
+    pub outcome:    Outcome,
+}
+
+/// A sequence of recorded invocations, stored as a YAML file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub records: Vec<Record>,
+}
+
+impl Fixture {
+    #[context("Failed to read the command fixture from {}.", path.as_ref().display())]
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_yaml::from_str(&crate::fs::read_to_string(&path)?)?)
+    }
+
+    #[context("Failed to write the command fixture to {}.", path.as_ref().display())]
+    pub fn write(&self, path: impl AsRef<Path>) -> Result {
+        crate::fs::write(&path, serde_yaml::to_string(self)?)
+    }
+}
+
+
+
+// ================
+// === Recorder ===
+// ================
+
+/// Runs the commands and records their invocations and outcomes.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    pub placeholders: Placeholders,
+    pub fixture:      Arc<Mutex<Fixture>>,
+}
+
+impl Recorder {
+    pub fn new(placeholders: Placeholders) -> Self {
+        Self { placeholders, fixture: default() }
+    }
+
+    /// The invocations recorded so far.
+    ///
+    /// The output of a spawned process is recorded once it is read to the end, and its exit code
+    /// once the process is waited for. Until then, the process is recorded as terminated by a
+    /// signal.
+    pub fn fixture(&self) -> Fixture {
+        self.fixture.lock().unwrap().clone()
+    }
+
+    /// Pass the output stream through, recording it in the given record once it ends.
+    fn record_stream(
+        &self,
+        stream: child::OutputStream,
+        index: usize,
+        field: fn(&mut Outcome) -> &mut String,
+    ) -> child::OutputStream {
+        // The channel is unbounded, so the process is not blocked if nobody reads its output.
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let placeholders = self.placeholders.clone();
+        let fixture = self.fixture.clone();
+        tokio::spawn(async move {
+            let mut chunks = ReaderStream::new(stream);
+            let mut recorded = Vec::new();
+            while let Some(chunk) = chunks.next().await {
+                let failed = chunk.is_err();
+                if let Ok(chunk) = &chunk {
+                    recorded.extend_from_slice(chunk);
+                }
+                // The output is recorded even if the reader is gone.
+                let _ = sender.send(chunk);
+                if failed {
+                    break;
+                }
+            }
+            let text = placeholders.redact(&String::from_utf8_lossy(&recorded));
+            *field(&mut fixture.lock().unwrap().records[index].outcome) = text;
+            // The reader sees the end of the stream only once it is recorded.
+            drop(sender);
+        });
+        Box::new(StreamReader::new(UnboundedReceiverStream::new(receiver)))
+    }
+}
+
+impl Executor for Recorder {
+    fn execute(&self, command: &mut Command) -> BoxFuture<'static, Result<Output>> {
+        let invocation = Invocation::new(command).redact(&self.placeholders);
+        command.stdout(Stdio::piped());
+        command.stderr(Stdio::piped());
+        let child = command.spawn_process();
+        let placeholders = self.placeholders.clone();
+        let fixture = self.fixture.clone();
+        async move {
+            let output = child?.wait_with_output().await?;
+            let outcome = Outcome::new(&output);
+            let outcome = Outcome {
+                stdout: placeholders.redact(&outcome.stdout),
+                stderr: placeholders.redact(&outcome.stderr),
+                ..outcome
+            };
+            fixture.lock().unwrap().records.push(Record { invocation, outcome });
+            Ok(output)
+        }
+        .boxed()
+    }
+
+    fn spawn(&self, command: &mut Command) -> Result<Child> {
+        let invocation = Invocation::new(command).redact(&self.placeholders);
+        command.stdout(Stdio::piped());
+        command.stderr(Stdio::piped());
+        let mut child = Child::from(command.spawn_process()?);
+        let index = {
+            let mut fixture = self.fixture.lock().unwrap();
+            let outcome = Outcome { code: -1, ..default() };
+            fixture.records.push(Record { invocation, outcome });
+            fixture.records.len() - 1
+        };
+        child.stdout = child
+            .stdout
+            .take()
+            .map(|stream| self.record_stream(stream, index, |outcome| &mut outcome.stdout));
+        child.stderr = child
+            .stderr
+            .take()
+            .map(|stream| self.record_stream(stream, index, |outcome| &mut outcome.stderr));
+        let fixture = self.fixture.clone();
+        Ok(child.on_exit(move |status| {
+            fixture.lock().unwrap().records[index].outcome.code = status.code().unwrap_or(-1);
+        }))
+    }
+}
+
+
+
+// ================
+// === Replayer ===
+// ================
+
+/// Serves the recorded outcomes, requiring the commands to be invoked as recorded.
+#[derive(Debug, Default)]
+pub struct Replayer {
+    pub placeholders: Placeholders,
+    pub expected:     Mutex<VecDeque<Record>>,
+}
+
+impl Replayer {
+    pub fn new(fixture: Fixture, placeholders: Placeholders) -> Self {
+        Self { placeholders, expected: Mutex::new(fixture.records.into()) }
+    }
+
+    /// Take the outcome recorded for the command, which must be the next expected one.
+    fn next_outcome(&self, command: &Command) -> Result<Outcome> {
+        let invocation = Invocation::new(command).redact(&self.placeholders);
+        let next = self.expected.lock().unwrap().pop_front();
+        let record =
+            next.with_context(|| format!("Unexpected command, none was expected: {invocation}"))?;
+        ensure!(
+            record.invocation.matches(&invocation),
+            "Unexpected command: {invocation}\nExpected: {}",
+            record.invocation
+        );
+        Ok(Outcome {
+            stdout: self.placeholders.expand(&record.outcome.stdout),
+            stderr: self.placeholders.expand(&record.outcome.stderr),
+            ..record.outcome
+        })
+    }
+
+    /// Check that all the recorded invocations have been made.
+    pub fn finish(&self) -> Result {
+        let expected = self.expected.lock().unwrap();
+        ensure!(
+            expected.is_empty(),
+            "{} expected command(s) were not invoked, starting with: {}",
+            expected.len(),
+            expected.iter().map(|record| &record.invocation).join("\n")
+        );
+        Ok(())
+    }
+}
+
+impl Executor for Replayer {
+    fn execute(&self, command: &mut Command) -> BoxFuture<'static, Result<Output>> {
+        let output = self.next_outcome(command).map(|outcome| outcome.to_output());
+        ready(output).boxed()
+    }
+
+    fn spawn(&self, command: &mut Command) -> Result<Child> {
+        let Output { status, stdout, stderr } = self.next_outcome(command)?.to_output();
+        Ok(Child::exited(status, stdout, stderr))
+    }
+
+    fn lookup(&self, executable_name: &str) -> Option<PathBuf> {
+        Some(executable_name.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::Git;
+    use tokio::io::AsyncReadExt;
+
+    fn fixture() -> Fixture {
+        let invocation = |args: &[&str]| Invocation {
+            program:     "git".into(),
+            args:        args.iter().map(|arg| arg.to_string()).collect(),
+            env:         default(),
+            current_dir: Some("$ROOT".into()),
+        };
+        Fixture {
+            records: vec![
+                Record {
+                    invocation: invocation(&["rev-parse", "HEAD"]),
+                    outcome:    Outcome::success("0123abcd\n"),
+                },
+                Record {
+                    invocation: invocation(&["status", "--porcelain"]),
+                    outcome:    Outcome {
+                        code:   128,
+                        stdout: default(),
+                        stderr: "fatal\n".into(),
+                    },
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn replaying() -> Result {
+        let root = tempfile::tempdir()?;
+        let placeholders = Placeholders::default().with("$ROOT", root.path());
+        let replayer = Arc::new(Replayer::new(fixture(), placeholders));
+        scope(replayer.clone(), async {
+            let head = Git
+                .cmd()?
+                .with_current_dir(root.path())
+                .args(["rev-parse", "HEAD"])
+                .run_stdout()
+                .await?;
+            assert_eq!(head, "0123abcd\n");
+            // The recorded failure is reported as the real one would be.
+            let status = Git
+                .cmd()?
+                .with_current_dir(root.path())
+                .args(["status", "--porcelain"])
+                .run_ok()
+                .await;
+            assert!(status.is_err());
+            // Any command beyond the fixture is rejected.
+            assert!(Git.cmd()?.arg("fetch").run_ok().await.is_err());
+            Result::Ok(())
+        })
+        .await?;
+        replayer.finish()
+    }
+
+    #[tokio::test]
+    async fn replaying_rejects_unexpected_commands() -> Result {
+        let replayer = Arc::new(Replayer::new(fixture(), default()));
+        scope(replayer.clone(), async {
+            assert!(Git.cmd()?.args(["rev-parse", "HEAD"]).run_ok().await.is_err());
+            Result::Ok(())
+        })
+        .await?;
+        // The mismatched invocation consumed the first record.
+        assert!(replayer.finish().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replaying_spawned_processes() -> Result {
+        let root = tempfile::tempdir()?;
+        let placeholders = Placeholders::default().with("$ROOT", root.path());
+        let replayer = Arc::new(Replayer::new(fixture(), placeholders));
+        scope(replayer.clone(), async {
+            let git = || Ok::<_, anyhow::Error>(Git.cmd()?.with_current_dir(root.path()));
+            let child = git()?.args(["rev-parse", "HEAD"]).spawn()?;
+            assert_eq!(child.id(), None);
+            let output = child.wait_with_output().await?;
+            assert!(output.status.success());
+            assert_eq!(output.stdout, b"0123abcd\n");
+            let mut child = git()?.args(["status", "--porcelain"]).spawn()?;
+            assert_eq!(child.wait().await?.code(), Some(128));
+            Result::Ok(())
+        })
+        .await?;
+        replayer.finish()
+    }
+
+    #[test]
+    fn wildcards() {
+        let invocation = |args: &[&str]| Invocation {
+            program: "sbt".into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..default()
+        };
+        let pattern = invocation(&["http-test-helper/run localhost $ANY"]);
+        assert!(pattern.matches(&invocation(&["http-test-helper/run localhost 51234"])));
+        assert!(!pattern.matches(&invocation(&["http-test-helper/run 127.0.0.1 51234"])));
+        assert!(!pattern.matches(&invocation(&["http-test-helper/run localhost 1", "test"])));
+        assert!(matches_pattern("$ANY:$ANY", "a:b:c"));
+        assert!(matches_pattern("a$ANYb", "ab"));
+        assert!(!matches_pattern("ab$ANYba", "aba"));
+        assert!(!matches_pattern("ab", "abc"));
+    }
+
+    #[test]
+    fn fixture_round_trip() -> Result {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("fixture.yaml");
+        fixture().write(&path)?;
+        assert_eq!(Fixture::read(&path)?, fixture());
+        Ok(())
+    }
+
+    #[test]
+    fn placeholders() {
+        let placeholders = Placeholders::default()
+            .with("$ROOT", "/home/user/enso")
+            .with("$DIST", "/home/user/enso/dist");
+        let text = "/home/user/enso/dist/ide and /home/user/enso/app";
+        let redacted = placeholders.redact(text);
+        assert_eq!(redacted, "$DIST/ide and $ROOT/app");
+        assert_eq!(placeholders.expand(&redacted), text);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn recording() -> Result {
+        let recorder = Arc::new(Recorder::default());
+        scope(recorder.clone(), async { Git.cmd()?.arg("--version").run_stdout().await }).await?;
+        let fixture = recorder.fixture();
+        assert_eq!(fixture.records.len(), 1);
+        assert_eq!(fixture.records[0].invocation.program, "git");
+        assert!(fixture.records[0].outcome.stdout.starts_with("git version"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn recording_spawned_processes() -> Result {
+        let recorder = Arc::new(Recorder::default());
+        let output = scope(recorder.clone(), async {
+            let mut child = Git.cmd()?.arg("--version").spawn()?;
+            let mut output = String::new();
+            child.stdout.take().unwrap().read_to_string(&mut output).await?;
+            child.wait().await?;
+            Result::Ok(output)
+        })
+        .await?;
+        assert!(output.starts_with("git version"));
+        let fixture = recorder.fixture();
+        assert_eq!(fixture.records.len(), 1);
+        assert_eq!(fixture.records[0].outcome, Outcome::success(output));
+        Ok(())
+    }
+}
@@ -15,3 +15,33 @@ pub async fn format(path: impl AsRef<Path>) -> Result {
 pub fn command() -> Result<Command> {
     Cargo.cmd().map(|c| c.with_arg("fmt"))
 }
+
+/// Program formatting Rust source files. Unlike `cargo fmt`, it processes only the given files.
+#[derive(Clone, Copy, Debug)]
+pub struct Rustfmt;
+
+impl Program for Rustfmt {
+    type Command = Command;
+    type Version = Version;
+
+    fn executable_name(&self) -> &str {
+        "rustfmt"
+    }
+}
+
+/// Format the given files with `rustfmt`, using the configuration found in the `root` directory.
+///
+/// Only the given files are formatted, not the out-of-line modules they declare. Otherwise, a file
+/// could be rewritten while it is formatted by another invocation, or behind the caller's back.
+#[context("Failed to run rustfmt on {} files in '{}'", files.len(), root.as_ref().display())]
+pub async fn format_files(root: impl AsRef<Path>, files: &[PathBuf]) -> Result {
+    Rustfmt
+        .cmd()?
+        .with_stdin(Stdio::null())
+        .with_current_dir(&root)
+        .args(["--edition", "2021"])
+        .args(["--config", "skip_children=true"])
+        .args(files)
+        .run_ok()
+        .await
+}
@@ -157,6 +157,23 @@ impl Context {
             .collect_vec())
     }
 
+    /// List files under the working directory that are neither tracked nor ignored.
+    ///
+    /// Paths are absolute, like the ones returned by [`Context::diff_against`].
+    pub async fn untracked_files(&self) -> Result<Vec<PathBuf>> {
+        let root = self.repository_root.as_path();
+        Ok(self
+            .cmd()?
+            .arg(Command::LsFiles)
+            .args(["--others", "--exclude-standard", "--full-name"])
+            .output_ok()
+            .await?
+            .into_stdout_string()?
+            .lines()
+            .map(|line| root.join(line.trim()).normalize())
+            .collect_vec())
+    }
+
     /// Get the repository root directory.
     pub async fn repository_root(&self) -> Result<PathBuf> {
         let output = self
@@ -193,6 +210,13 @@ impl Context {
         output.lines().map(RemoteLsEntry::from_str).try_collect()
     }
 
+    /// List the tags in the local repository.
+    pub async fn list_tags(&self) -> Result<Vec<String>> {
+        let output =
+            self.cmd()?.arg(Command::Tag).arg("--list").output_ok().await?.into_stdout_string()?;
+        Ok(output.lines().map(|line| line.trim().to_owned()).filter(|t| !t.is_empty()).collect())
+    }
+
     /// Get the commit history of the current branch.
     ///
     /// Fails if there are no commits in the repository.
@@ -219,6 +243,27 @@ impl Context {
     pub async fn log_fields(
         &self,
         fields: &[pretty_format::Placeholder],
+    ) -> Result<Vec<Vec<String>>> {
+        self.log_fields_of(None, fields).await
+    }
+
+    /// Get the commits that are reachable from `to` but not from `from`, with the given fields.
+    ///
+    /// The commits are listed from the newest.
+    #[context("Failed to get the commit history between {from} and {to}.")]
+    pub async fn log_range_fields(
+        &self,
+        from: &str,
+        to: &str,
+        fields: &[pretty_format::Placeholder],
+    ) -> Result<Vec<Vec<String>>> {
+        self.log_fields_of(Some(format!("{from}..{to}")), fields).await
+    }
+
+    async fn log_fields_of(
+        &self,
+        revision_range: Option<String>,
+        fields: &[pretty_format::Placeholder],
     ) -> Result<Vec<Vec<String>>> {
         let field_count = fields.len();
         let format = fields.iter().map(ToString::to_string).join(RECORD_SEPARATOR);
@@ -226,6 +271,7 @@ impl Context {
             .cmd()?
             .arg(Command::Log)
             .args(["--decorate=full", &format!("--pretty=format:{format}")])
+            .args(revision_range)
             .output_ok()
             .await?
             .into_stdout_string()?;
@@ -279,10 +325,14 @@ pub enum Command {
     Fetch,
     /// Show commit logs.
     Log,
+    /// Show information about files in the index and the working tree.
+    LsFiles,
     /// List references in a remote repository.
     LsRemote,
     /// Pick out and massage parameters.
     RevParse,
+    /// Create, list, delete or verify a tag object.
+    Tag,
 }
 
 impl AsRef<OsStr> for Command {
@@ -294,8 +344,10 @@ impl AsRef<OsStr> for Command {
             Command::Init => "init",
             Command::Fetch => "fetch",
             Command::Log => "log",
+            Command::LsFiles => "ls-files",
             Command::LsRemote => "ls-remote",
             Command::RevParse => "rev-parse",
+            Command::Tag => "tag",
         })
     }
 }
@@ -33,6 +33,7 @@ pub enum Compression {
     Gzip,
     Lzma,
     Xz,
+    Zstd,
 }
 
 impl Compression {
@@ -46,6 +47,8 @@ impl Compression {
             Ok(Compression::Lzma)
         } else if extension == "xz" {
             Ok(Compression::Xz)
+        } else if extension == "zst" {
+            Ok(Compression::Zstd)
         } else {
             bail!("The extension `{}` does not denote a supported compression algorithm for TAR archives.", extension)
         }
@@ -60,6 +63,7 @@ impl Display for Compression {
             Gzip => "gzip",
             Lzma => "lzma",
             Xz => "xz",
+            Zstd => "zstd",
         })
     }
 }
@@ -71,6 +75,7 @@ impl AsRef<str> for Compression {
             Compression::Gzip => "-z",
             Compression::Lzma => "--lzma",
             Compression::Xz => "-J",
+            Compression::Zstd => "--zstd",
         }
     }
 }
@@ -282,6 +287,7 @@ pub mod tests {
         expect_ok("gz", Compression::Gzip);
         expect_ok("lzma", Compression::Lzma);
         expect_ok("xz", Compression::Xz);
+        expect_ok("zst", Compression::Zstd);
     }
 
     #[tokio::test]
@@ -0,0 +1,666 @@
+//! Build steps that are skipped when nothing they depend on has changed since their last run.
+//!
+//! Each [`Step`] declares its [inputs](Input) and outputs. Before a step is run, the current state
+//! of its inputs is condensed into a [`Fingerprint`] and compared against the one recorded after
+//! the previous successful run. If they match and all the outputs are still present, the step is
+//! skipped. Otherwise, the step is run and the [`Reason`] explains why it was necessary.
+
+use crate::prelude::*;
+
+use crate::env::accessor::RawVariable;
+
+use glob::MatchOptions;
+use glob::Pattern;
+use sha2::Digest;
+
+
+
+// =============
+// === Input ===
+// =============
+
+/// Options for matching the file patterns: `*` does not cross directories, while `**` does.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive:              true,
+    require_literal_separator:   true,
+    require_literal_leading_dot: false,
+};
+
+/// Something that the result of a step depends on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// Files under the root directory that match any of the `include` glob patterns and none of
+    /// the `exclude` ones. The patterns are relative to the root.
+    Files { root: PathBuf, include: Vec<String>, exclude: Vec<String> },
+    /// The value of an environment variable.
+    Env { name: String },
+    /// The version of a program used by the step.
+    Tool { name: String, version: String },
+    /// Any other value, like the version of the product being built.
+    Value { name: String, value: String },
+}
+
+impl Input {
+    /// Files under the root directory that match any of the glob patterns.
+    pub fn files(root: impl Into<PathBuf>, include: impl IntoIterator<Item: Into<String>>) -> Self {
+        let include = include.into_iter().map_into().collect();
+        Self::Files { root: root.into(), include, exclude: default() }
+    }
+
+    /// Skip the files and directories matching the glob patterns. Has no effect on inputs other
+    /// than [`Input::Files`].
+    pub fn excluding(mut self, patterns: impl IntoIterator<Item: Into<String>>) -> Self {
+        if let Self::Files { exclude, .. } = &mut self {
+            exclude.extend(patterns.into_iter().map_into());
+        }
+        self
+    }
+
+    /// The value of the environment variable.
+    pub fn env(variable: &impl RawVariable) -> Self {
+        Self::Env { name: variable.name().into() }
+    }
+
+    /// The version of the program, as reported by it.
+    pub async fn tool(program: &impl Program) -> Result<Self> {
+        let name = program.executable_name().to_string();
+        let version = program.version_string().await?;
+        Ok(Self::Tool { name, version })
+    }
+
+    /// A named value.
+    pub fn value(name: impl Into<String>, value: impl ToString) -> Self {
+        Self::Value { name: name.into(), value: value.to_string() }
+    }
+
+    /// Describe the current state of the input as entries of a [`Fingerprint`].
+    fn entries(&self) -> Result<BTreeMap<String, String>> {
+        let mut ret = BTreeMap::new();
+        match self {
+            Self::Files { root, include, exclude } =>
+                for path in matching_files(root, include, exclude)? {
+                    ret.insert(format!("file {}", path.display()), file_digest(&path)?);
+                },
+            Self::Env { name } => {
+                // The value is stored only as a digest, as it might be a secret.
+                let value = std::env::var(name).ok().map(|value| text_digest(&value));
+                ret.insert(format!("environment variable {name}"), value.unwrap_or_default());
+            }
+            Self::Tool { name, version } => {
+                ret.insert(format!("program {name}"), version.clone());
+            }
+            Self::Value { name, value } => {
+                ret.insert(format!("value {name}"), value.clone());
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// The leading components of the pattern that contain no wildcards.
+fn literal_prefix(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            !component.contains(['*', '?', '['])
+        })
+        .collect()
+}
+
+/// All the files under the root that match any of the `include` patterns and are not excluded.
+///
+/// Directories matching an `exclude` pattern are not entered at all, so excluding large
+/// directories like `node_modules` also saves the time needed to traverse them.
+#[context("Failed to list the files matching {include:?} in {}.", root.display())]
+fn matching_files(
+    root: &Path,
+    include: &[String],
+    exclude: &[String],
+) -> Result<BTreeSet<PathBuf>> {
+    let compile = |patterns: &[String]| -> Result<Vec<Pattern>> {
+        patterns.iter().map(|pattern| Ok(Pattern::new(pattern)?)).collect()
+    };
+    let include_patterns = compile(include)?;
+    let exclude_patterns = compile(exclude)?;
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+    let is_excluded = |path: &Path| {
+        let path = relative(path);
+        exclude_patterns.iter().any(|pattern| pattern.matches_path_with(&path, MATCH_OPTIONS))
+    };
+    let mut ret = BTreeSet::new();
+    for pattern in include {
+        let start = root.join(literal_prefix(pattern));
+        if !start.exists() {
+            continue;
+        }
+        let walker = walkdir::WalkDir::new(&start).into_iter();
+        for entry in walker.filter_entry(|entry| !is_excluded(entry.path())) {
+            let entry = entry?;
+            let path = relative(entry.path());
+            let is_included = include_patterns
+                .iter()
+                .any(|pattern| pattern.matches_path_with(&path, MATCH_OPTIONS));
+            if entry.file_type().is_file() && is_included {
+                ret.insert(entry.into_path());
+            }
+        }
+    }
+    Ok(ret)
+}
+
+fn encode_digest(digest: &[u8]) -> String {
+    data_encoding::BASE64URL_NOPAD.encode(digest)
+}
+
+fn text_digest(text: &str) -> String {
+    encode_digest(&sha2::Sha256::digest(text))
+}
+
+fn file_digest(path: &Path) -> Result<String> {
+    Ok(encode_digest(&crate::io::Sha256Digest::of_file(path)?.bytes))
+}
+
+
+
+// ===================
+// === Fingerprint ===
+// ===================
+
+/// The state of a step's inputs: a map from the description of each input part (like a single
+/// file) to its digest or value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub entries: BTreeMap<String, String>,
+}
+
+impl Fingerprint {
+    /// Describe the current state of the inputs.
+    pub fn compute(inputs: &[Input]) -> Result<Self> {
+        let mut entries = BTreeMap::new();
+        for input in inputs {
+            entries.extend(input.entries()?);
+        }
+        Ok(Self { entries })
+    }
+
+    /// A short digest of the whole fingerprint.
+    pub fn digest(&self) -> String {
+        let text = self.entries.iter().map(|(key, value)| format!("{key}\0{value}\0")).join("");
+        text_digest(&text)
+    }
+
+    /// What has changed since the `previous` state.
+    pub fn changes_since(&self, previous: &Fingerprint) -> Vec<Change> {
+        let keys: BTreeSet<&String> = self.entries.keys().chain(previous.entries.keys()).collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                let change = match (previous.entries.get(key), self.entries.get(key)) {
+                    (None, Some(_)) => Change::Added(key.clone()),
+                    (Some(_), None) => Change::Removed(key.clone()),
+                    (Some(old), Some(new)) if old != new => Change::Modified(key.clone()),
+                    _ => return None,
+                };
+                Some(change)
+            })
+            .collect()
+    }
+}
+
+/// A difference between two [fingerprints](Fingerprint).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added(String),
+    Removed(String),
+    Modified(String),
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added(entry) => write!(f, "{entry} was added"),
+            Self::Removed(entry) => write!(f, "{entry} was removed"),
+            Self::Modified(entry) => write!(f, "{entry} has changed"),
+        }
+    }
+}
+
+/// Why a step had to be run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// The step declares no inputs, so it cannot be known to be up to date.
+    NoInputs,
+    /// There is no record of the step having completed before.
+    NeverRun,
+    /// The output is not present.
+    MissingOutput(PathBuf),
+    /// The inputs have changed since the last run.
+    Changed(Vec<Change>),
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        /// How many changes are listed, as there might be thousands of changed files.
+        const SHOWN_CHANGES: usize = 10;
+        match self {
+            Self::NoInputs => write!(f, "it declares no inputs"),
+            Self::NeverRun => write!(f, "there is no record of a previous run"),
+            Self::MissingOutput(path) => write!(f, "output {} is missing", path.display()),
+            Self::Changed(changes) => {
+                write!(f, "{}", changes.iter().take(SHOWN_CHANGES).join(", "))?;
+                if changes.len() > SHOWN_CHANGES {
+                    write!(f, " and {} more changes", changes.len() - SHOWN_CHANGES)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The result of running a step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The step was skipped, as nothing has changed since its last run.
+    UpToDate,
+    /// The step was run.
+    Ran(Reason),
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UpToDate => write!(f, "up to date"),
+            Self::Ran(reason) => write!(f, "ran, as {reason}"),
+        }
+    }
+}
+
+
+
+// =============
+// === Store ===
+// =============
+
+/// The directory where the fingerprints of the completed steps are recorded.
+#[derive(Clone, Debug)]
+pub struct Store {
+    /// The directory with a `<step name>.json` file for each step that has completed.
+    pub root:    PathBuf,
+    /// Whether to log why each step is run, rather than only that it is.
+    pub explain: bool,
+}
+
+impl Store {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), explain: false }
+    }
+
+    fn record_path(&self, name: &str) -> PathBuf {
+        self.root.join(name).with_appended_extension("json")
+    }
+
+    /// Tell why the step needs to be run, if it does, given the current state of its inputs.
+    pub fn reason(
+        &self,
+        name: &str,
+        fingerprint: &Fingerprint,
+        has_inputs: bool,
+        outputs: &[PathBuf],
+    ) -> Result<Option<Reason>> {
+        let reason = if !has_inputs {
+            Some(Reason::NoInputs)
+        } else if let Some(missing) = outputs.iter().find(|output| !output.exists()) {
+            Some(Reason::MissingOutput(missing.clone()))
+        } else {
+            let path = self.record_path(name);
+            if path.exists() {
+                let previous = path.read_to_json::<Fingerprint>()?;
+                let changes = fingerprint.changes_since(&previous);
+                (!changes.is_empty()).then_some(Reason::Changed(changes))
+            } else {
+                Some(Reason::NeverRun)
+            }
+        };
+        Ok(reason)
+    }
+
+    /// Record that the step has completed with the given inputs' state.
+    pub fn record(&self, name: &str, fingerprint: &Fingerprint) -> Result {
+        self.record_path(name).write_as_json(fingerprint)
+    }
+
+    /// Remove the record of the step's completion, so it is run the next time.
+    ///
+    /// Should be done before the step is run, as a failure may leave its outputs damaged.
+    pub fn forget(&self, name: &str) -> Result {
+        crate::fs::remove_file_if_exists(self.record_path(name))
+    }
+
+    /// Report the status of the step.
+    pub fn report(&self, name: &str, status: &Status) {
+        match status {
+            Status::UpToDate => info!("Step {name} is up to date, skipping it."),
+            Status::Ran(reason) if self.explain => info!("Running step {name}, as {reason}."),
+            Status::Ran(reason) => {
+                info!("Running step {name}.");
+                debug!("Step {name} needs to run, as {reason}.");
+            }
+        }
+    }
+}
+
+
+
+// ============
+// === Step ===
+// ============
+
+/// A unit of work in the [`Graph`].
+#[derive_where(Debug)]
+pub struct Step {
+    /// Unique name of the step, also used as the name of its record file.
+    pub name:         String,
+    /// Names of the steps that must complete before this one.
+    pub dependencies: Vec<String>,
+    pub inputs:       Vec<Input>,
+    /// Files or directories produced by the step. If any is missing, the step is run.
+    pub outputs:      Vec<PathBuf>,
+    #[derive_where(skip)]
+    pub action:       BoxFuture<'static, Result>,
+}
+
+impl Step {
+    pub fn new(
+        name: impl Into<String>,
+        action: impl Future<Output = Result> + Send + 'static,
+    ) -> Self {
+        Self {
+            name:         name.into(),
+            dependencies: default(),
+            inputs:       default(),
+            outputs:      default(),
+            action:       action.boxed(),
+        }
+    }
+
+    pub fn with_dependency(mut self, name: impl Into<String>) -> Self {
+        self.dependencies.push(name.into());
+        self
+    }
+
+    pub fn with_input(mut self, input: Input) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    pub fn with_inputs(mut self, inputs: impl IntoIterator<Item = Input>) -> Self {
+        self.inputs.extend(inputs);
+        self
+    }
+
+    pub fn with_output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.outputs.push(path.into());
+        self
+    }
+}
+
+
+
+// =============
+// === Graph ===
+// =============
+
+/// Steps with dependencies between them, run in the dependency order.
+///
+/// The fingerprint of a step also covers the fingerprints of its dependencies. Thus, whenever the
+/// inputs of a step change, all the steps depending on it are run as well.
+#[derive(Debug)]
+pub struct Graph {
+    pub store: Store,
+    steps:     Vec<Step>,
+}
+
+impl Graph {
+    pub fn new(store: Store) -> Self {
+        Self { store, steps: default() }
+    }
+
+    pub fn add(&mut self, step: Step) -> &mut Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Order the steps so that each comes after its dependencies, keeping the order of addition
+    /// where possible.
+    fn ordered(steps: Vec<Step>) -> Result<Vec<Step>> {
+        let names: BTreeSet<String> = steps.iter().map(|step| step.name.clone()).collect();
+        ensure!(names.len() == steps.len(), "Step names in the build graph are not unique.");
+        for step in &steps {
+            for dependency in &step.dependencies {
+                ensure!(
+                    names.contains(dependency),
+                    "Step {} depends on an unknown step {dependency}.",
+                    step.name
+                );
+            }
+        }
+        let mut done = BTreeSet::new();
+        let mut remaining = steps;
+        let mut ret = Vec::new();
+        while !remaining.is_empty() {
+            let index = remaining
+                .iter()
+                .position(|step| step.dependencies.iter().all(|name| done.contains(name)))
+                .with_context(|| {
+                    let names = remaining.iter().map(|step| &step.name).join(", ");
+                    format!("Steps {names} have a dependency cycle.")
+                })?;
+            let step = remaining.remove(index);
+            done.insert(step.name.clone());
+            ret.push(step);
+        }
+        Ok(ret)
+    }
+
+    /// Run the steps that are not up to date, stopping at the first failure.
+    ///
+    /// A step that declares no inputs is always run, and so are all the steps depending on it.
+    pub async fn run(self) -> Result<Vec<(String, Status)>> {
+        let Self { store, steps } = self;
+        let mut digests = BTreeMap::<String, String>::new();
+        let mut ret = Vec::new();
+        for step in Self::ordered(steps)? {
+            let Step { name, dependencies, inputs, outputs, action } = step;
+            let dependency_entries = dependencies
+                .iter()
+                .map(|dependency| {
+                    let digest = digests.get(dependency).cloned().unwrap_or_default();
+                    (format!("step {dependency}"), digest)
+                })
+                .collect::<BTreeMap<_, _>>();
+            // Hashing the input trees is blocking, so it is moved off the async runtime.
+            let mut fingerprint = {
+                let inputs = inputs.clone();
+                tokio::task::spawn_blocking(move || Fingerprint::compute(&inputs)).await??
+            };
+            fingerprint.entries.extend(dependency_entries.clone());
+            let reason = store.reason(&name, &fingerprint, !inputs.is_empty(), &outputs)?;
+            let status = match reason {
+                None => Status::UpToDate,
+                Some(reason) => Status::Ran(reason),
+            };
+            store.report(&name, &status);
+            if let Status::Ran(_) = status {
+                store.forget(&name)?;
+                action.await.with_context(|| format!("Step {name} failed."))?;
+                // Some steps write into their own input trees (e.g. the Engine build places the
+                // built libraries' jars in `distribution`), so the state is recorded after the run.
+                fingerprint =
+                    tokio::task::spawn_blocking(move || Fingerprint::compute(&inputs)).await??;
+                fingerprint.entries.extend(dependency_entries);
+                store.record(&name, &fingerprint)?;
+            }
+            let digest = if let Status::Ran(Reason::NoInputs) = status {
+                // Nothing tells whether the result is the same as before, so the dependent steps
+                // must see it as changed.
+                Uuid::new_v4().to_string()
+            } else {
+                fingerprint.digest()
+            };
+            digests.insert(name.clone(), digest);
+            ret.push((name, status));
+        }
+        Ok(ret)
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    fn counting_step(name: &str, counter: &Arc<Mutex<Vec<String>>>) -> Step {
+        let counter = counter.clone();
+        let name_owned = name.to_string();
+        Step::new(name, async move {
+            counter.lock().unwrap().push(name_owned);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn matching_files_by_patterns() -> Result {
+        let temp = tempfile::tempdir()?;
+        let root = temp.path();
+        for file in ["src/lib.rs", "src/a/b.rs", "src/a/b.txt", "node_modules/x/y.rs", "main.rs"] {
+            crate::fs::write(root.join(file), "")?;
+        }
+        let input = Input::files(root, ["**/*.rs"]).excluding(["**/node_modules"]);
+        let Input::Files { include, exclude, .. } = &input else { unreachable!() };
+        let found = matching_files(root, include, exclude)?;
+        let found = found.iter().map(|path| path.strip_prefix(root).unwrap()).collect_vec();
+        let expected = ["main.rs", "src/a/b.rs", "src/lib.rs"].map(PathBuf::from);
+        assert_eq!(found, expected);
+
+        let found = matching_files(root, &["src/*.rs".into()], &[])?;
+        assert_eq!(found, [root.join("src/lib.rs")].into());
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_changes() -> Result {
+        let temp = tempfile::tempdir()?;
+        let root = temp.path();
+        crate::fs::write(root.join("a.txt"), "a")?;
+        let inputs = [Input::files(root, ["*.txt"]), Input::value("version", "1.0")];
+        let before = Fingerprint::compute(&inputs)?;
+        assert_eq!(Fingerprint::compute(&inputs)?, before);
+
+        crate::fs::write(root.join("a.txt"), "changed")?;
+        crate::fs::write(root.join("b.txt"), "b")?;
+        let inputs = [Input::files(root, ["*.txt"])];
+        let after = Fingerprint::compute(&inputs)?;
+        let file = |name: &str| format!("file {}", root.join(name).display());
+        assert_eq!(after.changes_since(&before), [
+            Change::Modified(file("a.txt")),
+            Change::Added(file("b.txt")),
+            Change::Removed("value version".into()),
+        ]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn skipping_up_to_date_steps() -> Result {
+        let temp = tempfile::tempdir()?;
+        let sources = temp.path().join("sources");
+        let output = temp.path().join("output");
+        crate::fs::write(sources.join("main.rs"), "fn main() {}")?;
+        crate::fs::write(output.join("app"), "")?;
+        let store = Store::new(temp.path().join("fingerprints"));
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let run_graph = || {
+            let mut graph = Graph::new(store.clone());
+            graph.add(counting_step("test", &ran).with_dependency("build"));
+            graph.add(
+                counting_step("build", &ran)
+                    .with_input(Input::files(&sources, ["**/*.rs"]))
+                    .with_output(&output),
+            );
+            graph.add(counting_step("lint", &ran).with_input(Input::value("strict", true)));
+            graph.add(
+                counting_step("package", &ran)
+                    .with_dependency("build")
+                    .with_input(Input::value("format", "zip")),
+            );
+            graph.run()
+        };
+        let take_ran = || std::mem::take(&mut *ran.lock().unwrap());
+
+        let statuses = run_graph().await?;
+        assert_eq!(take_ran(), ["build", "test", "lint", "package"]);
+        assert_eq!(statuses[0], ("build".into(), Status::Ran(Reason::NeverRun)));
+        assert_eq!(statuses[1], ("test".into(), Status::Ran(Reason::NoInputs)));
+
+        run_graph().await?;
+        assert_eq!(take_ran(), ["test"]);
+
+        crate::fs::write(sources.join("main.rs"), "fn main() { println!() }")?;
+        let statuses = run_graph().await?;
+        assert_eq!(take_ran(), ["build", "test", "package"]);
+        assert_eq!(
+            statuses[3].1,
+            Status::Ran(Reason::Changed(vec![Change::Modified("step build".into())]))
+        );
+        let Status::Ran(Reason::Changed(changes)) = &statuses[0].1 else {
+            panic!("Unexpected status: {:?}", statuses[0]);
+        };
+        assert_eq!(changes, &[Change::Modified(format!(
+            "file {}",
+            sources.join("main.rs").display()
+        ))]);
+
+        crate::fs::remove_dir_if_exists(&output)?;
+        let statuses = run_graph().await?;
+        assert_eq!(statuses[0].1, Status::Ran(Reason::MissingOutput(output.clone())));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn steps_without_inputs_are_always_changed() -> Result {
+        let temp = tempfile::tempdir()?;
+        let store = Store::new(temp.path());
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        for _ in 0..2 {
+            let mut graph = Graph::new(store.clone());
+            graph.add(counting_step("download", &ran));
+            graph.add(
+                counting_step("install", &ran)
+                    .with_dependency("download")
+                    .with_input(Input::value("prefix", "/usr")),
+            );
+            graph.run().await?;
+        }
+        // The downloaded files might have changed, so they are installed again.
+        assert_eq!(*ran.lock().unwrap(), ["download", "install", "download", "install"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_graphs() {
+        let mut graph = Graph::new(Store::new("fingerprints"));
+        graph.add(Step::new("a", ready(Ok(()))).with_dependency("b"));
+        graph.add(Step::new("b", ready(Ok(()))).with_dependency("a"));
+        assert!(graph.run().await.unwrap_err().to_string().contains("cycle"));
+
+        let mut graph = Graph::new(Store::new("fingerprints"));
+        graph.add(Step::new("a", ready(Ok(()))).with_dependency("c"));
+        assert!(graph.run().await.unwrap_err().to_string().contains("unknown step c"));
+    }
+}
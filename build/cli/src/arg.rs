@@ -11,7 +11,6 @@ use clap::Parser;
 use clap::Subcommand;
 use clap::ValueEnum;
 use derive_where::derive_where;
-use ide_ci::cache;
 use ide_ci::github::Repo;
 use octocrab::models::RunId;
 
@@ -22,6 +21,10 @@ use octocrab::models::RunId;
 // ==============
 
 pub mod backend;
+pub mod cache;
+pub mod changelog;
+pub mod ci;
+pub mod config;
 pub mod git_clean;
 pub mod gui;
 pub mod ide;
@@ -49,7 +52,7 @@ pub fn default_repo_remote() -> Repo {
 }
 
 pub fn default_cache_path() -> Option<PathBuf> {
-    cache::default_path().ok()
+    ide_ci::cache::default_path().ok()
 }
 
 /// Extensions to the `clap::Arg`, intended to be used as argument attributes.
@@ -129,17 +132,26 @@ pub enum Target {
     /// Clean the repository. Keeps the IntelliJ's .idea directory intact. WARNING: This removes
     /// files that are not under version control in the repository subtree.
     GitClean(git_clean::Options),
+    /// Inspect and clean up the build script's cache.
+    Cache(cache::Target),
+    /// Run the generated CI workflows locally.
+    Ci(ci::Target),
+    /// Inspect the option values, including the ones from the `enso-build.toml` configuration
+    /// files.
+    Config(config::Target),
     /// Lint non-TypeScript codebase. TypeScript Linting is part of the GUI Check target.
     Lint,
     /// Apply automatic formatters on the repository.
     #[clap(alias = "format")]
-    Fmt,
+    Fmt(enso_formatter::Options),
     /// Release-related subcommand.
     Release(release::Target),
     /// Regenerate `syntax2` library (new parser).
     JavaGen(java_gen::Target),
     /// Check if the changelog has been updated. Requires CI environment.
     ChangelogCheck,
+    /// Lint or edit the changelog.
+    Changelog(changelog::Target),
     /// Enso-libraries related subcommand.
     Libraries(libraries::Target),
 }
@@ -168,14 +180,39 @@ pub struct Cli {
     #[clap(long, global = true, default_value_t = TARGET_OS, enso_env(), value_parser = possible_os_parser(&[OS::Windows, OS::Linux, OS::MacOS]))]
     pub target_os: OS,
 
+    /// Apply the named profile from the configuration files, e.g. `ci-linux`.
+    #[clap(long, global = true, enso_env())]
+    pub profile: Option<String>,
+
     /// Does not check the program version requirements defined in the build-config.yaml.
     #[clap(long, global = true, enso_env())]
     pub skip_version_check: bool,
 
+    /// Install the programs required by the build-config.yaml that are missing or in wrong
+    /// versions into the cache, instead of failing the version check. The installed versions and
+    /// their package digests are pinned in the `build-config.lock.yaml` file.
+    #[clap(long, global = true, enso_env())]
+    pub provision_tools: bool,
+
+    /// Directory with the packages of the provisioned programs, used instead of downloading them.
+    /// The packages are looked up by their file names. Useful on machines without network access.
+    #[clap(long, global = true, value_parser(normalize_path), enso_env())]
+    pub tools_mirror: Option<PathBuf>,
+
     /// Assume that `npm install` was already run in the repository root and skip it.
     #[clap(long, global = true, enso_env())]
     pub skip_npm_install: bool,
 
+    /// Log why each build step is run, rather than skipped as up to date.
+    #[clap(long, global = true, enso_env())]
+    pub explain: bool,
+
+    /// Store the objects that would be uploaded to (or downloaded from) S3 buckets in the given
+    /// local directory instead. Each bucket becomes a subdirectory. Useful for testing the release
+    /// flows without AWS credentials.
+    #[clap(long, global = true, value_parser(normalize_path), enso_env())]
+    pub local_storage: Option<PathBuf>,
+
     #[clap(subcommand)]
     pub target: Target,
 }
@@ -0,0 +1,83 @@
+use crate::prelude::*;
+
+use clap::Args;
+use clap::Subcommand;
+use std::time::Duration;
+
+
+
+/// Parse a size in bytes, optionally with a decimal (`K`, `M`, `G`, `T`) or binary (`KiB`, `MiB`,
+/// `GiB`, `TiB`) unit, e.g. `20G` or `512MiB`.
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let split_at = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split_at);
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().trim_end_matches('b') {
+        "" => 1,
+        "k" => 1000,
+        "m" => 1000_u64.pow(2),
+        "g" => 1000_u64.pow(3),
+        "t" => 1000_u64.pow(4),
+        "ki" => 1 << 10,
+        "mi" => 1 << 20,
+        "gi" => 1 << 30,
+        "ti" => 1 << 40,
+        _ => bail!("Unknown size unit: {unit}."),
+    };
+    let number = number.parse::<f64>().with_context(|| format!("Invalid size: {text}."))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse a duration given as a number of seconds (`s`), minutes (`m`), hours (`h`), days (`d`) or
+/// weeks (`w`), e.g. `30d`.
+pub fn parse_age(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let split_at = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split_at);
+    let seconds: u64 = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!("Unknown or missing age unit in {text}. Use one of: s, m, h, d, w."),
+    };
+    let number = number.parse::<u64>().with_context(|| format!("Invalid age: {text}."))?;
+    Ok(Duration::from_secs(number * seconds))
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// List the cache entries, least recently used first.
+    List,
+    /// Remove stale entries. Incomplete entries (e.g. left by an interrupted download) are
+    /// always removed.
+    Prune {
+        /// Remove the entries that were not used for this long, e.g. `30d`.
+        #[clap(long, value_parser = parse_age)]
+        max_age:  Option<Duration>,
+        /// Remove the least recently used entries until the cache fits in this size, e.g. `20G`.
+        #[clap(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// Do not remove anything, just print what would be removed.
+        #[clap(long)]
+        dry_run:  bool,
+    },
+    /// Check that the files of the cache entries were not modified or damaged.
+    Verify {
+        /// Remove the entries that fail the verification.
+        #[clap(long)]
+        remove_invalid: bool,
+    },
+    /// Remove a single entry.
+    Remove {
+        /// Digest of the entry, as printed by `cache list`.
+        digest: String,
+    },
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct Target {
+    #[clap(subcommand)]
+    pub action: Command,
+}
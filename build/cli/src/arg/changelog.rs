@@ -0,0 +1,33 @@
+use crate::prelude::*;
+
+use clap::Args;
+use clap::Subcommand;
+use enso_build::changelog::model::SectionKind;
+
+
+
+#[derive(Args, Clone, Debug, PartialEq, Eq)]
+pub struct Add {
+    /// The section of the next release to add the entry to.
+    #[clap(long, value_enum)]
+    pub section:     SectionKind,
+    /// Number of the pull request introducing the change. The entry links to it.
+    #[clap(long)]
+    pub pr:          u64,
+    /// Description of the change, e.g. `Added a limit for dropdown width`.
+    pub description: String,
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Check that the links match their definitions and the entries are in the right sections.
+    Lint,
+    /// Add an entry to the next release.
+    Add(Add),
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct Target {
+    #[clap(subcommand)]
+    pub action: Command,
+}
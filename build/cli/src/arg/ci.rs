@@ -0,0 +1,40 @@
+use crate::prelude::*;
+
+use clap::Args;
+use clap::Subcommand;
+
+
+
+/// Parse a `name=value` pair.
+pub fn parse_assignment(text: &str) -> Result<(String, String)> {
+    let (name, value) =
+        text.split_once('=').with_context(|| format!("Expected `name=value`, got `{text}`."))?;
+    Ok((name.trim().to_owned(), value.to_owned()))
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Run a generated workflow on this machine. Actions used by the workflow are replaced with
+    /// local fakes, and secrets are read from the environment variables of the same name.
+    RunLocal {
+        /// The workflow, given by its file name (e.g. `gui.yml` or `gui`) or its display name.
+        workflow: String,
+        /// Run only this job, together with the jobs it needs.
+        #[clap(long)]
+        job:      Option<String>,
+        /// Value of a workflow input, as `name=value`. Inputs that are not given take their
+        /// default values.
+        #[clap(long = "input", value_parser = parse_assignment)]
+        inputs:   Vec<(String, String)>,
+        /// Value of a matrix parameter, as `name=value`. By default, the first value of each
+        /// parameter is used.
+        #[clap(long = "matrix", value_parser = parse_assignment)]
+        matrix:   Vec<(String, String)>,
+    },
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct Target {
+    #[clap(subcommand)]
+    pub action: Command,
+}
@@ -0,0 +1,18 @@
+use crate::prelude::*;
+
+use clap::Args;
+use clap::Subcommand;
+
+
+
+#[derive(Subcommand, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Print the effective values of the configurable options and where they come from.
+    Show,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct Target {
+    #[clap(subcommand)]
+    pub action: Command,
+}
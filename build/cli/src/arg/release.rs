@@ -19,6 +19,61 @@ pub struct Promote {
     pub designation: enso_build::version::promote::Designation,
 }
 
+/// Structure that represents `verify-reproducible` subcommand arguments.
+#[derive(Args, Clone, Debug)]
+pub struct VerifyReproducible {
+    /// Directory to pack, e.g. an engine or project manager bundle.
+    pub directory:    PathBuf,
+    /// Name of the archive to create. Its extension determines the format.
+    #[clap(long, default_value_t = format!("bundle.{}", ide_ci::github::release::archive_extension()))]
+    pub archive_name: String,
+    /// An existing archive (e.g. a downloaded release asset) that must be identical to the one
+    /// packed from the directory.
+    #[clap(long)]
+    pub expected:     Option<PathBuf>,
+}
+
+/// Format of the generated release notes.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NotesFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+/// Structure that represents `notes` subcommand arguments.
+#[derive(Args, Clone, Debug)]
+pub struct Notes {
+    /// The revision to start after. Either a git revision, like a tag, or a designation to use the
+    /// latest local tag of such release, e.g. `stable`.
+    #[clap(long)]
+    pub from:   enso_build::release::notes::Bound,
+    /// The last revision to include. Accepts the same values as `--from`.
+    #[clap(long, default_value = "HEAD")]
+    pub to:     enso_build::release::notes::Bound,
+    #[clap(long, value_enum, default_value_t)]
+    pub format: NotesFormat,
+    /// Write the notes to the given file instead of the standard output.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Structure that represents `verify` subcommand arguments.
+#[derive(Args, Clone, Debug)]
+pub struct Verify {
+    /// Directory with the downloaded release assets. If not given, the assets are downloaded from
+    /// the GitHub release.
+    #[clap(long, conflicts_with = "version")]
+    pub directory:  Option<PathBuf>,
+    /// Version of the GitHub release to verify.
+    #[clap(long, required_unless_present = "directory")]
+    pub version:    Option<Version>,
+    /// Public key to verify the signatures with. Either a path to the minisign public key file, or
+    /// the base64-encoded key. If not given, only the digests are checked.
+    #[clap(long)]
+    pub public_key: Option<String>,
+}
+
 #[derive(Subcommand, Clone, Debug)]
 pub enum Action {
     /// Create a release draft on GitHub.
@@ -27,6 +82,15 @@ pub enum Action {
     DeployRuntime(DeployRuntime),
     Publish,
     Promote(Promote),
+    /// Pack a directory deterministically twice and check that the archives are identical. The
+    /// modification time stored in the archive is taken from `SOURCE_DATE_EPOCH`.
+    VerifyReproducible(VerifyReproducible),
+    /// Generate the notes for the changes between two revisions from the local git history,
+    /// listing the pull requests missing from the changelog.
+    Notes(Notes),
+    /// Check the release assets against the digests in the manifest and `SHA256SUMS`, and verify
+    /// the signatures of these files.
+    Verify(Verify),
 }
 
 #[derive(Args, Clone, Debug)]
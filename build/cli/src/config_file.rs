@@ -0,0 +1,398 @@
+//! Configuration files providing the default values of the CLI options.
+//!
+//! Any option that can be set through an `ENSO_BUILD_*` environment variable (see
+//! [`ArgExt::enso_env`](crate::arg::ArgExt::enso_env)) can also be set in a TOML configuration
+//! file, under the option name. Named profiles, selected with `--profile`, override the top-level
+//! values. Example:
+//! ```toml
+//! repo-remote = "enso-org/enso"
+//! cache-path = "/data/enso-build-cache"
+//!
+//! [profiles.ci-linux]
+//! target-os = "linux"
+//! skip-npm-install = true
+//! ```
+//!
+//! The values are taken from the following sources, in the order of decreasing precedence:
+//! 1. Command line flags.
+//! 2. Environment variables.
+//! 3. The selected profile, from the repository file first, then from the user file.
+//! 4. The repository file, [`REPO_CONFIG_FILENAME`] in the repository root.
+//! 5. The user file, [`USER_CONFIG_FILENAME`] in the user's configuration directory.
+//! 6. The default values.
+
+use crate::prelude::*;
+
+use crate::arg::default_repo_path;
+use crate::arg::ENVIRONMENT_VARIABLE_NAME_PREFIX;
+
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use clap::Command;
+
+
+
+/// Name of the configuration file placed in the repository root.
+pub const REPO_CONFIG_FILENAME: &str = "enso-build.toml";
+
+/// Name of the configuration file placed in the `enso-build` subdirectory of the user's
+/// configuration directory, e.g. `~/.config/enso-build/config.toml` on Linux.
+pub const USER_CONFIG_FILENAME: &str = "config.toml";
+
+/// Name of the option selecting the profile. It cannot be set in the configuration files.
+pub const PROFILE_OPTION: &str = "profile";
+
+/// Path to the user configuration file, if the user's configuration directory is known.
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("enso-build").join(USER_CONFIG_FILENAME))
+}
+
+/// Name of the environment variable that sets the option, e.g. `ENSO_BUILD_REPO_REMOTE` for
+/// `repo-remote`.
+pub fn environment_variable(option: &str) -> String {
+    format!("{ENVIRONMENT_VARIABLE_NAME_PREFIX}_{}", option.to_uppercase().replace('-', "_"))
+}
+
+/// Load the configuration files and expose their values to `clap` through the environment, see
+/// [`Layers::apply`].
+///
+/// The repository path and the profile must be known to load the files, so the command line is
+/// parsed once before that, ignoring any errors.
+pub fn apply_files(command: &Command) -> Result<BTreeMap<String, Source>> {
+    let matches = command.clone().ignore_errors(true).try_get_matches().ok();
+    let raw = |id: &str| {
+        let mut values = matches.as_ref()?.try_get_raw(id).ok()??;
+        values.next().map(ToOwned::to_owned)
+    };
+    let Some(repo_path) = raw("repo_path").map(PathBuf::from).or_else(default_repo_path) else {
+        debug!("Cannot locate the repository, not loading the configuration files.");
+        return Ok(default());
+    };
+    let profile = raw(PROFILE_OPTION).map(|profile| profile.to_string_lossy().into_owned());
+    let user_file = user_config_path();
+    let repo_file = repo_path.join(REPO_CONFIG_FILENAME);
+    let known = known_options(command);
+    let layers = Layers::load(user_file.as_deref(), &repo_file, profile.as_deref(), &known)?;
+    layers.apply()
+}
+
+/// Names of the options that can be set through the environment, in all the (sub)commands.
+pub fn known_options(command: &Command) -> BTreeSet<String> {
+    let prefix = format!("{ENVIRONMENT_VARIABLE_NAME_PREFIX}_");
+    let own = command.get_arguments().filter_map(|arg| {
+        let variable = arg.get_env()?.to_str()?;
+        let name = variable.strip_prefix(&prefix)?;
+        Some(name.to_lowercase().replace('_', "-"))
+    });
+    let nested = command.get_subcommands().flat_map(known_options);
+    own.chain(nested).collect()
+}
+
+
+
+// ============
+// === File ===
+// ============
+
+/// Contents of a configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct ConfigFile {
+    /// Option values by the option names.
+    #[serde(flatten)]
+    pub options:  BTreeMap<String, toml::Value>,
+    /// Named sets of option values.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, BTreeMap<String, toml::Value>>,
+}
+
+impl ConfigFile {
+    /// Read the file, if it exists.
+    #[context("Failed to load the configuration file '{}'.", path.as_ref().display())]
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(toml::from_str(&ide_ci::fs::read_to_string(path)?)?))
+    }
+
+    /// Check that the file, including all its profiles, sets only the `known` options, with
+    /// supported values. The `path` is only used to describe the errors.
+    pub fn validate(&self, path: &Path, known: &BTreeSet<String>) -> Result {
+        let top_level = once((None, &self.options));
+        let profiles = self.profiles.iter().map(|(name, values)| (Some(name.clone()), values));
+        for (profile, values) in top_level.chain(profiles) {
+            let source = Source::File { path: path.to_owned(), profile };
+            for (option, value) in values {
+                ensure!(option != PROFILE_OPTION, "Option '{option}' cannot be set in {source}.");
+                ensure!(known.contains(option), "Unknown option '{option}' in {source}.");
+                format_value(value).with_context(|| format!("Invalid '{option}' in {source}."))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describe the value of an option in the form that `clap` accepts from the environment.
+pub fn format_value(value: &toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(text) => Ok(text.clone()),
+        toml::Value::Integer(number) => Ok(number.to_string()),
+        toml::Value::Float(number) => Ok(number.to_string()),
+        toml::Value::Boolean(flag) => Ok(flag.to_string()),
+        toml::Value::Datetime(datetime) => Ok(datetime.to_string()),
+        toml::Value::Array(_) | toml::Value::Table(_) =>
+            bail!("Only strings, numbers and booleans are supported, found: {value}."),
+    }
+}
+
+
+
+// ==============
+// === Layers ===
+// ==============
+
+/// Where the effective value of an option comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    CommandLine,
+    /// The environment variable with the given name.
+    Environment(String),
+    File {
+        path:    PathBuf,
+        /// The profile that defined the value, if it was not the top-level one.
+        profile: Option<String>,
+    },
+    Default,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Source::CommandLine => write!(f, "command line"),
+            Source::Environment(variable) => write!(f, "environment variable {variable}"),
+            Source::File { path, profile: None } => write!(f, "{}", path.display()),
+            Source::File { path, profile: Some(profile) } =>
+                write!(f, "profile {profile} in {}", path.display()),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A set of option values from a single file, or a single profile in a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub source: Source,
+    pub values: BTreeMap<String, toml::Value>,
+}
+
+/// The option values from the configuration files, from the lowest to the highest precedence.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layers(pub Vec<Layer>);
+
+impl Layers {
+    /// Read and validate the configuration files, and select the profile from them.
+    pub fn load(
+        user_file: Option<&Path>,
+        repo_file: &Path,
+        profile: Option<&str>,
+        known: &BTreeSet<String>,
+    ) -> Result<Self> {
+        let mut files = Vec::new();
+        for path in user_file.into_iter().chain([repo_file]) {
+            if let Some(file) = ConfigFile::load(path)? {
+                file.validate(path, known)?;
+                files.push((path.to_owned(), file));
+            }
+        }
+        Self::new(files, profile)
+    }
+
+    /// Layer the files, given from the lowest to the highest precedence.
+    pub fn new(files: Vec<(PathBuf, ConfigFile)>, profile: Option<&str>) -> Result<Self> {
+        let mut top_level = Vec::new();
+        let mut profiles = Vec::new();
+        for (path, mut file) in files {
+            if let Some(profile) = profile {
+                if let Some(values) = file.profiles.remove(profile) {
+                    let source =
+                        Source::File { path: path.clone(), profile: Some(profile.into()) };
+                    profiles.push(Layer { source, values });
+                }
+            }
+            let source = Source::File { path, profile: None };
+            top_level.push(Layer { source, values: file.options });
+        }
+        if let Some(profile) = profile {
+            ensure!(
+                !profiles.is_empty(),
+                "Profile '{profile}' is not defined in any of the configuration files: {}.",
+                top_level.iter().map(|layer| layer.source.to_string()).join(", ")
+            );
+        }
+        Ok(Self(top_level.into_iter().chain(profiles).collect()))
+    }
+
+    /// The value of each option with the layer that provides it.
+    pub fn effective(&self) -> BTreeMap<&str, (&toml::Value, &Source)> {
+        let mut effective = BTreeMap::new();
+        for layer in &self.0 {
+            for (option, value) in &layer.values {
+                effective.insert(option.as_str(), (value, &layer.source));
+            }
+        }
+        effective
+    }
+
+    /// Set the environment variables of the options that are not set in the environment already,
+    /// so `clap` uses the values from the files with lower precedence than the ones from the
+    /// environment.
+    ///
+    /// Returns the sources of the values that were set.
+    pub fn apply(&self) -> Result<BTreeMap<String, Source>> {
+        let mut applied = BTreeMap::new();
+        for (option, (value, source)) in self.effective() {
+            let variable = environment_variable(option);
+            if std::env::var_os(&variable).is_some() {
+                debug!("Ignoring '{option}' from {source}, as {variable} is set.");
+                continue;
+            }
+            ide_ci::env::set_var(&variable, format_value(value)?);
+            applied.insert(option.to_owned(), source.clone());
+        }
+        Ok(applied)
+    }
+}
+
+
+
+// ==============
+// === Values ===
+// ==============
+
+/// The effective value of an option.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Value {
+    pub option: String,
+    pub value:  Option<String>,
+    pub source: Option<Source>,
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let assignment = match &self.value {
+            Some(value) => format!("{} = {value:?}", self.option),
+            None => format!("# {} is not set", self.option),
+        };
+        match &self.source {
+            Some(source) => write!(f, "{assignment:<60} # {source}"),
+            None => write!(f, "{assignment}"),
+        }
+    }
+}
+
+/// Describe the effective values of all the options that can be configured.
+///
+/// The `applied` are the sources of the values set by [`Layers::apply`]. Only the global options
+/// can be given on the command line, as the subcommand showing the values is the one parsed.
+pub fn effective_values(
+    command: &Command,
+    matches: &ArgMatches,
+    applied: &BTreeMap<String, Source>,
+) -> Vec<Value> {
+    let global_args = command.get_arguments().filter(|arg| arg.is_global_set());
+    let command_line = global_args
+        .filter_map(|arg| {
+            let id = arg.get_id().as_str();
+            (matches.value_source(id) == Some(ValueSource::CommandLine)).then_some(())?;
+            let variable = arg.get_env()?.to_string_lossy().into_owned();
+            let value = matches.get_raw(id)?.map(|value| value.to_string_lossy()).join(",");
+            Some((variable, value))
+        })
+        .collect::<HashMap<_, _>>();
+    let defaults = all_arguments(command)
+        .filter_map(|arg| {
+            let variable = arg.get_env()?.to_string_lossy().into_owned();
+            let default = arg.get_default_values().iter().map(|v| v.to_string_lossy()).join(",");
+            (!default.is_empty()).then_some((variable, default))
+        })
+        .collect::<HashMap<_, _>>();
+    known_options(command)
+        .into_iter()
+        .map(|option| {
+            let variable = environment_variable(&option);
+            let (value, source) = if let Some(value) = command_line.get(&variable) {
+                (Some(value.clone()), Some(Source::CommandLine))
+            } else if let Ok(value) = std::env::var(&variable) {
+                let source = applied.get(&option).cloned();
+                (Some(value), Some(source.unwrap_or(Source::Environment(variable))))
+            } else if let Some(default) = defaults.get(&variable) {
+                (Some(default.clone()), Some(Source::Default))
+            } else {
+                (None, None)
+            };
+            Value { option, value, source }
+        })
+        .collect()
+}
+
+/// The arguments of the command and all its subcommands.
+fn all_arguments(command: &Command) -> Box<dyn Iterator<Item = &clap::Arg> + '_> {
+    let nested = command.get_subcommands().flat_map(all_arguments);
+    Box::new(command.get_arguments().chain(nested))
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layering() -> Result {
+        let user: ConfigFile = toml::from_str(
+            r#"
+            repo-remote = "user/enso"
+            cache-path = "/user/cache"
+            [profiles.ci-linux]
+            target-os = "linux"
+            cache-path = "/ci/cache"
+            "#,
+        )?;
+        let repo: ConfigFile = toml::from_str(
+            r#"
+            repo-remote = "enso-org/enso"
+            skip-npm-install = true
+            "#,
+        )?;
+        let files = vec![("user.toml".into(), user), ("enso-build.toml".into(), repo)];
+
+        let layers = Layers::new(files.clone(), None)?;
+        let effective = layers.effective();
+        assert_eq!(effective["repo-remote"].0.as_str(), Some("enso-org/enso"));
+        assert_eq!(effective["cache-path"].0.as_str(), Some("/user/cache"));
+        assert!(!effective.contains_key("target-os"));
+
+        let layers = Layers::new(files.clone(), Some("ci-linux"))?;
+        let effective = layers.effective();
+        assert_eq!(effective["cache-path"].0.as_str(), Some("/ci/cache"));
+        assert_eq!(effective["cache-path"].1.to_string(), "profile ci-linux in user.toml");
+        assert_eq!(effective["skip-npm-install"].1.to_string(), "enso-build.toml");
+        assert!(Layers::new(files.clone(), Some("ci-windows")).is_err());
+
+        let known = ["repo-remote", "cache-path", "target-os", "skip-npm-install"];
+        let known = known.into_iter().map(String::from).collect();
+        for (path, file) in &files {
+            file.validate(path, &known)?;
+        }
+        let typo: ConfigFile = toml::from_str("[profiles.ci-linux]\nrepo-remtoe = \"enso\"")?;
+        assert!(typo.validate(Path::new("a.toml"), &known).is_err());
+        let array: ConfigFile = toml::from_str("repo-remote = [\"enso\"]")?;
+        assert!(array.validate(Path::new("a.toml"), &known).is_err());
+        Ok(())
+    }
+}
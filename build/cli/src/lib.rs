@@ -7,6 +7,7 @@
 // ==============
 
 pub mod arg;
+pub mod config_file;
 
 
 
@@ -17,6 +18,7 @@ pub mod prelude {
 
 use crate::prelude::*;
 
+use crate::arg::changelog;
 use crate::arg::java_gen;
 use crate::arg::libraries;
 use crate::arg::release::Action;
@@ -28,7 +30,8 @@ use crate::arg::Target;
 use crate::arg::WatchJob;
 use anyhow::Context;
 use arg::BuildDescription;
-use clap::Parser;
+use clap::CommandFactory;
+use clap::FromArgMatches;
 use enso_build::config::Config;
 use enso_build::context::BuildContext;
 use enso_build::engine::context::EnginePackageProvider;
@@ -48,6 +51,8 @@ use enso_build::project::runtime::Runtime;
 use enso_build::project::IsTarget;
 use enso_build::project::IsWatchable;
 use enso_build::project::IsWatcher;
+use enso_build::project::TargetStep;
+use enso_build::release::checksum::upload_asset_as;
 use enso_build::source::BuildSource;
 use enso_build::source::BuildTargetJob;
 use enso_build::source::CiRunSource;
@@ -60,7 +65,11 @@ use enso_build::source::WatchTargetJob;
 use enso_build::source::WithDestination;
 use enso_build::version;
 use futures_util::future::try_join;
+use ide_ci::actions::workflow::definition::WorkflowToWrite;
 use ide_ci::actions::workflow::is_in_env;
+use ide_ci::actions::workflow::local::LocalRun;
+use ide_ci::actions::workflow::local::Outcome;
+use ide_ci::cache::maintenance::PrunePolicy;
 use ide_ci::cache::Cache;
 use ide_ci::define_env_var;
 use ide_ci::fs::remove_if_exists;
@@ -68,14 +77,15 @@ use ide_ci::github::release;
 use ide_ci::github::setup_octocrab;
 use ide_ci::global;
 use ide_ci::ok_ready_boxed;
+use ide_ci::program::command::Child;
 use ide_ci::programs::cargo;
 use ide_ci::programs::git;
 use ide_ci::programs::git::clean;
 use ide_ci::programs::rustc;
 use ide_ci::programs::Cargo;
+use ide_ci::task;
 use octocrab::models::ReleaseId;
 use std::time::Duration;
-use tokio::process::Child;
 
 pub fn void<T>(_t: T) {}
 
@@ -113,11 +123,17 @@ impl Processor {
         let mut triple = TargetTriple::new(versions);
         triple.os = cli.target_os;
         triple.versions.publish().await?;
+        let repo_root = enso_build::paths::new_repo_root(absolute_repo_path, &triple);
+        let fingerprints = task::Store {
+            root:    repo_root.target.fingerprints.to_path_buf(),
+            explain: cli.explain,
+        };
         let context = BuildContext {
             inner: project::Context {
                 cache: Cache::new(&cli.cache_path).await?,
                 octocrab,
-                repo_root: enso_build::paths::new_repo_root(absolute_repo_path, &triple),
+                repo_root,
+                fingerprints,
             },
             triple,
             remote_repo: cli.repo_remote.clone(),
@@ -288,6 +304,21 @@ impl Processor {
         async move { target?.get(context, get_task?.await?).await }.boxed()
     }
 
+    /// Like [`Processor::get`], but describe getting the target as a step of the build graph.
+    pub fn get_step<Target>(
+        &self,
+        target_source: arg::Source<Target>,
+    ) -> BoxFuture<'static, Result<TargetStep<Target::Artifact>>>
+    where
+        Target: IsTarget + IsTargetSource + Send + Sync + 'static,
+        Target: Resolvable,
+    {
+        let target = self.target::<Target>();
+        let get_task = self.target().map(|target| self.resolve(target, target_source));
+        let context = self.context();
+        async move { target?.get_step(context, get_task?.await?).await }.boxed()
+    }
+
     pub fn build<Target: Resolvable>(&self, job: BuildJob<Target>) -> BoxFuture<'static, Result> {
         let context = self.context();
         let target = self.target::<Target>();
@@ -306,7 +337,15 @@ impl Processor {
                 let wasm_browsers =
                     if no_wasm { default() } else { browser.into_iter().map_into().collect_vec() };
                 let root = self.repo_root.to_path_buf();
-                async move { project::wasm::test(root, &wasm_browsers, !no_native).await }.boxed()
+                let fingerprints = self.fingerprints.clone();
+                async move {
+                    let step = project::wasm::test_step(root, wasm_browsers, !no_native).await?;
+                    let mut graph = task::Graph::new(fingerprints);
+                    graph.add(step);
+                    graph.run().await?;
+                    Ok(())
+                }
+                .boxed()
             }
         }
     }
@@ -342,6 +381,92 @@ impl Processor {
         }
     }
 
+    pub fn handle_cache(&self, cache: arg::cache::Target) -> BoxFuture<'static, Result> {
+        let store = self.cache.clone();
+        async move {
+            match cache.action {
+                arg::cache::Command::List => {
+                    let mut entries = store.entries().await?;
+                    entries.sort_by_key(|entry| entry.last_used());
+                    for entry in &entries {
+                        println!("{entry}");
+                    }
+                    let total_size = entries.iter().map(|entry| entry.size).sum::<u64>();
+                    info!(
+                        "{} entries, {total_size} bytes in {}.",
+                        entries.len(),
+                        store.path().display()
+                    );
+                }
+                arg::cache::Command::Prune { max_age, max_size, dry_run } => {
+                    let policy = PrunePolicy { max_age, max_size };
+                    let removed = store.prune(&policy, dry_run).await?;
+                    for entry in &removed {
+                        println!("{entry}");
+                    }
+                    let freed = removed.iter().map(|entry| entry.size).sum::<u64>();
+                    let verb = if dry_run { "Would remove" } else { "Removed" };
+                    info!("{verb} {} entries, {freed} bytes.", removed.len());
+                }
+                arg::cache::Command::Verify { remove_invalid } => {
+                    let mut invalid = 0;
+                    for entry in store.entries().await? {
+                        let verification = store.verify(&entry).await?;
+                        println!("{}  {verification}", entry.digest);
+                        if verification.is_invalid() {
+                            invalid += 1;
+                            if remove_invalid {
+                                store.remove(&entry.digest).await?;
+                            }
+                        }
+                    }
+                    if !remove_invalid {
+                        ensure!(invalid == 0, "{invalid} cache entries failed the verification.");
+                    }
+                }
+                arg::cache::Command::Remove { digest } => store.remove(&digest).await?,
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    pub fn handle_ci(&self, ci: arg::ci::Target) -> BoxFuture<'static, Result> {
+        let repo_root = self.repo_root.clone();
+        let remote_repo = self.remote_repo.clone();
+        let commit = self.commit();
+        async move {
+            match ci.action {
+                arg::ci::Command::RunLocal { workflow, job, inputs, matrix } => {
+                    let workflows = enso_build::ci_gen::generate(&repo_root.github.workflows)?;
+                    let WorkflowToWrite { workflow, .. } = workflows
+                        .into_iter()
+                        .find(|candidate| {
+                            let path = &candidate.path;
+                            path.file_name() == Some(OsStr::new(&workflow))
+                                || path.file_stem() == Some(OsStr::new(&workflow))
+                                || candidate.workflow.name == workflow
+                        })
+                        .with_context(|| format!("There is no generated workflow {workflow}."))?;
+                    let mut local_run = LocalRun::new(workflow, repo_root.to_path_buf());
+                    local_run.inputs.extend(inputs);
+                    local_run.matrix.extend(matrix);
+                    local_run.github.insert("repository".into(), remote_repo.to_string().into());
+                    local_run.github.insert("sha".into(), commit.await?.into());
+                    let report = local_run.run(job.as_deref()).await?;
+                    for (job, outcome) in &report {
+                        println!("{job}  {outcome}");
+                    }
+                    let failed = report.iter().filter(|(_, outcome)| *outcome == Outcome::Failure);
+                    let failed = failed.count();
+                    ensure!(failed == 0, "{failed} jobs failed.");
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
     pub fn handle_runtime(&self, gui: arg::runtime::Target) -> BoxFuture<'static, Result> {
         match gui.command {
             arg::runtime::Command::Build(job) => self.build(job),
@@ -467,6 +592,7 @@ impl Processor {
         let paths = enso_build::paths::Paths::new_triple(&self.repo_root, self.triple.clone());
         let config = config.into();
         let octocrab = self.octocrab.clone();
+        let fingerprints = self.fingerprints.clone();
         async move {
             let paths = paths?;
             let inner = project::Context {
@@ -474,6 +600,7 @@ impl Processor {
                 // upload_artifacts: true,
                 octocrab,
                 cache: Cache::new_default().await?,
+                fingerprints,
             };
             Ok(enso_build::engine::RunContext { inner, config, paths, external_runtime: None })
         }
@@ -502,12 +629,10 @@ impl Processor {
         };
         async move {
             let artifacts = build_job.await?;
-            release
-                .upload_asset_file_with_custom_name(&artifacts.image, add_prefix.clone())
-                .await?;
-            release
-                .upload_asset_file_with_custom_name(&artifacts.image_checksum, add_prefix)
-                .await?;
+            for file in [&artifacts.image, &artifacts.image_checksum] {
+                let asset_name = add_prefix(file.try_file_name()?.as_str().into());
+                upload_asset_as(&release, file, &asset_name).await?;
+            }
             Ok(())
         }
         .boxed()
@@ -557,25 +682,32 @@ impl Processor {
             build_info_path.write_as_json(&build_info)
         };
 
-        let gui = self.get(gui);
-
-        let input = ide::BuildInput {
-            gui: async move {
-                build_info.await?;
-                gui.await
-            }
-            .boxed(),
-            project_manager: self.get(project_manager),
-            version: self.triple.versions.version.clone(),
-            electron_target,
-            artifact_name: "ide".into(),
-        };
-
+        let gui = self.get_step(gui);
+        let project_manager = self.get_step(project_manager);
+        let version = self.triple.versions.version.clone();
         let target = Ide { target_os: self.triple.os, target_arch: self.triple.arch };
-        let artifact_name_prefix = input.artifact_name.clone();
-        let build_job = target.build(&self.context, input, output_path);
+        let context = self.context();
         async move {
-            let artifacts = build_job.await?;
+            // The build info is one of the GUI inputs, so it is written before the graph is run.
+            build_info.await?;
+            let (gui, project_manager) = try_join!(gui, project_manager)?;
+            let input = ide::BuildInput {
+                gui: gui.artifact,
+                project_manager: project_manager.artifact,
+                version,
+                electron_target,
+                artifact_name: "ide".into(),
+            };
+            let artifact_name_prefix = input.artifact_name.clone();
+            let ide = target.build_step(&context, input, output_path).await?;
+            let ide_step = ide
+                .step
+                .with_dependency(&gui.step.name)
+                .with_dependency(&project_manager.step.name);
+            let mut graph = task::Graph::new(context.fingerprints.clone());
+            graph.add(gui.step).add(project_manager.step).add(ide_step);
+            graph.run().await?;
+            let artifacts = ide.artifact.await?;
             if is_in_env() {
                 artifacts.upload_as_ci_artifact(artifact_name_prefix).await?;
             }
@@ -672,19 +804,46 @@ pub async fn main_internal(config: Option<Config>) -> Result {
     trace!("Creating the build context.");
     debug!("Initial configuration for the CLI driver: {config:#?}");
 
-    let cli = Cli::parse();
+    let applied_config = config_file::apply_files(&Cli::command())?;
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
 
     debug!("Parsed CLI arguments: {cli:#?}");
 
+    // Showing the configuration must not depend on the environment that it configures, so it is
+    // done before checking the programs and setting up the build context.
+    if let Target::Config(target) = &cli.target {
+        match target.action {
+            arg::config::Command::Show => {
+                let command = Cli::command();
+                for value in config_file::effective_values(&command, &matches, &applied_config) {
+                    println!("{value}");
+                }
+            }
+        }
+        return Ok(());
+    }
+
     if cli.skip_npm_install {
         enso_build::web::assume_installed();
     }
 
-    if !cli.skip_version_check {
+    if let Some(root) = &cli.local_storage {
+        enso_build::aws::s3::storage::use_local_storage(root);
+    }
+
+    if cli.provision_tools {
+        let cache = Cache::new(&cli.cache_path).await?;
+        let mirror = cli.tools_mirror.clone();
+        enso_build::config::provision::provision(&config, &cli.repo_path, &cache, mirror)
+            .await
+            .context("Failed to provision the required programs.")?;
+    } else if !cli.skip_version_check {
         // Let's be helpful!
         let error_message = "Program requirements were not fulfilled. Please do one of the \
         following:\n * Install the tools in the required versions.\n * Update the requirements in \
-        `build-config.yaml`.\n * Run the build with `--skip-version-check` flag.";
+        `build-config.yaml`.\n * Run the build with `--provision-tools` flag.\n * Run the build \
+        with `--skip-version-check` flag.";
         config.check_programs().await.context(error_message)?;
     }
 
@@ -729,6 +888,9 @@ pub async fn main_internal(config: Option<Config>) -> Result {
             };
             try_join(git_clean, clean_cache).await?;
         }
+        Target::Cache(cache) => ctx.handle_cache(cache).await?,
+        Target::Ci(ci) => ctx.handle_ci(ci).await?,
+        Target::Config(_) => {} // Handled before creating the build context.
         Target::Lint => {
             Cargo
                 .cmd()?
@@ -753,12 +915,15 @@ pub async fn main_internal(config: Option<Config>) -> Result {
 
             enso_build::rust::enso_linter::lint_all(ctx.repo_root.clone()).await?;
         }
-        Target::Fmt => {
+        Target::Fmt(options) => {
             enso_build::web::install(&ctx.repo_root).await?;
             let prettier =
                 enso_build::web::run_script(&ctx.repo_root, enso_build::web::Script::Format);
-            let our_formatter =
-                enso_formatter::process_path(&ctx.repo_root, enso_formatter::Action::Format);
+            let our_formatter = enso_formatter::process_path_with(
+                &ctx.repo_root,
+                enso_formatter::Action::Format,
+                &options,
+            );
             try_join!(prettier, our_formatter)?;
         }
         Target::Release(release) => match release.action {
@@ -781,27 +946,95 @@ pub async fn main_internal(config: Option<Config>) -> Result {
                 let arg::release::Promote { designation } = args;
                 enso_build::release::promote_release(&ctx, designation).await?;
             }
+            Action::VerifyReproducible(args) => {
+                let arg::release::VerifyReproducible { directory, archive_name, expected } = args;
+                let digest = ide_ci::archive::reproducible::verify(
+                    &directory,
+                    &archive_name,
+                    expected.as_deref(),
+                )
+                .await?;
+                println!("{digest}");
+            }
+            Action::Notes(args) => {
+                let arg::release::Notes { from, to, format, output } = args;
+                let git = ctx.git().await?;
+                let changelog =
+                    enso_build::changelog::model::Document::read(&ctx.repo_root.changelog_md)?;
+                let notes =
+                    enso_build::release::notes::generate(&git, &changelog, &from, &to).await?;
+                let text = match format {
+                    arg::release::NotesFormat::Markdown => notes.to_markdown(&ctx.remote_repo)?,
+                    arg::release::NotesFormat::Json => notes.to_json()?,
+                };
+                match output {
+                    Some(path) => ide_ci::fs::write(path, text)?,
+                    None => println!("{text}"),
+                }
+            }
+            Action::Verify(args) => {
+                let arg::release::Verify { directory, version, public_key } = args;
+                let source = match (directory, version) {
+                    (Some(directory), _) => enso_build::release::AssetSource::Directory(directory),
+                    (None, Some(version)) => enso_build::release::AssetSource::Release {
+                        repo: ctx.remote_repo.clone(),
+                        version,
+                    },
+                    (None, None) => bail!("Either the directory or the version must be given."),
+                };
+                let public_key = public_key
+                    .map(|key| -> Result<enso_build::release::signature::PublicKey> {
+                        if Path::new(&key).is_file() {
+                            enso_build::release::signature::PublicKey::read(&key)
+                        } else {
+                            key.parse()
+                        }
+                    })
+                    .transpose()?;
+                enso_build::release::verify_release_assets(&source, public_key.as_ref()).await?;
+            }
         },
         Target::JavaGen(command) => {
+            use enso_build::rust::parser;
             let repo_root = ctx.repo_root.clone();
-            async move {
-                let generate_job = enso_build::rust::parser::generate_java(&repo_root);
-                match command.action {
-                    java_gen::Command::Build => generate_job.await,
-                    java_gen::Command::Test => {
-                        generate_job.await?;
-                        let backend_context = ctx.prepare_backend_context(default()).await?;
-                        backend_context.prepare_build_env().await?;
-                        enso_build::rust::parser::run_self_tests(&repo_root).await
-                    }
+            let mut graph = task::Graph::new(ctx.fingerprints.clone());
+            graph.add(parser::generate_java_step(&repo_root));
+            match command.action {
+                java_gen::Command::Build => {}
+                java_gen::Command::Test => {
+                    let backend_context = ctx.prepare_backend_context(default());
+                    let test_job = async move {
+                        backend_context.await?.prepare_build_env().await?;
+                        parser::run_self_tests(&repo_root).await
+                    };
+                    let step = task::Step::new("test-generated-java", test_job)
+                        .with_dependency(parser::GENERATE_JAVA_STEP);
+                    graph.add(step);
                 }
             }
-            .await?;
+            graph.run().await?;
         }
         Target::ChangelogCheck => {
             let ci_context = ide_ci::actions::context::Context::from_env()?;
             enso_build::changelog::check::check(ctx.repo_root.clone(), ci_context).await?;
         }
+        Target::Changelog(command) => {
+            use enso_build::changelog::model;
+            let path = &ctx.repo_root.changelog_md;
+            match command.action {
+                changelog::Command::Lint => {
+                    enso_build::changelog::lint::lint_file(path)?;
+                }
+                changelog::Command::Add(changelog::Add { section, pr, description }) => {
+                    let mut document = model::Document::read(path)?;
+                    let label = pr.to_string();
+                    let entry = model::Entry::new(&description, &label);
+                    let link = model::LinkDefinition::pull_request(&ctx.remote_repo, pr)?;
+                    document.add_entry(section, entry, link)?;
+                    document.write(path)?;
+                }
+            }
+        }
         Target::Libraries(command) => match command.action {
             libraries::Command::Lint => {
                 enso_build::rust::enso_linter::lint_all(ctx.repo_root.clone()).await?;
@@ -817,7 +1050,14 @@ pub fn lib_main(config: Option<Config>) -> Result {
     trace!("Starting the tokio runtime.");
     let rt = tokio::runtime::Runtime::new()?;
     trace!("Entering main.");
-    rt.block_on(async { main_internal(config).await })?;
+    rt.block_on(async {
+        let result = main_internal(config).await;
+        // The trace is also wanted for the failed runs, so it is written out regardless.
+        if let Err(error) = ide_ci::log::trace::finish().await {
+            warn!("Failed to write out the build trace: {error:?}");
+        }
+        result
+    })?;
     rt.shutdown_timeout(Duration::from_secs(60 * 30));
     info!("Successfully ending.");
     Ok(())
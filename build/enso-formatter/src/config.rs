@@ -0,0 +1,160 @@
+//! Project-level formatter configuration.
+//!
+//! The configuration is read from the [`CONFIG_FILENAME`] file in the processed root path. All the
+//! rules it controls are disabled when the file is missing. Example:
+//! ```toml
+//! # Modules, besides `prelude`, `traits` and `super`, whose contents can be imported with `*`.
+//! allowed-star-imports = ["HeaderToken"]
+//!
+//! [rules]
+//! section-spacing = true
+//! star-imports = true
+//!
+//! # Lints from `OPTIONAL_LINTER_ATTRIBS` enabled in the crate placed in the given directory.
+//! [crates."build/ci_utils"]
+//! lints = ["warn(missing_docs)", "warn(unused_qualifications)"]
+//! ```
+
+use ide_ci::prelude::*;
+
+use crate::OPTIONAL_LINTER_ATTRIBS;
+
+
+
+/// Name of the configuration file, placed in the processed root path.
+pub const CONFIG_FILENAME: &str = "enso-formatter.toml";
+
+/// Rules that are applied in addition to sorting the imports and the module attributes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Rules {
+    /// Normalize the blank lines around the section banners and between the top-level items.
+    pub section_spacing: bool,
+    /// Report star imports of modules other than `prelude`, `traits` and `super`.
+    pub star_imports:    bool,
+}
+
+/// Configuration of a single crate.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CrateConfig {
+    /// Entries of [`OPTIONAL_LINTER_ATTRIBS`] added to the crate's `lib.rs` and `main.rs` files.
+    pub lints: Vec<String>,
+}
+
+/// Contents of the [`CONFIG_FILENAME`] file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Rules enabled in the whole project.
+    pub rules:                Rules,
+    /// Names of modules, besides `prelude`, `traits` and `super`, that can be imported with `*`.
+    pub allowed_star_imports: Vec<String>,
+    /// Configuration of crates, by their directories relative to the processed root path.
+    pub crates:               BTreeMap<PathBuf, CrateConfig>,
+}
+
+impl Config {
+    /// Path to the configuration file for the given root path.
+    pub fn path(root: impl AsRef<Path>) -> PathBuf {
+        root.as_ref().join(CONFIG_FILENAME)
+    }
+
+    /// Load the configuration for the given root path. Missing file yields the default one.
+    #[context("Failed to load the formatter configuration from '{}'.", root.as_ref().display())]
+    pub async fn load(root: impl AsRef<Path>) -> Result<Self> {
+        let path = Self::path(root);
+        if !path.exists() {
+            return Ok(default());
+        }
+        let config: Self = toml::from_str(&ide_ci::fs::tokio::read_to_string(&path).await?)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that the crates enable only known lints.
+    pub fn validate(&self) -> Result {
+        for (dir, config) in &self.crates {
+            for lint in &config.lints {
+                ensure!(
+                    OPTIONAL_LINTER_ATTRIBS.contains(&lint.as_str()),
+                    "Lint '{lint}' enabled for crate '{}' is not one of the optional lints: {}.",
+                    dir.display(),
+                    OPTIONAL_LINTER_ATTRIBS.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Configuration for the file with given path, relative to the processed root path.
+    pub fn for_file(&self, path: impl AsRef<Path>) -> FileConfig {
+        let path = path.as_ref();
+        // Crates can be nested, the innermost one applies.
+        let crate_config = self
+            .crates
+            .iter()
+            .filter(|(dir, _)| path.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count())
+            .map(|(_, config)| config);
+        FileConfig {
+            section_spacing:      self.rules.section_spacing,
+            allowed_star_imports: self
+                .rules
+                .star_imports
+                .then(|| self.allowed_star_imports.clone()),
+            lints:                crate_config.map(|c| c.lints.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Configuration applying to a single file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileConfig {
+    /// Whether the section spacing should be normalized.
+    pub section_spacing:      bool,
+    /// If star imports are checked, the additionally allowed module names.
+    pub allowed_star_imports: Option<Vec<String>>,
+    /// Additional linter configuration, if the file is a main or library main source file.
+    pub lints:                Vec<String>,
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_crate_configuration() -> Result {
+        let config: Config = toml::from_str(
+            r#"
+            allowed-star-imports = ["HeaderToken"]
+            [rules]
+            star-imports = true
+            [crates."build"]
+            lints = ["warn(missing_docs)"]
+            [crates."build/ci_utils"]
+            lints = ["warn(unused_qualifications)"]
+            "#,
+        )?;
+        config.validate()?;
+        assert!(!config.rules.section_spacing);
+
+        let file = config.for_file("build/ci_utils/src/lib.rs");
+        assert_eq!(file.allowed_star_imports, Some(vec!["HeaderToken".to_string()]));
+        assert_eq!(file.lints, vec!["warn(unused_qualifications)".to_string()]);
+        assert_eq!(config.for_file("build/cli/src/lib.rs").lints, vec![
+            "warn(missing_docs)".to_string()
+        ]);
+        assert!(config.for_file("lib/rust/parser/src/lib.rs").lints.is_empty());
+
+        let invalid: Config = toml::from_str("[crates.build]\nlints = [\"warn(no_such_lint)\"]")?;
+        assert!(invalid.validate().is_err());
+        Ok(())
+    }
+}
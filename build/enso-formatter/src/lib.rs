@@ -3,11 +3,14 @@
 //! - Sorting imports into groups (e.g. local imports, pub imports, etc.).
 //! - Sorting module attributes into groups.
 //! - Adding standard lint configuration to `lib.rs` and `main.rs` files.
-//! - (Currently disabled) Emitting warnings about star imports that are not ending with `traits::*`
-//!   nor `prelude::*`.
 //!
-//! Possible extensions, not implemented yet:
-//! - Sections are automatically keeping spacing.
+//! The following rules are disabled by default, and can be enabled in the
+//! [configuration file](config::CONFIG_FILENAME) of the project:
+//! - Keeping the spacing around sections and between top-level items, see [`spacing`].
+//! - Emitting warnings about star imports that are not ending with `traits::*` nor `prelude::*`,
+//!   see [`star_import`].
+//! - Adding lints from [`OPTIONAL_LINTER_ATTRIBS`] to `lib.rs` and `main.rs` files of the chosen
+//!   crates.
 
 // === Non-Standard Linter Configuration ===
 #![allow(missing_docs)]
@@ -31,11 +34,23 @@
 
 use ide_ci::prelude::*;
 
+use crate::config::Config;
+use crate::config::FileConfig;
+
 use ide_ci::fs::tokio as fs;
 use regex::Regex;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
-use tokio as _;
+
+
+// ==============
+// === Export ===
+// ==============
+
+pub mod config;
+pub mod source;
+pub mod spacing;
+pub mod star_import;
 
 
 
@@ -43,38 +58,16 @@ use tokio as _;
 // === Constants ===
 // =================
 
-// TODO: The below lints should be uncommented, one-by-one, and the existing code should be
-//       adjusted.
-
 /// Standard linter configuration. It will be used in every `main.rs` and `lib.rs` file in the
 /// codebase.
 const STD_LINTER_ATTRIBS: &[&str] = &[
     // Rustc lints that are allowed by default:
-    // "warn(absolute_paths_not_starting_with_crate)",
-    // "warn(elided_lifetimes_in_paths)",
-    // "warn(explicit_outlives_requirements)",
-    // "deny(keyword_idents)",
-    // "deny(macro_use_extern_crate)",
-    // "deny(missing_abi)",
     "warn(missing_copy_implementations)",
     "warn(missing_debug_implementations)",
-    // "warn(missing_docs)",
     "deny(non_ascii_idents)",
-    // "warn(noop_method_call)",
-    // "deny(pointer_structural_match)",
-    // "warn(single_use_lifetimes)",
-    // "warn(trivial_casts)",
     "warn(trivial_numeric_casts)",
     "warn(unsafe_code)",
-    // "deny(unsafe_op_in_unsafe_fn)",
-    // "warn(unused_crate_dependencies)",
-    // "warn(unused_extern_crates)",
     "warn(unused_import_braces)",
-    // "warn(unused_lifetimes)",
-    // "warn(unused_qualifications)",
-    // "warn(variant_size_differences)",
-    // Rustc lints that emit a warning by default:
-    // "deny(unconditional_recursion)",
     // The code triggering this lint is usually more readable than the suggested alternative.
     "allow(clippy::bool_to_int_with_if)",
     // This is allowed because in some cases, it allows way nicer formatting. For example, the
@@ -97,6 +90,34 @@ const STD_LINTER_ATTRIBS: &[&str] = &[
     "allow(clippy::let_and_return)",
 ];
 
+// TODO: The below lints should be moved to `STD_LINTER_ATTRIBS`, one-by-one, and the existing code
+//       should be adjusted.
+
+/// Linter configuration that is not standard yet. Crates can enable its entries in the
+/// [configuration file](config::CONFIG_FILENAME) of the project.
+pub const OPTIONAL_LINTER_ATTRIBS: &[&str] = &[
+    // Rustc lints that are allowed by default:
+    "warn(absolute_paths_not_starting_with_crate)",
+    "warn(elided_lifetimes_in_paths)",
+    "warn(explicit_outlives_requirements)",
+    "deny(keyword_idents)",
+    "deny(macro_use_extern_crate)",
+    "deny(missing_abi)",
+    "warn(missing_docs)",
+    "warn(noop_method_call)",
+    "deny(pointer_structural_match)",
+    "warn(single_use_lifetimes)",
+    "warn(trivial_casts)",
+    "deny(unsafe_op_in_unsafe_fn)",
+    "warn(unused_crate_dependencies)",
+    "warn(unused_extern_crates)",
+    "warn(unused_lifetimes)",
+    "warn(unused_qualifications)",
+    "warn(variant_size_differences)",
+    // Rustc lints that emit a warning by default:
+    "deny(unconditional_recursion)",
+];
+
 
 
 // =============
@@ -337,6 +358,159 @@ pub enum Action {
 
 
 
+// ===============
+// === Options ===
+// ===============
+
+/// Options controlling which files are processed and how.
+#[derive(clap::Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Process only the files changed relative to the given git revision, including the untracked
+    /// ones.
+    #[clap(long, value_name = "REVISION")]
+    pub since:    Option<String>,
+    /// Process also the files that did not change since they were last formatted.
+    #[clap(long)]
+    pub no_cache: bool,
+    /// Maximum number of files processed concurrently. Defaults to the available parallelism.
+    #[clap(long)]
+    pub jobs:     Option<usize>,
+    /// Write a JSON report listing the processed and changed files to the given path.
+    #[clap(long, value_name = "PATH")]
+    pub report:   Option<PathBuf>,
+}
+
+impl Options {
+    /// The number of files that may be processed concurrently.
+    pub fn jobs(&self) -> usize {
+        self.jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from))
+            .max(1)
+    }
+}
+
+
+
+// ==============
+// === Report ===
+// ==============
+
+/// Summary of a formatter run, meant to be consumed by editors and pre-commit hooks.
+///
+/// All paths are relative to the processed root path.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    /// Files that were processed by the formatter.
+    pub processed:   Vec<PathBuf>,
+    /// Number of files that were skipped, as they did not change since they were last formatted.
+    pub skipped:     usize,
+    /// Files whose contents were changed by the formatter.
+    pub changed:     Vec<PathBuf>,
+    /// Problems found in the processed files that cannot be fixed automatically.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A problem found in a source file that cannot be fixed automatically.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Path to the file.
+    pub path:    PathBuf,
+    /// Line number, starting from 1.
+    pub line:    usize,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.path.display(), self.line, self.message)
+    }
+}
+
+
+
+// =============
+// === Cache ===
+// =============
+
+/// Name of the cache file, placed in the `target` directory of the processed root path.
+pub const CACHE_FILENAME: &str = "enso-formatter-cache.json";
+
+/// Version of the formatting rules. It must be bumped whenever [`process_file_content`] output
+/// changes, so the files formatted with the previous rules are processed again.
+const CACHE_VERSION: u32 = 1;
+
+/// Files that affect the formatting result, besides the formatter itself.
+const CACHE_DEPENDENCIES: &[&str] =
+    &["rustfmt.toml", "rust-toolchain.toml", config::CONFIG_FILENAME];
+
+/// Hashes of the file contents as they were left by the last formatter run.
+///
+/// A file whose hash matches the cached one is already formatted and does not need to be processed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cache {
+    /// Hash of the formatter version and the configuration files the files were formatted with.
+    fingerprint: u64,
+    /// Hashes of the formatted files, by their paths relative to the processed root path.
+    files:       BTreeMap<PathBuf, u64>,
+}
+
+impl Cache {
+    /// Path to the cache file for the given root path.
+    pub fn path(root: impl AsRef<Path>) -> PathBuf {
+        root.as_ref().join("target").join(CACHE_FILENAME)
+    }
+
+    /// Compute the fingerprint of the current formatter version and configuration.
+    pub async fn fingerprint(root: impl AsRef<Path>) -> u64 {
+        let mut configs = Vec::new();
+        for name in CACHE_DEPENDENCIES {
+            configs.push(fs::read_to_string(root.as_ref().join(name)).await.ok());
+        }
+        calculate_hash(&(CACHE_VERSION, configs))
+    }
+
+    /// Load the cache for the given root path.
+    ///
+    /// If the cache is missing, cannot be read or was created with a different fingerprint, an
+    /// empty one is returned.
+    pub fn load(root: impl AsRef<Path>, fingerprint: u64) -> Self {
+        let path = Self::path(root);
+        match ide_ci::fs::read_json::<Self>(&path) {
+            Ok(cache) if cache.fingerprint == fingerprint => cache,
+            Ok(_) => {
+                debug!("Discarding outdated formatter cache {}.", path.display());
+                Self { fingerprint, ..default() }
+            }
+            Err(e) => {
+                debug!("Not using formatter cache: {e:?}");
+                Self { fingerprint, ..default() }
+            }
+        }
+    }
+
+    /// Store the cache for the given root path.
+    pub fn save(&self, root: impl AsRef<Path>) -> Result {
+        ide_ci::fs::write_json(Self::path(root), self)
+    }
+
+    /// Check whether the file was left with the given hash by the last formatter run.
+    pub fn is_formatted(&self, path: impl AsRef<Path>, hash: u64) -> bool {
+        self.files.get(path.as_ref()) == Some(&hash)
+    }
+
+    /// Record the hash of the formatted file.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, hash: u64) {
+        self.files.insert(path.into(), hash);
+    }
+
+    /// Drop entries of the files that are not among the given ones.
+    pub fn retain_files(&mut self, paths: &HashSet<&Path>) {
+        self.files.retain(|path, _| paths.contains(path.as_path()));
+    }
+}
+
+
+
 // ==================
 // === Processing ===
 // ==================
@@ -350,45 +524,153 @@ pub struct RustSourcePath {
     is_main: bool,
 }
 
+/// Maximum number of files passed to a single `rustfmt` invocation.
+const RUSTFMT_CHUNK_SIZE: usize = 64;
+
 /// Process all files of the given path recursively.
 ///
-/// Please note that the [`hash_map`] variable contains hashes of all files before processing. After
-/// the processing is done by this formatter, `rustfmt` is run on the whole codebase, and the hashes
-/// are compared with new files. This allows checking if running the formatting changed the files.
-/// An alternative design is possible – we could run this formatter and pass its output to stdin of
-/// `rustfmt`, run it in memory, and get the results without affecting files on the disk.
+/// See [`process_path_with`] for details.
+pub async fn process_path(path: impl AsRef<Path>, action: Action) -> Result {
+    process_path_with(path, action, &default()).await.map(drop)
+}
+
+/// Process files of the given path recursively.
+///
+/// The files are first processed by this formatter, and then `rustfmt` is run on them. Both steps
+/// run concurrently for multiple files. The hashes of the file contents are taken before
+/// processing and compared with the final ones, which allows checking if the formatting changed the
+/// files. An alternative design is possible – we could run this formatter and pass its output to
+/// stdin of `rustfmt`, run it in memory, and get the results without affecting files on the disk.
 /// Unfortunately, such solution requires either running a separate `rustfmt` process per file, or
 /// using its API. The former solution is very slow (16 seconds for the whole codebase), the second
 /// uses non-documented API and is slow as well (8 seconds for the whole codebase). It should be
 /// possible to improve the latter solution to get good performance, but it seems way harder than it
 /// should be.
+///
+/// Unless disabled in [`Options`], the final hashes are stored in the [`Cache`], and files that
+/// did not change since the last run are skipped. The cache is not used for [`Action::DryRun`].
+///
+/// The optional rules are applied according to the [configuration file](config::CONFIG_FILENAME)
+/// found in the root path.
 #[context("Enso Formatter: failed to process root path '{}'.", path.as_ref().display())]
-pub async fn process_path(path: impl AsRef<Path> + Copy, action: Action) -> Result {
-    let paths = discover_paths(path)?;
-    let total = paths.len();
-    let mut hash_map = HashMap::<PathBuf, u64>::new();
-    for (i, sub_path) in paths.iter().enumerate() {
-        let dbg_msg = if sub_path.is_main { " [main]" } else { "" };
-        info!("[{}/{}] Processing {}{}.", i + 1, total, sub_path.path.display(), dbg_msg);
-        let hash = process_file(&sub_path.path, action, sub_path.is_main).await?;
-        hash_map.insert((&sub_path.path).into(), hash);
+pub async fn process_path_with(
+    path: impl AsRef<Path>,
+    action: Action,
+    options: &Options,
+) -> Result<Report> {
+    let root = path.as_ref();
+    let mut paths = discover_paths(root)?;
+    if let Some(since) = &options.since {
+        let changed = changed_files(root, since).await?;
+        paths.retain(|sub_path| {
+            ide_ci::fs::canonicalize(&sub_path.path).is_ok_and(|path| changed.contains(&path))
+        });
     }
 
-    if action == Action::Format || action == Action::FormatAndCheck {
-        ide_ci::programs::cargo::fmt::format(&path).await?;
+    let config = Config::load(root).await?;
+    let writes = action != Action::DryRun;
+    let mut cache =
+        if writes { Cache::load(root, Cache::fingerprint(root).await) } else { default() };
+    let mut hashes = HashMap::<PathBuf, u64>::new();
+    let mut to_process = Vec::new();
+    for sub_path in paths.iter() {
+        let relative = relative_path(root, &sub_path.path);
+        let (hash, _) = read_file_with_hash(&sub_path.path).await?;
+        if options.no_cache || !cache.is_formatted(&relative, hash) {
+            to_process.push(sub_path.clone());
+        }
+        hashes.insert(relative, hash);
+    }
+    let skipped = paths.len() - to_process.len();
+    info!("Processing {} files, skipping {skipped} unchanged ones.", to_process.len());
+
+    // Dry run prints the formatted files, so they must not be processed concurrently.
+    let jobs = if writes { options.jobs() } else { 1 };
+    let total = to_process.len();
+    futures::stream::iter(to_process.iter().cloned().enumerate())
+        .map(|(i, sub_path)| {
+            let dbg_msg = if sub_path.is_main { " [main]" } else { "" };
+            info!("[{}/{}] Processing {}{}.", i + 1, total, sub_path.path.display(), dbg_msg);
+            let file_config = config.for_file(relative_path(root, &sub_path.path));
+            tokio::spawn(async move {
+                process_file(&sub_path.path, action, sub_path.is_main, &file_config).await
+            })
+        })
+        .buffer_unordered(jobs)
+        .map(|result| result?)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let files = to_process.iter().map(|sub_path| sub_path.path.clone()).collect_vec();
+    if writes {
+        futures::stream::iter(files.chunks(RUSTFMT_CHUNK_SIZE))
+            .map(|chunk| ide_ci::programs::cargo::fmt::format_files(root, chunk))
+            .buffer_unordered(jobs)
+            .try_collect::<Vec<_>>()
+            .await?;
     }
 
-    if action == Action::FormatAndCheck {
-        let mut changed = Vec::new();
-        for sub_path in &paths {
-            let (hash, _) = read_file_with_hash(&sub_path.path).await?;
-            if hash_map.get(&sub_path.path) != Some(&hash) {
-                changed.push(sub_path.path.clone());
+    let mut report = Report { skipped, ..default() };
+    for file in &files {
+        let relative = relative_path(root, file);
+        if writes {
+            let (hash, content) = read_file_with_hash(file).await?;
+            if hashes.get(&relative) != Some(&hash) {
+                report.changed.push(relative.clone());
+            }
+            let diagnostics = match &config.for_file(&relative).allowed_star_imports {
+                Some(allowed) => star_import::check(&relative, &content, allowed),
+                None => default(),
+            };
+            // Files with problems are not cached, so the problems are reported on every run.
+            if diagnostics.is_empty() {
+                cache.insert(relative.clone(), hash);
             }
+            report.diagnostics.extend(diagnostics);
         }
+        report.processed.push(relative);
+    }
+    if writes {
+        if options.since.is_none() {
+            cache.retain_files(&hashes.keys().map(PathBuf::as_path).collect());
+        }
+        cache.save(root)?;
+    }
+    if let Some(report_path) = &options.report {
+        ide_ci::fs::write_json(report_path, &report)?;
+    }
+    for diagnostic in &report.diagnostics {
+        warn!("{diagnostic}");
+    }
+
+    if action == Action::FormatAndCheck {
+        let changed = &report.changed;
         ensure!(changed.is_empty(), "{} files changed:\n{:#?}", changed.len(), changed);
+        let diagnostics = &report.diagnostics;
+        ensure!(
+            diagnostics.is_empty(),
+            "{} problems found:\n{}",
+            diagnostics.len(),
+            diagnostics.iter().join("\n")
+        );
     }
-    Ok(())
+    Ok(report)
+}
+
+/// Get the absolute paths of files changed relative to the given git revision, including the
+/// untracked ones.
+#[context("Failed to list files changed since '{since}'.")]
+pub async fn changed_files(root: impl AsRef<Path>, since: &str) -> Result<HashSet<PathBuf>> {
+    let git = ide_ci::programs::git::Context::new(root.as_ref()).await?;
+    let mut changed = git.diff_against(since).await?;
+    changed.extend(git.untracked_files().await?);
+    // Deleted files cannot be canonicalized, but they need not be formatted anyway.
+    Ok(changed.into_iter().filter_map(|path| ide_ci::fs::canonicalize(path).ok()).collect())
+}
+
+/// Path relative to the processed root path, as used in the [`Cache`] and the [`Report`].
+fn relative_path(root: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
 }
 
 /// Discover all paths containing Rust sources, recursively.
@@ -437,10 +719,11 @@ pub async fn process_file(
     path: impl AsRef<Path>,
     action: Action,
     is_main_file: bool,
+    config: &FileConfig,
 ) -> Result<u64> {
     let path = path.as_ref();
     let (hash, input) = read_file_with_hash(path).await?;
-    let out = process_file_content(input, is_main_file)?;
+    let out = process_file_content(input, is_main_file, config)?;
     if action == Action::DryRun {
         println!("{out}")
     } else if action == Action::Format || action == Action::FormatAndCheck {
@@ -450,7 +733,11 @@ pub async fn process_file(
 }
 
 /// Process a single source file.
-pub fn process_file_content(input: String, is_main_file: bool) -> Result<String> {
+pub fn process_file_content(
+    input: String,
+    is_main_file: bool,
+    config: &FileConfig,
+) -> Result<String> {
     let mut str_ptr: &str = &input;
     let mut attrs = vec![];
     let mut header = vec![];
@@ -505,19 +792,6 @@ pub fn process_file_content(input: String, is_main_file: bool) -> Result<String>
         );
     }
 
-    // Error if the star import is used for non prelude- or traits-like imports.
-    // TODO: This is commented for now because it requires several non-trivial changes in the code.
-    // let invalid_star_import = header.iter().any(|t| {
-    //     t.token == UseStar
-    //         && !t.reg_match.contains("prelude::*")
-    //         && !t.reg_match.contains("traits::*")
-    //         && !t.reg_match.contains("super::*")
-    // });
-    //
-    // if invalid_star_import {
-    //     Err("Star imports only allowed for `prelude`, `traits`, and `super`
-    // modules.".to_string())?; }
-
     // Build a mapping between tokens and registered entries.
     let mut map = HashMap::<HeaderToken, Vec<String>>::new();
     for elem in header {
@@ -546,6 +820,21 @@ pub fn process_file_content(input: String, is_main_file: bool) -> Result<String>
 
         let std_linter_attribs = STD_LINTER_ATTRIBS.iter().map(|t| format!("#![{t}]\n"));
         map.entry(StandardLinterConfig).or_default().extend(std_linter_attribs);
+
+        // Add the optional linter configuration enabled for the crate, unless already present.
+        for lint in &config.lints {
+            let token = if lint.starts_with("allow") {
+                ModuleAttribAllow
+            } else if lint.starts_with("deny") {
+                ModuleAttribDeny
+            } else {
+                ModuleAttribWarn
+            };
+            let vec = map.entry(token).or_default();
+            if !vec.iter().any(|t| t.contains(lint.as_str())) {
+                vec.push(format!("#![{lint}]\n"));
+            }
+        }
     }
 
     // Print the results.
@@ -587,7 +876,12 @@ pub fn process_file_content(input: String, is_main_file: bool) -> Result<String>
         PubUse,
     ]);
     out.push_str("\n\n");
-    out.push_str(&input[total_len..]);
+    let body = &input[total_len..];
+    if config.section_spacing {
+        out.push_str(&spacing::normalize(body));
+    } else {
+        out.push_str(body);
+    }
     Ok(out)
 }
 
@@ -663,7 +957,86 @@ pub use lib_f::item_1;
 
 pub struct Struct1 {}
 "#;
-        assert_eq!(process_file_content(input.into(), true)?, output);
+        assert_eq!(process_file_content(input.into(), true, &default())?, output);
+        Ok(())
+    }
+
+    #[test]
+    fn optional_lints() -> Result {
+        let input = "//! Docs.\n\n#![warn(missing_docs)]\n\nuse crate::prelude::*;\n";
+        let config = FileConfig {
+            lints: vec!["warn(missing_docs)".into(), "deny(keyword_idents)".into()],
+            ..default()
+        };
+        let output = r#"//! Docs.
+
+// === Non-Standard Linter Configuration ===
+#![deny(keyword_idents)]
+#![warn(missing_docs)]
+
+use crate::prelude::*;
+
+
+
+"#;
+        assert_eq!(process_file_content(input.into(), true, &config)?, output);
+        // Only the main and library main source files get the linter configuration.
+        assert!(!process_file_content(input.into(), false, &config)?.contains("keyword_idents"));
+        Ok(())
+    }
+
+    #[test]
+    fn cache_round_trip() -> Result {
+        let root = tempfile::tempdir()?;
+        let mut cache = Cache::load(root.path(), 1);
+        assert!(!cache.is_formatted("src/lib.rs", 42));
+        cache.insert("src/lib.rs", 42);
+        cache.insert("src/removed.rs", 7);
+        cache.retain_files(&[Path::new("src/lib.rs")].into_iter().collect());
+        cache.save(root.path())?;
+
+        let cache = Cache::load(root.path(), 1);
+        assert!(cache.is_formatted("src/lib.rs", 42));
+        assert!(!cache.is_formatted("src/lib.rs", 43));
+        assert!(!cache.is_formatted("src/removed.rs", 7));
+        // A different formatter version or configuration invalidates the cache.
+        assert!(!Cache::load(root.path(), 2).is_formatted("src/lib.rs", 42));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn processing_path() -> Result {
+        let temp = tempfile::tempdir()?;
+        let root = ide_ci::fs::canonicalize(temp.path())?;
+        let lib = PathBuf::from_iter(["src", "lib.rs"]);
+        let child = PathBuf::from_iter(["src", "child.rs"]);
+        // The child module is not formatted, so any `rustfmt` run over it would be noticed.
+        let child_content = "fn  child(){}\n";
+        ide_ci::fs::write(root.join(&lib), "mod child;\n")?;
+        ide_ci::fs::write(root.join(&child), child_content)?;
+        let git = ide_ci::programs::Git.init(&root).await?;
+        git.cmd()?.args(["add", "--all"]).run_ok().await?;
+        let author = ["-c", "user.name=Enso", "-c", "user.email=contact@enso.org"];
+        git.cmd()?.args(author).args(["commit", "--message", "Initial commit."]).run_ok().await?;
+
+        // Only the changed file is processed, its child module is left intact.
+        ide_ci::fs::write(root.join(&lib), "mod child;\nfn  lib(){}\n")?;
+        let since = Options { since: Some("HEAD".into()), ..default() };
+        let report = process_path_with(&root, Action::Format, &since).await?;
+        assert_eq!(report.processed, [lib.clone()]);
+        assert_eq!(report.changed, [lib.clone()]);
+        assert_eq!(report.skipped, 0);
+        assert_eq!(ide_ci::fs::read_to_string(root.join(&child))?, child_content);
+
+        // The formatted file is cached, so it is skipped until it changes again.
+        let report = process_path_with(&root, Action::Format, &since).await?;
+        assert!(report.processed.is_empty());
+        assert_eq!(report.skipped, 1);
+        let report = process_path_with(&root, Action::Format, &default()).await?;
+        assert_eq!(report.processed, [child.clone()]);
+        assert_eq!(report.changed, [child.clone()]);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(ide_ci::fs::read_to_string(root.join(&child))?, "fn child() {}\n");
         Ok(())
     }
 }
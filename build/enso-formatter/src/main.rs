@@ -1,10 +1,26 @@
 use ide_ci::prelude::*;
 
+use clap::Parser;
+use enso_formatter::Action;
+use enso_formatter::Options;
 
 
+
+/// Apply the Enso-specific formatting rules and `rustfmt` to the Rust sources.
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Fail if any file is not formatted, instead of just formatting it.
+    #[clap(long)]
+    check:   bool,
+    #[clap(flatten)]
+    options: Options,
+}
+
 #[tokio::main]
 async fn main() -> Result {
     setup_logging()?;
+    let cli = Cli::parse();
     info!("Enso Formatter running in {}", ide_ci::env::current_dir()?.display());
-    enso_formatter::process_path(".", enso_formatter::Action::Format).await
+    let action = if cli.check { Action::FormatAndCheck } else { Action::Format };
+    enso_formatter::process_path_with(".", action, &cli.options).await.map(drop)
 }
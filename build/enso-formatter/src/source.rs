@@ -0,0 +1,135 @@
+//! Line-level analysis of Rust sources, used by the rules that do not need a full parser.
+
+use ide_ci::prelude::*;
+
+
+
+/// State of the scanner at a given position of the source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Code,
+    String,
+    /// Raw string literal with the given number of `#` characters.
+    RawString(usize),
+    /// Block comment with the given nesting depth.
+    BlockComment(usize),
+}
+
+/// What a line of the source starts with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineStart {
+    Code,
+    /// The line is a continuation of a multi-line string literal.
+    Literal,
+    /// The line is a continuation of a block comment.
+    Comment,
+}
+
+/// Check for each line of the source whether it starts inside a string literal or a block comment.
+///
+/// The lines are separated by `\n`, so the result has one more element than there are `\n`
+/// characters in the source.
+pub fn line_starts(code: &str) -> Vec<LineStart> {
+    let chars = code.chars().collect_vec();
+    let at = |i: usize| chars.get(i).copied();
+    let is_ident = |c: Option<char>| c.map_or(false, |c| c.is_alphanumeric() || c == '_');
+    let mut state = State::Code;
+    let mut result = vec![LineStart::Code];
+    let mut i = 0;
+    while let Some(c) = at(i) {
+        let before = |n: usize| i.checked_sub(n).and_then(at);
+        match state {
+            State::Code => match c {
+                '/' if at(i + 1) == Some('/') => {
+                    while at(i).map_or(false, |c| c != '\n') {
+                        i += 1;
+                    }
+                    continue;
+                }
+                '/' if at(i + 1) == Some('*') => {
+                    state = State::BlockComment(1);
+                    i += 2;
+                    continue;
+                }
+                '"' => state = State::String,
+                'r' if !is_ident(before(1)) || (before(1) == Some('b') && !is_ident(before(2))) => {
+                    let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                    if at(i + 1 + hashes) == Some('"') {
+                        state = State::RawString(hashes);
+                        i += hashes + 2;
+                        continue;
+                    }
+                }
+                '\'' if at(i + 1) == Some('\\') => {
+                    // Escaped character literal, like `'\n'` or `'\u{7FFF}'`.
+                    i += 3;
+                    while at(i).map_or(false, |c| c != '\'') {
+                        i += 1;
+                    }
+                }
+                '\'' if at(i + 2) == Some('\'') => i += 2,
+                _ => {}
+            },
+            State::String => match c {
+                '\\' if at(i + 1) != Some('\n') => {
+                    i += 2;
+                    continue;
+                }
+                '"' => state = State::Code,
+                _ => {}
+            },
+            State::RawString(hashes) =>
+                if c == '"'
+                    && chars[i + 1..].iter().take(hashes).filter(|c| **c == '#').count() == hashes
+                {
+                    state = State::Code;
+                    i += hashes;
+                },
+            State::BlockComment(depth) =>
+                if c == '/' && at(i + 1) == Some('*') {
+                    state = State::BlockComment(depth + 1);
+                    i += 1;
+                } else if c == '*' && at(i + 1) == Some('/') {
+                    state = if depth == 1 { State::Code } else { State::BlockComment(depth - 1) };
+                    i += 1;
+                },
+        }
+        if c == '\n' {
+            result.push(match state {
+                State::Code => LineStart::Code,
+                State::String | State::RawString(_) => LineStart::Literal,
+                State::BlockComment(_) => LineStart::Comment,
+            });
+        }
+        i += 1;
+    }
+    result
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literals() {
+        let code = r####"let a = "one
+two";
+let b = r#"three "quoted"
+four"#;
+/* five /* nested */
+six */ let c = '"';
+let d: &'static str = "\"";
+seven
+"####;
+        use LineStart::*;
+        assert_eq!(line_starts(code), vec![
+            Code, Literal, Code, Literal, Code, Comment, Code, Code, Code
+        ]);
+    }
+}
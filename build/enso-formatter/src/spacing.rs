@@ -0,0 +1,224 @@
+//! Normalization of the vertical spacing of top-level code, as described in the Rust style guide:
+//! - 3 blank lines before each section.
+//! - 2 blank lines before each sub-section.
+//! - 1 blank line after each section / sub-section.
+//! - 1 blank line before functions / structures / impls.
+//!
+//! The spacing overlaps, if multiple rules apply, the maximum of the spacings is used. Spacing that
+//! no rule applies to is left intact.
+
+use ide_ci::prelude::*;
+
+use crate::source::line_starts;
+use crate::source::LineStart;
+
+use regex::Regex;
+
+
+
+// ============
+// === Line ===
+// ============
+
+lazy_static! {
+    static ref SECTION_BORDER: Regex = Regex::new(r"^// =+\s*$").unwrap();
+    static ref SECTION_TITLE: Regex = Regex::new(r"^// === .* ===\s*$").unwrap();
+    static ref ITEM: Regex = Regex::new(
+        r#"^(pub(\([^)]*\))? +)?((const|async|unsafe|default|extern( +"[^"]*")?) +)*(fn|struct|enum|union|trait|impl|macro_rules!)\b"#
+    )
+    .unwrap();
+    static ref MODULE: Regex = Regex::new(r"^(pub(\([^)]*\))? +)?mod +\w+ *\{").unwrap();
+}
+
+/// Kind of a source line, as far as spacing is concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Line {
+    Blank,
+    /// The top border of a section banner.
+    SectionStart,
+    /// The title line of a section banner.
+    SectionTitle,
+    /// The bottom border of a section banner.
+    SectionEnd,
+    SubSection,
+    /// A top-level comment or attribute, which belongs to the item below it.
+    Lead,
+    /// The first line of a top-level function, structure, impl, etc.
+    Item,
+    /// Any other line, including the nested ones and the ones inside literals.
+    Other,
+}
+
+impl Line {
+    /// Classify each of the lines.
+    fn classify(lines: &[&str], starts: &[LineStart]) -> Vec<Self> {
+        let code = |i: usize| lines.get(i).copied().filter(|_| starts[i] == LineStart::Code);
+        let matches = |i: usize, regex: &Regex| code(i).map_or(false, |line| regex.is_match(line));
+        let mut kinds = Vec::with_capacity(lines.len());
+        let mut i = 0;
+        while i < lines.len() {
+            let Some(line) = code(i) else {
+                // Block comments started by a top-level line belong to the item below them.
+                let in_comment = starts[i] == LineStart::Comment;
+                let top_level = kinds.last() == Some(&Line::Lead);
+                kinds.push(if in_comment && top_level { Line::Lead } else { Line::Other });
+                i += 1;
+                continue;
+            };
+            if matches(i, &SECTION_BORDER)
+                && matches(i + 1, &SECTION_TITLE)
+                && matches(i + 2, &SECTION_BORDER)
+            {
+                kinds.extend([Line::SectionStart, Line::SectionTitle, Line::SectionEnd]);
+                i += 3;
+                continue;
+            }
+            let kind = if line.trim().is_empty() {
+                Line::Blank
+            } else if SECTION_TITLE.is_match(line) {
+                Line::SubSection
+            } else if line.starts_with("//") || line.starts_with("/*") || line.starts_with("#[") {
+                Line::Lead
+            } else if ITEM.is_match(line) || MODULE.is_match(line) {
+                Line::Item
+            } else {
+                Line::Other
+            };
+            kinds.push(kind);
+            i += 1;
+        }
+        kinds
+    }
+
+    /// Number of blank lines required after this line.
+    fn space_after(self) -> usize {
+        match self {
+            Line::SectionEnd | Line::SubSection => 1,
+            _ => 0,
+        }
+    }
+
+    /// Number of blank lines required before this line, given the previous non-blank line.
+    fn space_before(self, previous: Line) -> usize {
+        match self {
+            Line::SectionStart => 3,
+            Line::SubSection => 2,
+            // Comments and attributes are kept together with the item they describe.
+            Line::Lead | Line::Item if previous != Line::Lead => 1,
+            _ => 0,
+        }
+    }
+}
+
+
+
+// =================
+// === Normalize ===
+// =================
+
+/// Normalize the number of blank lines around the section banners and between the top-level items.
+///
+/// The spacing at the beginning and at the end of the code is left intact.
+pub fn normalize(code: &str) -> String {
+    let lines = code.split('\n').collect_vec();
+    let kinds = Line::classify(&lines, &line_starts(code));
+    let mut out = Vec::with_capacity(lines.len());
+    let mut blanks = Vec::new();
+    let mut previous = None;
+    for (line, kind) in lines.into_iter().zip(kinds) {
+        if kind == Line::Blank {
+            blanks.push(line);
+            continue;
+        }
+        let required = previous
+            .map_or(0, |previous: Line| previous.space_after().max(kind.space_before(previous)));
+        if required > 0 {
+            out.extend(std::iter::repeat("").take(required));
+            blanks.clear();
+        } else {
+            out.append(&mut blanks);
+        }
+        out.push(line);
+        previous = Some(kind);
+    }
+    out.append(&mut blanks);
+    out.join("\n")
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spacing() {
+        let input = r#"const A: usize = 1;
+const B: usize = 2;
+// =============
+// === Items ===
+// =============
+
+
+/// A structure.
+#[derive(Debug)]
+pub struct Item;
+impl Item {
+    fn nested() {}
+
+
+    fn kept() {}
+}
+/* A block comment
+ * describing the function.
+ */
+pub(crate) fn text() -> &'static str {
+    "
+fn inside_literal() {}
+"
+}
+// === Helpers ===
+fn helper() {}
+"#;
+        let output = r#"const A: usize = 1;
+const B: usize = 2;
+
+
+
+// =============
+// === Items ===
+// =============
+
+/// A structure.
+#[derive(Debug)]
+pub struct Item;
+
+impl Item {
+    fn nested() {}
+
+
+    fn kept() {}
+}
+
+/* A block comment
+ * describing the function.
+ */
+pub(crate) fn text() -> &'static str {
+    "
+fn inside_literal() {}
+"
+}
+
+
+// === Helpers ===
+
+fn helper() {}
+"#;
+        assert_eq!(normalize(input), output);
+        assert_eq!(normalize(output), output);
+    }
+}
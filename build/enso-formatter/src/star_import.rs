@@ -0,0 +1,84 @@
+//! Checking that star imports are used only for prelude- or traits-like modules.
+
+use ide_ci::prelude::*;
+
+use crate::source::line_starts;
+use crate::source::LineStart;
+use crate::Diagnostic;
+
+use regex::Regex;
+
+
+
+/// Modules whose contents can always be imported with `*`.
+pub const ALLOWED_MODULES: &[&str] = &["prelude", "traits", "super"];
+
+lazy_static! {
+    static ref STAR_IMPORT: Regex =
+        Regex::new(r"^\s*(pub(\([^)]*\))?\s+)?use\s+((\w+\s*::\s*)*(\w+))\s*::\s*\*\s*;").unwrap();
+}
+
+/// Report the star imports of modules other than [`ALLOWED_MODULES`] and the `allowed` ones.
+///
+/// The `path` is only used to describe the location of the diagnostics.
+pub fn check(path: impl AsRef<Path>, code: &str, allowed: &[String]) -> Vec<Diagnostic> {
+    let is_allowed = |module: &str| {
+        ALLOWED_MODULES.contains(&module) || allowed.iter().any(|allowed| allowed == module)
+    };
+    let starts = line_starts(code);
+    code.split('\n')
+        .enumerate()
+        .filter(|(index, _)| starts[*index] == LineStart::Code)
+        .filter_map(|(index, line)| {
+            let captures = STAR_IMPORT.captures(line)?;
+            let module = captures.get(5)?.as_str();
+            (!is_allowed(module)).then(|| Diagnostic {
+                path:    path.as_ref().to_path_buf(),
+                line:    index + 1,
+                message: format!(
+                    "Star import of `{}` is not allowed, only `prelude`, `traits` and `super` \
+                    modules can be imported with `*`.",
+                    captures[3].split_whitespace().collect::<String>()
+                ),
+            })
+        })
+        .collect()
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_imports() {
+        let code = r#"use crate::prelude::*;
+use logger::traits::*;
+use crate::model::*;
+pub use Token::*;
+
+mod tests {
+    use super::*;
+    const CODE: &str = "
+use crate::inside_literal::*;
+";
+}
+"#;
+        let lines = |allowed: &[String]| {
+            check("lib.rs", code, allowed).into_iter().map(|d| d.line).collect_vec()
+        };
+        assert_eq!(lines(&[]), vec![3, 4]);
+        assert_eq!(lines(&["Token".into()]), vec![3]);
+        let diagnostic = &check("lib.rs", code, &[])[0];
+        assert_eq!(
+            diagnostic.to_string(),
+            "lib.rs:3: Star import of `crate::model` is not allowed, only `prelude`, `traits` and \
+            `super` modules can be imported with `*`."
+        );
+    }
+}
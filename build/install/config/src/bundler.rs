@@ -1,4 +1,4 @@
-//! Utilities for building the Windows installer/uninstaller for the Enso application.
+//! Utilities for building the installer/uninstaller for the Enso application, on Windows and Linux.
 //!
 //! See the [`bundle`] function as the main entry point.
 
@@ -13,7 +13,7 @@ use ide_ci::programs::Cargo;
 
 
 
-/// Input necessary to generate a Windows installer from unpacked Electron application bundle.
+/// Input necessary to generate an installer from unpacked Electron application bundle.
 #[derive(Debug)]
 pub struct Config {
     /// File to the JSON file containing the Electron Builder configuration.
@@ -30,7 +30,7 @@ pub struct Config {
     pub intermediate_dir:         PathBuf,
     /// Certificate used to sign the installer and uninstaller.
     ///
-    /// If `None`, the installer and uninstaller will not be signed.
+    /// If `None`, the installer and uninstaller will not be signed. Only used on Windows.
     pub certificate:              Option<WindowsSigningCredentials>,
 }
 
@@ -31,6 +31,10 @@ fn main() {
         embed_resource::compile(&rc_file, embed_resource::NONE);
     } else {
         println!("cargo:warning={ENSO_INSTALL_ARCHIVE_PATH} is not set, the installer will fail at runtime.");
+        // On Linux the archive is included directly, so it needs to point to some file.
+        let placeholder_path = OUT_DIR.get().unwrap().join("payload.tar.gz");
+        ide_ci::fs::write_if_different(&placeholder_path, b"").unwrap();
+        cargo::build::expose_env_var(ENSO_INSTALL_ARCHIVE_PATH, placeholder_path.as_str());
     }
 
     cargo::build::rerun_if_env_changed(ENSO_INSTALL_METADATA_PATH);
@@ -1,14 +1,17 @@
-//! This crate implements the Windows installer for the Enso IDE.
+//! This crate implements the Windows and Linux installers for the Enso IDE.
 
 use enso_install::prelude::*;
 
 use enso_install::access_built_time_env;
+use flate2::read::GzDecoder;
 
 
 // ==============
 // === Export ===
 // ==============
 
+#[cfg(target_os = "linux")]
+pub mod linux;
 #[cfg(windows)]
 pub mod win;
 
@@ -51,3 +54,102 @@ pub fn access_payload_metadata() -> &'static enso_install_config::payload::Metad
         "payload metadata"
     )
 }
+
+/// Extract the payload archive into the installation directory.
+///
+/// The `report_progress` callback receives the extraction progress as a number between 0 and 1.
+/// Returns the total size of the extracted files in bytes.
+pub fn extract_payload(
+    payload: Payload,
+    install_location: &Path,
+    mut report_progress: impl FnMut(f64),
+) -> Result<u64> {
+    let enso_install_config::payload::Metadata { total_files, total_bytes } = *payload.metadata;
+    let decoder = GzDecoder::new(payload.data);
+    let archive = tar::Archive::new(decoder);
+
+    let mut files_extracted = 0;
+    let mut bytes_extracted = 0;
+    let mut bytes_being_extracted = 0;
+    let to_our_path = |entry: &tar::Entry<GzDecoder<&[u8]>>| -> Option<PathBuf> {
+        // If we receive a new file, update the counters.
+        files_extracted += 1;
+        bytes_extracted += bytes_being_extracted;
+        bytes_being_extracted = entry.header().size().unwrap_or(0);
+
+        let files_ratio = (files_extracted as f64 / total_files as f64).min(1.0);
+        let bytes_ratio = (bytes_extracted as f64 / total_bytes as f64).min(1.0);
+        let extraction_progresss = (files_ratio + bytes_ratio) / 2.0;
+        trace!("files_extracted: {files_extracted}/{total_files}, bytes_extracted: {bytes_extracted}/{total_bytes}, extraction_progresss: {extraction_progresss}");
+        report_progress(extraction_progresss);
+        Some(install_location.join(entry.path().ok()?))
+    };
+
+    ide_ci::archive::tar::extract_files_sync(archive, to_our_path)?;
+    // As we've been incrementing this values when extracting the next file, we need to cover the
+    // last file.
+    bytes_extracted += bytes_being_extracted;
+    Ok(bytes_extracted)
+}
+
+/// Check if there is enough disk space to install the application.
+///
+/// If the space is insufficient, returns an error message. If the space is sufficient, returns
+/// `None`. If the necessary information cannot be obtained, returns an error.
+///
+/// Note that usually it is better to ignore the error than to fail the installation process. Not
+/// knowing that the disk space is sufficient is not meaning that it is insufficient.
+/// For example, we might be targetting a network path for which we cannot obtain the disk space.
+pub fn check_disk_space(
+    installation_directory: &Path,
+    bytes_required: u64,
+) -> Result<Option<String>> {
+    use sysinfo::Disks;
+    let disks = Disks::new_with_refreshed_list();
+    // This should yield an absolute path, prefixed with the drive label.
+    let path = installation_directory
+        .canonicalize()
+        // We use absolutize as a fallback, because canonicalize fails for non-existent paths. We
+        // attempt to use canonicalize first, because it resolves symlinks.
+        .or_else(|_| installation_directory.absolutize().map(PathBuf::from))?;
+
+    // We need to remove the verbatim prefix (that canonicalize likes to add) in order to match the
+    // disk list mount points format.
+    let path = path.without_verbatim_prefix();
+    let disk = disks
+        .into_iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        // On Linux the mount points are nested (e.g. `/` and `/home`), so we need the most
+        // specific one.
+        .max_by_key(|disk| disk.mount_point().components().count())
+        .context("No disk information found for the installation directory.")?;
+
+    let required_space = bytesize::ByteSize(bytes_required);
+    let free_space = bytesize::ByteSize(disk.available_space());
+
+    if free_space < required_space {
+        let msg = format!(
+            "Not enough disk space on {} to install. Required: {:.2}, available: {:.2}.",
+            disk.mount_point().display(),
+            required_space,
+            free_space
+        );
+        return Ok(Some(msg));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    /// Test to manually check the generated disk space message.
+    fn check_disk_space_test() -> Result {
+        let my_path = ide_ci::env::current_dir()?;
+        let r = check_disk_space(&my_path, 10_000_000_000_000);
+        let _ = dbg!(r);
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use ide_ci::prelude::*;
+
+use crate::access_payload_metadata;
+use crate::linux::config::Config;
+use crate::linux::logic::install_with_updates;
+use crate::InstallerUpdate;
+use crate::Payload;
+
+use enso_install::linux::Layout;
+
+
+// ==============
+// === Export ===
+// ==============
+
+pub mod config;
+pub mod logic;
+
+
+
+/// The compiled-in payload archive.
+///
+/// Unlike on Windows, where the archive is embedded as a resource, we include the bytes directly.
+static PAYLOAD_DATA: &[u8] = include_bytes!(env!("ENSO_INSTALL_ARCHIVE_PATH"));
+
+/// Retrieve the compiled-in installer payload with metadata.
+pub fn access_payload() -> Result<Payload> {
+    ensure!(
+        !PAYLOAD_DATA.is_empty(),
+        "The installer was built without the payload. The `ENSO_INSTALL_ARCHIVE_PATH` environment \
+        variable was not set during the build."
+    );
+    Ok(Payload { data: PAYLOAD_DATA, metadata: access_payload_metadata() })
+}
+
+/// Spawn a thread that will install the Enso IDE in the given layout.
+pub fn spawn_installer_thread(
+    layout: Layout,
+    payload: Payload,
+    config: Config,
+) -> Result<(std::thread::JoinHandle<Result>, std::sync::mpsc::Receiver<InstallerUpdate>)> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let handle = std::thread::Builder::new()
+        .name("Installer Logic".into())
+        .spawn(move || {
+            let result = install_with_updates(&layout, payload, &config, &sender);
+            if let Err(err) = result {
+                let msg = format!("Installation failed: {err:?}.");
+                let _ = sender.send(InstallerUpdate::Finished(Result::Err(err)));
+                bail!(msg);
+            }
+            Ok(())
+        })
+        .context("Failed to spawn the installer logic thread.")?;
+    Ok((handle, receiver))
+}
+
+/// The installer's entry point.
+///
+/// The installation is non-interactive: the progress is reported to the standard error output and
+/// to the log file.
+pub fn main() -> Result {
+    let logfile = enso_install::setup_logging(env!("CARGO_PKG_NAME"))?;
+    info!("Logging to: {}", logfile.display());
+    let config = config::fill_config()?;
+    let layout = Layout::for_current_user()?;
+    let payload = access_payload()?;
+    let install_dir = layout.install_dir(&config.app_id);
+    info!("Installing {} {} to {}.", config.pretty_name, config.version, install_dir.display());
+
+    let (handle, receiver) = spawn_installer_thread(layout, payload, config)?;
+    for update in receiver {
+        match update {
+            InstallerUpdate::Stage(stage) => info!("{stage}"),
+            InstallerUpdate::Progress(_) => {}
+            InstallerUpdate::Finished(Ok(())) => info!("Installation finished."),
+            InstallerUpdate::Finished(Err(err)) => error!(
+                "Installation failed: {err}. See the log file for details: {}.",
+                logfile.display()
+            ),
+        }
+    }
+    handle.join().map_err(|_| anyhow!("The installer logic thread panicked."))?
+}
@@ -0,0 +1,81 @@
+//! Information defining the installer's behavior.
+
+use crate::prelude::*;
+
+use enso_install::linux::mime::FileType;
+
+
+
+/// All the configuration and constants needed to build the installer.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// E.g. `org.enso`.
+    ///
+    /// Used to name the installation directory, the desktop entry and the MIME package.
+    pub app_id: String,
+
+    /// E.g. `Enso`.
+    pub executable_filename: PathBuf,
+
+    /// E.g. `New Byte Order sp. z o.o.`.
+    pub publisher: String,
+
+    /// E.g. `Enso`.
+    pub pretty_name: String,
+
+    /// E.g. `Enso`.
+    ///
+    /// Used as the name of the desktop entry.
+    pub shortcut_name: String,
+
+    /// Version of the application.
+    pub version: Version,
+
+    /// The URL protocols that will be registered for the application, e.g. `enso`.
+    pub url_protocols: Vec<String>,
+
+    /// The menu categories of the desktop entry, e.g. `Development`.
+    pub categories: Vec<String>,
+
+    /// File associations.
+    pub file_associations: Vec<FileType>,
+}
+
+/// Generate the `Config` for the installer from the compiled-in Electron Builder configuration.
+pub fn fill_config() -> Result<Config> {
+    let electron = enso_install::sanitized_electron_builder_config();
+
+    let app_id = electron.app_id.clone();
+    let executable_filename = enso_install::executable_filename();
+    let publisher = electron.extra_metadata.installer.publisher.clone();
+    let pretty_name = electron.product_name.clone();
+    let shortcut_name = enso_install::shortcut_name().to_owned();
+    let version = electron.extra_metadata.version.clone();
+    let url_protocols = electron.protocols.iter().flat_map(|p| &p.schemes).map_into().collect();
+    let categories = vec!["Development".to_owned()];
+    let file_associations = electron
+        .extra_metadata
+        .installer
+        .file_associations
+        .iter()
+        .map(|file_association| {
+            FileType::from_prog_id(
+                &file_association.prog_id,
+                &file_association.ext,
+                &file_association.mime_type,
+                &file_association.name,
+            )
+        })
+        .collect();
+    Ok(Config {
+        app_id,
+        executable_filename,
+        publisher,
+        pretty_name,
+        shortcut_name,
+        version,
+        url_protocols,
+        categories,
+        file_associations,
+    })
+}
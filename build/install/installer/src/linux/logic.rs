@@ -0,0 +1,255 @@
+//! Code that performs the installation.
+
+use crate::prelude::*;
+
+use crate::check_disk_space;
+use crate::extract_payload;
+use crate::linux::config::Config;
+use crate::Payload;
+
+use enso_install::linux::desktop_entry;
+use enso_install::linux::desktop_entry::DesktopEntry;
+use enso_install::linux::mime;
+use enso_install::linux::uninstall::UninstallInfo;
+use enso_install::linux::Layout;
+use enso_install_config::UNINSTALLER_NAME;
+
+
+
+/// Generate the desktop entry for the installed application.
+///
+/// The entry declares the file types and URL protocols handled by the application and offers an
+/// action running the uninstaller.
+pub fn desktop_entry(config: &Config, install_location: &Path) -> DesktopEntry {
+    let mut entry = DesktopEntry::new(
+        &config.shortcut_name,
+        install_location.join(&config.executable_filename),
+    );
+    entry.mime_types = config
+        .file_associations
+        .iter()
+        .map(|file_type| file_type.mime_type.clone())
+        .chain(config.url_protocols.iter().map(|protocol| format!("x-scheme-handler/{protocol}")))
+        .collect();
+    entry.categories = config.categories.clone();
+    entry.actions.push(desktop_entry::Action {
+        id:   "uninstall".into(),
+        name: format!("Uninstall {}", config.pretty_name),
+        exec: install_location.join(UNINSTALLER_NAME),
+    });
+    entry
+}
+
+/// Install Enso.
+///
+/// The archive payload is binary data of the tar.gz archive that contains the Enso app. If there is
+/// already an installation in the target location, it is replaced.
+pub fn install_with_updates(
+    layout: &Layout,
+    payload: Payload,
+    config: &Config,
+    sender: &std::sync::mpsc::Sender<crate::InstallerUpdate>,
+) -> Result {
+    let send = |update| {
+        info!("Sending update: {update:?}");
+        let _ = sender.send(update);
+    };
+    let report_progress = |progress| {
+        send(crate::InstallerUpdate::Progress(progress));
+    };
+    macro_rules! stage_at {
+        ($progress:tt, $($arg:tt)*) => {
+            send(crate::InstallerUpdate::Stage(format!($($arg)*)));
+            send(crate::InstallerUpdate::Progress($progress));
+        };
+    }
+    macro_rules! bail {
+        ($($arg:tt)*) => {{
+            let msg = format!($($arg)*);
+            let err = anyhow::Error::msg(msg.clone());
+            send(crate::InstallerUpdate::Finished(Err(err)));
+            anyhow::bail!("{msg}");
+        }};
+    }
+
+    // Only one installer / uninstaller can run at a time.
+    let _guard = enso_install::locked_installation_lock()?;
+
+    let install_location = layout.install_dir(&config.app_id);
+    let install_location = install_location.as_path();
+
+    let enso_install_config::payload::Metadata { total_files, total_bytes } = *payload.metadata;
+    stage_at!(0.00, "Checking disk space.");
+    let per_file_overhead = 4096; // The default block size on ext4.
+    let space_required = total_bytes + (total_files * per_file_overhead);
+    match check_disk_space(install_location, space_required) {
+        Ok(Some(msg)) => bail!("{msg}"),
+        Ok(None) => {} // Ok, enough space.
+        Err(err) => {
+            // We don't know, so let's just log the warning and try to carry on.
+            warn!("Failed to check disk space: {err:?}");
+        }
+    }
+
+    let previous = if install_location.exists() {
+        stage_at!(0.01, "Checking for running processes.");
+        match enso_install::is_already_running(install_location, &[]) {
+            Ok(Some(msg)) => bail!("{msg}"),
+            Ok(None) => {} // Ok, no colliding processes.
+            Err(err) => {
+                // We don't know, so let's just log the warning and try to carry on.
+                warn!("Failed to check for running processes: {err:?}");
+            }
+        }
+        UninstallInfo::read(install_location).unwrap_or_else(|err| {
+            warn!("Failed to read the previous installation information: {err:?}");
+            None
+        })
+    } else {
+        None
+    };
+
+    if let Some(previous) = &previous {
+        stage_at!(0.02, "Upgrading from version {}.", previous.display_version);
+        // The integration files will be recreated below. We remove the old ones first, as the
+        // previous version might have registered files that the new one does not.
+        if let Err(err) = previous.remove_integration_files() {
+            warn!("Failed to remove the previous integration files: {err:?}");
+        }
+    }
+
+    stage_at!(0.03, "Removing old installation files (if present).");
+    ide_ci::fs::reset_dir(install_location)?;
+
+    let extraction_progress_start = 0.06;
+    let extraction_progress_step = 0.82;
+    stage_at!(extraction_progress_start, "Extracting files.");
+    let bytes_extracted = extract_payload(payload, install_location, |progress| {
+        report_progress(extraction_progress_start + extraction_progress_step * progress);
+    })?;
+
+    let post_extraction_progress = extraction_progress_start + extraction_progress_step;
+
+    stage_at!(post_extraction_progress, "Registering file types.");
+    let mime_package = layout.mime_package_path(&config.app_id);
+    mime::write_package(&mime_package, &config.file_associations)?;
+
+    stage_at!(0.92, "Creating the application menu entry.");
+    let desktop_entry_path = layout.desktop_entry_path(&config.app_id);
+    desktop_entry(config, install_location).write(&desktop_entry_path)?;
+
+    stage_at!(0.94, "Registering the uninstaller.");
+    let uninstall_info = UninstallInfo {
+        display_name:       config.pretty_name.clone(),
+        display_version:    config.version.clone(),
+        publisher:          config.publisher.clone(),
+        install_location:   install_location.to_owned(),
+        install_date:       chrono::Local::now().to_string(),
+        estimated_size_kib: bytes_extracted / 1024,
+        uninstaller:        install_location.join(UNINSTALLER_NAME),
+        integration_files:  vec![desktop_entry_path, mime_package],
+    };
+    uninstall_info.write()?;
+
+    stage_at!(0.96, "Refreshing the desktop database.");
+    enso_install::linux::refresh_databases(layout);
+
+    stage_at!(1.0, "Installation complete.");
+    send(crate::InstallerUpdate::Finished(Ok(())));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enso_install::linux::mime::FileType;
+    use enso_install_config::payload::Metadata;
+    use flate2::write::GzEncoder;
+
+    /// Build a leaked payload with the given files, as the real one is `'static`.
+    fn payload(files: &[(&str, &str)]) -> Result<Payload> {
+        let encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let mut builder = tar::Builder::new(encoder);
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents.as_bytes())?;
+        }
+        let data = builder.into_inner()?.finish()?;
+        let metadata = Metadata {
+            total_files: files.len() as u64,
+            total_bytes: files.iter().map(|(_, contents)| contents.len() as u64).sum(),
+        };
+        Ok(Payload { data: data.leak(), metadata: Box::leak(Box::new(metadata)) })
+    }
+
+    fn config(version: &str, file_associations: Vec<FileType>) -> Result<Config> {
+        Ok(Config {
+            app_id: "org.enso.test".into(),
+            executable_filename: "Enso".into(),
+            publisher: "New Byte Order sp. z o.o.".into(),
+            pretty_name: "Enso".into(),
+            shortcut_name: "Enso".into(),
+            version: Version::parse(version)?,
+            url_protocols: vec!["enso".into()],
+            categories: vec!["Development".into()],
+            file_associations,
+        })
+    }
+
+    fn install(layout: &Layout, payload: Payload, config: &Config) -> Result {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        install_with_updates(layout, payload, config, &sender)?;
+        drop(sender);
+        let finished = receiver
+            .iter()
+            .any(|update| matches!(update, crate::InstallerUpdate::Finished(Ok(()))));
+        ensure!(finished, "The installer did not report success.");
+        Ok(())
+    }
+
+    #[test]
+    fn install_and_upgrade() -> Result {
+        setup_logging().ok();
+        let home = tempfile::tempdir()?;
+        let layout = Layout::new(home.path().join(".local/share"));
+        let install_dir = layout.install_dir("org.enso.test");
+        let source =
+            FileType::from_prog_id("Enso.Source", "enso", "text/plain", "Enso Source File");
+        let bundle = FileType::from_prog_id(
+            "Enso.ProjectBundle",
+            "enso-project",
+            "application/gzip",
+            "Enso Project Bundle",
+        );
+
+        let old_config = config("2024.1.1", vec![source.clone(), bundle])?;
+        install(&layout, payload(&[("Enso", "v1"), ("old.txt", "old")])?, &old_config)?;
+        assert_eq!(ide_ci::fs::read_to_string(install_dir.join("Enso"))?, "v1");
+        let entry = ide_ci::fs::read_to_string(layout.desktop_entry_path("org.enso.test"))?;
+        assert!(entry.contains(
+            "MimeType=application/x-enso-source;application/x-enso-projectbundle;\
+            x-scheme-handler/enso;"
+        ));
+        let package = ide_ci::fs::read_to_string(layout.mime_package_path("org.enso.test"))?;
+        assert!(package.contains(r#"<glob pattern="*.enso-project"/>"#));
+
+        // Upgrade: the old files are gone and the registration reflects the new version.
+        let new_config = config("2024.2.1", vec![source])?;
+        install(&layout, payload(&[("Enso", "v2")])?, &new_config)?;
+        assert_eq!(ide_ci::fs::read_to_string(install_dir.join("Enso"))?, "v2");
+        assert!(!install_dir.join("old.txt").exists());
+        let package = ide_ci::fs::read_to_string(layout.mime_package_path("org.enso.test"))?;
+        assert!(!package.contains("enso-project"));
+        let info = UninstallInfo::read(&install_dir)?.context("Missing uninstall information.")?;
+        assert_eq!(info.display_version, Version::new(2024, 2, 1));
+        assert_eq!(info.uninstaller, install_dir.join(UNINSTALLER_NAME));
+        assert_eq!(info.integration_files, [
+            layout.desktop_entry_path("org.enso.test"),
+            layout.mime_package_path("org.enso.test")
+        ]);
+        Ok(())
+    }
+}
@@ -9,9 +9,14 @@ fn main() -> Result {
     enso_installer::win::main()
 }
 
-#[cfg(not(windows))]
+#[cfg(target_os = "linux")]
 fn main() -> Result {
-    bail!("This installer is only supported on Windows.")
+    enso_installer::linux::main()
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn main() -> Result {
+    bail!("This installer is only supported on Windows and Linux.")
 }
 
 #[cfg(test)]
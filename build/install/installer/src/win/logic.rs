@@ -2,11 +2,12 @@
 
 use crate::prelude::*;
 
+use crate::check_disk_space;
+use crate::extract_payload;
 use crate::win::config::Config;
 use crate::Payload;
 
 use enso_install_config::UNINSTALLER_NAME;
-use flate2::read::GzDecoder;
 
 
 
@@ -125,37 +126,12 @@ pub fn install_with_updates(
 
     let executable_location = install_location.join(&config.executable_filename);
 
-    // Extract the files.
-    let decoder = GzDecoder::new(payload.data);
-    let archive = tar::Archive::new(decoder);
-
     let extraction_progress_start = 0.06;
     let extraction_progress_step = 0.82;
-    let mut files_extracted = 0;
-    let mut bytes_extracted = 0;
-
     stage_at!(extraction_progress_start, "Extracting files.");
-    let mut bytes_being_extracted = 0;
-    let to_our_path = |entry: &tar::Entry<GzDecoder<&[u8]>>| -> Option<PathBuf> {
-        // If we receive a new file, update the counters.
-        files_extracted += 1;
-        bytes_extracted += bytes_being_extracted;
-        bytes_being_extracted = entry.header().size().unwrap_or(0);
-
-        let files_ratio = (files_extracted as f64 / total_files as f64).min(1.0);
-        let bytes_ratio = (bytes_extracted as f64 / total_bytes as f64).min(1.0);
-        let extraction_progresss = (files_ratio + bytes_ratio) / 2.0;
-        let progress = extraction_progress_start + extraction_progress_step * extraction_progresss;
-        trace!("files_extracted: {files_extracted}/{total_files}, bytes_extracted: {bytes_extracted}/{total_bytes}, extraction_progresss: {extraction_progresss}, progress: {progress}");
-        report_progress(progress);
-        Some(install_location.join(entry.path().ok()?))
-    };
-
-    ide_ci::archive::tar::extract_files_sync(archive, to_our_path)?;
-    // As we've been incrementing this values when extracting the next file, we need to cover the
-    // last file.
-    bytes_extracted += bytes_being_extracted;
-
+    let bytes_extracted = extract_payload(payload, install_location, |progress| {
+        report_progress(extraction_progress_start + extraction_progress_step * progress);
+    })?;
 
     let post_extraction_progress = extraction_progress_start + extraction_progress_step;
 
@@ -193,64 +169,10 @@ pub fn install_with_updates(
     Ok(())
 }
 
-/// Check if there is enough disk space to install the application.
-///
-/// If the space is insufficient, returns an error message. If the space is sufficient, returns
-/// `None`. If the necessary information cannot be obtained, returns an error.
-///
-/// Note that usually it is better to ignore the error than to fail the installation process. Not
-/// knowing that the disk space is sufficient is not meaning that it is insufficient.
-/// For example, we might be targetting a network path for which we cannot obtain the disk space.
-pub fn check_disk_space(
-    installation_directory: &Path,
-    bytes_required: u64,
-) -> Result<Option<String>> {
-    use sysinfo::Disks;
-    let disks = Disks::new_with_refreshed_list();
-    // This should yield an absolute path, prefixed with the drive label.
-    let path = installation_directory
-        .canonicalize()
-        // We use absolutize as a fallback, because canonicalize fails for non-existent paths. We
-        // attempt to use canonicalize first, because it resolves symlinks.
-        .or_else(|_| installation_directory.absolutize().map(PathBuf::from))?;
-
-    // We need to remove the verbatim prefix (that canonicalize likes to add) in order to match the
-    // disk list mount points format.
-    let path = path.without_verbatim_prefix();
-    let disk = disks
-        .into_iter()
-        .find(|disk| path.starts_with(disk.mount_point()))
-        .context("No disk information found for the installation directory.")?;
-
-    let required_space = bytesize::ByteSize(bytes_required);
-    let free_space = bytesize::ByteSize(disk.available_space());
-
-    if free_space < required_space {
-        let msg = format!(
-            "Not enough disk space on {} to install. Required: {:.2}, available: {:.2}.",
-            disk.mount_point().display(),
-            required_space,
-            free_space
-        );
-        return Ok(Some(msg));
-    }
-    Ok(None)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    #[ignore]
-    /// Test to manually check the generated disk space message.
-    fn check_disk_space_test() -> Result {
-        let my_path = ide_ci::env::current_dir()?;
-        let r = check_disk_space(&my_path, 10_000_000_000_000);
-        let _ = dbg!(r);
-        Ok(())
-    }
-
     #[test]
     #[ignore]
     /// Test to manually check the running processes.
@@ -18,6 +18,8 @@ use ide_ci::log::GlobalFilteringLayer;
 use prelude::*;
 use sysinfo::Pid;
 
+#[cfg(target_os = "linux")]
+pub mod linux;
 #[cfg(windows)]
 pub mod win;
 
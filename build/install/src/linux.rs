@@ -0,0 +1,99 @@
+//! Linux-specific code for the Enso installer.
+//!
+//! The installation is user-level: everything is placed under the user's data directory, as
+//! defined by the [XDG Base Directory Specification](https://specifications.freedesktop.org/basedir-spec/latest/).
+//! By default this is `~/.local/share`.
+
+use crate::prelude::*;
+
+
+// ==============
+// === Export ===
+// ==============
+
+pub mod desktop_entry;
+pub mod mime;
+pub mod uninstall;
+
+
+
+/// Locations used by a user-level installation.
+///
+/// All paths are derived from the user's data directory, so the whole installation can be
+/// redirected (e.g. to a temporary `HOME` in tests) by constructing the layout with
+/// [`Layout::new`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    /// The user's data directory, e.g. `~/.local/share`.
+    pub data_home: PathBuf,
+}
+
+impl Layout {
+    /// Create a layout rooted at the given data directory.
+    pub fn new(data_home: impl Into<PathBuf>) -> Self {
+        Self { data_home: data_home.into() }
+    }
+
+    /// The layout for the current user.
+    ///
+    /// Uses `$XDG_DATA_HOME` if set, otherwise `$HOME/.local/share`.
+    pub fn for_current_user() -> Result<Self> {
+        let data_home =
+            dirs::data_dir().context("Failed to get the local user's data directory path.")?;
+        Ok(Self::new(data_home))
+    }
+
+    /// The directory where the application bundle is installed, e.g. `~/.local/share/org.enso`.
+    ///
+    /// We use the application ID rather than the product name, as `~/.local/share/enso` is already
+    /// used by the Enso engine for its own data.
+    pub fn install_dir(&self, app_id: &str) -> PathBuf {
+        self.data_home.join(app_id)
+    }
+
+    /// The directory where the desktop entries are placed, e.g. `~/.local/share/applications`.
+    pub fn applications_dir(&self) -> PathBuf {
+        self.data_home.join("applications")
+    }
+
+    /// The path of the application's desktop entry, e.g.
+    /// `~/.local/share/applications/org.enso.desktop`.
+    pub fn desktop_entry_path(&self, app_id: &str) -> PathBuf {
+        self.applications_dir().join(format!("{app_id}.desktop"))
+    }
+
+    /// The root of the user's shared MIME-info database, e.g. `~/.local/share/mime`.
+    pub fn mime_dir(&self) -> PathBuf {
+        self.data_home.join("mime")
+    }
+
+    /// The path of the MIME package describing the application's file types, e.g.
+    /// `~/.local/share/mime/packages/org.enso.xml`.
+    pub fn mime_package_path(&self, app_id: &str) -> PathBuf {
+        self.mime_dir().join("packages").join(format!("{app_id}.xml"))
+    }
+}
+
+/// Rebuild the desktop entry and MIME caches, so the changes are picked up by the desktop
+/// environment without logging out.
+///
+/// The tools used (`update-desktop-database` and `update-mime-database`) are not available on every
+/// system. Their failures are logged and otherwise ignored, as the caches are only an optimization
+/// and will eventually be rebuilt by the system.
+pub fn refresh_databases(layout: &Layout) {
+    let commands = [
+        ("update-desktop-database", layout.applications_dir()),
+        ("update-mime-database", layout.mime_dir()),
+    ];
+    for (program, directory) in commands {
+        if !directory.exists() {
+            continue;
+        }
+        debug!("Running `{program} {}`.", directory.display());
+        match std::process::Command::new(program).arg(&directory).status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => warn!("`{program}` failed with {status}."),
+            Err(err) => warn!("Failed to run `{program}`: {err}."),
+        }
+    }
+}
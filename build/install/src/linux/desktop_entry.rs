@@ -0,0 +1,160 @@
+//! XDG desktop entries (`.desktop` files).
+//!
+//! A desktop entry makes the application appear in the desktop environment's application menu and
+//! declares which MIME types (including URL schemes) the application can handle. See the
+//! [specification](https://specifications.freedesktop.org/desktop-entry-spec/latest/).
+
+use crate::prelude::*;
+
+
+
+/// An additional action of the application, shown e.g. in the context menu of the launcher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Action {
+    /// The action identifier, e.g. `uninstall`.
+    pub id:   String,
+    /// The human-readable name of the action, e.g. `Uninstall Enso`.
+    pub name: String,
+    /// The executable to be run by the action.
+    pub exec: PathBuf,
+}
+
+/// A desktop entry of the `Application` type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DesktopEntry {
+    /// The name of the application, e.g. `Enso`.
+    pub name:       String,
+    /// The application executable.
+    ///
+    /// The executable is invoked with the opened files or URLs as arguments.
+    pub exec:       PathBuf,
+    /// The application icon, either an absolute path or a name from the icon theme.
+    pub icon:       Option<PathBuf>,
+    /// The MIME types supported by the application, e.g. `x-scheme-handler/enso`.
+    pub mime_types: Vec<String>,
+    /// The menu categories the application should be listed in, e.g. `Development`.
+    pub categories: Vec<String>,
+    /// Additional application actions.
+    pub actions:    Vec<Action>,
+}
+
+impl DesktopEntry {
+    /// Create an entry that runs the given executable.
+    pub fn new(name: impl Into<String>, exec: impl Into<PathBuf>) -> Self {
+        Self {
+            name:       name.into(),
+            exec:       exec.into(),
+            icon:       None,
+            mime_types: default(),
+            categories: default(),
+            actions:    default(),
+        }
+    }
+
+    /// Write the entry to the given path, replacing any existing file.
+    pub fn write(&self, path: &Path) -> Result {
+        info!("Writing desktop entry to {}.", path.display());
+        ide_ci::fs::write(path, self.to_string())
+    }
+}
+
+impl Display for DesktopEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "[Desktop Entry]")?;
+        writeln!(f, "Type=Application")?;
+        writeln!(f, "Version=1.0")?;
+        writeln!(f, "Name={}", escape_string(&self.name))?;
+        writeln!(f, "Exec={} %U", quote_exec_argument(&self.exec))?;
+        if let Some(icon) = &self.icon {
+            writeln!(f, "Icon={}", escape_string(&icon.display().to_string()))?;
+        }
+        writeln!(f, "Terminal=false")?;
+        if !self.mime_types.is_empty() {
+            writeln!(f, "MimeType={}", list(&self.mime_types))?;
+        }
+        if !self.categories.is_empty() {
+            writeln!(f, "Categories={}", list(&self.categories))?;
+        }
+        if !self.actions.is_empty() {
+            writeln!(f, "Actions={}", list(self.actions.iter().map(|action| &action.id)))?;
+        }
+        for action in &self.actions {
+            writeln!(f)?;
+            writeln!(f, "[Desktop Action {}]", action.id)?;
+            writeln!(f, "Name={}", escape_string(&action.name))?;
+            writeln!(f, "Exec={}", quote_exec_argument(&action.exec))?;
+        }
+        Ok(())
+    }
+}
+
+/// Escape a value of the `string` or `localestring` type.
+fn escape_string(value: &str) -> String {
+    value.replace('\\', r"\\").replace('\n', r"\n").replace('\t', r"\t").replace('\r', r"\r")
+}
+
+/// Format a list of values, each terminated with a semicolon.
+fn list<T: AsRef<str>>(values: impl IntoIterator<Item = T>) -> String {
+    values.into_iter().map(|value| value.as_ref().replace(';', r"\;") + ";").collect()
+}
+
+/// Quote a single argument of the `Exec` key.
+///
+/// The argument is always put in double quotes, so it can contain spaces. Characters that are
+/// special inside the quotes are escaped with a backslash, which in turn must be escaped because
+/// the whole value is also a `string`. Percent signs are doubled, as they would otherwise start a
+/// field code.
+fn quote_exec_argument(path: &Path) -> String {
+    let mut quoted = String::from('"');
+    for c in path.display().to_string().chars() {
+        match c {
+            '"' | '`' | '$' => {
+                quoted.push_str(r"\\");
+                quoted.push(c);
+            }
+            '\\' => quoted.push_str(r"\\\\"),
+            '%' => quoted.push_str("%%"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_entry_with_actions() {
+        let mut entry = DesktopEntry::new("Enso", "/home/user/.local/share/org.enso/Enso");
+        entry.mime_types = vec!["application/x-enso-source".into(), "x-scheme-handler/enso".into()];
+        entry.categories = vec!["Development".into()];
+        entry.actions.push(Action {
+            id:   "uninstall".into(),
+            name: "Uninstall Enso".into(),
+            exec: "/home/user/.local/share/org.enso/enso-uninstaller".into(),
+        });
+        let expected = r#"[Desktop Entry]
+Type=Application
+Version=1.0
+Name=Enso
+Exec="/home/user/.local/share/org.enso/Enso" %U
+Terminal=false
+MimeType=application/x-enso-source;x-scheme-handler/enso;
+Categories=Development;
+Actions=uninstall;
+
+[Desktop Action uninstall]
+Name=Uninstall Enso
+Exec="/home/user/.local/share/org.enso/enso-uninstaller"
+"#;
+        assert_eq!(entry.to_string(), expected);
+    }
+
+    #[test]
+    fn quotes_special_characters() {
+        let path = Path::new(r#"/home/a "b"/$HOME/100%/c\d"#);
+        assert_eq!(quote_exec_argument(path), r#""/home/a \\"b\\"/\\$HOME/100%%/c\\\\d""#);
+    }
+}
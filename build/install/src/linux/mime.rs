@@ -0,0 +1,111 @@
+//! File type registration in the shared MIME-info database.
+//!
+//! The file types are described by a MIME package (an XML file) placed in the user's
+//! `mime/packages` directory. See the
+//! [specification](https://specifications.freedesktop.org/shared-mime-info-spec/latest/).
+
+use crate::prelude::*;
+
+
+
+/// A file type registered by the application.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileType {
+    /// The dedicated MIME type of the files, e.g. `application/x-enso-source`.
+    pub mime_type:    String,
+    /// The generic MIME type the files also conform to, e.g. `text/plain`.
+    ///
+    /// This allows other applications (like text editors) to still open the files.
+    pub sub_class_of: String,
+    /// A human-readable description of the file type, e.g. `Enso Source File`.
+    pub comment:      String,
+    /// The file extension without the leading dot, e.g. `enso`.
+    pub extension:    String,
+}
+
+impl FileType {
+    /// Describe a file type identified by a Windows-style programmatic identifier.
+    ///
+    /// The dedicated MIME type is derived from the identifier, e.g. `Enso.Source` yields
+    /// `application/x-enso-source`.
+    pub fn from_prog_id(
+        prog_id: &str,
+        extension: impl Into<String>,
+        sub_class_of: impl Into<String>,
+        comment: impl Into<String>,
+    ) -> Self {
+        let mime_type = format!("application/x-{}", prog_id.to_lowercase().replace('.', "-"));
+        Self {
+            mime_type,
+            sub_class_of: sub_class_of.into(),
+            comment: comment.into(),
+            extension: extension.into(),
+        }
+    }
+}
+
+/// Generate the MIME package describing the given file types.
+pub fn package(file_types: &[FileType]) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(r#"<mime-info xmlns="http://www.freedesktop.org/standards/shared-mime-info">"#);
+    xml.push('\n');
+    for file_type in file_types {
+        let FileType { mime_type, sub_class_of, comment, extension } = file_type;
+        xml.push_str(&format!("  <mime-type type=\"{}\">\n", escape(mime_type)));
+        xml.push_str(&format!("    <comment>{}</comment>\n", escape(comment)));
+        xml.push_str(&format!("    <sub-class-of type=\"{}\"/>\n", escape(sub_class_of)));
+        xml.push_str(&format!("    <glob pattern=\"*.{}\"/>\n", escape(extension)));
+        xml.push_str("  </mime-type>\n");
+    }
+    xml.push_str("</mime-info>\n");
+    xml
+}
+
+/// Write the MIME package describing the given file types to the given path.
+pub fn write_package(path: &Path, file_types: &[FileType]) -> Result {
+    info!("Writing MIME package to {}.", path.display());
+    ide_ci::fs::write(path, package(file_types))
+}
+
+/// Escape text for use in XML content and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_package() {
+        let source =
+            FileType::from_prog_id("Enso.Source", "enso", "text/plain", "Enso Source File");
+        assert_eq!(source.mime_type, "application/x-enso-source");
+        let bundle = FileType::from_prog_id(
+            "Enso.ProjectBundle",
+            "enso-project",
+            "application/gzip",
+            "Enso Project <Bundle> & more",
+        );
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<mime-info xmlns="http://www.freedesktop.org/standards/shared-mime-info">
+  <mime-type type="application/x-enso-source">
+    <comment>Enso Source File</comment>
+    <sub-class-of type="text/plain"/>
+    <glob pattern="*.enso"/>
+  </mime-type>
+  <mime-type type="application/x-enso-projectbundle">
+    <comment>Enso Project &lt;Bundle&gt; &amp; more</comment>
+    <sub-class-of type="application/gzip"/>
+    <glob pattern="*.enso-project"/>
+  </mime-type>
+</mime-info>
+"#;
+        assert_eq!(package(&[source, bundle]), expected);
+    }
+}
@@ -0,0 +1,83 @@
+//! Uninstaller information for Linux.
+//!
+//! There is no system-wide registry of user-level installations on Linux, so the information is
+//! stored as a JSON file in the installation directory. Besides the metadata, it records the files
+//! created outside the installation directory, so they can be removed by the uninstaller or
+//! replaced when upgrading.
+
+use crate::prelude::*;
+
+
+
+/// The name of the file (in the installation directory) that stores the [`UninstallInfo`].
+pub const INFO_FILENAME: &str = "uninstall.json";
+
+/// Information about an existing installation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallInfo {
+    /// The name of the application, e.g. `Enso`.
+    pub display_name:       String,
+    /// The installed version of the application.
+    pub display_version:    Version,
+    /// The publisher of the application.
+    pub publisher:          String,
+    /// The installation directory.
+    pub install_location:   PathBuf,
+    /// The date the application was installed.
+    pub install_date:       String,
+    /// The size of the application in kibibytes.
+    pub estimated_size_kib: u64,
+    /// The uninstaller executable.
+    pub uninstaller:        PathBuf,
+    /// Files created outside the installation directory, like the desktop entry.
+    pub integration_files:  Vec<PathBuf>,
+}
+
+impl UninstallInfo {
+    /// The path of the information file for the given installation directory.
+    pub fn path(install_dir: &Path) -> PathBuf {
+        install_dir.join(INFO_FILENAME)
+    }
+
+    /// Read the information about the installation in the given directory.
+    ///
+    /// Returns `None` if there is no installation there.
+    pub fn read(install_dir: &Path) -> Result<Option<Self>> {
+        let path = Self::path(install_dir);
+        if path.exists() {
+            ide_ci::fs::read_json(&path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Store the information in the installation directory.
+    #[context("Failed to write '{}' uninstall information.", self.display_name)]
+    pub fn write(&self) -> Result {
+        trace!("Writing uninstall information: {self:#?}");
+        ide_ci::fs::write_json(Self::path(&self.install_location), self)
+    }
+
+    /// Remove all the integration files that still exist.
+    ///
+    /// Failing to remove one file does not stop the removal of the others.
+    pub fn remove_integration_files(&self) -> Result {
+        let failed = self
+            .integration_files
+            .iter()
+            .filter(|path| {
+                info!("Removing {}.", path.display());
+                ide_ci::fs::remove_file_if_exists(path)
+                    .inspect_err(|err| warn!("Failed to remove {}: {err:?}", path.display()))
+                    .is_err()
+            })
+            .collect_vec();
+        ensure!(
+            failed.is_empty(),
+            "Failed to remove: {}.",
+            failed.iter().map(|path| path.display()).join(", ")
+        );
+        Ok(())
+    }
+}
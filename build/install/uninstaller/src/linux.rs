@@ -0,0 +1,171 @@
+use enso_install::prelude::*;
+
+use enso_install::is_already_running;
+use enso_install::linux::uninstall::UninstallInfo;
+use enso_install::linux::Layout;
+use enso_install::locked_installation_lock;
+use enso_install::sanitized_electron_builder_config;
+
+
+
+/// The parent directory of this (uninstaller) executable.
+///
+/// This is a good candidate for the install directory of Enso.
+fn parent_directory() -> Result<PathBuf> {
+    let exe_path = ide_ci::env::current_exe()?;
+    exe_path.try_parent().map(Into::into)
+}
+
+/// Handle an error, logging it and adding it to the list of errors.
+fn handle_error<T>(errors: &mut Vec<anyhow::Error>, result: Result<T>) -> Option<T> {
+    match result {
+        Err(error) => {
+            error!("Encountered an error: {error}.");
+            errors.push(error);
+            None
+        }
+        Ok(value) => Some(value),
+    }
+}
+
+/// The files created outside the installation directory.
+///
+/// They are normally known from the [`UninstallInfo`]. If it is missing (e.g. the installation was
+/// damaged), we fall back to the default locations.
+fn integration_files(install_dir: &Path, layout: &Layout) -> Result<Vec<PathBuf>> {
+    if let Some(info) = UninstallInfo::read(install_dir)? {
+        return Ok(info.integration_files);
+    }
+    warn!("No uninstall information found, assuming the default locations.");
+    let app_id = &sanitized_electron_builder_config().app_id;
+    Ok(vec![layout.desktop_entry_path(app_id), layout.mime_package_path(app_id)])
+}
+
+/// Remove the installation in the given directory, together with the files integrating it with the
+/// desktop environment.
+///
+/// Failing to remove one file does not stop the removal of the others. The errors encountered are
+/// returned.
+pub fn uninstall(install_dir: &Path, layout: &Layout) -> Result<Vec<anyhow::Error>> {
+    // Make sure that the Enso executable is in the same directory as this uninstaller. This is to
+    // prevent situation where just the uninstaller binary is placed by accident elsewhere and ends
+    // up deleting the whole directory.
+    let executable_filename = enso_install::executable_filename();
+    let expected_executable = install_dir.join(&executable_filename);
+    ensure!(
+        expected_executable.exists(),
+        "{} not found in the presumed install directory {}",
+        executable_filename.display(),
+        install_dir.display()
+    );
+
+    let mut errors = vec![];
+    let files =
+        handle_error(&mut errors, integration_files(install_dir, layout)).unwrap_or_default();
+    for file in &files {
+        info!("Removing {}.", file.display());
+        handle_error(&mut errors, ide_ci::fs::remove_file_if_exists(file));
+    }
+
+    // Unlike on Windows, a running executable can be deleted, so we can remove the whole directory
+    // including this uninstaller.
+    info!("Removing install directory.");
+    handle_error(&mut errors, ide_ci::fs::remove_dir_if_exists(install_dir));
+
+    enso_install::linux::refresh_databases(layout);
+    Ok(errors)
+}
+
+pub async fn main() -> Result {
+    let logfile = enso_install::setup_logging(env!("CARGO_PKG_NAME"))?;
+    info!("Logging to: {}", logfile.display());
+    let _guard = locked_installation_lock()?;
+
+    // Unwrap is safe, because the uninstaller path (being an executable) will never be a root.
+    let install_dir = parent_directory().unwrap();
+
+    // Check if there is already running instance of Enso or any of its subprograms.
+    let already_running = sysinfo::get_current_pid()
+        .map_err(|text| anyhow!("Failed to get current process ID: {text}"))
+        .and_then(|my_pid| {
+            is_already_running(&install_dir, &[my_pid])
+                .context("Failed to check if already running.")
+        });
+    match already_running {
+        Ok(Some(message)) => bail!("{message}"),
+        Ok(None) => (),
+        Err(error) => warn!("Failed to check if there is already running instance: {error:?}"),
+    };
+
+    let layout = Layout::for_current_user()?;
+    let errors = uninstall(&install_dir, &layout)?;
+    if !errors.is_empty() {
+        error!("Encountered {} errors.", errors.len());
+        for error in &errors {
+            error!(" * {error:?}");
+        }
+        bail!(
+            "Uninstallation failed. Some files may have been left behind. Please see the log file \
+            for details: {}",
+            logfile.display()
+        );
+    } else {
+        info!("Enso has been successfully uninstalled.");
+        Ok(())
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uninstall_removes_installation() -> Result {
+        let home = tempfile::tempdir()?;
+        let layout = Layout::new(home.path().join(".local/share"));
+        let install_dir = layout.install_dir("org.enso.test");
+        ide_ci::fs::write(install_dir.join(enso_install::executable_filename()), "")?;
+        let desktop_entry = layout.desktop_entry_path("org.enso.test");
+        let mime_package = layout.mime_package_path("org.enso.test");
+        let unrelated = layout.desktop_entry_path("org.example.other");
+        for file in [&desktop_entry, &mime_package, &unrelated] {
+            ide_ci::fs::write(file, "")?;
+        }
+        let info = UninstallInfo {
+            display_name:       "Enso".into(),
+            display_version:    Version::new(2024, 2, 1),
+            publisher:          "New Byte Order sp. z o.o.".into(),
+            install_location:   install_dir.clone(),
+            install_date:       "2024-06-01".into(),
+            estimated_size_kib: 0,
+            uninstaller:        install_dir.join(enso_install_config::UNINSTALLER_NAME),
+            integration_files:  vec![desktop_entry.clone(), mime_package.clone()],
+        };
+        info.write()?;
+
+        let errors = uninstall(&install_dir, &layout)?;
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(!install_dir.exists());
+        assert!(!desktop_entry.exists());
+        assert!(!mime_package.exists());
+        assert!(unrelated.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn directory_without_executable_is_kept() -> Result {
+        let home = tempfile::tempdir()?;
+        let layout = Layout::new(home.path().join(".local/share"));
+        let directory = home.path().join("Downloads");
+        ide_ci::fs::write(directory.join("notes.txt"), "")?;
+        assert!(uninstall(&directory, &layout).is_err());
+        assert!(directory.join("notes.txt").exists());
+        Ok(())
+    }
+}
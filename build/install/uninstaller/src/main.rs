@@ -7,6 +7,8 @@ use enso_install::prelude::*;
 // === Export ===
 // ==============
 
+#[cfg(target_os = "linux")]
+pub mod linux;
 #[cfg(windows)]
 pub mod win;
 
@@ -19,9 +21,16 @@ pub async fn main() -> Result {
 }
 
 
-#[cfg(not(windows))]
+#[cfg(target_os = "linux")]
+#[tokio::main]
+pub async fn main() -> Result {
+    linux::main().await
+}
+
+
+#[cfg(not(any(windows, target_os = "linux")))]
 fn main() -> Result {
-    bail!("This uninstaller is only supported on Windows.")
+    bail!("This uninstaller is only supported on Windows and Linux.")
 }
 
 
@@ -104,6 +104,7 @@ pub mod format;
 pub mod lexer;
 pub mod macros;
 pub mod metadata;
+pub mod ranges;
 pub mod serialization;
 pub mod source;
 pub mod syntax;
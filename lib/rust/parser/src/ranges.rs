@@ -0,0 +1,422 @@
+//! Structural source ranges derived from a syntax tree, supporting editor features:
+//! - Selection ranges: The chain of nodes enclosing a position, used to implement "expand
+//!   selection".
+//! - Folding ranges: The multi-line regions of a module that can be collapsed, classified by
+//!   [`FoldingRangeKind`].
+//!
+//! All ranges are absolute [`Location`]s, as reported by [`Span::range`]; consumers can use
+//! whichever of the UTF-8, UTF-16 or line/column coordinates they need.
+
+use crate::prelude::*;
+use crate::syntax::*;
+
+use crate::source::code::Location;
+use crate::syntax::tree::block;
+use crate::syntax::tree::TextElement;
+use crate::syntax::tree::Variant;
+
+
+
+// =======================
+// === Selection Range ===
+// =======================
+
+/// Return the ranges of the nodes enclosing the given position, smallest first. The position is
+/// an offset from the beginning of the source, in UTF-16 code units.
+///
+/// Identical ranges of nested nodes are reported once. A position located exactly between two
+/// nodes is considered to be inside the node starting there.
+pub fn selection_ranges(tree: &Tree, position: u32) -> Vec<Range<Location>> {
+    let mut ranges: Vec<Range<Location>> = default();
+    let mut current = Some(tree);
+    while let Some(node) = current {
+        let range = node.span.range();
+        if !contains(&range, position) {
+            break;
+        }
+        if ranges.last() != Some(&range) {
+            ranges.push(range);
+        }
+        current = child_at(node, position);
+    }
+    ranges.reverse();
+    ranges
+}
+
+fn contains(range: &Range<Location>, position: u32) -> bool {
+    range.start.utf16 <= position && position <= range.end.utf16
+}
+
+/// Return the child of the node containing the position, preferring a child starting at the
+/// position over one ending there.
+fn child_at<'s, 'a>(tree: &'a Tree<'s>, position: u32) -> Option<&'a Tree<'s>> {
+    let mut ending_here = None;
+    let mut found = None;
+    for_each_child(tree, &mut |child| {
+        if found.is_some() {
+            return;
+        }
+        let range = child.span.range();
+        if range.start.utf16 <= position && position < range.end.utf16 {
+            found = Some(child);
+        } else if range.end.utf16 == position && ending_here.is_none() {
+            ending_here = Some(child);
+        }
+    });
+    found.or(ending_here)
+}
+
+
+
+// =====================
+// === Folding Range ===
+// =====================
+
+/// A region of the source code that can be collapsed by an editor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoldingRange {
+    /// The extent of the region. Its first line is the line that remains visible when folded.
+    pub range: Range<Location>,
+    /// What the region contains.
+    pub kind:  FoldingRangeKind,
+}
+
+/// Classification of a [`FoldingRange`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FoldingRangeKind {
+    /// An indented block, or a bracketed expression spanning multiple lines.
+    Block,
+    /// A documentation comment.
+    Comment,
+    /// A sequence of consecutive `import` or `export` statements.
+    Imports,
+    /// A multi-line text literal.
+    Text,
+}
+
+/// Return all the foldable regions of the tree, ordered by their start locations. Only regions
+/// spanning more than one line are reported; the root node is never reported.
+pub fn folding_ranges(tree: &Tree) -> Vec<FoldingRange> {
+    let mut ranges = default();
+    if let Variant::BodyBlock(block) = &tree.variant {
+        collect_import_groups(&block.statements, &mut ranges);
+    }
+    for_each_child(tree, &mut |child| collect_folding_ranges(child, &mut ranges));
+    ranges.sort_by_key(|folding: &FoldingRange| (folding.range.start, folding.range.end));
+    ranges
+}
+
+fn collect_folding_ranges(tree: &Tree, out: &mut Vec<FoldingRange>) {
+    let kind = match &tree.variant {
+        Variant::BodyBlock(block) => {
+            collect_import_groups(&block.statements, out);
+            Some(FoldingRangeKind::Block)
+        }
+        Variant::ArgumentBlockApplication(_)
+        | Variant::OperatorBlockApplication(_)
+        | Variant::Group(_)
+        | Variant::Array(_)
+        | Variant::Tuple(_) => Some(FoldingRangeKind::Block),
+        Variant::TextLiteral(_) => Some(FoldingRangeKind::Text),
+        Variant::Documented(documented) => {
+            let range = doc_comment_range(&documented.documentation);
+            push_if_multiline(out, range, FoldingRangeKind::Comment);
+            None
+        }
+        _ => None,
+    };
+    if let Some(kind) = kind {
+        push_if_multiline(out, tree.span.range(), kind);
+    }
+    for_each_child(tree, &mut |child| collect_folding_ranges(child, out));
+}
+
+fn push_if_multiline(out: &mut Vec<FoldingRange>, range: Range<Location>, kind: FoldingRangeKind) {
+    if range.start.line < range.end.line {
+        out.push(FoldingRange { range, kind });
+    }
+}
+
+/// Report each run of two or more consecutive import or export statements. Empty lines do not
+/// break a run.
+fn collect_import_groups(lines: &[block::Line], out: &mut Vec<FoldingRange>) {
+    let mut group: Option<Range<Location>> = None;
+    let mut finish = |group: &mut Option<Range<Location>>| {
+        if let Some(range) = group.take() {
+            push_if_multiline(out, range, FoldingRangeKind::Imports);
+        }
+    };
+    for line in lines {
+        match &line.expression {
+            Some(expression) if is_import_or_export(expression) => {
+                let range = expression.span.range();
+                match &mut group {
+                    Some(group) => group.end = range.end,
+                    None => group = Some(range),
+                }
+            }
+            Some(_) => finish(&mut group),
+            None => {}
+        }
+    }
+    finish(&mut group);
+}
+
+fn is_import_or_export(tree: &Tree) -> bool {
+    match &tree.variant {
+        Variant::Import(_) | Variant::Export(_) => true,
+        Variant::Private(private) => private.body.as_ref().map_or(false, is_import_or_export),
+        _ => false,
+    }
+}
+
+/// Return the range of the text of a documentation comment, excluding any trailing empty lines.
+fn doc_comment_range(doc: &tree::DocComment) -> Range<Location> {
+    let start = doc.open.code.range().start;
+    let end = doc
+        .elements
+        .iter()
+        .rev()
+        .find_map(|element| match element {
+            TextElement::Section { text } => Some(text.code.range().end),
+            TextElement::Escape { token } => Some(token.code.range().end),
+            TextElement::Splice { close, .. } => Some(close.code.range().end),
+            TextElement::Newline { .. } => None,
+        })
+        .unwrap_or_else(|| doc.open.code.range().end);
+    start..end
+}
+
+
+
+// ================
+// === Children ===
+// ================
+
+/// Apply the function to each [`Tree`] that is a direct child of the node, in source order.
+///
+/// Unlike the visitors generated with the `debug` feature, this is available in all builds.
+fn for_each_child<'s, 'a>(tree: &'a Tree<'s>, f: &mut impl FnMut(&'a Tree<'s>)) {
+    match &tree.variant {
+        Variant::Invalid(invalid) => f(&invalid.ast),
+        Variant::BodyBlock(block) => lines(&block.statements, f),
+        Variant::ArgumentBlockApplication(block) => {
+            block.lhs.iter().for_each(&mut *f);
+            lines(&block.arguments, f);
+        }
+        Variant::OperatorBlockApplication(block) => {
+            block.lhs.iter().for_each(&mut *f);
+            for line in &block.expressions {
+                if let Some(expression) = &line.expression {
+                    f(&expression.expression);
+                }
+            }
+            lines(&block.excess, f);
+        }
+        Variant::Ident(_)
+        | Variant::Number(_)
+        | Variant::Wildcard(_)
+        | Variant::SuspendedDefaultArguments(_)
+        | Variant::AutoscopedIdentifier(_) => {}
+        Variant::Private(private) => private.body.iter().for_each(f),
+        Variant::TextLiteral(text) => text_elements(&text.elements, f),
+        Variant::App(app) => {
+            f(&app.func);
+            f(&app.arg);
+        }
+        Variant::NamedApp(app) => {
+            f(&app.func);
+            f(&app.arg);
+        }
+        Variant::OprApp(app) => {
+            app.lhs.iter().for_each(&mut *f);
+            app.rhs.iter().for_each(f);
+        }
+        Variant::UnaryOprApp(app) => app.rhs.iter().for_each(f),
+        Variant::OprSectionBoundary(boundary) => f(&boundary.ast),
+        Variant::TemplateFunction(template) => f(&template.ast),
+        Variant::MultiSegmentApp(app) =>
+            app.segments.iter().filter_map(|segment| segment.body.as_ref()).for_each(f),
+        Variant::TypeDef(def) => {
+            def.params.iter().for_each(|param| argument_definition(param, f));
+            lines(&def.body, f);
+        }
+        Variant::Assignment(assignment) => {
+            f(&assignment.pattern);
+            f(&assignment.expr);
+        }
+        Variant::Function(function) => {
+            f(&function.name);
+            function.args.iter().for_each(|arg| argument_definition(arg, f));
+            function.returns.iter().for_each(|returns| f(&returns.r#type));
+            function.body.iter().for_each(f);
+        }
+        Variant::ForeignFunction(function) => {
+            function.args.iter().for_each(|arg| argument_definition(arg, f));
+            f(&function.body);
+        }
+        Variant::Import(import) => {
+            for segment in [&import.polyglot, &import.from].into_iter().flatten() {
+                segment.body.iter().for_each(&mut *f);
+            }
+            import.import.body.iter().for_each(&mut *f);
+            for segment in [&import.as_, &import.hiding].into_iter().flatten() {
+                segment.body.iter().for_each(&mut *f);
+            }
+        }
+        Variant::Export(export) => {
+            export.from.iter().filter_map(|segment| segment.body.as_ref()).for_each(&mut *f);
+            export.export.body.iter().for_each(&mut *f);
+            export.as_.iter().filter_map(|segment| segment.body.as_ref()).for_each(f);
+        }
+        Variant::Group(group) => group.body.iter().for_each(f),
+        Variant::TypeSignature(signature) => {
+            f(&signature.variable);
+            f(&signature.type_);
+        }
+        Variant::TypeAnnotated(annotated) => {
+            f(&annotated.expression);
+            f(&annotated.type_);
+        }
+        Variant::CaseOf(case_of) => {
+            case_of.expression.iter().for_each(&mut *f);
+            for case in case_of.cases.iter().filter_map(|line| line.case.as_ref()) {
+                if let Some(documentation) = &case.documentation {
+                    text_elements(&documentation.elements, f);
+                }
+                case.pattern.iter().for_each(&mut *f);
+                case.expression.iter().for_each(&mut *f);
+            }
+        }
+        Variant::Lambda(lambda) => {
+            lambda.arguments.iter().for_each(|arg| argument_definition(arg, f));
+            f(&lambda.body);
+        }
+        Variant::Array(array) => {
+            array.first.iter().for_each(&mut *f);
+            array.rest.iter().filter_map(|item| item.body.as_ref()).for_each(f);
+        }
+        Variant::Tuple(tuple) => {
+            tuple.first.iter().for_each(&mut *f);
+            tuple.rest.iter().filter_map(|item| item.body.as_ref()).for_each(f);
+        }
+        Variant::Annotated(annotated) => {
+            annotated.argument.iter().for_each(&mut *f);
+            annotated.expression.iter().for_each(f);
+        }
+        Variant::AnnotatedBuiltin(annotated) => annotated.expression.iter().for_each(f),
+        Variant::Documented(documented) => {
+            text_elements(&documented.documentation.elements, f);
+            documented.expression.iter().for_each(f);
+        }
+        Variant::ConstructorDefinition(constructor) => {
+            constructor.arguments.iter().for_each(|arg| argument_definition(arg, f));
+            for line in &constructor.block {
+                line.argument.iter().for_each(|arg| argument_definition(arg, f));
+            }
+        }
+    }
+}
+
+fn lines<'s, 'a>(lines: &'a [block::Line<'s>], f: &mut dyn FnMut(&'a Tree<'s>)) {
+    lines.iter().filter_map(|line| line.expression.as_ref()).for_each(f)
+}
+
+fn text_elements<'s, 'a>(elements: &'a [TextElement<'s>], f: &mut dyn FnMut(&'a Tree<'s>)) {
+    for element in elements {
+        if let TextElement::Splice { expression: Some(expression), .. } = element {
+            f(expression);
+        }
+    }
+}
+
+fn argument_definition<'s, 'a>(
+    arg: &'a tree::ArgumentDefinition<'s>,
+    f: &mut dyn FnMut(&'a Tree<'s>),
+) {
+    f(&arg.pattern);
+    if let Some(type_) = &arg.type_ {
+        f(&type_.type_);
+    }
+    if let Some(default) = &arg.default {
+        f(&default.expression);
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Return the text of each range in the input.
+    fn texts(code: &str, ranges: impl IntoIterator<Item = Range<Location>>) -> Vec<&str> {
+        ranges
+            .into_iter()
+            .map(|range| &code[range.start.utf8 as usize..range.end.utf8 as usize])
+            .collect()
+    }
+
+    #[test]
+    fn selection_ranges_in_expression() {
+        let code = "main =\n    x = foo (bar 1) 2\n    x";
+        let tree = crate::Parser::new().run(code);
+        let position = code.find("bar").unwrap() as u32 + 1;
+        let ranges = selection_ranges(&tree, position);
+        assert_eq!(texts(code, ranges), [
+            "bar",
+            "bar 1",
+            "(bar 1)",
+            "foo (bar 1)",
+            "foo (bar 1) 2",
+            "x = foo (bar 1) 2",
+            "\n    x = foo (bar 1) 2\n    x",
+            "main =\n    x = foo (bar 1) 2\n    x",
+        ]);
+    }
+
+    #[test]
+    fn selection_ranges_outside_tree() {
+        let code = "foo";
+        let tree = crate::Parser::new().run(code);
+        assert_eq!(selection_ranges(&tree, 10), []);
+    }
+
+    #[test]
+    fn folding_ranges_kinds() {
+        let code = [
+            "import Standard.Base",
+            "from Standard.Table import all",
+            "",
+            "## Documentation",
+            "   of main.",
+            "main =",
+            "    x = foo",
+            "        1",
+            "        2",
+            "    y = '''",
+            "        text",
+            "    x",
+        ]
+        .join("\n");
+        let tree = crate::Parser::new().run(&code);
+        let ranges = folding_ranges(&tree);
+        let kinds: Vec<_> = ranges.iter().map(|folding| folding.kind).collect();
+        assert_eq!(kinds, [
+            FoldingRangeKind::Imports,
+            FoldingRangeKind::Comment,
+            FoldingRangeKind::Block,
+            FoldingRangeKind::Block,
+            FoldingRangeKind::Text,
+        ]);
+        let texts = texts(&code, ranges.into_iter().map(|folding| folding.range));
+        assert_eq!(texts[0], "import Standard.Base\nfrom Standard.Table import all");
+        assert_eq!(texts[1], "## Documentation\n   of main.");
+        assert_eq!(texts[3], "foo\n        1\n        2");
+        assert_eq!(texts[4], "'''\n        text");
+    }
+}
@@ -0,0 +1,42 @@
+//! Statistics shared by the language server benchmarking tools.
+
+
+
+// ==================
+// === Percentile ===
+// ==================
+
+/// Return the value below which the given percentage of the sorted values fall, using the
+/// nearest-rank method. An empty slice yields the default value (e.g. a zero duration).
+pub fn percentile<T: Copy + Default>(sorted: &[T], percent: f64) -> T {
+    if sorted.is_empty() {
+        return T::default();
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let values = (1..=10).collect::<Vec<u32>>();
+        assert_eq!(percentile(&values, 0.0), 1);
+        assert_eq!(percentile(&values, 10.0), 1);
+        assert_eq!(percentile(&values, 11.0), 2);
+        assert_eq!(percentile(&values, 50.0), 5);
+        assert_eq!(percentile(&values, 90.0), 9);
+        assert_eq!(percentile(&values, 99.0), 10);
+        assert_eq!(percentile(&values, 100.0), 10);
+        assert_eq!(percentile(&[7], 50.0), 7);
+        assert_eq!(percentile::<std::time::Duration>(&[], 90.0), std::time::Duration::ZERO);
+    }
+}
@@ -4,11 +4,16 @@
 // === Non-Standard Linter Configuration ===
 #![allow(unused_qualifications)] // https://github.com/enso-org/enso/issues/5168
 
+
+
+mod report;
+
 use enso_prelude::*;
 
 use clap::Parser;
 use clap::ValueHint;
 use lazy_static::lazy_static;
+use logstat::percentile;
 use regex::Regex;
 use std::io::Result;
 use std::path::PathBuf;
@@ -50,6 +55,43 @@ struct Args {
     /// Calculate median instead of mean.
     #[clap(long)]
     median: bool,
+
+    /// Format of the log files.
+    #[clap(long, value_enum, default_value_t = LogFormat::Auto)]
+    log_format: LogFormat,
+
+    /// Format of the printed statistics.
+    #[clap(long, value_enum, default_value_t = report::Format::Text)]
+    format: report::Format,
+
+    /// Include the duration of each operation in every iteration in the output.
+    #[clap(long)]
+    iterations: bool,
+
+    /// Logfile of a baseline run to compare the results with. It is analyzed using the same
+    /// specification.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    baseline_log: Option<PathBuf>,
+
+    /// Wstest log file of the baseline run.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath, requires = "baseline_log")]
+    baseline_wstest_log: Option<PathBuf>,
+
+    /// Maximum allowed slowdown of any operation relative to the baseline, in percent. If
+    /// exceeded, the process exits with a non-zero code.
+    #[clap(long, value_name = "PERCENT", requires = "baseline_log")]
+    regression_threshold: Option<f64>,
+}
+
+/// Format of the log lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum LogFormat {
+    /// Bracketed text lines, like `[info] [2022-07-20T12:09:36.123Z] [enso] Message`.
+    Text,
+    /// JSON objects, one per line, with `timestamp`, `logger` and `message` fields.
+    Json,
+    /// Detect the format of each line.
+    Auto,
 }
 
 
@@ -119,10 +161,40 @@ impl Iteration {
 /// Final statistics about benchmarked operation.
 #[derive(Debug)]
 struct Stats {
-    min:  Duration,
-    max:  Duration,
-    avg:  Duration,
-    line: String,
+    min:        Duration,
+    max:        Duration,
+    /// Either the mean or the median, depending on the `--median` option.
+    avg:        Duration,
+    mean:       Duration,
+    median:     Duration,
+    p90:        Duration,
+    p99:        Duration,
+    stddev:     Duration,
+    /// Durations of the operation in each iteration.
+    iterations: Vec<Duration>,
+    line:       String,
+}
+
+impl Stats {
+    /// Compute the statistics of the operation durations.
+    fn new(line: String, durations: Vec<Duration>, use_median: bool) -> Self {
+        let mut sorted = durations.clone();
+        sorted.sort();
+        let min = sorted.first().copied().unwrap_or(Duration::ZERO);
+        let max = sorted.last().copied().unwrap_or(Duration::ZERO);
+        let mean = if sorted.is_empty() {
+            Duration::ZERO
+        } else {
+            sorted.iter().copied().sum::<Duration>() / sorted.len() as u32
+        };
+        let median =
+            if sorted.is_empty() { Duration::ZERO } else { median(sorted.iter().copied()) };
+        let p90 = percentile(&sorted, 90.0);
+        let p99 = percentile(&sorted, 99.0);
+        let stddev = stddev(&sorted, mean);
+        let avg = if use_median { median } else { mean };
+        Stats { min, max, avg, mean, median, p90, p99, stddev, iterations: durations, line }
+    }
 }
 
 impl Display for Stats {
@@ -179,8 +251,59 @@ async fn read_specs(path: &PathBuf) -> Result<Spec> {
     Ok(Spec { matches })
 }
 
+/// Parse a bracketed text log line into its timestamp and message.
+fn parse_text_line(line: &str) -> Option<(OffsetDateTime, String)> {
+    let cap = RE_LOGLINE.captures(line)?;
+    let groups =
+        (cap.get(RE_LOGLINE_TIMESTAMP_CAPTURE_GROUP), cap.get(RE_LOGLINE_MESSAGE_CAPTURE_GROUP));
+    match groups {
+        (Some(timestamp), Some(message)) => {
+            let timestamp = OffsetDateTime::parse(timestamp.as_str(), &Rfc3339).unwrap();
+            Some((timestamp, message.as_str().to_string()))
+        }
+        _ => {
+            eprintln!("[ERR] Invalid log line [{line}]");
+            None
+        }
+    }
+}
+
+/// Parse a JSON-structured log line into its timestamp and message. The message is prefixed with
+/// the bracketed logger name, so that the same specification can be used for both log formats.
+///
+/// The timestamp can be either an RFC 3339 string or a number of milliseconds since the epoch.
+fn parse_json_line(line: &str) -> Option<(OffsetDateTime, String)> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let field = |names: &[&str]| names.iter().find_map(|name| value.get(*name));
+    let timestamp = match field(&["timestamp", "@timestamp", "time"])? {
+        serde_json::Value::String(timestamp) => OffsetDateTime::parse(timestamp, &Rfc3339).ok()?,
+        serde_json::Value::Number(millis) => {
+            let nanos = i128::from(millis.as_i64()?) * 1_000_000;
+            OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()?
+        }
+        _ => return None,
+    };
+    let message = field(&["message", "msg"])?.as_str()?;
+    let logger = field(&["logger", "loggerName", "logger_name"]).and_then(|l| l.as_str());
+    let message = match logger {
+        Some(logger) => format!("[{logger}] {message}"),
+        None => message.to_string(),
+    };
+    Some((timestamp, message))
+}
+
+/// Parse a log line into its timestamp and message.
+fn parse_line(line: &str, format: LogFormat) -> Option<(OffsetDateTime, String)> {
+    match format {
+        LogFormat::Text => parse_text_line(line),
+        LogFormat::Json => parse_json_line(line),
+        LogFormat::Auto if line.trim_start().starts_with('{') => parse_json_line(line),
+        LogFormat::Auto => parse_text_line(line),
+    }
+}
+
 /// Extract the iterations information from logfile according to the provided spec.
-async fn read_logfile(path: &PathBuf, spec: &Spec) -> Result<Vec<Iteration>> {
+async fn read_logfile(path: &PathBuf, spec: &Spec, format: LogFormat) -> Result<Vec<Iteration>> {
     let file = File::open(path).await?;
     let mut lines = BufReader::new(file).lines();
 
@@ -190,32 +313,20 @@ async fn read_logfile(path: &PathBuf, spec: &Spec) -> Result<Vec<Iteration>> {
     let mut current_match = matches.next().expect("Empty spec!");
 
     while let Some(line) = lines.next_line().await? {
-        if let Some(cap) = RE_LOGLINE.captures(line.as_str()) {
-            let groups = (
-                cap.get(RE_LOGLINE_TIMESTAMP_CAPTURE_GROUP),
-                cap.get(RE_LOGLINE_MESSAGE_CAPTURE_GROUP),
-            );
-            match groups {
-                (Some(timestamp), Some(message)) =>
-                    if message.as_str().contains(current_match) {
-                        let duration = Duration::ZERO;
-                        let timestamp =
-                            OffsetDateTime::parse(timestamp.as_str(), &Rfc3339).unwrap();
-                        let line = message.as_str().to_string();
-
-                        current_operations.push(Operation { duration, timestamp, line });
-
-                        if let Some(m) = matches.next() {
-                            current_match = m
-                        } else {
-                            matches = spec.matches.iter();
-                            current_match = matches.next().expect("Empty spec!");
-                            iterations.push(Iteration { operations: current_operations });
-                            current_operations = vec![];
-                        }
-                    },
-                _ => {
-                    eprintln!("[ERR] Invalid log line [{line}]");
+        if let Some((timestamp, message)) = parse_line(line.as_str(), format) {
+            if message.contains(current_match) {
+                let duration = Duration::ZERO;
+                let line = message;
+
+                current_operations.push(Operation { duration, timestamp, line });
+
+                if let Some(m) = matches.next() {
+                    current_match = m
+                } else {
+                    matches = spec.matches.iter();
+                    current_match = matches.next().expect("Empty spec!");
+                    iterations.push(Iteration { operations: current_operations });
+                    current_operations = vec![];
                 }
             }
         }
@@ -238,6 +349,17 @@ where I: Iterator<Item = Duration> {
     }
 }
 
+/// Calculate the population standard deviation of the durations.
+fn stddev(durations: &[Duration], mean: Duration) -> Duration {
+    if durations.is_empty() {
+        return Duration::ZERO;
+    }
+    let mean = mean.as_seconds_f64();
+    let variance = durations.iter().map(|d| (d.as_seconds_f64() - mean).powi(2)).sum::<f64>()
+        / durations.len() as f64;
+    Duration::seconds_f64(variance.sqrt())
+}
+
 /// Merge iterations from two log files.
 ///
 /// Function insert operations from logfile after the first operation of wstest tool.
@@ -266,61 +388,44 @@ fn calculate_durations(iterations: &mut Vec<Iteration>) {
 
 /// Analyze benchmark results.
 fn analyze_iterations(iterations: &[Iteration], use_median: bool) -> Vec<Stats> {
-    let iterations_len = iterations.len();
-    let operations_len = iterations.first().unwrap().operations.len();
+    let operations_len = iterations.first().map_or(0, |it| it.operations.len());
 
     let mut stats = (0..operations_len)
         .map(|operation_index| {
             let current_line = &iterations[0].operations[operation_index].line;
             let durations = iterations.iter().map(|it| it.operations[operation_index].duration);
-
-            let min = durations.clone().min().unwrap_or(Duration::ZERO);
-            let max = durations.clone().max().unwrap_or(Duration::ZERO);
-            let avg = if use_median {
-                median(durations)
-            } else {
-                durations.sum::<Duration>() / iterations_len as u32
-            };
             let line = current_line.to_string();
 
-            Stats { min, max, avg, line }
+            Stats::new(line, durations.collect(), use_median)
         })
         .collect::<Vec<_>>();
 
-    let overall_stats = iterations_average(iterations, &stats);
+    let overall_stats = iterations_average(iterations, &stats, use_median);
     stats.push(overall_stats);
 
     stats
 }
 
-/// Calculate the average of all iterations.
-fn iterations_average(ops: &[Iteration], stats: &[Stats]) -> Stats {
-    let min_opt = ops.iter().map(|o| o.total_time()).min();
-    let max_opt = ops.iter().map(|o| o.total_time()).max();
-
-    let min = min_opt.unwrap_or(Duration::ZERO);
-    let max = max_opt.unwrap_or(Duration::ZERO);
-    let avg = stats.iter().map(|s| s.avg).sum();
+/// Calculate the statistics of the total time of all iterations.
+fn iterations_average(ops: &[Iteration], stats: &[Stats], use_median: bool) -> Stats {
+    let totals = ops.iter().map(|o| o.total_time()).collect();
     let line = String::from("Total");
-
-    Stats { min, max, avg, line }
+    let mut total = Stats::new(line, totals, use_median);
+    total.avg = stats.iter().map(|s| s.avg).sum();
+    total
 }
 
+/// Read the iterations of a benchmark run, skipping the warmup iterations.
+async fn read_iterations(
+    log: &PathBuf,
+    wstest_log: Option<&PathBuf>,
+    spec: &Spec,
+    args: &Args,
+) -> Result<Vec<Iteration>> {
+    let log_iterations = read_logfile(log, spec, args.log_format).await?;
 
-
-// ============
-// === Main ===
-// ============
-
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-    let spec = read_specs(&args.spec).await?;
-
-    let log_iterations = read_logfile(&args.log, &spec).await?;
-
-    let mut iterations = if let Some(path_buf) = args.wstest_log {
-        let mut ws_iterations = read_logfile(&path_buf, &WSTEST_SPEC).await?;
+    let mut iterations = if let Some(path_buf) = wstest_log {
+        let mut ws_iterations = read_logfile(path_buf, &WSTEST_SPEC, args.log_format).await?;
 
         // skip warmup iterations
         let start_time = &ws_iterations[0].operations[0].timestamp;
@@ -349,14 +454,51 @@ async fn main() -> Result<()> {
     };
 
     // cleanup iterations info before analyzing
-    iterations.drain(..args.skip_iterations);
+    iterations.drain(..args.skip_iterations.min(iterations.len()));
     calculate_durations(&mut iterations);
 
+    Ok(iterations)
+}
+
+
+
+// ============
+// === Main ===
+// ============
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let spec = read_specs(&args.spec).await?;
+
+    let iterations = read_iterations(&args.log, args.wstest_log.as_ref(), &spec, &args).await?;
     let stats = analyze_iterations(&iterations, args.median);
+    let current = report::Report::new(iterations.len(), &stats, args.median, args.iterations);
+
+    let baseline = match &args.baseline_log {
+        Some(log) => {
+            let wstest_log = args.baseline_wstest_log.as_ref();
+            let iterations = read_iterations(log, wstest_log, &spec, &args).await?;
+            let stats = analyze_iterations(&iterations, args.median);
+            Some(report::Report::new(iterations.len(), &stats, args.median, args.iterations))
+        }
+        None => None,
+    };
+
+    let regressed = match baseline {
+        Some(baseline) => {
+            let comparison = report::Comparison::new(baseline, current, args.regression_threshold);
+            comparison.print(args.format)?;
+            comparison.has_regressions()
+        }
+        None => {
+            current.print(args.format)?;
+            false
+        }
+    };
 
-    println!("avg [min..max] (of {} records)", iterations.len());
-    for s in &stats {
-        println!("{s}");
+    if regressed {
+        process::exit(2);
     }
 
     Ok(())
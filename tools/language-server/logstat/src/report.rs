@@ -0,0 +1,380 @@
+//! Output of the computed statistics in the text, JSON and CSV formats, and comparison with a
+//! baseline run.
+
+use enso_prelude::*;
+
+use crate::Stats;
+
+use serde::Serialize;
+use std::io::Result;
+use std::io::Write;
+use time::Duration;
+
+
+
+// ==============
+// === Format ===
+// ==============
+
+/// Output format of the statistics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Human-readable text, one operation per line.
+    Text,
+    /// A single JSON document.
+    Json,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+/// Convert the duration to fractional milliseconds.
+fn millis(duration: Duration) -> f64 {
+    duration.as_seconds_f64() * 1000.0
+}
+
+/// Quote the CSV field if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Write the JSON document, followed by a newline.
+fn write_json(out: &mut impl Write, value: &impl Serialize) -> Result<()> {
+    writeln!(out, "{}", serde_json::to_string_pretty(value)?)
+}
+
+
+
+// =================
+// === Operation ===
+// =================
+
+/// Statistics of a single operation, in milliseconds.
+#[derive(Clone, Debug, Serialize)]
+pub struct Operation {
+    line:          String,
+    count:         usize,
+    avg_ms:        f64,
+    mean_ms:       f64,
+    median_ms:     f64,
+    min_ms:        f64,
+    max_ms:        f64,
+    p50_ms:        f64,
+    p90_ms:        f64,
+    p99_ms:        f64,
+    stddev_ms:     f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iterations_ms: Option<Vec<f64>>,
+    #[serde(skip)]
+    text:          String,
+}
+
+impl Operation {
+    fn new(stats: &Stats, include_iterations: bool) -> Self {
+        let iterations = stats.iterations.iter().copied().map(millis);
+        Operation {
+            line:          stats.line.clone(),
+            count:         stats.iterations.len(),
+            avg_ms:        millis(stats.avg),
+            mean_ms:       millis(stats.mean),
+            median_ms:     millis(stats.median),
+            min_ms:        millis(stats.min),
+            max_ms:        millis(stats.max),
+            p50_ms:        millis(stats.median),
+            p90_ms:        millis(stats.p90),
+            p99_ms:        millis(stats.p99),
+            stddev_ms:     millis(stats.stddev),
+            iterations_ms: include_iterations.then(|| iterations.collect()),
+            text:          stats.to_string(),
+        }
+    }
+
+    /// The CSV columns of the statistics.
+    const CSV_HEADER: &'static str =
+        "line,count,avg_ms,mean_ms,median_ms,min_ms,max_ms,p50_ms,p90_ms,p99_ms,stddev_ms";
+
+    fn csv_values(&self) -> String {
+        let values = [
+            self.avg_ms,
+            self.mean_ms,
+            self.median_ms,
+            self.min_ms,
+            self.max_ms,
+            self.p50_ms,
+            self.p90_ms,
+            self.p99_ms,
+            self.stddev_ms,
+        ];
+        let values = values.iter().map(|value| format!("{value:.3}")).collect::<Vec<_>>().join(",");
+        format!("{},{},{values}", csv_field(&self.line), self.count)
+    }
+}
+
+
+
+// ==============
+// === Report ===
+// ==============
+
+/// Statistics of a single benchmark run.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    /// Number of analyzed iterations.
+    records:    usize,
+    /// The statistic reported as the average: `mean` or `median`.
+    statistic:  &'static str,
+    /// The operations, followed by the total time of the iteration.
+    operations: Vec<Operation>,
+}
+
+impl Report {
+    /// Create a report of the analyzed operations.
+    pub fn new(
+        records: usize,
+        stats: &[Stats],
+        use_median: bool,
+        include_iterations: bool,
+    ) -> Self {
+        let statistic = if use_median { "median" } else { "mean" };
+        let operations = stats.iter().map(|s| Operation::new(s, include_iterations)).collect();
+        Report { records, statistic, operations }
+    }
+
+    /// Print the report in the given format to the standard output.
+    pub fn print(&self, format: Format) -> Result<()> {
+        self.write(&mut std::io::stdout().lock(), format)
+    }
+
+    /// Write the report in the given format.
+    pub fn write(&self, out: &mut impl Write, format: Format) -> Result<()> {
+        match format {
+            Format::Text => {
+                writeln!(out, "avg [min..max] (of {} records)", self.records)?;
+                for operation in &self.operations {
+                    writeln!(out, "{}", operation.text)?;
+                    if let Some(iterations) = &operation.iterations_ms {
+                        let iterations = iterations
+                            .iter()
+                            .map(|i| format!("{i:.0}ms"))
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        writeln!(out, "    {iterations}")?;
+                    }
+                }
+                Ok(())
+            }
+            Format::Json => write_json(out, self),
+            Format::Csv => {
+                let iterations = self.operations.iter().filter_map(|o| o.iterations_ms.as_ref());
+                let iterations_len = iterations.map(Vec::len).max().unwrap_or_default();
+                let mut header = vec![Operation::CSV_HEADER.to_string()];
+                header.extend((0..iterations_len).map(|i| format!("iteration_{i}_ms")));
+                writeln!(out, "{}", header.join(","))?;
+                for operation in &self.operations {
+                    let mut row = vec![operation.csv_values()];
+                    let iterations = operation.iterations_ms.iter().flatten();
+                    row.extend(iterations.map(|i| format!("{i:.3}")));
+                    writeln!(out, "{}", row.join(","))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+
+
+// ==================
+// === Comparison ===
+// ==================
+
+/// Change of a single operation relative to the baseline.
+#[derive(Clone, Debug, Serialize)]
+pub struct Change {
+    line:        String,
+    baseline_ms: f64,
+    current_ms:  f64,
+    /// Relative change of the average, in percent. Positive values mean a slowdown.
+    change:      f64,
+    regression:  bool,
+}
+
+/// Comparison of a benchmark run with a baseline run.
+#[derive(Clone, Debug, Serialize)]
+pub struct Comparison {
+    baseline:  Report,
+    current:   Report,
+    /// Maximum allowed slowdown, in percent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    threshold: Option<f64>,
+    changes:   Vec<Change>,
+}
+
+impl Comparison {
+    /// Compare the reports. Operations are matched by position, as both runs are analyzed using
+    /// the same specification.
+    pub fn new(baseline: Report, current: Report, threshold: Option<f64>) -> Self {
+        let operations = baseline.operations.iter().zip(&current.operations);
+        let changes = operations
+            .map(|(before, after)| {
+                let change = if before.avg_ms > 0.0 {
+                    (after.avg_ms - before.avg_ms) / before.avg_ms * 100.0
+                } else {
+                    0.0
+                };
+                Change {
+                    line: after.line.clone(),
+                    baseline_ms: before.avg_ms,
+                    current_ms: after.avg_ms,
+                    change,
+                    regression: threshold.is_some_and(|threshold| change > threshold),
+                }
+            })
+            .collect();
+        Comparison { baseline, current, threshold, changes }
+    }
+
+    /// Check whether any operation slowed down more than the threshold allows.
+    pub fn has_regressions(&self) -> bool {
+        self.changes.iter().any(|change| change.regression)
+    }
+
+    /// Print the comparison in the given format to the standard output.
+    pub fn print(&self, format: Format) -> Result<()> {
+        self.write(&mut std::io::stdout().lock(), format)
+    }
+
+    /// Write the comparison in the given format.
+    pub fn write(&self, out: &mut impl Write, format: Format) -> Result<()> {
+        match format {
+            Format::Text => {
+                writeln!(
+                    out,
+                    "baseline -> current (of {} -> {} records)",
+                    self.baseline.records, self.current.records
+                )?;
+                for change in &self.changes {
+                    let baseline = change.baseline_ms.round();
+                    let current = change.current_ms.round();
+                    let marker = if change.regression { " REGRESSION" } else { "" };
+                    let line = change.line.chars().take(80).collect::<String>();
+                    let change = change.change;
+                    writeln!(out, "{baseline}ms -> {current}ms ({change:+.1}%){marker} {line}")?;
+                }
+                Ok(())
+            }
+            Format::Json => write_json(out, self),
+            Format::Csv => {
+                writeln!(out, "line,baseline_ms,current_ms,change_percent,regression")?;
+                for change in &self.changes {
+                    writeln!(
+                        out,
+                        "{},{:.3},{:.3},{:.3},{}",
+                        csv_field(&change.line),
+                        change.baseline_ms,
+                        change.current_ms,
+                        change.change,
+                        change.regression
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(line: &str, millis: &[i64]) -> Stats {
+        let durations = millis.iter().map(|&millis| Duration::milliseconds(millis)).collect();
+        Stats::new(line.into(), durations, false)
+    }
+
+    fn output(write: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> String {
+        let mut out = Vec::new();
+        write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn report_output() {
+        let stats = [stats("open, file", &[10, 20, 30]), stats("total", &[40, 60, 50])];
+        let report = Report::new(3, &stats, false, true);
+
+        let text = output(|out| report.write(out, Format::Text));
+        let expected =
+            "avg [min..max] (of 3 records)\n20ms [10..30] open, file\n    10ms 20ms 30ms\n\
+                        50ms [40..60] total\n    40ms 60ms 50ms\n";
+        assert_eq!(text, expected);
+
+        let csv = output(|out| report.write(out, Format::Csv));
+        let header =
+            format!("{},iteration_0_ms,iteration_1_ms,iteration_2_ms", Operation::CSV_HEADER);
+        let expected = [
+            header.as_str(),
+            "\"open, file\",3,20.000,20.000,20.000,10.000,30.000,20.000,30.000,30.000,8.165,\
+             10.000,20.000,30.000",
+            "total,3,50.000,50.000,50.000,40.000,60.000,50.000,60.000,60.000,8.165,40.000,60.000,\
+             50.000",
+        ];
+        assert_eq!(csv.lines().collect::<Vec<_>>(), expected);
+
+        let json = output(|out| report.write(out, Format::Json));
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(json["records"], 3);
+        assert_eq!(json["statistic"], "mean");
+        assert_eq!(json["operations"][0]["line"], "open, file");
+        assert_eq!(json["operations"][1]["p90_ms"], 60.0);
+        assert_eq!(json["operations"][1]["iterations_ms"], serde_json::json!([40.0, 60.0, 50.0]));
+        let without_iterations = Report::new(3, &stats, true, false);
+        let text = output(|out| without_iterations.write(out, Format::Text));
+        assert_eq!(
+            text,
+            "avg [min..max] (of 3 records)\n20ms [10..30] open, file\n50ms [40..60] total\n"
+        );
+    }
+
+    #[test]
+    fn comparison_output() {
+        let baseline =
+            Report::new(2, &[stats("open", &[50]), stats("total", &[100])], false, false);
+        let current = Report::new(3, &[stats("open", &[45]), stats("total", &[120])], false, false);
+        let comparison = Comparison::new(baseline.clone(), current.clone(), Some(10.0));
+        assert!(comparison.has_regressions());
+
+        let text = output(|out| comparison.write(out, Format::Text));
+        let expected = "baseline -> current (of 2 -> 3 records)\n50ms -> 45ms (-10.0%) open\n\
+                        100ms -> 120ms (+20.0%) REGRESSION total\n";
+        assert_eq!(text, expected);
+
+        let csv = output(|out| comparison.write(out, Format::Csv));
+        let expected = [
+            "line,baseline_ms,current_ms,change_percent,regression",
+            "open,50.000,45.000,-10.000,false",
+            "total,100.000,120.000,20.000,true",
+        ];
+        assert_eq!(csv.lines().collect::<Vec<_>>(), expected);
+
+        let json = output(|out| comparison.write(out, Format::Json));
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(json["threshold"], 10.0);
+        assert_eq!(json["changes"][1]["regression"], true);
+        assert_eq!(json["current"]["records"], 3);
+
+        let lenient = Comparison::new(baseline, current, Some(25.0));
+        assert!(!lenient.has_regressions());
+        let text = output(|out| lenient.write(out, Format::Text));
+        assert!(text.contains("100ms -> 120ms (+20.0%) total"));
+    }
+}
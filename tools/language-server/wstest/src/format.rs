@@ -1,3 +1,5 @@
+use std::fmt::Debug;
+use std::fmt::Display;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
@@ -14,6 +16,17 @@ static BENCH_REQUEST_SENT: &str = "wstest sent bench request";
 static WARMUP_REQUEST_SENT: &str = "wstest sent warmup request";
 static RESPONSE_HANDLED: &str = "wstest handled response";
 static RESPONSE_IGNORED: &str = "wstest ignored response";
+static SCENARIO_REQUEST_SENT: &str = "wstest sent scenario request";
+static SCENARIO_STEP_PASSED: &str = "wstest scenario step passed";
+static SCENARIO_STEP_FAILED: &str = "wstest scenario step failed";
+static LISTENING: &str = "wstest listening";
+static CONNECTION_ERROR: &str = "wstest connection error";
+static REPLAY_RESPONSE_SENT: &str = "wstest sent replayed response";
+static REPLAY_MISMATCH: &str = "wstest received unexpected request, recorded";
+static REPLAY_EXHAUSTED: &str = "wstest received request after the end of the recording";
+static LOAD_CLIENT_STARTED: &str = "wstest load client started";
+static LOAD_CLIENT_FINISHED: &str = "wstest load client finished";
+static LOAD_CLIENT_FAILED: &str = "wstest load client failed";
 
 static FMT_LEVEL: &str = "info";
 static FMT_MODULE: &str = "main";
@@ -54,6 +67,61 @@ pub fn response_ignored(message: &str) -> String {
     fmt(format!("{RESPONSE_IGNORED} [{message}]").as_str())
 }
 
+/// Message for logging the scenario request
+pub fn scenario_request(message: &str) -> String {
+    fmt(format!("{SCENARIO_REQUEST_SENT} [{message}]").as_str())
+}
+
+/// Message for logging the passed scenario step
+pub fn scenario_step_passed(step: &str) -> String {
+    fmt(format!("{SCENARIO_STEP_PASSED} [{step}]").as_str())
+}
+
+/// Message for logging the failed scenario step
+pub fn scenario_step_failed(step: &str, reason: &str) -> String {
+    fmt(format!("{SCENARIO_STEP_FAILED} [{step}] {reason}").as_str())
+}
+
+/// Message for logging the address on which a socket accepts connections
+pub fn listening(socket: impl Debug, address: impl Display) -> String {
+    fmt(format!("{LISTENING} [{socket:?}] [{address}]").as_str())
+}
+
+/// Message for logging an error of a proxied or replayed connection
+pub fn connection_error(socket: impl Debug, error: &str) -> String {
+    fmt(format!("{CONNECTION_ERROR} [{socket:?}] [{error}]").as_str())
+}
+
+/// Message for logging the replayed response
+pub fn replay_response(message: &str) -> String {
+    fmt(format!("{REPLAY_RESPONSE_SENT} [{message}]").as_str())
+}
+
+/// Message for logging the request that differs from the recorded one
+pub fn replay_mismatch(recorded: &str) -> String {
+    fmt(format!("{REPLAY_MISMATCH} [{recorded}]").as_str())
+}
+
+/// Message for logging the request received when there are no more recorded responses
+pub fn replay_exhausted() -> String {
+    fmt(REPLAY_EXHAUSTED)
+}
+
+/// Message for logging the start of a load test client
+pub fn load_client_started(client: usize) -> String {
+    fmt(format!("{LOAD_CLIENT_STARTED} [{client}]").as_str())
+}
+
+/// Message for logging the load test client that completed all iterations
+pub fn load_client_finished(client: usize) -> String {
+    fmt(format!("{LOAD_CLIENT_FINISHED} [{client}]").as_str())
+}
+
+/// Message for logging the load test client that lost its connection
+pub fn load_client_failed(client: usize, error: &str) -> String {
+    fmt(format!("{LOAD_CLIENT_FAILED} [{client}] [{error}]").as_str())
+}
+
 fn fmt(message: &str) -> String {
     let time_now = OffsetDateTime::now_utc();
     format!(
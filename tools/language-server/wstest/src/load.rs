@@ -0,0 +1,331 @@
+//! The load mode: many clients run a scenario concurrently, to see how the language server behaves
+//! when serving multiple IDE sessions.
+//!
+//! Every client opens its own text (and binary) connection, executes the `setup` steps of the
+//! scenario once, and then repeats its `steps` for the given number of iterations. The clients are
+//! started evenly over the ramp-up period. Each step is timed from sending its request until all
+//! of its expectations are met; a failed step aborts the current iteration of the client.
+//!
+//! Besides the variables of the scenario, each client defines `clientIndex` and `clientUuid`,
+//! which can be used to give the clients distinct identities, e.g. as the `clientId` of
+//! `session/initProtocolConnection`.
+
+use enso_prelude::*;
+
+use crate::format;
+use crate::scenario::Scenario;
+use crate::scenario::Session;
+
+use logstat::percentile;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use url::Url;
+use uuid::Uuid;
+use websocket_lite::ClientBuilder;
+use websocket_lite::Result;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Upper bounds of the latency histogram buckets, in milliseconds.
+const HISTOGRAM_BUCKETS_MS: &[u64] = &[1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000, 10000];
+
+/// Width of the longest bar of the latency histogram.
+const HISTOGRAM_WIDTH: usize = 40;
+
+
+
+// ==============
+// === Config ===
+// ==============
+
+/// Parameters of the load test.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Text websocket of the language server.
+    pub text_socket:   Url,
+    /// Binary websocket of the language server.
+    pub binary_socket: Option<Url>,
+    /// Number of concurrent clients.
+    pub clients:       usize,
+    /// Time over which the clients are started.
+    pub ramp_up:       Duration,
+    /// Number of times each client repeats the scenario steps.
+    pub iterations:    usize,
+}
+
+
+
+// ===============
+// === Metrics ===
+// ===============
+
+/// Measurements of a single request type.
+#[derive(Clone, Debug, Default)]
+struct RequestStats {
+    /// Latencies of the successful requests.
+    latencies: Vec<Duration>,
+    /// Number of failed requests.
+    errors:    usize,
+}
+
+impl RequestStats {
+    fn merge(&mut self, other: RequestStats) {
+        self.latencies.extend(other.latencies);
+        self.errors += other.errors;
+    }
+}
+
+/// Measurements of the requests, by the step label.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    requests:       BTreeMap<String, RequestStats>,
+    /// Number of clients that could not connect, or lost the connection.
+    failed_clients: usize,
+}
+
+impl Metrics {
+    fn record(&mut self, label: &str, latency: Duration, success: bool) {
+        let stats = self.requests.entry(label.to_owned()).or_default();
+        if success {
+            stats.latencies.push(latency);
+        } else {
+            stats.errors += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Metrics) {
+        for (label, stats) in other.requests {
+            self.requests.entry(label).or_default().merge(stats);
+        }
+        self.failed_clients += other.failed_clients;
+    }
+}
+
+
+
+// ==============
+// === Report ===
+// ==============
+
+/// Result of the load test.
+#[derive(Clone, Debug)]
+pub struct Report {
+    metrics:    Metrics,
+    clients:    usize,
+    iterations: usize,
+    elapsed:    Duration,
+}
+
+/// Count the latencies in each histogram bucket. The last bucket contains the latencies above the
+/// highest bound.
+fn histogram(latencies: &[Duration]) -> Vec<usize> {
+    let mut buckets = vec![0; HISTOGRAM_BUCKETS_MS.len() + 1];
+    for latency in latencies {
+        let millis = latency.as_millis() as u64;
+        let bucket = HISTOGRAM_BUCKETS_MS.iter().position(|bound| millis < *bound);
+        buckets[bucket.unwrap_or(HISTOGRAM_BUCKETS_MS.len())] += 1;
+    }
+    buckets
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.elapsed.as_secs_f64();
+        let requests = self.metrics.requests.values();
+        let completed = requests.clone().map(|stats| stats.latencies.len()).sum::<usize>();
+        let errors = requests.map(|stats| stats.errors).sum::<usize>();
+        writeln!(
+            f,
+            "{} clients, {} iterations each, finished in {seconds:.1}s",
+            self.clients, self.iterations
+        )?;
+        writeln!(
+            f,
+            "{completed} requests ({:.1}/s), {errors} errors, {} failed clients",
+            completed as f64 / seconds,
+            self.metrics.failed_clients
+        )?;
+        for (label, stats) in &self.metrics.requests {
+            let mut latencies = stats.latencies.clone();
+            latencies.sort();
+            let millis = |percent| percentile(&latencies, percent).as_millis();
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{label}: {} requests ({:.1}/s), {} errors",
+                latencies.len(),
+                latencies.len() as f64 / seconds,
+                stats.errors
+            )?;
+            writeln!(
+                f,
+                "  p50 {}ms, p90 {}ms, p99 {}ms, max {}ms",
+                millis(50.0),
+                millis(90.0),
+                millis(99.0),
+                millis(100.0)
+            )?;
+            let buckets = histogram(&latencies);
+            let largest = buckets.iter().copied().max().unwrap_or_default().max(1);
+            for (index, count) in buckets.into_iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                let bucket = match HISTOGRAM_BUCKETS_MS.get(index) {
+                    Some(bound) => format!("<{bound}ms"),
+                    None => format!(">={}ms", HISTOGRAM_BUCKETS_MS[index - 1]),
+                };
+                let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(largest));
+                writeln!(f, "  {bucket:>9} {count:>7} {bar}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+
+// ============
+// === Load ===
+// ============
+
+/// Run the scenario on concurrent clients and collect the measurements.
+pub async fn run(scenario: Scenario, config: Config) -> Result<Report> {
+    let scenario = Arc::new(scenario);
+    let config = Arc::new(config);
+    let start = Instant::now();
+    let clients = (0..config.clients).map(|index| {
+        let scenario = scenario.clone();
+        let config = config.clone();
+        let delay = config.ramp_up.mul_f64(index as f64 / config.clients as f64);
+        tokio::spawn(async move {
+            tokio::time::sleep_until((start + delay).into()).await;
+            client(index, &scenario, &config).await
+        })
+    });
+    let clients = clients.collect::<Vec<_>>();
+    let mut metrics = Metrics::default();
+    for client in clients {
+        metrics.merge(client.await.map_err(|error| error.to_string())?);
+    }
+    let elapsed = start.elapsed();
+    Ok(Report { metrics, clients: config.clients, iterations: config.iterations, elapsed })
+}
+
+/// Connect a single client and run the scenario iterations.
+async fn client(index: usize, scenario: &Scenario, config: &Config) -> Metrics {
+    let mut metrics = Metrics::default();
+    println!("{}", format::load_client_started(index));
+    let result: Result<()> = async {
+        let text = ClientBuilder::from_url(config.text_socket.clone()).async_connect().await?;
+        let binary = match &config.binary_socket {
+            Some(url) => Some(ClientBuilder::from_url(url.clone()).async_connect().await?),
+            None => None,
+        };
+        let mut session = Session::new(scenario, text, binary);
+        session.verbose = false;
+        session.variables.insert("clientIndex".into(), index.to_string());
+        session.variables.insert("clientUuid".into(), Uuid::new_v4().to_string());
+
+        for (step_index, step) in scenario.setup.iter().enumerate() {
+            let label = step.label(step_index);
+            let start = Instant::now();
+            let outcome = session.step(&label, step).await?;
+            metrics.record(&label, start.elapsed(), outcome.is_ok());
+            if let Err(reason) = outcome {
+                return Err(format!("Setup step {label} failed: {reason}").into());
+            }
+        }
+
+        let initial_variables = session.variables.clone();
+        for _ in 0..config.iterations {
+            session.variables = initial_variables.clone();
+            for (step_index, step) in scenario.steps.iter().enumerate() {
+                let label = step.label(scenario.setup.len() + step_index);
+                let start = Instant::now();
+                let outcome = session.step(&label, step).await?;
+                metrics.record(&label, start.elapsed(), outcome.is_ok());
+                if outcome.is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+    .await;
+    match result {
+        Ok(()) => println!("{}", format::load_client_finished(index)),
+        Err(error) => {
+            metrics.failed_clients += 1;
+            eprintln!("{}", format::load_client_failed(index, &error.to_string()));
+        }
+    }
+    metrics
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: impl IntoIterator<Item = u64>) -> Vec<Duration> {
+        values.into_iter().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let buckets = histogram(&millis([0, 1, 4, 5, 9999, 10000, 60000]));
+        assert_eq!(buckets.len(), HISTOGRAM_BUCKETS_MS.len() + 1);
+        assert_eq!(buckets[0], 1);
+        assert_eq!(buckets[1], 1);
+        assert_eq!(buckets[2], 1);
+        assert_eq!(buckets[3], 1);
+        assert_eq!(buckets[HISTOGRAM_BUCKETS_MS.len() - 1], 1);
+        assert_eq!(buckets[HISTOGRAM_BUCKETS_MS.len()], 2);
+        assert_eq!(buckets.iter().sum::<usize>(), 7);
+        assert!(histogram(&[]).iter().all(|count| *count == 0));
+    }
+
+    #[test]
+    fn report_summary() {
+        let mut metrics = Metrics::default();
+        for latency in millis(1..=100) {
+            metrics.record("open", latency, true);
+        }
+        metrics.record("open", Duration::ZERO, false);
+        let mut other = Metrics { failed_clients: 1, ..default() };
+        other.record("close", Duration::from_millis(3), true);
+        metrics.merge(other);
+        let report = Report { metrics, clients: 2, iterations: 5, elapsed: Duration::from_secs(2) };
+        let expected = "\
+2 clients, 5 iterations each, finished in 2.0s
+101 requests (50.5/s), 1 errors, 1 failed clients
+
+close: 1 requests (0.5/s), 0 errors
+  p50 3ms, p90 3ms, p99 3ms, max 3ms
+       <5ms       1 ########################################
+
+open: 100 requests (50.0/s), 1 errors
+  p50 50ms, p90 90ms, p99 99ms, max 100ms
+       <2ms       1 #
+       <5ms       3 ###
+      <10ms       5 ####
+      <20ms      10 ########
+      <50ms      30 ########################
+     <100ms      50 ########################################
+     <200ms       1 #
+";
+        assert_eq!(report.to_string(), expected);
+    }
+}
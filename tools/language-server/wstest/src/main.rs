@@ -6,12 +6,20 @@
 
 
 mod format;
+mod load;
+mod proxy;
+mod recording;
+mod replay;
+mod scenario;
+mod server;
 
 use enso_prelude::*;
 
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use clap::builder::RangedU64ValueParser;
 use clap::Parser;
 use clap::ValueHint;
 use futures::SinkExt;
@@ -43,10 +51,15 @@ static EXPECT_BINARY_RESPONSE: &str = "b|";
 
 #[derive(Parser, Debug)]
 #[clap(version, about)]
+#[clap(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct Args {
+    /// Mode other than benchmarking.
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Text websocket to connect to.
-    #[clap(value_name = "URL", value_hint = ValueHint::Url)]
-    text_socket: Url,
+    #[clap(value_name = "URL", value_hint = ValueHint::Url, required = true)]
+    text_socket: Option<Url>,
 
     /// File containing messages to initialize the main socket.
     #[clap(long, value_hint = ValueHint::FilePath)]
@@ -92,6 +105,87 @@ struct Args {
     /// Time in milliseconds to wait before sending the next request from the `input` file.
     #[clap(long, value_name = "MILLISECONDS", default_value = "0")]
     wait_after_response: u64,
+
+    /// Scenario file (YAML or JSON) to run instead of the benchmark. The process exits with a
+    /// non-zero code if any of the scenario assertions fails.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath, conflicts_with_all = [
+        "init_text_socket",
+        "init_binary_socket",
+        "ignore_text_socket_responses",
+        "input",
+    ])]
+    scenario: Option<PathBuf>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Forward the connections of a client to the language server, recording all frames.
+    Proxy {
+        /// Text websocket of the language server.
+        #[clap(value_name = "URL", value_hint = ValueHint::Url)]
+        text_socket: Url,
+
+        /// Binary websocket of the language server.
+        #[clap(long, value_name = "URL", value_hint = ValueHint::Url, requires = "listen_binary")]
+        binary_socket: Option<Url>,
+
+        /// Address on which to accept client connections to the text socket.
+        #[clap(long, value_name = "ADDRESS")]
+        listen_text: SocketAddr,
+
+        /// Address on which to accept client connections to the binary socket.
+        #[clap(long, value_name = "ADDRESS", requires = "binary_socket")]
+        listen_binary: Option<SocketAddr>,
+
+        /// File to write the recording to.
+        #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+    },
+    /// Run a scenario on many concurrent clients, and report the latencies of its requests.
+    Load {
+        /// Text websocket of the language server.
+        #[clap(value_name = "URL", value_hint = ValueHint::Url)]
+        text_socket: Url,
+
+        /// Binary websocket of the language server.
+        #[clap(long, value_name = "URL", value_hint = ValueHint::Url)]
+        binary_socket: Option<Url>,
+
+        /// Scenario file (YAML or JSON) executed by every client.
+        #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        scenario: PathBuf,
+
+        /// Number of concurrent clients.
+        #[clap(long, default_value = "10")]
+        #[clap(value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        clients: usize,
+
+        /// Time in milliseconds over which the clients are started.
+        #[clap(long, value_name = "MILLISECONDS", default_value = "0")]
+        ramp_up: u64,
+
+        /// Number of times each client repeats the scenario steps.
+        #[clap(long, default_value = "10")]
+        iterations: usize,
+    },
+    /// Act as a language server, answering requests with the responses from a recording.
+    Replay {
+        /// Recording created by the `proxy` command.
+        #[clap(value_name = "FILE", value_hint = ValueHint::FilePath)]
+        recording: PathBuf,
+
+        /// Address on which to accept client connections to the text socket.
+        #[clap(long, value_name = "ADDRESS")]
+        listen_text: SocketAddr,
+
+        /// Address on which to accept client connections to the binary socket.
+        #[clap(long, value_name = "ADDRESS")]
+        listen_binary: Option<SocketAddr>,
+
+        /// Reproduce the recorded delays between requests and responses.
+        #[clap(long)]
+        preserve_timing: bool,
+    },
 }
 
 
@@ -130,8 +224,53 @@ async fn read_lines(path_buf: PathBuf) -> std::io::Result<Vec<String>> {
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    match args.command {
+        Some(Command::Proxy { text_socket, binary_socket, listen_text, listen_binary, output }) => {
+            let text = proxy::Route { listen: listen_text, upstream: text_socket };
+            let binary = listen_binary
+                .zip(binary_socket)
+                .map(|(listen, upstream)| proxy::Route { listen, upstream });
+            return proxy::run(text, binary, &output).await;
+        }
+        Some(Command::Load {
+            text_socket,
+            binary_socket,
+            scenario,
+            clients,
+            ramp_up,
+            iterations,
+        }) => {
+            let scenario = scenario::Scenario::read(&scenario).await?;
+            let ramp_up = Duration::from_millis(ramp_up);
+            let config = load::Config { text_socket, binary_socket, clients, ramp_up, iterations };
+            let report = load::run(scenario, config).await?;
+            print!("{report}");
+            return Ok(());
+        }
+        Some(Command::Replay { recording, listen_text, listen_binary, preserve_timing }) => {
+            let frames = recording::read(&recording).await?;
+            return replay::run(frames, listen_text, listen_binary, preserve_timing).await;
+        }
+        None => {}
+    }
+    let text_socket = args.text_socket.ok_or("Missing text socket URL.")?;
+
+    if let Some(path) = &args.scenario {
+        let scenario = scenario::Scenario::read(path).await?;
+        let text_client = ClientBuilder::from_url(text_socket).async_connect().await?;
+        let binary_client = match args.binary_socket {
+            Some(url) => Some(ClientBuilder::from_url(url).async_connect().await?),
+            None => None,
+        };
+        let outcome = scenario::run(&scenario, text_client, binary_client).await?;
+        if outcome == scenario::Outcome::Failed {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // text socket connection
-    let text_socket_client = ClientBuilder::from_url(args.text_socket).async_connect().await?;
+    let text_socket_client = ClientBuilder::from_url(text_socket).async_connect().await?;
     let (text_sink, text_stream) = text_socket_client.split();
 
     // binary socket connection
@@ -0,0 +1,112 @@
+//! The proxy mode: `wstest` sits between a client and the language server, forwarding the frames
+//! of the text and binary sockets in both directions, and recording them.
+
+use enso_prelude::*;
+
+use crate::format;
+use crate::recording::Recorder;
+use crate::recording::Sender;
+use crate::scenario::Socket;
+use crate::server;
+
+use futures::Sink;
+use futures::SinkExt;
+use futures::Stream;
+use futures::StreamExt;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use url::Url;
+use websocket_lite::ClientBuilder;
+use websocket_lite::Message;
+use websocket_lite::Opcode;
+use websocket_lite::Result;
+
+
+
+// =============
+// === Proxy ===
+// =============
+
+/// A socket to proxy: the local address on which clients are accepted, and the language server
+/// socket to which their frames are forwarded.
+#[derive(Clone, Debug)]
+pub struct Route {
+    /// The local address to listen on.
+    pub listen:   SocketAddr,
+    /// The language server socket.
+    pub upstream: Url,
+}
+
+/// Proxy the text socket, and the binary socket if given, until interrupted. All frames are
+/// recorded to the given file.
+pub async fn run(text: Route, binary: Option<Route>, output: &Path) -> Result<()> {
+    let recorder = Arc::new(Recorder::create(output)?);
+    let text_loop = serve(Socket::Text, text, recorder.clone());
+    let binary_loop = async {
+        match binary {
+            Some(binary) => serve(Socket::Binary, binary, recorder.clone()).await,
+            None => futures::future::pending().await,
+        }
+    };
+    tokio::try_join!(text_loop, binary_loop)?;
+    Ok(())
+}
+
+/// Accept clients on the route, proxying each connection in a separate task.
+async fn serve(socket: Socket, route: Route, recorder: Arc<Recorder>) -> Result<()> {
+    let listener = TcpListener::bind(route.listen).await?;
+    println!("{}", format::listening(socket, route.listen));
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let upstream = route.upstream.clone();
+        let recorder = recorder.clone();
+        tokio::spawn(async move {
+            let result = async {
+                let client = server::accept(stream).await?;
+                let server = ClientBuilder::from_url(upstream).async_connect().await?;
+                forward(socket, client, server, &recorder).await
+            };
+            if let Err(error) = result.await {
+                eprintln!("{}", format::connection_error(socket, &error.to_string()));
+            }
+        });
+    }
+}
+
+/// Forward frames between the client and the server until either side closes the connection.
+async fn forward<C, S>(socket: Socket, client: C, server: S, recorder: &Recorder) -> Result<()>
+where
+    C: Stream<Item = Result<Message>> + Sink<Message, Error = websocket_lite::Error>,
+    S: Stream<Item = Result<Message>> + Sink<Message, Error = websocket_lite::Error>, {
+    let (client_sink, client_stream) = client.split();
+    let (server_sink, server_stream) = server.split();
+    let upstream = pipe(socket, Sender::Client, client_stream, server_sink, recorder);
+    let downstream = pipe(socket, Sender::Server, server_stream, client_sink, recorder);
+    tokio::select! {
+        result = upstream => result,
+        result = downstream => result,
+    }
+}
+
+/// Record and forward frames from the stream to the sink, until the stream ends or a close frame
+/// is forwarded.
+async fn pipe(
+    socket: Socket,
+    from: Sender,
+    mut stream: impl Stream<Item = Result<Message>> + Unpin,
+    mut sink: impl Sink<Message, Error = websocket_lite::Error> + Unpin,
+    recorder: &Recorder,
+) -> Result<()> {
+    while let Some(message) = stream.next().await {
+        let message = message?;
+        recorder.record(socket, from, &message)?;
+        let is_close = message.opcode() == Opcode::Close;
+        sink.send(message).await?;
+        if is_close {
+            break;
+        }
+    }
+    Ok(())
+}
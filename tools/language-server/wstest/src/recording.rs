@@ -0,0 +1,128 @@
+//! Recordings of language server sessions, as captured by the proxy mode and served by the replay
+//! mode.
+//!
+//! A recording is a JSON Lines file; each line describes a single frame:
+//!
+//! ```json
+//! {"time":12,"socket":"text","from":"client","text":"{\"jsonrpc\":\"2.0\",...}"}
+//! {"time":40,"socket":"binary","from":"server","binary":"EAAAAAwAHgAIAAAABwAY..."}
+//! ```
+//!
+//! The `time` is the number of milliseconds since the recording started. Binary payloads are
+//! base64-encoded.
+
+use enso_prelude::*;
+
+use crate::scenario::Socket;
+
+use serde::Deserialize;
+use serde::Serialize;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use websocket_lite::Message;
+use websocket_lite::Opcode;
+use websocket_lite::Result;
+
+
+
+// =============
+// === Frame ===
+// =============
+
+/// The side of the connection that sent a frame.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sender {
+    /// The IDE, or another language server client.
+    Client,
+    /// The language server.
+    Server,
+}
+
+/// The content of a frame.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Payload {
+    /// A text message.
+    Text(String),
+    /// A base64-encoded binary message.
+    Binary(String),
+}
+
+impl Payload {
+    /// Return the payload of a data message. Control messages have no recorded payload.
+    pub fn of(message: &Message) -> Option<Self> {
+        match message.opcode() {
+            Opcode::Text => message.as_text().map(|text| Payload::Text(text.to_owned())),
+            Opcode::Binary => Some(Payload::Binary(base64::encode(message.data()))),
+            _ => None,
+        }
+    }
+
+    /// Convert the payload back to a message.
+    pub fn to_message(&self) -> Result<Message> {
+        Ok(match self {
+            Payload::Text(text) => Message::text(text.as_str()),
+            Payload::Binary(data) => Message::binary(base64::decode(data)?),
+        })
+    }
+}
+
+/// A single recorded frame.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Frame {
+    /// Milliseconds since the start of the recording.
+    pub time:    u64,
+    /// The socket the frame was sent over.
+    pub socket:  Socket,
+    /// The sender of the frame.
+    pub from:    Sender,
+    /// The content of the frame.
+    #[serde(flatten)]
+    pub payload: Payload,
+}
+
+
+
+// ================
+// === Recorder ===
+// ================
+
+/// Appends frames to a recording file. Can be shared between the connections of a session.
+#[derive(Debug)]
+pub struct Recorder {
+    start:  Instant,
+    output: Mutex<BufWriter<std::fs::File>>,
+}
+
+impl Recorder {
+    /// Create the recording file, truncating it if it exists.
+    pub fn create(path: &Path) -> Result<Self> {
+        let output = Mutex::new(BufWriter::new(std::fs::File::create(path)?));
+        Ok(Self { start: Instant::now(), output })
+    }
+
+    /// Record the message, if it is a data message. The file is flushed after every frame, so that
+    /// the recording is usable even if the session is interrupted.
+    pub fn record(&self, socket: Socket, from: Sender, message: &Message) -> Result<()> {
+        if let Some(payload) = Payload::of(message) {
+            let time = self.start.elapsed().as_millis() as u64;
+            let frame = Frame { time, socket, from, payload };
+            let mut output = self.output.lock().map_err(|_| "Recording file poisoned.")?;
+            serde_json::to_writer(&mut *output, &frame)?;
+            output.write_all(b"\n")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Read all the frames of a recording file.
+pub async fn read(path: &Path) -> Result<Vec<Frame>> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    let lines = contents.lines().filter(|line| !line.trim().is_empty());
+    Ok(lines.map(serde_json::from_str).collect::<serde_json::Result<_>>()?)
+}
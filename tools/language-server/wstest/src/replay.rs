@@ -0,0 +1,337 @@
+//! The replay mode: `wstest` acts as a fake language server, answering the requests of a client
+//! with the responses from a recording.
+//!
+//! The recording is split into exchanges: each frame sent by the client, followed by all frames
+//! the server sent before the next client frame. The n-th frame received from the client is
+//! answered with the server frames of the n-th exchange, so the client is expected to repeat the
+//! recorded session; deviations from the recorded requests are reported, but do not stop the
+//! replay.
+
+use enso_prelude::*;
+
+use crate::format;
+use crate::recording::Frame;
+use crate::recording::Payload;
+use crate::recording::Sender;
+use crate::scenario::Socket;
+use crate::server;
+
+use futures::stream::SplitSink;
+use futures::SinkExt;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use websocket_lite::Message;
+use websocket_lite::Opcode;
+use websocket_lite::Result;
+
+
+
+// ================
+// === Exchange ===
+// ================
+
+/// A client frame and the server frames that followed it.
+#[derive(Clone, Debug)]
+struct Exchange {
+    request:   Frame,
+    responses: Vec<Frame>,
+}
+
+/// Split the recording into the server frames sent before any request, and the exchanges.
+fn split_exchanges(frames: Vec<Frame>) -> (Vec<Frame>, Vec<Exchange>) {
+    let mut initial = Vec::new();
+    let mut exchanges: Vec<Exchange> = Vec::new();
+    for frame in frames {
+        match (frame.from, exchanges.last_mut()) {
+            (Sender::Client, _) =>
+                exchanges.push(Exchange { request: frame, responses: default() }),
+            (Sender::Server, Some(exchange)) => exchange.responses.push(frame),
+            (Sender::Server, None) => initial.push(frame),
+        }
+    }
+    (initial, exchanges)
+}
+
+
+
+// ==============
+// === Replay ===
+// ==============
+
+type ConnectionSink = SplitSink<server::Connection, Message>;
+
+/// Events sent from the connection tasks to the replay loop.
+enum Event {
+    Connected(Socket, ConnectionSink),
+    Received(Socket, Message),
+    Due(Frame),
+    Disconnected(Socket),
+}
+
+/// Serve the recording on the given addresses. Returns when the client disconnects from the text
+/// socket.
+///
+/// If `preserve_timing` is set, the delays between the recorded frames are reproduced; otherwise,
+/// responses are sent as soon as possible.
+pub async fn run(
+    frames: Vec<Frame>,
+    text: SocketAddr,
+    binary: Option<SocketAddr>,
+    preserve_timing: bool,
+) -> Result<()> {
+    let (initial, exchanges) = split_exchanges(frames);
+    let (events, mut received) = mpsc::unbounded_channel();
+    tokio::spawn(listen(Socket::Text, text, events.clone()));
+    if let Some(binary) = binary {
+        tokio::spawn(listen(Socket::Binary, binary, events.clone()));
+    }
+    drop(events);
+
+    // Responses whose recorded delay has passed, see `schedule`.
+    let (due_sender, mut due) = mpsc::unbounded_channel();
+    let mut sinks: HashMap<Socket, ConnectionSink> = default();
+    // Frames waiting for the client to connect to their socket.
+    let mut pending: Vec<Frame> = initial;
+    let mut exchanges = exchanges.into_iter();
+    loop {
+        let event = tokio::select! {
+            event = received.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            Some(frame) = due.recv() => Event::Due(frame),
+        };
+        match event {
+            Event::Connected(socket, sink) => {
+                sinks.insert(socket, sink);
+                let (ready, waiting) =
+                    pending.into_iter().partition(|frame| frame.socket == socket);
+                pending = waiting;
+                send(&mut sinks, &mut pending, ready).await?;
+            }
+            Event::Received(socket, message) => match message.opcode() {
+                Opcode::Text | Opcode::Binary => {
+                    let Some(exchange) = exchanges.next() else {
+                        println!("{}", format::replay_exhausted());
+                        continue;
+                    };
+                    let payload = Payload::of(&message);
+                    if exchange.request.socket != socket
+                        || payload.as_ref() != Some(&exchange.request.payload)
+                    {
+                        println!(
+                            "{}",
+                            format::replay_mismatch(&describe(&exchange.request.payload))
+                        );
+                    }
+                    if preserve_timing {
+                        let time = exchange.request.time;
+                        tokio::spawn(schedule(time, exchange.responses, due_sender.clone()));
+                    } else {
+                        send(&mut sinks, &mut pending, exchange.responses).await?;
+                    }
+                }
+                Opcode::Ping =>
+                    if let Some(sink) = sinks.get_mut(&socket) {
+                        sink.send(Message::pong(message.into_data())).await?;
+                    },
+                _ => {}
+            },
+            Event::Due(frame) => send(&mut sinks, &mut pending, vec![frame]).await?,
+            Event::Disconnected(socket) => {
+                sinks.remove(&socket);
+                if socket == Socket::Text {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pass the responses back to the replay loop once their recorded delays since the request have
+/// passed. Runs as a separate task, so that the loop keeps serving the client in the meantime.
+async fn schedule(request_time: u64, responses: Vec<Frame>, due: mpsc::UnboundedSender<Frame>) {
+    let mut previous = request_time;
+    for frame in responses {
+        tokio::time::sleep(Duration::from_millis(frame.time.saturating_sub(previous))).await;
+        previous = frame.time;
+        if due.send(frame).is_err() {
+            return;
+        }
+    }
+}
+
+/// Send the frames to the connected sockets; frames for sockets with no connected client are
+/// queued.
+async fn send(
+    sinks: &mut HashMap<Socket, ConnectionSink>,
+    pending: &mut Vec<Frame>,
+    frames: Vec<Frame>,
+) -> Result<()> {
+    for frame in frames {
+        match sinks.get_mut(&frame.socket) {
+            Some(sink) => {
+                println!("{}", format::replay_response(&describe(&frame.payload)));
+                sink.send(frame.payload.to_message()?).await?;
+            }
+            None => pending.push(frame),
+        }
+    }
+    Ok(())
+}
+
+fn describe(payload: &Payload) -> String {
+    match payload {
+        Payload::Text(text) => text.clone(),
+        Payload::Binary(_) => format::MESSAGE_BINARY.into(),
+    }
+}
+
+/// Accept clients on the address, forwarding their frames to the replay loop.
+///
+/// Each connection is handled in a separate task, so a failed handshake of one client does not
+/// stop the listener.
+async fn listen(socket: Socket, address: SocketAddr, events: mpsc::UnboundedSender<Event>) {
+    let result: Result<()> = async {
+        let listener = TcpListener::bind(address).await?;
+        println!("{}", format::listening(socket, address));
+        loop {
+            let (stream, _) = listener.accept().await?;
+            if events.is_closed() {
+                return Ok(());
+            }
+            let events = events.clone();
+            tokio::spawn(async move {
+                let connection = match server::accept(stream).await {
+                    Ok(connection) => connection,
+                    Err(error) => {
+                        eprintln!("{}", format::connection_error(socket, &error.to_string()));
+                        return;
+                    }
+                };
+                let (sink, mut stream) = connection.split();
+                if events.send(Event::Connected(socket, sink)).is_err() {
+                    return;
+                }
+                while let Some(Ok(message)) = stream.next().await {
+                    if events.send(Event::Received(socket, message)).is_err() {
+                        return;
+                    }
+                }
+                let _ = events.send(Event::Disconnected(socket));
+            });
+        }
+    }
+    .await;
+    if let Err(error) = result {
+        eprintln!("{}", format::connection_error(socket, &error.to_string()));
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::recording;
+    use crate::recording::Recorder;
+
+    use tokio::io::AsyncWriteExt;
+    use url::Url;
+    use websocket_lite::ClientBuilder;
+
+    fn text(time: u64, from: Sender, text: &str) -> Frame {
+        Frame { time, socket: Socket::Text, from, payload: Payload::Text(text.into()) }
+    }
+
+    #[test]
+    fn splitting_exchanges() {
+        let hello = text(0, Sender::Server, "hello");
+        let request = text(5, Sender::Client, "request");
+        let response = text(7, Sender::Server, "response");
+        let data = Frame { socket: Socket::Binary, ..text(8, Sender::Server, "data") };
+        let again = text(9, Sender::Client, "again");
+        let frames =
+            vec![hello.clone(), request.clone(), response.clone(), data.clone(), again.clone()];
+        let (initial, exchanges) = split_exchanges(frames);
+        assert_eq!(initial, vec![hello]);
+        assert_eq!(exchanges.len(), 2);
+        assert_eq!(exchanges[0].request, request);
+        assert_eq!(exchanges[0].responses, vec![response, data]);
+        assert_eq!(exchanges[1].request, again);
+        assert!(exchanges[1].responses.is_empty());
+
+        let (initial, exchanges) = split_exchanges(default());
+        assert!(initial.is_empty() && exchanges.is_empty());
+    }
+
+    #[tokio::test]
+    async fn replaying_a_recording() -> Result<()> {
+        let directory = tempfile::tempdir()?;
+        let path = directory.path().join("session.jsonl");
+        let recorder = Recorder::create(&path)?;
+        recorder.record(Socket::Text, Sender::Client, &Message::text("ping 1"))?;
+        recorder.record(Socket::Text, Sender::Server, &Message::text("pong 1"))?;
+        recorder.record(Socket::Text, Sender::Server, &Message::pong(vec![1]))?;
+        recorder.record(Socket::Text, Sender::Client, &Message::text("ping 2"))?;
+        recorder.record(Socket::Binary, Sender::Server, &Message::binary(vec![1, 2, 3]))?;
+        recorder.record(Socket::Text, Sender::Server, &Message::text("pong 2"))?;
+        drop(recorder);
+        let frames = recording::read(&path).await?;
+        let payloads = frames.iter().map(|frame| (frame.socket, frame.from, &frame.payload));
+        let expected = [
+            (Socket::Text, Sender::Client, &Payload::Text("ping 1".into())),
+            (Socket::Text, Sender::Server, &Payload::Text("pong 1".into())),
+            (Socket::Text, Sender::Client, &Payload::Text("ping 2".into())),
+            (Socket::Binary, Sender::Server, &Payload::Binary("AQID".into())),
+            (Socket::Text, Sender::Server, &Payload::Text("pong 2".into())),
+        ];
+        assert_eq!(payloads.collect::<Vec<_>>(), expected);
+
+        let address = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        let replay = tokio::spawn(run(frames, address, None, true));
+        // A client failing the handshake does not stop the replay server.
+        let mut bad_client = None;
+        for _ in 0..100 {
+            match tokio::net::TcpStream::connect(address).await {
+                Ok(connected) => {
+                    bad_client = Some(connected);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        let mut bad_client = bad_client.ok_or("Cannot connect to the replay server.")?;
+        bad_client.write_all(b"GET / HTTP/1.1\r\n\r\n").await?;
+        drop(bad_client);
+        let url = Url::parse(&format!("ws://{address}"))?;
+        let mut client = None;
+        for _ in 0..100 {
+            match ClientBuilder::from_url(url.clone()).async_connect().await {
+                Ok(connected) => {
+                    client = Some(connected);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        let mut client = client.ok_or("Cannot connect to the replay server.")?;
+        for (request, response) in [("ping 1", "pong 1"), ("ping 2", "pong 2")] {
+            client.send(Message::text(request)).await?;
+            let message = client.next().await.ok_or("Connection closed.")??;
+            assert_eq!(message.as_text(), Some(response));
+        }
+        drop(client);
+        replay.await?
+    }
+}
@@ -0,0 +1,522 @@
+//! Replayable scenarios: an ordered list of requests sent to the language server, together with
+//! assertions about the responses they should trigger.
+//!
+//! Scenarios are stored as YAML (or JSON, which is a subset of YAML). For example:
+//!
+//! ```yaml
+//! variables:
+//!   clientId: ae1ab3f2-026a-4a7c-a106-7727341f3dff
+//! steps:
+//!   - name: init
+//!     text: '{"jsonrpc":"2.0","id":0,"method":"session/initProtocolConnection","params":{"clientId":"${clientId}"}}'
+//!     expect:
+//!       - assert:
+//!           - path: $.id
+//!             equals: 0
+//!         capture:
+//!           rootId: $.result.contentRoots[0].id
+//!   - name: create context
+//!     timeout: 10000
+//!     text: '{"jsonrpc":"2.0","id":1,"method":"executionContext/create","params":{}}'
+//!     expect:
+//!       - assert:
+//!           - path: $.id
+//!             equals: 1
+//!         capture:
+//!           contextId: $.result.contextId
+//! ```
+//!
+//! Each step sends at most one request and then waits for each of its expectations in order. An
+//! expectation is met by the first response on its socket that satisfies all of its assertions;
+//! responses that do not match (e.g. unrelated notifications) are skipped. A step fails if its
+//! expectations are not met within its timeout.
+//!
+//! Steps listed under `setup` are executed once per connection before the `steps`. This matters
+//! for the load mode, which repeats the `steps` on every connection, but sends the requests that
+//! initialize the session (like `session/initProtocolConnection`) only once.
+
+use enso_prelude::*;
+
+use crate::format;
+
+use futures::Sink;
+use futures::SinkExt;
+use futures::Stream;
+use futures::StreamExt;
+use regex::Regex;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+use websocket_lite::Message;
+use websocket_lite::Opcode;
+use websocket_lite::Result;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Step timeout used when neither the step nor the scenario specify one.
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+
+
+// ================
+// === Scenario ===
+// ================
+
+/// A sequence of steps, see the module docs.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// Initial values of variables, which can be referenced as `${name}` in requests and
+    /// assertions.
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    /// Timeout of steps that don't specify their own, in milliseconds.
+    #[serde(default)]
+    pub timeout:   Option<u64>,
+    /// Steps initializing the connection, executed once before the `steps`.
+    #[serde(default)]
+    pub setup:     Vec<Step>,
+    /// The steps, executed in order.
+    pub steps:     Vec<Step>,
+}
+
+impl Scenario {
+    /// Read the scenario from a YAML or JSON file.
+    pub async fn read(path: &Path) -> Result<Self> {
+        let contents = tokio::fs::read_to_string(path).await?;
+        Ok(serde_yaml::from_str(&contents)?)
+    }
+}
+
+/// A single request and the responses it is expected to cause.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Name used when reporting the step result.
+    #[serde(default)]
+    pub name:    Option<String>,
+    /// Text message to send to the text socket.
+    #[serde(default)]
+    pub text:    Option<String>,
+    /// Base64-encoded message to send to the binary socket.
+    #[serde(default)]
+    pub binary:  Option<String>,
+    /// Time limit for receiving all the expected responses, in milliseconds.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// The expected responses, in the order they should arrive.
+    #[serde(default)]
+    pub expect:  Vec<Expectation>,
+}
+
+impl Step {
+    /// Name used when reporting the step: the given name, or the method of the JSON-RPC request.
+    pub fn label(&self, index: usize) -> String {
+        let method = || {
+            let request = serde_json::from_str::<Value>(self.text.as_ref()?).ok()?;
+            Some(request.get("method")?.as_str()?.to_owned())
+        };
+        self.name.clone().or_else(method).unwrap_or_else(|| format!("step {index}"))
+    }
+}
+
+/// A socket of the language server.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Socket {
+    /// The JSON-RPC socket.
+    #[default]
+    Text,
+    /// The binary data socket.
+    Binary,
+}
+
+/// A response that should arrive on a socket.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    /// The socket on which the response should arrive.
+    #[serde(default)]
+    pub socket:  Socket,
+    /// Conditions the response must satisfy. Binary responses are matched by their base64
+    /// encoding.
+    #[serde(default)]
+    pub assert:  Vec<Assertion>,
+    /// Variables to capture from the response, given as JSONPath expressions.
+    #[serde(default)]
+    pub capture: BTreeMap<String, String>,
+}
+
+/// A condition on a response. If `path` is given, the conditions apply to the selected value of
+/// the JSON response; otherwise, they apply to the whole response text.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Assertion {
+    /// JSONPath expression selecting the checked value, e.g. `$.result.contentRoots[0].id`.
+    #[serde(default)]
+    pub path:   Option<String>,
+    /// Expected value. Strings may reference variables.
+    #[serde(default)]
+    pub equals: Option<Value>,
+    /// Regular expression the value (or its JSON representation, if it is not a string) must
+    /// match. May reference variables.
+    #[serde(default)]
+    pub regex:  Option<String>,
+}
+
+
+
+// ==================
+// === Evaluation ===
+// ==================
+
+/// Replace all `${name}` references with the values of the variables. Unknown variables are left
+/// unchanged.
+pub fn substitute(template: &str, variables: &BTreeMap<String, String>) -> String {
+    let mut result = template.to_owned();
+    for (name, value) in variables {
+        result = result.replace(&format!("${{{name}}}"), value);
+    }
+    result
+}
+
+/// Evaluate a JSONPath expression. The supported subset consists of the root `$`, member access
+/// (`.name` or `['name']`) and array indexing (`[0]`).
+pub fn select<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut current = value;
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            current = current.get(&tail[..end])?;
+            rest = &tail[end..];
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']')?;
+            let index = tail[..end].trim();
+            current = match index.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+                Some(name) => current.get(name)?,
+                None => current.get(index.parse::<usize>().ok()?)?,
+            };
+            rest = &tail[end + 1..];
+        } else {
+            return None;
+        }
+    }
+    Some(current)
+}
+
+/// Render a value for use in a variable or regex match: strings are used verbatim, other values
+/// are rendered as JSON.
+fn to_plain_string(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Substitute variables in all strings within the value.
+fn substitute_value(value: &Value, variables: &BTreeMap<String, String>) -> Value {
+    match value {
+        Value::String(string) => Value::String(substitute(string, variables)),
+        Value::Array(items) =>
+            Value::Array(items.iter().map(|item| substitute_value(item, variables)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields.iter().map(|(k, v)| (k.clone(), substitute_value(v, variables))).collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+impl Assertion {
+    /// Check the assertion against a response. On failure, describe the mismatch.
+    pub fn check(
+        &self,
+        response: &str,
+        json: Option<&Value>,
+        variables: &BTreeMap<String, String>,
+    ) -> std::result::Result<(), String> {
+        let selected = match &self.path {
+            Some(path) => {
+                let json = json.ok_or("response is not JSON")?;
+                let value = select(json, path).ok_or_else(|| format!("{path} not found"))?;
+                Some(value)
+            }
+            None => None,
+        };
+        if let Some(expected) = &self.equals {
+            let expected = substitute_value(expected, variables);
+            let matches = match selected {
+                Some(actual) => actual == &expected,
+                None => json.map_or(false, |json| json == &expected),
+            };
+            if !matches {
+                return Err(format!("expected {expected}"));
+            }
+        }
+        if let Some(pattern) = &self.regex {
+            let pattern = substitute(pattern, variables);
+            let regex = Regex::new(&pattern).map_err(|e| e.to_string())?;
+            let actual = selected.map(to_plain_string);
+            let actual = actual.as_deref().unwrap_or(response);
+            if !regex.is_match(actual) {
+                return Err(format!("{actual} does not match /{pattern}/"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Expectation {
+    /// If the response meets the expectation, return the variables captured from it.
+    pub fn matches(
+        &self,
+        response: &str,
+        variables: &BTreeMap<String, String>,
+    ) -> std::result::Result<BTreeMap<String, String>, String> {
+        let json = serde_json::from_str::<Value>(response).ok();
+        for assertion in &self.assert {
+            assertion.check(response, json.as_ref(), variables)?;
+        }
+        let mut captured = BTreeMap::new();
+        for (name, path) in &self.capture {
+            let json = json.as_ref().ok_or("response is not JSON")?;
+            let value = select(json, path).ok_or_else(|| format!("{path} not found"))?;
+            captured.insert(name.clone(), to_plain_string(value));
+        }
+        Ok(captured)
+    }
+}
+
+
+
+// ==============
+// === Runner ===
+// ==============
+
+/// Outcome of a scenario run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// All the expectations were met.
+    Passed,
+    /// Some step failed; the following steps were not executed.
+    Failed,
+}
+
+/// Run the scenario against the given connections, reporting each step on the standard output.
+pub async fn run<T, B>(scenario: &Scenario, text: T, binary: Option<B>) -> Result<Outcome>
+where
+    T: Stream<Item = Result<Message>> + Sink<Message, Error = websocket_lite::Error> + Unpin,
+    B: Stream<Item = Result<Message>> + Sink<Message, Error = websocket_lite::Error> + Unpin, {
+    let mut session = Session::new(scenario, text, binary);
+    let steps = scenario.setup.iter().chain(&scenario.steps);
+    for (index, step) in steps.enumerate() {
+        let name = step.label(index);
+        match session.step(&name, step).await? {
+            Ok(()) => println!("{}", format::scenario_step_passed(&name)),
+            Err(reason) => {
+                println!("{}", format::scenario_step_failed(&name, &reason));
+                return Ok(Outcome::Failed);
+            }
+        }
+    }
+    Ok(Outcome::Passed)
+}
+
+/// Connections to the language server, and the variables captured so far.
+#[derive(Debug)]
+pub struct Session<T, B> {
+    text:            T,
+    binary:          Option<B>,
+    /// The current values of the variables.
+    pub variables:   BTreeMap<String, String>,
+    default_timeout: u64,
+    /// Whether the requests and responses are logged on the standard output.
+    pub verbose:     bool,
+}
+
+impl<T, B> Session<T, B>
+where
+    T: Stream<Item = Result<Message>> + Sink<Message, Error = websocket_lite::Error> + Unpin,
+    B: Stream<Item = Result<Message>> + Sink<Message, Error = websocket_lite::Error> + Unpin,
+{
+    /// Create a verbose session with the initial variables of the scenario.
+    pub fn new(scenario: &Scenario, text: T, binary: Option<B>) -> Self {
+        let variables = scenario.variables.clone();
+        let default_timeout = scenario.timeout.unwrap_or(DEFAULT_TIMEOUT_MS);
+        Self { text, binary, variables, default_timeout, verbose: true }
+    }
+
+    /// Execute the step. The inner result describes why the step failed; the captured variables
+    /// are stored in the session.
+    pub async fn step(
+        &mut self,
+        name: &str,
+        step: &Step,
+    ) -> Result<std::result::Result<(), String>> {
+        if let Some(request) = &step.text {
+            let request = substitute(request, &self.variables);
+            if self.verbose {
+                println!("{}", format::scenario_request(&request));
+            }
+            self.text.send(Message::text(request)).await?;
+        }
+        if let Some(request) = &step.binary {
+            let Some(binary) = self.binary.as_mut() else {
+                return Err(format!("Step {name} requires a binary socket.").into());
+            };
+            let bytes = base64::decode(substitute(request, &self.variables))?;
+            if self.verbose {
+                println!("{}", format::scenario_request(format::MESSAGE_BINARY));
+            }
+            binary.send(Message::binary(bytes)).await?;
+        }
+        let timeout = Duration::from_millis(step.timeout.unwrap_or(self.default_timeout));
+        let deadline = Instant::now() + timeout;
+        let verbose = self.verbose;
+        for expectation in &step.expect {
+            let variables = &self.variables;
+            let received = match expectation.socket {
+                Socket::Text =>
+                    wait_for(&mut self.text, expectation, variables, deadline, verbose).await?,
+                Socket::Binary => match self.binary.as_mut() {
+                    Some(binary) =>
+                        wait_for(binary, expectation, variables, deadline, verbose).await?,
+                    None => return Err(format!("Step {name} requires a binary socket.").into()),
+                },
+            };
+            match received {
+                Ok(captured) => self.variables.extend(captured),
+                Err(reason) => return Ok(Err(reason)),
+            }
+        }
+        Ok(Ok(()))
+    }
+}
+
+/// Read responses from the socket until one meets the expectation, or the deadline passes. The
+/// inner result describes why the expectation was not met.
+async fn wait_for<S>(
+    socket: &mut S,
+    expectation: &Expectation,
+    variables: &BTreeMap<String, String>,
+    deadline: Instant,
+    verbose: bool,
+) -> Result<std::result::Result<BTreeMap<String, String>, String>>
+where
+    S: Stream<Item = Result<Message>> + Unpin,
+{
+    let mut last_mismatch = None;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let message = match tokio::time::timeout(remaining, socket.next()).await {
+            Ok(Some(message)) => message?,
+            Ok(None) => return Ok(Err("connection closed".into())),
+            Err(_) => {
+                let reason = match last_mismatch {
+                    Some(mismatch) => format!("timed out; last mismatch: {mismatch}"),
+                    None => "timed out".into(),
+                };
+                return Ok(Err(reason));
+            }
+        };
+        let response = match (message.opcode(), expectation.socket) {
+            (Opcode::Text, Socket::Text) => message.as_text().unwrap_or_default().to_owned(),
+            (Opcode::Binary, Socket::Binary) => base64::encode(message.data()),
+            _ => continue,
+        };
+        match expectation.matches(&response, variables) {
+            Ok(captured) => {
+                match expectation.socket {
+                    Socket::Text if verbose => println!("{}", format::response_text(&response)),
+                    Socket::Binary if verbose => println!("{}", format::response_binary()),
+                    _ => {}
+                }
+                return Ok(Ok(captured));
+            }
+            Err(mismatch) => {
+                if verbose {
+                    println!("{}", format::response_ignored(&response));
+                }
+                last_mismatch = Some(mismatch);
+            }
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn substituting_variables() {
+        let variables = variables(&[("id", "42"), ("name", "main")]);
+        assert_eq!(substitute("${id}/${name}/${id}", &variables), "42/main/42");
+        assert_eq!(substitute("${unknown} $id {id}", &variables), "${unknown} $id {id}");
+    }
+
+    #[test]
+    fn selecting_values() {
+        let value = serde_json::json!({
+            "id": 1,
+            "result": {"contentRoots": [{"id": "a"}, {"id": "b"}], "odd key": true}
+        });
+        assert_eq!(select(&value, "$"), Some(&value));
+        assert_eq!(select(&value, "$.id"), Some(&Value::from(1)));
+        assert_eq!(select(&value, " $.result.contentRoots[1].id "), Some(&Value::from("b")));
+        assert_eq!(select(&value, "$['result']['odd key']"), Some(&Value::from(true)));
+        assert_eq!(select(&value, "$.result.contentRoots[2]"), None);
+        assert_eq!(select(&value, "$.missing"), None);
+        assert_eq!(select(&value, "$.result.contentRoots[x]"), None);
+        assert_eq!(select(&value, "$[0"), None);
+        assert_eq!(select(&value, "id"), None);
+    }
+
+    #[test]
+    fn checking_assertions() {
+        let response = r#"{"id":3,"result":{"contextId":"ctx-7"}}"#;
+        let json = serde_json::from_str::<Value>(response).ok();
+        let json = json.as_ref();
+        let variables = variables(&[("context", "ctx-7")]);
+        let assertion = |path: Option<&str>, equals: Option<Value>, regex: Option<&str>| {
+            Assertion { path: path.map(Into::into), equals, regex: regex.map(Into::into) }
+        };
+
+        let check = |assertion: Assertion| assertion.check(response, json, &variables);
+        assert!(check(assertion(Some("$.id"), Some(3.into()), None)).is_ok());
+        let context = Some("${context}".into());
+        assert!(check(assertion(Some("$.result.contextId"), context, None)).is_ok());
+        assert!(check(assertion(Some("$.id"), None, Some("^3$"))).is_ok());
+        assert!(check(assertion(None, None, Some(r#""contextId":"ctx-\d+""#))).is_ok());
+        assert!(check(assertion(None, Some(serde_json::from_str(response).unwrap()), None)).is_ok());
+
+        let error = check(assertion(Some("$.id"), Some(4.into()), None)).unwrap_err();
+        assert_eq!(error, "expected 4");
+        let error = check(assertion(Some("$.error"), None, None)).unwrap_err();
+        assert_eq!(error, "$.error not found");
+        let error = check(assertion(Some("$.result.contextId"), None, Some("^${context}0$")));
+        assert_eq!(error.unwrap_err(), "ctx-7 does not match /^ctx-70$/");
+        assert!(check(assertion(None, None, Some("("))).is_err());
+
+        let not_json = assertion(Some("$.id"), None, None).check("pong", None, &variables);
+        assert_eq!(not_json.unwrap_err(), "response is not JSON");
+        assert!(assertion(None, None, Some("^pong$")).check("pong", None, &variables).is_ok());
+    }
+}
@@ -0,0 +1,117 @@
+//! The server side of a WebSocket connection, used by the modes in which `wstest` stands in for
+//! the language server.
+
+use enso_prelude::*;
+
+use std::collections::HashMap;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+use tokio_util::codec::FramedParts;
+use websocket_codec::ClientRequest;
+use websocket_codec::Message;
+use websocket_codec::MessageCodec;
+use websocket_lite::Result;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Upper limit on the size of the HTTP upgrade request.
+const MAX_HANDSHAKE_SIZE: usize = 64 * 1024;
+
+
+
+// ==================
+// === Connection ===
+// ==================
+
+/// An accepted WebSocket connection.
+pub type Connection = Framed<TcpStream, MessageCodec>;
+
+/// Perform the HTTP upgrade handshake on a freshly accepted TCP connection.
+pub async fn accept(mut stream: TcpStream) -> Result<Connection> {
+    let mut request = Vec::new();
+    let mut chunk = [0; 1024];
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err("Connection closed during the WebSocket handshake.".into());
+        }
+        request.extend_from_slice(&chunk[..read]);
+        if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if request.len() > MAX_HANDSHAKE_SIZE {
+            return Err("WebSocket handshake request too large.".into());
+        }
+    };
+    let header = std::str::from_utf8(&request[..header_end])?;
+    let headers: HashMap<String, &str> = header
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim()))
+        .collect();
+    let client_request =
+        ClientRequest::parse(|name| headers.get(&name.to_ascii_lowercase()).copied())?;
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        client_request.ws_accept()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    // The client may send its first frames without waiting for the response, so they may have
+    // been read together with the request.
+    let mut parts = FramedParts::new::<Message>(stream, MessageCodec::server());
+    parts.read_buf.extend_from_slice(&request[header_end + 4..]);
+    Ok(Framed::from_parts(parts))
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::StreamExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn frames_sent_with_the_handshake() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(address).await?;
+            let request = "GET / HTTP/1.1\r\n\
+                Host: localhost\r\n\
+                Upgrade: websocket\r\n\
+                Connection: Upgrade\r\n\
+                Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                Sec-WebSocket-Version: 13\r\n\r\n";
+            // A masked text frame with a zero mask, written in the same segment as the request.
+            let frame = [0x81, 0x82, 0, 0, 0, 0, b'h', b'i'];
+            stream.write_all(&[request.as_bytes(), &frame].concat()).await?;
+            let mut response = [0; 1024];
+            let read = stream.read(&mut response).await?;
+            Result::Ok(String::from_utf8_lossy(&response[..read]).into_owned())
+        });
+        let (stream, _) = listener.accept().await?;
+        let mut connection = accept(stream).await?;
+        let message = connection.next().await.ok_or("Connection closed.")??;
+        assert_eq!(message.as_text(), Some("hi"));
+        let response = client.await??;
+        assert!(response.starts_with("HTTP/1.1 101"));
+        assert!(response.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+        Ok(())
+    }
+}